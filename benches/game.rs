@@ -0,0 +1,109 @@
+//! Measures the simulation and the drawing of the board, e.g. with `cargo bench --bench game`.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use snake_pixels::config::Config;
+use snake_pixels::framebuffer::FrameBuffer;
+use snake_pixels::game::{State, CELL_SIZE};
+use snake_pixels::grid::Direction;
+use snake_pixels::palette::Theme;
+use snake_pixels::renderer::{OffscreenRenderer, Renderer};
+use snake_pixels::world::World;
+
+const STEP_SIZES: [i32; 5] = [15, 64, 128, 256, 512];
+
+/// Larger boards would take hundreds of megabytes of pixels.
+const RENDER_SIZES: [i32; 3] = [15, 64, 128];
+
+/// Board drawn in bands, into a buffer of `BANDS_PIXELS` squared, i.e. with cells of 4 pixels.
+const BANDS_SIZE: i32 = 256;
+const BANDS_PIXELS: usize = 1024;
+
+/// Food appears often, so that there is always some on large boards.
+fn config(size: i32) -> Config {
+    Config {
+        width: size,
+        height: size,
+        food_interval: 2,
+        ..Config::default()
+    }
+}
+
+/// Turn of the snake before the tick `tick`, going around a small square so that it stays alive
+/// for a while on any board.
+fn turn(tick: u64) -> Option<Direction> {
+    const SQUARE: [Direction; 4] = [
+        Direction::Up,
+        Direction::Left,
+        Direction::Down,
+        Direction::Right,
+    ];
+    if tick.is_multiple_of(4) {
+        Some(SQUARE[(tick / 4 % 4) as usize])
+    } else {
+        None
+    }
+}
+
+fn step(c: &mut Criterion) {
+    let mut group = c.benchmark_group("step");
+    group.throughput(Throughput::Elements(1));
+    for &size in STEP_SIZES.iter() {
+        let start = World::new(&config(size), 1, 0);
+        group.bench_with_input(BenchmarkId::from_parameter(size), &start, |b, start| {
+            let mut world = start.clone();
+            b.iter(|| {
+                if world.is_over() {
+                    world = start.clone();
+                }
+                let turns = [turn(world.tick())];
+                black_box(world.step(&turns))
+            });
+        });
+    }
+    group.finish();
+}
+
+/// Draws the board and copies the frame out of the buffer, as every frame of a game does.
+fn render(c: &mut Criterion) {
+    let palette = Theme::Classic.palette();
+    let mut group = c.benchmark_group("render");
+    for &size in RENDER_SIZES.iter() {
+        let mut state = State::new(&config(size), 0);
+        // Move the snake away from the start, short of the wall.
+        for _ in 0..size / 4 {
+            state.step();
+        }
+        let pixels = (size * CELL_SIZE) as usize;
+        let mut renderer = OffscreenRenderer::new(pixels, pixels);
+        group.throughput(Throughput::Elements((pixels * pixels) as u64));
+        group.bench_function(BenchmarkId::from_parameter(size), |b| {
+            b.iter(|| {
+                state.render(&mut renderer, palette);
+                renderer.present().unwrap();
+            });
+        });
+    }
+    group.finish();
+}
+
+/// Draws a large board with several snakes on one thread and in two and four bands, see
+/// `FrameBuffer::draw_in_bands`.
+fn render_bands(c: &mut Criterion) {
+    let palette = Theme::Classic.palette();
+    let mut world = World::new(&config(BANDS_SIZE), 4, 0);
+    for _ in 0..BANDS_SIZE / 4 {
+        world.step(&[None; 4]);
+    }
+    let mut group = c.benchmark_group("render_bands");
+    group.throughput(Throughput::Elements((BANDS_PIXELS * BANDS_PIXELS) as u64));
+    for threads in [1, 2, 4] {
+        let mut buffer = FrameBuffer::new(BANDS_PIXELS, BANDS_PIXELS);
+        group.bench_function(BenchmarkId::from_parameter(threads), |b| {
+            b.iter(|| buffer.draw_in_bands(threads, |buffer| world.render(buffer, palette)));
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, step, render, render_bands);
+criterion_main!(benches);
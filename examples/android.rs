@@ -0,0 +1,26 @@
+//! The game as an Android app. Android loads it as a library and starts it with `android_main`
+//! instead of `main`, so this includes the source of the game, where `main` and what only it
+//! uses are never called.
+
+#![allow(dead_code)]
+
+include!("../src/main.rs");
+
+#[cfg(target_os = "android")]
+#[no_mangle]
+fn android_main(android: winit::platform::android::activity::AndroidApp) {
+    use winit::event_loop::EventLoopBuilder;
+    use winit::platform::android::EventLoopBuilderExtAndroid;
+
+    android_logger::init_once(
+        android_logger::Config::default().with_max_level(log::LevelFilter::Debug),
+    );
+    if let Some(dir) = android.internal_data_path() {
+        storage::set_data_dir(dir);
+    }
+    let event_loop = EventLoopBuilder::new()
+        .with_android_app(android)
+        .build()
+        .unwrap();
+    pollster::block_on(run(event_loop, Options::from_args()));
+}
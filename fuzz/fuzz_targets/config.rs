@@ -0,0 +1,27 @@
+//! Reads arbitrary bytes as the game parameters, as in the file given with `--config`, and
+//! plays a game with them if they pass the validation.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use snake_pixels::config::Config;
+use snake_pixels::world::World;
+
+fuzz_target!(|data: &[u8]| {
+    let config: Config = match serde_json::from_slice(data) {
+        Ok(config) => config,
+        Err(_) => return,
+    };
+    config.hash();
+    config.tick();
+    if config.validate().is_err() {
+        return;
+    }
+    let mut world = World::new(&config, 1, 0);
+    for _ in 0..100 {
+        if world.is_over() {
+            break;
+        }
+        world.step(&[None]);
+    }
+});
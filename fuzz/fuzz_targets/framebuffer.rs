@@ -0,0 +1,58 @@
+//! Draws arbitrary rectangles and pixels, most of them outside of the buffer, which should be
+//! clipped rather than written out of bounds.
+
+#![no_main]
+
+use libfuzzer_sys::arbitrary::{self, Arbitrary};
+use libfuzzer_sys::fuzz_target;
+use snake_pixels::framebuffer::{Color, FrameBuffer};
+
+#[derive(Arbitrary, Debug)]
+enum Op {
+    SetPixel {
+        x: i32,
+        y: i32,
+    },
+    Fill {
+        x: i32,
+        y: i32,
+        w: usize,
+        h: usize,
+    },
+    Blend {
+        x: i32,
+        y: i32,
+        w: usize,
+        h: usize,
+        alpha: u8,
+    },
+    Clear,
+}
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    width: u8,
+    height: u8,
+    ops: Vec<(Op, (u8, u8, u8))>,
+}
+
+fuzz_target!(|input: Input| {
+    let (width, height) = (input.width as usize, input.height as usize);
+    let mut buffer = FrameBuffer::new(width, height);
+    for (op, (r, g, b)) in input.ops {
+        let color = Color::rgb(r, g, b);
+        match op {
+            Op::SetPixel { x, y } => {
+                buffer.set_pixel(x, y, color);
+                let inside = x >= 0 && y >= 0 && (x as usize) < width && (y as usize) < height;
+                assert_eq!(buffer.pixel(x, y).is_some(), inside);
+            }
+            Op::Fill { x, y, w, h } => buffer.fill_rectangle(x, y, w, h, color),
+            Op::Blend { x, y, w, h, alpha } => buffer.blend_rectangle(x, y, w, h, color, alpha),
+            Op::Clear => buffer.clear(color),
+        }
+    }
+    assert_eq!(buffer.pixels().len(), width * height);
+    let mut frame = vec![0; width * height * 4];
+    buffer.copy_to(&mut frame);
+});
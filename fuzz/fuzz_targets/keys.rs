@@ -0,0 +1,63 @@
+//! Plays a game with an arbitrary sequence of key presses and ticks.
+
+#![no_main]
+
+use libfuzzer_sys::arbitrary::{self, Arbitrary};
+use libfuzzer_sys::fuzz_target;
+use snake_pixels::config::Config;
+use snake_pixels::game::{State, Update};
+use snake_pixels::grid::Direction;
+use snake_pixels::palette::Theme;
+use snake_pixels::renderer::{OffscreenRenderer, Renderer};
+
+#[derive(Arbitrary, Debug)]
+enum Key {
+    /// An arrow key, picked by the index in `Direction::ALL`.
+    Turn(u8),
+    /// The next tick, as when its time comes.
+    Tick,
+    /// The frame-step key of the debug mode.
+    Step,
+    Pause,
+    Resume,
+    Render,
+}
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    seed: u64,
+    keys: Vec<Key>,
+}
+
+fuzz_target!(|input: Input| {
+    let mut state = State::new(&Config::default(), input.seed);
+    let mut renderer = OffscreenRenderer::new(120, 120);
+    for key in input.keys {
+        let update = match key {
+            Key::Turn(code) => {
+                let direction = Direction::ALL[code as usize % Direction::ALL.len()];
+                state.turn(direction);
+                assert_eq!(state.pending_turn(0), Some(direction));
+                Update::Idle
+            }
+            Key::Tick if state.is_paused() => Update::Idle,
+            Key::Tick | Key::Step => state.step(),
+            Key::Pause => {
+                state.set_paused(true);
+                state.update()
+            }
+            Key::Resume => {
+                state.set_paused(false);
+                Update::Idle
+            }
+            Key::Render => {
+                state.render(&mut renderer, Theme::Classic.palette());
+                renderer.present().unwrap();
+                Update::Idle
+            }
+        };
+        if update == Update::GameOver {
+            break;
+        }
+    }
+});
@@ -0,0 +1,28 @@
+//! Reads arbitrary text as a level and plays it if it is valid.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use snake_pixels::config::Config;
+use snake_pixels::framebuffer::FrameBuffer;
+use snake_pixels::level::Level;
+use snake_pixels::palette::Theme;
+use snake_pixels::world::World;
+
+fuzz_target!(|text: &str| {
+    let level = match Level::parse(text) {
+        Ok(level) => level,
+        Err(_) => return,
+    };
+    if level.validate().is_err() {
+        return;
+    }
+    let mut world = World::from_level(&Config::default(), &level, 1, 0);
+    for _ in 0..100 {
+        if world.is_over() {
+            break;
+        }
+        world.step(&[None]);
+    }
+    world.render(&mut FrameBuffer::new(120, 120), Theme::Classic.palette());
+});
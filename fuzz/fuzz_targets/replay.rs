@@ -0,0 +1,36 @@
+//! Reads arbitrary bytes as a message of the server and replays the match that it starts, as
+//! the client does when joining in the middle of one.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use snake_pixels::grid::Direction;
+use snake_pixels::net::ServerMessage;
+use snake_pixels::rollback::Rollback;
+use snake_pixels::world::World;
+
+fuzz_target!(|data: &[u8]| {
+    let message: ServerMessage = match serde_json::from_slice(data) {
+        Ok(message) => message,
+        Err(_) => return,
+    };
+    if message.validate().is_err() {
+        return;
+    }
+    if let ServerMessage::Start {
+        snake,
+        config,
+        seed,
+        names,
+        history,
+        ..
+    } = message
+    {
+        let mut game = Rollback::new(World::new(&config, names.len(), seed), snake);
+        for (tick, turns) in history.iter().enumerate() {
+            game.confirm(tick as u64 + 1, turns);
+        }
+        game.turn(Direction::Up);
+        game.advance();
+    }
+});
@@ -0,0 +1,49 @@
+use crate::stats::LifetimeStats;
+use serde::{Deserialize, Serialize};
+use snake_pixels::telemetry::{EndReason, GameRecord};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Achievement {
+    FirstGame,
+    Length10,
+    Length25,
+    Length50,
+    Food100,
+    FullBoard,
+}
+
+impl Achievement {
+    pub const ALL: [Achievement; 6] = [
+        Achievement::FirstGame,
+        Achievement::Length10,
+        Achievement::Length25,
+        Achievement::Length50,
+        Achievement::Food100,
+        Achievement::FullBoard,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Achievement::FirstGame => "FIRST STEPS",
+            Achievement::Length10 => "LENGTH 10",
+            Achievement::Length25 => "LENGTH 25",
+            Achievement::Length50 => "LENGTH 50",
+            Achievement::Food100 => "100 FOOD EATEN",
+            Achievement::FullBoard => "FULL BOARD",
+        }
+    }
+
+    /// Whether the achievement is earned by the game `record`, given the lifetime statistics
+    /// that already include it.
+    pub fn is_earned(self, stats: &LifetimeStats, record: &GameRecord) -> bool {
+        match self {
+            Achievement::FirstGame => stats.games_played >= 1,
+            Achievement::Length10 => record.final_length >= 10,
+            Achievement::Length25 => record.final_length >= 25,
+            Achievement::Length50 => record.final_length >= 50,
+            Achievement::Food100 => stats.food_eaten >= 100,
+            Achievement::FullBoard => record.end_reason == EndReason::BoardFull,
+        }
+    }
+}
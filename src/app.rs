@@ -0,0 +1,2890 @@
+use crate::audio::{Audio, AudioSettings, Sound};
+use crate::autosave::{self, Autosave};
+use crate::bindings::{self, KeyBindings};
+#[cfg(feature = "chat")]
+use crate::chat::ChatControl;
+use crate::console::{self, Command, Console, ConsoleEvent};
+use crate::custom_game::{CustomGame, CustomGameEvent};
+use crate::customize::{CustomizeEvent, Customizer};
+use crate::editor::{Editor, EditorEvent};
+use crate::effects::Effects;
+use crate::exhibition::{Exhibition, ExhibitionEvent};
+use crate::heatmap::{self, Heatmap};
+use crate::i18n::{self, tr, tr_fmt};
+use crate::input::InputState;
+use crate::leaderboard::{self, Leaderboard};
+use crate::live_stats::LiveStats;
+use crate::menu::Menu;
+use crate::modal::{Modal, ModalEvent};
+#[cfg(any(feature = "lua", feature = "plugins"))]
+use crate::mods::Mod;
+use crate::name_entry::{NameEntry, NameEntryEvent};
+use crate::online::OnlineGame;
+use crate::packs::Pack;
+use crate::profile::{Profile, MAX_NAME_LEN, MAX_UI_SCALE};
+use crate::profiler::{self, Profiler};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::remote::{self, GameState, Remote, RemoteCommand};
+use crate::screensaver::Screensaver;
+use crate::speedrun::{self, Run};
+use crate::storage;
+use crate::toasts::{Notice, Toasts};
+use crate::touch::TouchControls;
+use crate::tuning::Tuning;
+#[cfg(feature = "hot-reload")]
+use crate::tuning::Watcher;
+use crate::tutorial::Tutorial;
+use log::{error, info, warn};
+use snake_pixels::autopilot::{self, Autopilot};
+use snake_pixels::campaign::{self, Medal, PackProgress, Unlock};
+use snake_pixels::config::{Config, Difficulty, Mode, MAX_LAYERS};
+use snake_pixels::controller::Keyboard;
+use snake_pixels::font::{draw_text, draw_text_centered, text_width, GLYPH_HEIGHT, LINE_HEIGHT};
+use snake_pixels::framebuffer::{Color, FrameBuffer};
+use snake_pixels::game::{State, Update, CELL_SIZE};
+use snake_pixels::grid::{Direction, Vec2};
+use snake_pixels::level::Level;
+use snake_pixels::palette::Palette;
+use snake_pixels::renderer::Renderer;
+use snake_pixels::replay::Replay;
+use snake_pixels::ruleset::Ruleset;
+use snake_pixels::share;
+use snake_pixels::skin::Skin;
+use snake_pixels::solver::{self, Verdict};
+use snake_pixels::telemetry::EndReason;
+use snake_pixels::world::{Camera, Event, SnakeId, World, GOLDEN_POINTS};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use web_time::Instant;
+use winit::event::{MouseButton, TouchPhase};
+use winit::keyboard::KeyCode;
+
+/// While this key is held, the autopilot shows how it decides on its moves.
+const PLAN_KEY: KeyCode = KeyCode::F3;
+
+/// Pauses the game for debugging, after which `STEP_KEY` advances it by one tick.
+const FRAME_STEP_KEY: KeyCode = KeyCode::F10;
+const STEP_KEY: KeyCode = KeyCode::Period;
+/// Takes a practice game back by `game::REWIND_TICKS` ticks.
+const REWIND_KEY: KeyCode = KeyCode::Backspace;
+/// Starts the game over with the same seed, on the same level.
+const RESTART_KEY: KeyCode = KeyCode::KeyR;
+/// Opacity of the tint of the cells where the snake would crash.
+const DANGER_ALPHA: u8 = 128;
+/// How many times longer the ticks are in slow motion.
+const SLOW_MOTION: f32 = 4.0;
+/// Make the ticks twice as fast or as slow in a debug session.
+const FASTER_KEYS: [KeyCode; 2] = [KeyCode::Equal, KeyCode::NumpadAdd];
+const SLOWER_KEYS: [KeyCode; 2] = [KeyCode::Minus, KeyCode::NumpadSubtract];
+
+/// Height in pixels of the bar that shows how much of the board each snake owns.
+const TERRITORY_BAR_HEIGHT: usize = 3;
+
+/// Title of the window, to which the minimal HUD adds the score.
+pub const TITLE: &str = "Snake";
+/// How often the title of the window follows the score with the minimal HUD.
+const TITLE_INTERVAL: Duration = Duration::from_secs(1);
+/// How long an announcement stays over the board.
+const ANNOUNCEMENT_TIME: Duration = Duration::from_millis(1500);
+/// How long the room that the snake enters takes to slide in, and the time between two frames
+/// meanwhile.
+const ROOM_SLIDE: Duration = Duration::from_millis(250);
+const SLIDE_FRAME: Duration = Duration::from_millis(16);
+
+/// The last game that counted, which `--verify` can check.
+const REPLAY_FILE: &str = "replay.json";
+
+/// Longest code of a game that can be entered, as long as fits on the smallest screen. Codes of
+/// games with the default parameters are shorter.
+const MAX_CODE_LEN: usize = 30;
+
+/// How long the title screen waits for a key before it starts the demo.
+const DEMO_DELAY: Duration = Duration::from_secs(15);
+
+/// Smallest size of a cell in pixels in the high-contrast mode, which zooms in on the player's
+/// snake on boards that would have smaller cells, and the width of the frame around every cell.
+const LARGE_CELL: i32 = 12;
+const CELL_BORDER: i32 = 2;
+
+/// Thickest frame around the board, in cells.
+const MAX_BOARD_BORDER: u32 = 2;
+
+/// Largest side of the frame in pixels. Larger boards get smaller cells than `CELL_SIZE`.
+const MAX_CANVAS: i32 = 320;
+
+/// Boards with at least this many cells are drawn on `render_threads` threads, see
+/// `FrameBuffer::draw_in_bands`.
+const PARALLEL_CELLS: i32 = 128 * 128;
+
+/// How often the watched files are checked for changes.
+#[cfg(feature = "hot-reload")]
+const RELOAD_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How often the commands of the remote control are checked for.
+#[cfg(not(target_arch = "wasm32"))]
+const REMOTE_POLL: Duration = Duration::from_millis(50);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum MenuAction {
+    Play,
+    Tutorial,
+    Practice,
+    CustomGame,
+    EnterCode,
+    Campaign,
+    #[cfg(feature = "chat")]
+    ChatPlay,
+    #[cfg(any(feature = "lua", feature = "plugins"))]
+    Mods,
+    Editor,
+    BotMatch,
+    Mode,
+    Difficulty,
+    Grid,
+    Layers,
+    Scores,
+    Stats,
+    Profiles,
+    Snake,
+    Theme,
+    HighContrast,
+    ReducedMotion,
+    DangerZones,
+    MinimalHud,
+    PauseHidden,
+    HoldToSprint,
+    Border,
+    SpeedrunTimer,
+    Scale,
+    Language,
+    Sound,
+    Controls,
+    Quit,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SoundSetting {
+    Master,
+    Music,
+    Effects,
+    Mute,
+}
+
+/// What a dialog over the screen was opened for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Dialog {
+    Quit,
+    /// Pressing the key for the direction.
+    Rebind(Direction),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ProfileChoice {
+    Existing(usize),
+    New,
+}
+
+enum Screen {
+    Menu,
+    /// Offers to resume the game that was in progress when the last run didn't exit normally.
+    Resume {
+        menu: Menu<bool>,
+        save: Autosave,
+    },
+    Playing(Box<State>),
+    /// A game played by the autopilot while nobody is at the keyboard.
+    Demo(Box<State>),
+    Online(Box<OnlineGame>),
+    Editor(Box<Editor>),
+    /// The lifetime statistics, with a line about the last export of the history.
+    Stats {
+        notice: String,
+    },
+    Profiles {
+        menu: Menu<ProfileChoice>,
+        names: Vec<String>,
+    },
+    NewProfile(NameEntry),
+    /// Entering the code of a game to play, see `share`.
+    EnterCode(NameEntry),
+    Customize(Customizer),
+    CustomGame(Box<CustomGame>),
+    /// Bots playing against each other for the player to watch.
+    Exhibition(Box<Exhibition>),
+    Sound(Menu<SoundSetting>),
+    /// The keys that steer the snake, each of which is changed by pressing the new one.
+    Controls(Menu<Direction>),
+    /// Choosing a campaign pack, by its index in `packs`.
+    Campaigns {
+        menu: Menu<usize>,
+        packs: Vec<Pack>,
+    },
+    /// Choosing a level of a campaign pack, with a line about the last choice or completion.
+    Campaign {
+        menu: Menu<usize>,
+        pack: Pack,
+        notice: String,
+    },
+    /// Choosing a mod to play, by its index in `mods`.
+    #[cfg(any(feature = "lua", feature = "plugins"))]
+    Mods {
+        menu: Menu<usize>,
+        mods: Vec<Mod>,
+    },
+    Scores {
+        page: usize,
+    },
+    /// Entering initials for a new high score.
+    HighScore {
+        entry: NameEntry,
+        mode: Mode,
+        difficulty: Difficulty,
+        score: leaderboard::Entry,
+    },
+}
+
+fn main_menu_items(profile: &Profile) -> Vec<(String, MenuAction)> {
+    let settings = &profile.settings;
+    let mut items = vec![
+        (tr("PLAY"), MenuAction::Play),
+        (tr("TUTORIAL"), MenuAction::Tutorial),
+        (tr("PRACTICE"), MenuAction::Practice),
+        (tr("CUSTOM GAME"), MenuAction::CustomGame),
+        (tr("ENTER CODE"), MenuAction::EnterCode),
+    ];
+    if !Pack::list().is_empty() {
+        items.push((tr("CAMPAIGN"), MenuAction::Campaign));
+    }
+    #[cfg(feature = "chat")]
+    if !settings.chat.channel.is_empty() {
+        items.push((tr("CHAT PLAY"), MenuAction::ChatPlay));
+    }
+    #[cfg(any(feature = "lua", feature = "plugins"))]
+    if !Mod::list().is_empty() {
+        items.push((tr("MODS"), MenuAction::Mods));
+    }
+    items.extend(vec![
+        (tr("EDITOR"), MenuAction::Editor),
+        (tr("BOT MATCH"), MenuAction::BotMatch),
+        (
+            tr_fmt("MODE: {}", &[&tr(settings.mode.name())]),
+            MenuAction::Mode,
+        ),
+        (
+            tr_fmt("DIFFICULTY: {}", &[&tr(settings.difficulty.name())]),
+            MenuAction::Difficulty,
+        ),
+        (
+            tr_fmt("GRID: {}", &[&tr(settings.grid.name())]),
+            MenuAction::Grid,
+        ),
+        (
+            tr_fmt("LAYERS: {}", &[&settings.layers]),
+            MenuAction::Layers,
+        ),
+        (tr("SCORES"), MenuAction::Scores),
+        (tr("STATS"), MenuAction::Stats),
+        (
+            tr_fmt("PROFILE: {}", &[&profile.name()]),
+            MenuAction::Profiles,
+        ),
+        (tr("SNAKE"), MenuAction::Snake),
+        (
+            tr_fmt("THEME: {}", &[&tr(settings.theme.name())]),
+            MenuAction::Theme,
+        ),
+        (
+            tr_fmt(
+                "HIGH CONTRAST: {}",
+                &[&tr(if settings.high_contrast { "ON" } else { "OFF" })],
+            ),
+            MenuAction::HighContrast,
+        ),
+        (
+            tr_fmt(
+                "REDUCED MOTION: {}",
+                &[&tr(if settings.reduced_motion { "ON" } else { "OFF" })],
+            ),
+            MenuAction::ReducedMotion,
+        ),
+        (
+            tr_fmt(
+                "DANGER ZONES: {}",
+                &[&tr(if settings.danger_zones { "ON" } else { "OFF" })],
+            ),
+            MenuAction::DangerZones,
+        ),
+        (
+            tr_fmt(
+                "MINIMAL HUD: {}",
+                &[&tr(if settings.minimal_hud { "ON" } else { "OFF" })],
+            ),
+            MenuAction::MinimalHud,
+        ),
+        (
+            tr_fmt(
+                "PAUSE WHEN HIDDEN: {}",
+                &[&tr(if settings.pause_hidden { "ON" } else { "OFF" })],
+            ),
+            MenuAction::PauseHidden,
+        ),
+        (
+            tr_fmt(
+                "HOLD TO SPRINT: {}",
+                &[&tr(if settings.hold_to_sprint { "ON" } else { "OFF" })],
+            ),
+            MenuAction::HoldToSprint,
+        ),
+        (
+            tr_fmt(
+                "BORDER: {}",
+                &[&match settings.border {
+                    0 => tr("OFF"),
+                    cells => cells.to_string(),
+                }],
+            ),
+            MenuAction::Border,
+        ),
+        (
+            tr_fmt(
+                "SPEEDRUN TIMER: {}",
+                &[&tr(if settings.speedrun_timer { "ON" } else { "OFF" })],
+            ),
+            MenuAction::SpeedrunTimer,
+        ),
+        (
+            tr_fmt("SCALE: {}X", &[&settings.ui_scale]),
+            MenuAction::Scale,
+        ),
+        (
+            tr_fmt("LANGUAGE: {}", &[&settings.language.name()]),
+            MenuAction::Language,
+        ),
+        (tr("SOUND"), MenuAction::Sound),
+        (tr("CONTROLS"), MenuAction::Controls),
+        (tr("QUIT"), MenuAction::Quit),
+    ]);
+    items
+}
+
+fn sound_menu_items(settings: &AudioSettings) -> Vec<(String, SoundSetting)> {
+    vec![
+        (
+            tr_fmt("MASTER: {}%", &[&settings.master]),
+            SoundSetting::Master,
+        ),
+        (
+            tr_fmt("MUSIC: {}%", &[&settings.music]),
+            SoundSetting::Music,
+        ),
+        (
+            tr_fmt("EFFECTS: {}%", &[&settings.effects]),
+            SoundSetting::Effects,
+        ),
+        (
+            tr_fmt(
+                "MUTE: {}",
+                &[&tr(if settings.muted { "ON" } else { "OFF" })],
+            ),
+            SoundSetting::Mute,
+        ),
+    ]
+}
+
+fn controls_menu_items(bindings: &KeyBindings) -> Vec<(String, Direction)> {
+    [
+        ("UP: {}", Direction::Up),
+        ("DOWN: {}", Direction::Down),
+        ("LEFT: {}", Direction::Left),
+        ("RIGHT: {}", Direction::Right),
+    ]
+    .iter()
+    .map(|&(label, direction)| {
+        let keys: Vec<String> = bindings
+            .keys(direction)
+            .iter()
+            .map(|&key| bindings::key_name(key))
+            .collect();
+        let keys = if keys.is_empty() {
+            "-".to_string()
+        } else {
+            keys.join(" ")
+        };
+        (tr_fmt(label, &[&keys]), direction)
+    })
+    .collect()
+}
+
+fn campaign_items(packs: &[Pack], profile: &Profile) -> Vec<(String, usize)> {
+    packs
+        .iter()
+        .enumerate()
+        .map(|(i, pack)| {
+            let levels = &pack.manifest.levels;
+            let completed = match profile.campaigns.get(&pack.dir) {
+                Some(progress) => levels.iter().filter(|l| l.is_completed(progress)).count(),
+                None => 0,
+            };
+            let name = pack.manifest.name.to_uppercase();
+            (format!("{} {}/{}", name, completed, levels.len()), i)
+        })
+        .collect()
+}
+
+fn campaign_level_items(pack: &Pack, progress: &PackProgress) -> Vec<(String, usize)> {
+    pack.manifest
+        .levels
+        .iter()
+        .enumerate()
+        .map(|(i, level)| {
+            let status = if !pack.manifest.is_unlocked(i, progress) {
+                tr("LOCKED")
+            } else if level.par.is_some() && level.is_par_beaten(progress) {
+                tr("PAR")
+            } else if level.is_completed(progress) {
+                tr("DONE")
+            } else {
+                String::new()
+            };
+            let name = level.name.to_uppercase();
+            (format!("{} {}", name, status).trim_end().to_string(), i)
+        })
+        .collect()
+}
+
+fn unlock_hint(unlock: Unlock) -> String {
+    match unlock {
+        Unlock::Always => String::new(),
+        Unlock::Previous => tr("FINISH THE LEVEL BEFORE"),
+        Unlock::Completed(n) => tr_fmt("FINISH {} LEVELS FIRST", &[&n]),
+        Unlock::Par(n) => tr_fmt("BEAT PAR ON {} LEVELS FIRST", &[&n]),
+    }
+}
+
+/// Medal icon, a row of pixels per string from the top: the ribbon and the disc.
+const MEDAL_ICON: [&str; 5] = ["r...r", ".r.r.", ".ddd.", "ddddd", ".ddd."];
+
+fn medal_color(medal: Medal) -> Color {
+    match medal {
+        Medal::Bronze => Color::rgb(0xCD, 0x7F, 0x32),
+        Medal::Silver => Color::rgb(0xC8, 0xC8, 0xD0),
+        Medal::Gold => Color::rgb(0xFF, 0xD0, 0x20),
+    }
+}
+
+/// Draws the icon of the medal with its top left corner at `(x, y)`, like the text.
+fn render_medal(buffer: &mut FrameBuffer, palette: &Palette, x: i32, y: i32, medal: Medal) {
+    for (row, line) in MEDAL_ICON.iter().enumerate() {
+        for (col, c) in line.chars().enumerate() {
+            let color = match c {
+                'r' => palette.selected,
+                'd' => medal_color(medal),
+                _ => continue,
+            };
+            buffer.fill_rectangle(x + col as i32, y - 1 - row as i32, 1, 1, color);
+        }
+    }
+}
+
+fn direction_name(direction: Direction) -> &'static str {
+    match direction {
+        Direction::Up => "UP",
+        Direction::Down => "DOWN",
+        Direction::Left => "LEFT",
+        Direction::Right => "RIGHT",
+        Direction::UpLeft => "UP LEFT",
+        Direction::UpRight => "UP RIGHT",
+        Direction::DownLeft => "DOWN LEFT",
+        Direction::DownRight => "DOWN RIGHT",
+    }
+}
+
+/// Shows the state of the player's snake in the paused game, at the bottom left.
+fn render_debug_dump(buffer: &mut FrameBuffer, palette: &Palette, state: &State) {
+    let snake = &state.world().snakes()[0];
+    let velocity = snake.direction.vec();
+    let pending = state.pending_turn(0).map_or("-", direction_name);
+    let lines = [
+        format!("INPUT {}", pending),
+        format!("VEL {:+},{:+}", velocity.0, velocity.1),
+        format!("HEAD {},{}", snake.head.0, snake.head.1),
+        format!("TICK {}", state.world().tick()),
+        tr("PAUSED"),
+    ];
+    for (i, line) in lines.iter().enumerate() {
+        let y = GLYPH_HEIGHT + 1 + i as i32 * LINE_HEIGHT;
+        draw_text(buffer, 1, y, line, 1, palette.text);
+    }
+}
+
+/// Tints the cells through the camera.
+fn render_danger(buffer: &mut FrameBuffer, camera: &Camera, cells: &[Vec2]) {
+    let cell = camera.cell;
+    for &pos in cells.iter().filter(|&&pos| camera.shows(pos)) {
+        let corner = camera.corner(pos);
+        buffer.blend_rectangle(
+            corner.0,
+            corner.1,
+            cell as usize,
+            cell as usize,
+            autopilot::DANGER,
+            DANGER_ALPHA,
+        );
+    }
+}
+
+/// The outcome of a game of the territory mode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Winner {
+    Player,
+    /// Numbered from 1, as the snakes after the player's.
+    Bot(SnakeId),
+    Draw,
+}
+
+/// Whoever owns the most cells, given the number of cells of each snake.
+fn winner(cells: &[usize]) -> Winner {
+    let most = cells.iter().copied().max().unwrap_or(0);
+    let mut leaders = (0..cells.len()).filter(|&id| cells[id] == most);
+    match (leaders.next(), leaders.next()) {
+        (Some(0), None) => Winner::Player,
+        (Some(id), None) => Winner::Bot(id),
+        _ => Winner::Draw,
+    }
+}
+
+/// Draws the bar at the bottom of the screen split between the snakes by the cells that they
+/// own, with the time left above its right end.
+fn render_territory_bar(
+    buffer: &mut FrameBuffer,
+    palette: &Palette,
+    cells: &[usize],
+    skin: Skin,
+    state: &State,
+) {
+    let world = state.world();
+    let width = buffer.width() as i32;
+    let board = world.width() * world.height();
+    buffer.fill_rectangle(0, 0, width as usize, TERRITORY_BAR_HEIGHT, palette.menu_bg);
+    let mut x = 0;
+    for (id, &count) in cells.iter().enumerate() {
+        let colors = match id {
+            0 => skin.colors(),
+            _ => None,
+        };
+        let (color, _) = colors.unwrap_or_else(|| palette.snake_colors(id));
+        let w = width * count as i32 / board.max(1);
+        buffer.fill_rectangle(x, 0, w as usize, TERRITORY_BAR_HEIGHT, color);
+        x += w;
+    }
+    let seconds = state.time_left().unwrap_or_default().as_secs_f64().ceil() as u64;
+    let time = format!("{}:{:02}", seconds / 60, seconds % 60);
+    let x = width - text_width(&time) - 1;
+    let y = TERRITORY_BAR_HEIGHT as i32 + GLYPH_HEIGHT + 1;
+    draw_text(buffer, x, y, &time, 1, palette.text);
+}
+
+fn as_str_items<T: Copy>(items: &[(String, T)]) -> Vec<(&str, T)> {
+    items
+        .iter()
+        .map(|(label, item)| (label.as_str(), *item))
+        .collect()
+}
+
+/// Top-level state of the application: which screen is shown and everything that outlives a
+/// single game.
+pub struct App {
+    config: Config,
+    /// Seed for every new game, or `None` to pick a random one each time.
+    seed: Option<u64>,
+    screen: Screen,
+    menu: Menu<MenuAction>,
+    profile: Profile,
+    leaderboard: Leaderboard,
+    audio: Audio,
+    touch: TouchControls,
+    /// Whether the autopilot steers the snake in the current game.
+    autopilot: bool,
+    /// Whether the autopilot has been on at any point of the current game, which then doesn't
+    /// count as the player's own.
+    autopilot_used: bool,
+    /// Whether the key showing how the autopilot decides is held.
+    show_plan: bool,
+    /// Whether the game has been paused for debugging or changed from the console at any point,
+    /// which then isn't recorded.
+    debug_used: bool,
+    /// Whether the paused game should advance by a tick on the next update.
+    step_requested: bool,
+    /// The developer console, if enabled.
+    console: Option<Console>,
+    /// The dialog over the screen, which takes the keys while it is open.
+    modal: Option<(Modal, Dialog)>,
+    console_open: bool,
+    /// Whether opening the console paused the game, which then resumes once it closes.
+    console_paused: bool,
+    /// Whether the window is minimized or covered by other windows, while which nothing is
+    /// drawn.
+    minimized: bool,
+    occluded: bool,
+    /// Whether the system has taken the window's surface away, as Android does for applications
+    /// in the background.
+    suspended: bool,
+    /// Whether hiding the window paused the game, which then resumes once it is seen again.
+    hidden_paused: bool,
+    /// Whether Ctrl is held, which turns keys into cheats while the console is enabled.
+    chord_held: bool,
+    /// The keys held down.
+    input: InputState,
+    /// Whether the last death could have been avoided, shown on the title screen.
+    last_death: Option<Verdict>,
+    /// Who won the last game of the territory mode, shown on the title screen.
+    last_winner: Option<Winner>,
+    /// Code of the last endless game, shown on the title screen so that it can be shared.
+    last_code: Option<String>,
+    /// Parameters of the game started from a code, which take the place of the settings until
+    /// it ends.
+    shared: Option<Config>,
+    /// Rules of the custom game being played, which go with the settings until it ends.
+    custom_rules: Option<Ruleset>,
+    /// Message over the board about what has just happened in the game, with the time when it
+    /// goes away.
+    announcement: Option<(String, Instant)>,
+    /// The room of the player's snake on a level with several, with the tick when it was there.
+    room: Option<(i32, u64)>,
+    /// The direction in which the player's snake went into the room that is sliding in, with the
+    /// time when it started.
+    room_slide: Option<(Direction, Instant)>,
+    effects: Effects,
+    /// The background of the title screen.
+    screensaver: Screensaver,
+    toasts: Toasts,
+    /// The level editor while its level is being played.
+    editing: Option<Box<Editor>>,
+    /// The pack and the index of its level being played.
+    campaign: Option<(Pack, usize)>,
+    /// The run through the pack of the campaign level, while its levels are cleared in order.
+    run: Option<Run>,
+    tutorial: Option<Tutorial>,
+    /// The file given with `--config` and its contents.
+    tuning: Option<(PathBuf, Tuning)>,
+    /// The palette of the theme with the colors from the `--config` file.
+    tuned_palette: Option<Palette>,
+    #[cfg(feature = "hot-reload")]
+    watcher: Option<Watcher>,
+    /// The file of the level being played, which is watched for changes.
+    #[cfg(feature = "hot-reload")]
+    level_file: Option<PathBuf>,
+    /// When the game in progress is saved next.
+    next_autosave: Instant,
+    /// Whether the game in progress has been saved.
+    autosaved: bool,
+    /// The title of the window as last set.
+    title: String,
+    /// When the title of the window shows the score next with the minimal HUD.
+    next_title: Instant,
+    /// Measures the frames while their times are shown, or all along with `--perf-report`.
+    profiler: Option<Profiler>,
+    /// Whether the times of the frames are shown.
+    show_profiler: bool,
+    /// Where to write the report of the profiler on exit.
+    perf_report: Option<PathBuf>,
+    /// Where food spawned and the player went in the games of the session.
+    heatmap: Heatmap,
+    /// Graphs of the game for the window of `--stats`.
+    live_stats: Option<LiveStats>,
+    /// Number of threads that draw the large boards, one unless `--render-threads` asks for
+    /// more.
+    render_threads: usize,
+    /// Size of the board of the last game, which the frame fits, see `canvas_size`.
+    board: (i32, i32),
+    /// Whether the demo starts when the title screen is left alone.
+    demo_enabled: bool,
+    /// Time of the last input, from which the title screen counts down to the demo.
+    idle_since: Instant,
+    /// Steers the snake in the current game by the votes in the chat.
+    #[cfg(feature = "chat")]
+    chat: Option<ChatControl>,
+    /// Commands from other programs, with `--remote`.
+    #[cfg(not(target_arch = "wasm32"))]
+    remote: Option<Remote>,
+    /// Where to save the next frame, as asked by the remote control.
+    #[cfg(not(target_arch = "wasm32"))]
+    screenshot: Option<PathBuf>,
+}
+
+impl App {
+    pub fn new(config: Config, seed: Option<u64>, profile_name: Option<&str>) -> Self {
+        let profile = match profile_name {
+            Some(name) => Profile::load(name),
+            None => Profile::load_last(),
+        };
+        i18n::set_language(profile.settings.language);
+        let menu = Menu::new("SNAKE", as_str_items(&main_menu_items(&profile)));
+        let audio = Audio::new();
+        audio.set_settings(&profile.settings.audio);
+        let screen = match Autosave::load() {
+            Some(save) => {
+                let items = [(tr("RESUME GAME"), true), (tr("DISCARD IT"), false)];
+                Screen::Resume {
+                    menu: Menu::new(&tr("RESUME?"), as_str_items(&items)),
+                    save,
+                }
+            }
+            None => Screen::Menu,
+        };
+        App {
+            config,
+            seed,
+            screen,
+            menu,
+            profile,
+            leaderboard: Leaderboard::load(),
+            audio,
+            touch: TouchControls::default(),
+            autopilot: false,
+            autopilot_used: false,
+            show_plan: false,
+            debug_used: false,
+            step_requested: false,
+            console: None,
+            modal: None,
+            console_open: false,
+            console_paused: false,
+            minimized: false,
+            occluded: false,
+            suspended: false,
+            hidden_paused: false,
+            chord_held: false,
+            input: InputState::default(),
+            last_death: None,
+            last_winner: None,
+            last_code: None,
+            shared: None,
+            custom_rules: None,
+            announcement: None,
+            room: None,
+            room_slide: None,
+            effects: Effects::default(),
+            screensaver: Screensaver::default(),
+            toasts: Toasts::default(),
+            editing: None,
+            campaign: None,
+            run: None,
+            tutorial: None,
+            tuning: None,
+            tuned_palette: None,
+            #[cfg(feature = "hot-reload")]
+            watcher: Watcher::new()
+                .map_err(|e| error!("Failed to start watching the files: {}", e))
+                .ok(),
+            #[cfg(feature = "hot-reload")]
+            level_file: None,
+            next_autosave: Instant::now(),
+            autosaved: false,
+            title: TITLE.to_string(),
+            next_title: Instant::now(),
+            profiler: None,
+            show_profiler: false,
+            perf_report: None,
+            heatmap: Heatmap::default(),
+            live_stats: None,
+            render_threads: 1,
+            board: (Config::default().width, Config::default().height),
+            demo_enabled: true,
+            idle_since: Instant::now(),
+            #[cfg(feature = "chat")]
+            chat: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            remote: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            screenshot: None,
+        }
+    }
+
+    /// Advances the game if one is in progress. Returns whether the screen needs to be redrawn.
+    pub fn update(&mut self) -> bool {
+        let start = Instant::now();
+        // The tick that is due now, which measures how late the event loop woke up for it.
+        let due = match &self.screen {
+            Screen::Playing(state) | Screen::Demo(state)
+                if !state.is_paused() && !state.world().is_over() =>
+            {
+                Some(state.next_wakeup()).filter(|&due| due <= start)
+            }
+            _ => None,
+        };
+        #[cfg(not(target_arch = "wasm32"))]
+        let commanded = self.run_remote_commands();
+        #[cfg(target_arch = "wasm32")]
+        let commanded = false;
+        self.update_sprint();
+        let toasts = self.toasts.update();
+        let changed = self.update_screen() || commanded || toasts;
+        if let (Some(profiler), Some(due)) = (&mut self.profiler, due) {
+            profiler.add_tick_latency(start - due);
+        }
+        if let (Some(live_stats), Screen::Playing(state) | Screen::Demo(state)) =
+            (&mut self.live_stats, &self.screen)
+        {
+            live_stats.add_tick(state);
+        }
+        if Instant::now() >= self.next_autosave {
+            self.autosave();
+        }
+        if let Some(profiler) = &mut self.profiler {
+            profiler.add_update(start.elapsed());
+        }
+        changed
+    }
+
+    fn update_screen(&mut self) -> bool {
+        #[cfg(feature = "hot-reload")]
+        if self.reload_changes() {
+            return true;
+        }
+        #[cfg(feature = "chat")]
+        let mut votes_changed = false;
+        #[cfg(feature = "chat")]
+        if let (Screen::Playing(state), Some(chat)) = (&mut self.screen, &mut self.chat) {
+            votes_changed = chat.update();
+            if !state.is_paused() && Instant::now() >= state.next_wakeup() {
+                if let Some(direction) = chat.close_vote() {
+                    state.turn(direction);
+                    self.audio.play(Sound::Turn);
+                }
+            }
+        }
+        let hidden = self.is_hidden();
+        let still = self.reduced_motion();
+        let update = match &mut self.screen {
+            Screen::Playing(state) => {
+                let update = if std::mem::take(&mut self.step_requested) {
+                    state.step()
+                } else {
+                    state.update()
+                };
+                // Pans the sound towards the side of the board where it happened.
+                let width = state.world().width();
+                let pan = |pos: Vec2| 2.0 * pos.0 as f32 / (width - 1).max(1) as f32 - 1.0;
+                let mut announcement = None;
+                let events = state.take_events();
+                if update != Update::Idle {
+                    self.heatmap.record(state.world(), &events);
+                }
+                for event in events {
+                    match event {
+                        Event::Ate { pos, .. } => self.audio.play_panned(Sound::Eat, pan(pos)),
+                        Event::AteRotten { pos, .. } => {
+                            self.audio.play_panned(Sound::Spoiled, pan(pos))
+                        }
+                        Event::AteGolden { snake, pos } => {
+                            self.audio.play_panned(Sound::Eat, pan(pos));
+                            if snake == 0 {
+                                let text = tr_fmt("BONUS +{}", &[&GOLDEN_POINTS]);
+                                announcement = Some(text);
+                            }
+                        }
+                        Event::Died { pos, .. } => self.audio.play_panned(Sound::Death, pan(pos)),
+                        Event::BoardFull | Event::TimeUp => self.audio.play(Sound::LevelClear),
+                        Event::FoodSpawned { .. } | Event::Dropped { .. } => (),
+                        Event::GoldenFoodSpawned { pos, .. } => {
+                            self.audio.play_panned(Sound::Golden, pan(pos));
+                            announcement = Some(tr("GOLDEN FOOD!"));
+                        }
+                        Event::GoldenFoodExpired { .. } => announcement = Some(tr("TOO LATE")),
+                        Event::Exploded { pos, .. } => {
+                            self.audio.play_panned(Sound::Explosion, pan(pos));
+                            self.effects.add_blast(pos);
+                        }
+                        Event::PassedThrough { pos, .. } => {
+                            self.audio.play_panned(Sound::Spoiled, pan(pos))
+                        }
+                        Event::CheckpointReached { snake, pos } => {
+                            if snake == 0 {
+                                self.audio.play_panned(Sound::Golden, pan(pos));
+                                announcement = Some(tr("CHECKPOINT"));
+                            }
+                        }
+                        Event::Respawned { snake, pos } => {
+                            if state.world().checkpoint(snake) == Some(pos) {
+                                announcement = Some(tr("BACK TO CHECKPOINT"));
+                            } else {
+                                let lives = state.world().lives().unwrap_or(0);
+                                announcement = Some(tr_fmt("LIVES LEFT: {}", &[&lives]));
+                            }
+                        }
+                    }
+                }
+                if let Some(text) = announcement {
+                    self.announcement = Some((text, Instant::now() + ANNOUNCEMENT_TIME));
+                }
+                let world = state.world();
+                if world.rooms() > 1 {
+                    let snake = &world.snakes()[0];
+                    let room = (world.room(snake.head), world.tick());
+                    // A new game starts over from an earlier tick.
+                    let entered =
+                        matches!(self.room, Some((last, tick)) if last != room.0 && tick < room.1);
+                    if entered && !still {
+                        self.room_slide = Some((snake.direction, Instant::now()));
+                    }
+                    self.room = Some(room);
+                }
+                let sliding = self
+                    .room_slide
+                    .is_some_and(|(_, start)| start.elapsed() < ROOM_SLIDE);
+                if !sliding {
+                    self.room_slide = None;
+                }
+                if (self.effects.update() || sliding) && update == Update::Idle {
+                    return true;
+                }
+                update
+            }
+            Screen::Demo(state) => {
+                let update = state.update();
+                state.take_events();
+                if update == Update::GameOver {
+                    self.show_menu();
+                }
+                return update != Update::Idle;
+            }
+            Screen::Online(game) => return game.update(),
+            Screen::Editor(editor) => return editor.update(),
+            Screen::Exhibition(exhibition) => return exhibition.update(),
+            Screen::Menu
+                if self.demo_enabled
+                    && self.modal.is_none()
+                    && Instant::now() >= self.idle_since + DEMO_DELAY =>
+            {
+                self.start_demo();
+                return true;
+            }
+            Screen::Menu if self.demo_enabled && !hidden && !still => {
+                return self.screensaver.update()
+            }
+            _ => Update::Idle,
+        };
+        // The tally on the screen has to follow the votes.
+        #[cfg(feature = "chat")]
+        let update = match update {
+            Update::Idle if votes_changed => Update::Changed,
+            update => update,
+        };
+        if update == Update::Changed {
+            self.update_tutorial();
+        }
+        if update == Update::Changed && self.level_goal_reached() {
+            self.complete_level();
+            return true;
+        }
+        match update {
+            Update::Idle => false,
+            Update::Changed => true,
+            // A practice game waits to be rewound, until the player leaves it.
+            Update::GameOver if self.practicing() => {
+                self.audio.stop_music();
+                true
+            }
+            Update::GameOver => {
+                if let Screen::Playing(state) = &self.screen {
+                    self.last_winner = state.world().territory().map(|cells| winner(&cells));
+                    let died = matches!(
+                        state.record().map(|record| record.end_reason),
+                        Some(EndReason::Wall | EndReason::Tail | EndReason::Snake)
+                    );
+                    if died {
+                        let verdict = solver::analyze_death(state.history(), 0);
+                        info!("Death analysis: {:?}", verdict);
+                        self.last_death = Some(verdict);
+                    }
+                }
+                self.finish_game();
+                true
+            }
+        }
+    }
+
+    /// The earliest moment at which `update` will have something to do, if any.
+    pub fn next_wakeup(&self) -> Option<Instant> {
+        let wakeup = match &self.screen {
+            Screen::Playing(_) if self.step_requested => Some(Instant::now()),
+            Screen::Playing(state) if state.is_paused() || state.world().is_over() => None,
+            Screen::Playing(state) => Some(state.next_wakeup()),
+            // Nobody would see the demo.
+            Screen::Demo(_) | Screen::Menu if self.is_hidden() => None,
+            Screen::Demo(state) => Some(state.next_wakeup()),
+            Screen::Menu if self.demo_enabled => {
+                let frame = Some(self.screensaver.next_frame()).filter(|_| !self.reduced_motion());
+                let demo = Some(self.idle_since + DEMO_DELAY).filter(|_| self.modal.is_none());
+                frame.into_iter().chain(demo).min()
+            }
+            Screen::Online(game) => Some(game.next_wakeup()),
+            Screen::Editor(editor) => editor.next_frame(),
+            Screen::Exhibition(exhibition) => exhibition.next_wakeup(),
+            _ => None,
+        };
+        // Neither would the animations.
+        let frame = IntoIterator::into_iter([
+            self.effects.next_frame(),
+            self.toasts.next_frame(),
+            self.room_slide.map(|_| Instant::now() + SLIDE_FRAME),
+        ])
+        .flatten()
+        .min()
+        .filter(|_| !self.is_hidden());
+        let wakeup = match (wakeup, frame) {
+            (Some(wakeup), Some(frame)) => Some(wakeup.min(frame)),
+            (wakeup, frame) => wakeup.or(frame),
+        };
+        #[cfg(not(target_arch = "wasm32"))]
+        let wakeup = match &self.remote {
+            Some(_) => {
+                let poll = Instant::now() + REMOTE_POLL;
+                Some(wakeup.map_or(poll, |wakeup| wakeup.min(poll)))
+            }
+            None => wakeup,
+        };
+        #[cfg(feature = "hot-reload")]
+        if self.tuning.is_some() || self.level_file.is_some() {
+            let reload = Instant::now() + RELOAD_INTERVAL;
+            return Some(wakeup.map_or(reload, |wakeup| wakeup.min(reload)));
+        }
+        wakeup
+    }
+
+    fn palette(&self) -> &Palette {
+        if self.profile.settings.high_contrast {
+            return Palette::high_contrast();
+        }
+        match &self.tuned_palette {
+            Some(palette) => palette,
+            None => self.profile.settings.theme.palette(),
+        }
+    }
+
+    /// Shows the whole board with room for its border around it, unless the high-contrast mode
+    /// enlarges the cells. Of a stacked board, shows the layer of the player's snake, and of a
+    /// level with several rooms its room, sliding in after the snake has just entered it.
+    fn camera(&self, buffer: &FrameBuffer, world: &World) -> Camera {
+        let size = world.extent();
+        let camera = if self.profile.settings.high_contrast {
+            let head = world.snakes()[0].head;
+            let head = Vec2(head.0, head.1.rem_euclid(world.layer_height()));
+            Camera::follow(buffer, size, LARGE_CELL, head).with_border(CELL_BORDER)
+        } else {
+            let border = self.board_border();
+            let camera = Camera::fit(buffer, (size.0 + 2 * border, size.1 + 2 * border));
+            let margin = border * camera.cell;
+            Camera {
+                origin: camera.origin + Vec2(margin, margin),
+                ..camera
+            }
+        };
+        let camera = world.focus(camera.with_shape(world.shape()));
+        let camera = camera.with_still(self.reduced_motion());
+        match self.room_slide {
+            Some((direction, start)) => {
+                let left = 1.0 - start.elapsed().as_secs_f32() / ROOM_SLIDE.as_secs_f32();
+                let shift = |cells: i32, step: i32| {
+                    (left.max(0.0) * (step * cells * camera.cell) as f32) as i32
+                };
+                let Vec2(dx, dy) = direction.vec();
+                Camera {
+                    origin: camera.origin + Vec2(shift(size.0, dx), shift(size.1, dy)),
+                    ..camera
+                }
+            }
+            None => camera,
+        }
+    }
+
+    /// Whether the animations are replaced or left out, as the tuning or else the profile has it.
+    fn reduced_motion(&self) -> bool {
+        self.tuning
+            .as_ref()
+            .and_then(|(_, tuning)| tuning.reduced_motion)
+            .unwrap_or(self.profile.settings.reduced_motion)
+    }
+
+    /// Thickness of the frame around the board in cells.
+    fn board_border(&self) -> i32 {
+        self.profile.settings.border.min(MAX_BOARD_BORDER) as i32
+    }
+
+    /// Draws the current screen and shows it.
+    pub fn render(&mut self, renderer: &mut dyn Renderer) -> Result<(), Box<dyn Error>> {
+        let start = Instant::now();
+        self.draw(renderer);
+        let drawn = Instant::now();
+        #[cfg(not(target_arch = "wasm32"))]
+        if let (Some(path), Some(remote)) = (self.screenshot.take(), &self.remote) {
+            let saved = remote::save_screenshot(&path, renderer.buffer_mut());
+            remote.reply(saved.map(|()| "ok".to_string()));
+        }
+        if let Some(profiler) = self.profiler.as_ref().filter(|_| self.show_profiler) {
+            profiler.render(renderer.buffer_mut(), self.palette());
+        }
+        let result = renderer.present();
+        if let Some(profiler) = &mut self.profiler {
+            profiler.add_frame(drawn - start, drawn.elapsed());
+        }
+        if let Some(live_stats) = &mut self.live_stats {
+            live_stats.add_frame();
+        }
+        result
+    }
+
+    /// Draws the graphs of `--stats` and shows them.
+    pub fn render_live_stats(&self, renderer: &mut dyn Renderer) -> Result<(), Box<dyn Error>> {
+        if let Some(live_stats) = &self.live_stats {
+            live_stats.render(renderer.buffer_mut(), self.palette());
+        }
+        renderer.present()
+    }
+
+    /// The new title of the window, if it has to change: the score and the length of the
+    /// player's snake once a second during a game with the minimal HUD, and just the name of the
+    /// game otherwise.
+    pub fn window_title(&mut self) -> Option<String> {
+        let title = match &self.screen {
+            Screen::Playing(state) if self.profile.settings.minimal_hud => {
+                if Instant::now() < self.next_title {
+                    return None;
+                }
+                self.next_title = Instant::now() + TITLE_INTERVAL;
+                let world = state.world();
+                let score: u32 = if world.is_coop() {
+                    world.snakes().iter().map(|s| s.score).sum()
+                } else {
+                    world.snakes()[0].score
+                };
+                let text = tr_fmt("SCORE {} LENGTH {}", &[&score, &state.length()]);
+                format!("{} - {}", TITLE, text)
+            }
+            _ => TITLE.to_string(),
+        };
+        if title == self.title {
+            return None;
+        }
+        self.title = title.clone();
+        Some(title)
+    }
+
+    fn draw(&self, renderer: &mut dyn Renderer) {
+        let palette = self.palette();
+        if let Screen::Playing(state) | Screen::Demo(state) = &self.screen {
+            let world = state.world();
+            let camera = self.camera(renderer.buffer_mut(), world);
+            let skins = [self.profile.settings.skin];
+            let effects = matches!(self.screen, Screen::Playing(_)).then_some(&self.effects);
+            let threads = if world.width() * world.height() >= PARALLEL_CELLS {
+                self.render_threads
+            } else {
+                1
+            };
+            match renderer.layers_mut() {
+                // The cells go over everything else drawn into the buffer.
+                (buffer, Some(quads)) => {
+                    world.render_with_quads(buffer, quads, palette, &camera, &skins);
+                    if let Some(effects) = effects {
+                        effects.render(buffer, &camera, palette.selected);
+                    }
+                }
+                (buffer, None) => buffer.draw_in_bands(threads, |buffer| {
+                    world.render_with_camera(buffer, palette, &camera, &skins);
+                    if let Some(effects) = effects {
+                        effects.render(buffer, &camera, palette.selected);
+                    }
+                }),
+            }
+            let buffer = renderer.buffer_mut();
+            world.render_border(buffer, palette, &camera, self.board_border());
+        }
+        let buffer = renderer.buffer_mut();
+        match &self.screen {
+            Screen::Menu => {
+                buffer.clear(palette.menu_bg);
+                if self.demo_enabled && !self.reduced_motion() {
+                    self.screensaver.render(buffer, palette);
+                }
+                self.menu.render_over(buffer, palette);
+                let text = match (self.last_winner, self.last_death) {
+                    (Some(Winner::Player), _) => tr("YOU WIN"),
+                    (Some(Winner::Bot(id)), _) => tr_fmt("BOT {} WINS", &[&id]),
+                    (Some(Winner::Draw), _) => tr("DRAW"),
+                    (None, Some(Verdict::Avoidable { ticks_before: 1 })) => {
+                        tr("THE LAST MOVE WAS FATAL")
+                    }
+                    (None, Some(Verdict::Avoidable { ticks_before })) => {
+                        tr_fmt("DEATH AVOIDABLE {} MOVES BACK", &[&ticks_before])
+                    }
+                    (None, Some(Verdict::Unavoidable)) => tr("LAST DEATH WAS UNAVOIDABLE"),
+                    (None, None) => String::new(),
+                };
+                // Above the line of the MUTED indicator.
+                let y = GLYPH_HEIGHT + 1 + LINE_HEIGHT;
+                draw_text_centered(buffer, y, &text, 1, palette.dim_text);
+                // On the left of the indicator.
+                if let Some(code) = &self.last_code {
+                    let text = format!("#{}", code);
+                    draw_text(buffer, 1, GLYPH_HEIGHT + 1, &text, 1, palette.dim_text);
+                }
+            }
+            Screen::Playing(state) => {
+                if self.profile.settings.danger_zones {
+                    let world = state.world();
+                    let camera = self.camera(buffer, world);
+                    render_danger(buffer, &camera, &solver::lethal_cells(world, 0));
+                }
+                if self.heatmap.is_shown() {
+                    let world = state.world();
+                    let camera = self.camera(buffer, world);
+                    self.heatmap.render(buffer, palette, &camera, world);
+                }
+                if self.autopilot && self.show_plan {
+                    let world = state.world();
+                    let camera = self.camera(buffer, world);
+                    autopilot::plan(world, 0).render(buffer, &camera, palette);
+                }
+                #[cfg(feature = "chat")]
+                if let Some(chat) = &self.chat {
+                    chat.render(buffer, palette);
+                }
+                // With the minimal HUD only the prompts that the game waits for stay.
+                let hud = !self.profile.settings.minimal_hud;
+                if self.autopilot && hud {
+                    let y = buffer.height() as i32 - 1;
+                    draw_text(buffer, 1, y, &tr("AUTOPILOT"), 1, palette.dim_text);
+                }
+                if let Some(cells) = state.world().territory().filter(|_| hud) {
+                    let skin = self.profile.settings.skin;
+                    render_territory_bar(buffer, palette, &cells, skin, state);
+                }
+                if let Some(lives) = state.world().lives().filter(|_| hud) {
+                    let score: u32 = state.world().snakes().iter().map(|s| s.score).sum();
+                    let text = tr_fmt("SCORE {} LIVES {}", &[&score, &lives]);
+                    let y = buffer.height() as i32 - 1;
+                    draw_text(buffer, 1, y, &text, 1, palette.text);
+                }
+                if let Some((text, until)) = self.announcement.as_ref().filter(|_| hud) {
+                    if Instant::now() < *until {
+                        let y = buffer.height() as i32 - 1 - LINE_HEIGHT;
+                        draw_text_centered(buffer, y, text, 1, palette.selected);
+                    }
+                }
+                // Reminds that the game won't count.
+                if self.debug_used {
+                    let scale = state.time_scale();
+                    let text = if scale == 1.0 {
+                        "DEBUG".to_string()
+                    } else {
+                        format!("DEBUG {}X", scale)
+                    };
+                    let x = buffer.width() as i32 - text_width(&text) - 1;
+                    let y = buffer.height() as i32 - 1;
+                    draw_text(buffer, x, y, &text, 1, palette.dim_text);
+                } else if state.is_practice() && hud {
+                    let text = tr_fmt("PRACTICE SEED {}", &[&state.seed()]);
+                    let x = buffer.width() as i32 - text_width(&text) - 1;
+                    let y = buffer.height() as i32 - 1;
+                    draw_text(buffer, x, y, &text, 1, palette.dim_text);
+                }
+                if self.profile.settings.speedrun_timer {
+                    let run = match (&self.run, &self.campaign) {
+                        (Some(run), Some((pack, _))) => Some((run, &pack.manifest)),
+                        _ => None,
+                    };
+                    speedrun::render(buffer, palette, self.game_time(state), run);
+                }
+                if state.is_practice() && state.world().is_over() {
+                    let y = (buffer.height() as i32 + GLYPH_HEIGHT) / 2;
+                    draw_text_centered(buffer, y, &tr("BACKSPACE TO REWIND"), 1, palette.text);
+                }
+                if let Some(tutorial) = &self.tutorial {
+                    let camera = self.camera(buffer, state.world());
+                    tutorial.render(buffer, palette, &camera, state.world());
+                } else if state.is_paused() {
+                    render_debug_dump(buffer, palette, state);
+                }
+            }
+            Screen::Demo(_) => {
+                let y = (buffer.height() as i32 - GLYPH_HEIGHT) / 2;
+                draw_text_centered(buffer, y, &tr("PRESS ANY KEY"), 1, palette.text);
+            }
+            Screen::Online(game) => game.render(buffer, palette),
+            Screen::Editor(editor) => editor.render(buffer, palette),
+            Screen::Stats { notice } => {
+                self.profile
+                    .stats
+                    .render(buffer, palette, self.profile.achievements.len());
+                draw_text_centered(buffer, 10 + LINE_HEIGHT, notice, 1, palette.dim_text);
+            }
+            Screen::Profiles { menu, .. } => menu.render(buffer, palette),
+            Screen::NewProfile(entry) | Screen::EnterCode(entry) => entry.render(buffer, palette),
+            Screen::Customize(customizer) => customizer.render(buffer, palette),
+            Screen::CustomGame(custom) => custom.render(buffer, palette),
+            Screen::Exhibition(exhibition) => exhibition.render(buffer, palette),
+            Screen::Sound(menu) => menu.render(buffer, palette),
+            Screen::Controls(menu) => menu.render(buffer, palette),
+            Screen::Resume { menu, save } => {
+                menu.render(buffer, palette);
+                let y = GLYPH_HEIGHT + 1 + LINE_HEIGHT;
+                let text = tr_fmt("SCORE {}", &[&save.score]);
+                draw_text_centered(buffer, y, &text, 1, palette.dim_text);
+            }
+            Screen::Campaigns { menu, .. } => menu.render(buffer, palette),
+            Screen::Campaign { menu, pack, notice } => {
+                menu.render(buffer, palette);
+                if let Some(progress) = self.profile.campaigns.get(&pack.dir) {
+                    for (i, y, right) in menu.visible(buffer) {
+                        if let Some(medal) = pack.manifest.levels[i].best_medal(progress) {
+                            render_medal(buffer, palette, right + 3, y, medal);
+                        }
+                    }
+                }
+                let y = GLYPH_HEIGHT + 1 + LINE_HEIGHT;
+                draw_text_centered(buffer, y, notice, 1, palette.dim_text);
+            }
+            #[cfg(any(feature = "lua", feature = "plugins"))]
+            Screen::Mods { menu, .. } => menu.render(buffer, palette),
+            Screen::Scores { page } => self.leaderboard.render(buffer, palette, *page),
+            Screen::HighScore { entry, .. } => entry.render(buffer, palette),
+        }
+        if let Some((modal, _)) = &self.modal {
+            modal.render(buffer, palette);
+        }
+        self.toasts.render(buffer, palette, self.reduced_motion());
+        if let Some(console) = self.console.as_ref().filter(|_| self.console_open) {
+            console.render(buffer, palette);
+        }
+        if self.profile.settings.audio.muted {
+            let muted = tr("MUTED");
+            let x = buffer.width() as i32 - text_width(&muted) - 1;
+            draw_text(buffer, x, GLYPH_HEIGHT + 1, &muted, 1, palette.dim_text);
+        }
+        self.touch.render(buffer, palette);
+    }
+
+    /// Handles a key going down on the keyboard, where `repeat` tells whether the system
+    /// repeats a held key. The repeats of the keys that steer a snake are dropped, so that they
+    /// don't queue the same turn over and over, while the menus scroll with them. Returns `true`
+    /// if the application should exit.
+    pub fn on_key_down(&mut self, keycode: KeyCode, repeat: bool) -> bool {
+        let first = self.input.press(keycode) && !repeat;
+        if !first && self.steers(keycode) {
+            return false;
+        }
+        self.on_keypress(keycode)
+    }
+
+    /// Whether the key turns a snake on the current screen.
+    fn steers(&self, keycode: KeyCode) -> bool {
+        let bindings = &self.profile.settings.key_bindings;
+        match &self.screen {
+            Screen::Playing(state) => {
+                bindings.direction(keycode).is_some()
+                    || (state.world().is_coop()
+                        && KeyBindings::second_player().direction(keycode).is_some())
+            }
+            Screen::Online(_) => bindings.direction(keycode).is_some(),
+            _ => false,
+        }
+    }
+
+    /// Called when the window loses the keyboard focus, after which the keys held down won't be
+    /// heard released.
+    pub fn on_focus_lost(&mut self) {
+        self.input.clear();
+        self.chord_held = false;
+    }
+
+    /// Makes the player's snake sprint while the key of its direction is held, if the player
+    /// wants.
+    fn update_sprint(&mut self) {
+        let steered = self.chat_controlled() || self.autopilot;
+        if let Screen::Playing(state) = &mut self.screen {
+            let settings = &self.profile.settings;
+            let direction = state.world().snakes()[0].direction;
+            let held = self.input.held_direction(&settings.key_bindings);
+            state.set_sprinting(settings.hold_to_sprint && !steered && held == Some(direction));
+        }
+    }
+
+    /// Returns `true` if the application should exit.
+    pub fn on_keypress(&mut self, keycode: KeyCode) -> bool {
+        self.idle_since = Instant::now();
+        if keycode == PLAN_KEY {
+            self.show_plan = true;
+            return false;
+        }
+        if keycode == profiler::TOGGLE_KEY {
+            self.show_profiler = !self.show_profiler;
+            if self.show_profiler {
+                self.profiler.get_or_insert_with(Profiler::default);
+            } else if self.perf_report.is_none() {
+                self.profiler = None;
+            }
+            return false;
+        }
+        if keycode == heatmap::TOGGLE_KEY {
+            self.heatmap.toggle();
+            return false;
+        }
+        if let Some(console) = &mut self.console {
+            if self.console_open {
+                match console.on_keypress(keycode) {
+                    ConsoleEvent::Run(command) => {
+                        let output = self.run_command(command);
+                        if let Some(console) = &mut self.console {
+                            console.print(output);
+                        }
+                    }
+                    ConsoleEvent::Close => self.toggle_console(),
+                    ConsoleEvent::None => (),
+                }
+                return false;
+            } else if keycode == console::TOGGLE_KEY {
+                self.toggle_console();
+                return false;
+            } else if console::is_chord_key(keycode) {
+                self.chord_held = true;
+                return false;
+            } else if let Some(command) = Command::chord(keycode).filter(|_| self.chord_held) {
+                let output = self.run_command(command);
+                if let Some(console) = &mut self.console {
+                    console.print(output);
+                }
+                return false;
+            }
+        }
+        if let Some((modal, dialog)) = &mut self.modal {
+            match (modal.on_keypress(keycode), *dialog) {
+                (ModalEvent::None, _) => (),
+                (ModalEvent::Confirmed, Dialog::Quit) => return true,
+                (ModalEvent::Captured(key), Dialog::Rebind(direction)) => {
+                    info!("Binding {:?} to {:?}", key, direction);
+                    let bindings = &mut self.profile.settings.key_bindings;
+                    bindings.bind(direction, key);
+                    self.profile.save();
+                    if let Screen::Controls(menu) = &mut self.screen {
+                        let items = controls_menu_items(&self.profile.settings.key_bindings);
+                        menu.set_items(as_str_items(&items));
+                    }
+                    self.modal = None;
+                }
+                _ => self.modal = None,
+            }
+            return false;
+        }
+        let chat_controlled = self.chat_controlled();
+        // M is a letter on the name entry screens, may be bound to a direction, and like any
+        // other key stops the demo.
+        if keycode == KeyCode::KeyM
+            && self
+                .profile
+                .settings
+                .key_bindings
+                .direction(keycode)
+                .is_none()
+            && !matches!(
+                self.screen,
+                Screen::NewProfile(_)
+                    | Screen::EnterCode(_)
+                    | Screen::HighScore { .. }
+                    | Screen::Demo(_)
+                    | Screen::Editor(_)
+            )
+        {
+            self.change_audio_settings(|settings| settings.muted = !settings.muted);
+            return false;
+        }
+        let in_menu = matches!(
+            self.screen,
+            Screen::Menu
+                | Screen::Resume { .. }
+                | Screen::Profiles { .. }
+                | Screen::Sound(_)
+                | Screen::Controls(_)
+                | Screen::Campaigns { .. }
+                | Screen::Campaign { .. }
+        );
+        #[cfg(any(feature = "lua", feature = "plugins"))]
+        let in_menu = in_menu || matches!(self.screen, Screen::Mods { .. });
+        if in_menu {
+            match keycode {
+                KeyCode::ArrowUp | KeyCode::ArrowDown => self.audio.play(Sound::MenuMove),
+                KeyCode::Enter | KeyCode::NumpadEnter | KeyCode::Space => {
+                    self.audio.play(Sound::MenuSelect)
+                }
+                _ => (),
+            }
+        }
+        match &mut self.screen {
+            Screen::Menu => match self.menu.on_keypress(keycode) {
+                Some(MenuAction::Play) if !self.profile.settings.tutorial_done => {
+                    self.start_tutorial()
+                }
+                Some(MenuAction::Play) => self.start_game(),
+                Some(MenuAction::Tutorial) => self.start_tutorial(),
+                Some(MenuAction::Practice) => self.start_practice(),
+                Some(MenuAction::CustomGame) => {
+                    self.screen = Screen::CustomGame(Box::new(self.custom_game()))
+                }
+                Some(MenuAction::EnterCode) => {
+                    let entry = NameEntry::new(&tr("ENTER CODE"), "", MAX_CODE_LEN);
+                    self.screen = Screen::EnterCode(entry);
+                }
+                Some(MenuAction::Campaign) => self.show_campaigns(),
+                #[cfg(feature = "chat")]
+                Some(MenuAction::ChatPlay) => {
+                    match ChatControl::connect(&self.profile.settings.chat) {
+                        Ok(chat) => {
+                            self.start_game();
+                            self.chat = Some(chat);
+                        }
+                        Err(e) => error!("Failed to connect to the chat: {}", e),
+                    }
+                }
+                #[cfg(any(feature = "lua", feature = "plugins"))]
+                Some(MenuAction::Mods) => {
+                    let mods = Mod::list();
+                    let items: Vec<(String, usize)> = mods
+                        .iter()
+                        .enumerate()
+                        .map(|(i, m)| (m.name().to_uppercase(), i))
+                        .collect();
+                    self.screen = Screen::Mods {
+                        menu: Menu::new(&tr("MODS"), as_str_items(&items)),
+                        mods,
+                    };
+                }
+                Some(MenuAction::Editor) => {
+                    let (width, height) = (self.config.width, self.config.height);
+                    let canvas = (width * CELL_SIZE, height * CELL_SIZE);
+                    let notifier = self.toasts.notifier();
+                    let editor = Editor::new(width, height, canvas, notifier);
+                    self.screen = Screen::Editor(Box::new(editor));
+                }
+                Some(MenuAction::BotMatch) => {
+                    let exhibition = Exhibition::new(self.game_config());
+                    self.screen = Screen::Exhibition(Box::new(exhibition));
+                }
+                Some(MenuAction::Mode) => {
+                    self.profile.settings.mode = self.profile.settings.mode.next();
+                    self.profile.save();
+                    self.update_menu();
+                }
+                Some(MenuAction::Difficulty) => {
+                    self.profile.settings.difficulty = self.profile.settings.difficulty.next();
+                    self.profile.save();
+                    self.update_menu();
+                }
+                Some(MenuAction::Grid) => {
+                    self.profile.settings.grid = self.profile.settings.grid.next();
+                    self.profile.save();
+                    self.update_menu();
+                }
+                Some(MenuAction::Layers) => {
+                    let layers = &mut self.profile.settings.layers;
+                    *layers = *layers % MAX_LAYERS + 1;
+                    self.profile.save();
+                    self.update_menu();
+                }
+                Some(MenuAction::Scores) => self.screen = Screen::Scores { page: 0 },
+                Some(MenuAction::Stats) => {
+                    self.screen = Screen::Stats {
+                        notice: String::new(),
+                    }
+                }
+                Some(MenuAction::Profiles) => self.show_profiles(),
+                Some(MenuAction::Snake) => {
+                    let skin = self.profile.settings.skin;
+                    self.screen = Screen::Customize(Customizer::new(skin));
+                }
+                Some(MenuAction::Theme) => {
+                    self.profile.settings.theme = self.profile.settings.theme.next();
+                    self.profile.save();
+                    self.update_menu();
+                    self.tune_palette();
+                }
+                Some(MenuAction::HighContrast) => {
+                    let settings = &mut self.profile.settings;
+                    settings.high_contrast = !settings.high_contrast;
+                    self.profile.save();
+                    self.update_menu();
+                }
+                Some(MenuAction::ReducedMotion) => {
+                    let settings = &mut self.profile.settings;
+                    settings.reduced_motion = !settings.reduced_motion;
+                    self.profile.save();
+                    self.update_menu();
+                }
+                Some(MenuAction::DangerZones) => {
+                    let settings = &mut self.profile.settings;
+                    settings.danger_zones = !settings.danger_zones;
+                    self.profile.save();
+                    self.update_menu();
+                }
+                Some(MenuAction::MinimalHud) => {
+                    let settings = &mut self.profile.settings;
+                    settings.minimal_hud = !settings.minimal_hud;
+                    self.profile.save();
+                    self.update_menu();
+                }
+                Some(MenuAction::PauseHidden) => {
+                    let settings = &mut self.profile.settings;
+                    settings.pause_hidden = !settings.pause_hidden;
+                    self.profile.save();
+                    self.update_menu();
+                }
+                Some(MenuAction::Border) => {
+                    let border = &mut self.profile.settings.border;
+                    *border = (*border + 1) % (MAX_BOARD_BORDER + 1);
+                    self.profile.save();
+                    self.update_menu();
+                }
+                Some(MenuAction::SpeedrunTimer) => {
+                    let settings = &mut self.profile.settings;
+                    settings.speedrun_timer = !settings.speedrun_timer;
+                    self.profile.save();
+                    self.update_menu();
+                }
+                Some(MenuAction::HoldToSprint) => {
+                    let settings = &mut self.profile.settings;
+                    settings.hold_to_sprint = !settings.hold_to_sprint;
+                    self.profile.save();
+                    self.update_menu();
+                }
+                Some(MenuAction::Scale) => {
+                    let settings = &mut self.profile.settings;
+                    settings.ui_scale = settings.ui_scale % MAX_UI_SCALE + 1;
+                    self.profile.save();
+                    self.update_menu();
+                }
+                Some(MenuAction::Language) => {
+                    let settings = &mut self.profile.settings;
+                    settings.language = settings.language.next();
+                    i18n::set_language(settings.language);
+                    self.profile.save();
+                    self.update_menu();
+                }
+                Some(MenuAction::Sound) => {
+                    let items = sound_menu_items(&self.profile.settings.audio);
+                    self.screen = Screen::Sound(Menu::new(&tr("SOUND"), as_str_items(&items)));
+                }
+                Some(MenuAction::Controls) => {
+                    let items = controls_menu_items(&self.profile.settings.key_bindings);
+                    let menu = Menu::new(&tr("CONTROLS"), as_str_items(&items));
+                    self.screen = Screen::Controls(menu);
+                }
+                Some(MenuAction::Quit) => {
+                    self.modal = Some((Modal::confirm(&tr("QUIT THE GAME?")), Dialog::Quit))
+                }
+                None if keycode == KeyCode::Escape => {
+                    self.modal = Some((Modal::confirm(&tr("QUIT THE GAME?")), Dialog::Quit))
+                }
+                None => (),
+            },
+            Screen::Playing(state) => {
+                let lesson_shown = matches!(&self.tutorial, Some(tutorial) if tutorial.is_shown());
+                if lesson_shown && keycode != KeyCode::Escape {
+                    // Any key goes on with the game, and the arrows turn right away.
+                    if self.tutorial.as_mut().is_some_and(Tutorial::dismiss) {
+                        self.finish_game();
+                    } else {
+                        state.set_paused(false);
+                        if let Some(direction) =
+                            self.profile.settings.key_bindings.direction(keycode)
+                        {
+                            state.turn(direction);
+                            self.audio.play(Sound::Turn);
+                        }
+                    }
+                } else if keycode == KeyCode::Escape {
+                    self.finish_game();
+                } else if keycode == REWIND_KEY && state.is_practice() {
+                    let over = state.world().is_over();
+                    if state.rewind() {
+                        if over {
+                            self.audio.play_music();
+                        }
+                        self.effects.clear();
+                        self.announcement =
+                            Some((tr("REWIND"), Instant::now() + ANNOUNCEMENT_TIME));
+                    }
+                } else if let Some(direction) = KeyBindings::second_player()
+                    .direction(keycode)
+                    .filter(|_| state.world().is_coop())
+                {
+                    state.turn_player(1, direction);
+                    self.audio.play(Sound::Turn);
+                } else if let Some(direction) =
+                    self.profile.settings.key_bindings.direction(keycode)
+                {
+                    if !chat_controlled && !self.autopilot {
+                        state.turn(direction);
+                        self.audio.play(Sound::Turn);
+                    }
+                } else if keycode == RESTART_KEY && !state.is_modded() {
+                    self.restart_game();
+                } else if keycode == FRAME_STEP_KEY {
+                    state.set_paused(!state.is_paused());
+                    self.debug_used = true;
+                    info!(
+                        "Frame step {}",
+                        if state.is_paused() { "on" } else { "off" }
+                    );
+                } else if keycode == STEP_KEY && state.is_paused() {
+                    self.step_requested = true;
+                } else if (FASTER_KEYS.contains(&keycode) || SLOWER_KEYS.contains(&keycode))
+                    && (self.debug_used || self.console.is_some())
+                {
+                    let factor = if FASTER_KEYS.contains(&keycode) {
+                        2.0
+                    } else {
+                        0.5
+                    };
+                    state.set_time_scale(state.time_scale() * factor);
+                    self.debug_used = true;
+                    info!("Time scale {}x", state.time_scale());
+                } else if keycode == KeyCode::KeyA && !chat_controlled {
+                    self.autopilot = !self.autopilot;
+                    self.autopilot_used |= self.autopilot;
+                    if self.autopilot {
+                        state.set_controller(0, Box::new(Autopilot));
+                    } else {
+                        state.set_controller(0, Box::new(Keyboard::default()));
+                    }
+                    info!("Autopilot {}", if self.autopilot { "on" } else { "off" });
+                }
+            }
+            Screen::Resume { menu, .. } => match menu.on_keypress(keycode) {
+                Some(resume) => {
+                    if let Screen::Resume { save, .. } =
+                        std::mem::replace(&mut self.screen, Screen::Menu)
+                    {
+                        if resume {
+                            self.resume_game(&save);
+                        } else {
+                            Autosave::remove();
+                        }
+                    }
+                }
+                // Asks again on the next run.
+                None if keycode == KeyCode::Escape => self.screen = Screen::Menu,
+                None => (),
+            },
+            Screen::Demo(_) => self.show_menu(),
+            Screen::Online(game) => {
+                if keycode == KeyCode::Escape {
+                    game.leave();
+                    self.screen = Screen::Menu;
+                } else if !game.on_keypress(keycode) {
+                    if let Some(direction) = self.profile.settings.key_bindings.direction(keycode) {
+                        game.turn(direction);
+                    }
+                }
+            }
+            Screen::Editor(editor) => match editor.on_keypress(keycode) {
+                EditorEvent::PlayTest(level) => self.play_test(&level),
+                EditorEvent::Exit => self.screen = Screen::Menu,
+                EditorEvent::None => (),
+            },
+            Screen::Stats { notice } => match keycode {
+                KeyCode::Escape => self.screen = Screen::Menu,
+                KeyCode::KeyE => *notice = self.profile.export_history(),
+                _ => {}
+            },
+            Screen::Profiles { menu, names } => match menu.on_keypress(keycode) {
+                Some(ProfileChoice::Existing(i)) => {
+                    let name = names[i].clone();
+                    self.switch_profile(&name);
+                }
+                Some(ProfileChoice::New) => {
+                    self.screen =
+                        Screen::NewProfile(NameEntry::new(&tr("NEW PROFILE"), "", MAX_NAME_LEN))
+                }
+                None if keycode == KeyCode::Escape => self.screen = Screen::Menu,
+                None => (),
+            },
+            Screen::NewProfile(entry) => match entry.on_keypress(keycode) {
+                NameEntryEvent::Confirmed(name) => self.switch_profile(&name),
+                NameEntryEvent::Cancelled => self.show_profiles(),
+                NameEntryEvent::None => (),
+            },
+            Screen::EnterCode(entry) => match entry.on_keypress(keycode) {
+                NameEntryEvent::Confirmed(code) => match share::decode(&code) {
+                    Ok((config, seed)) => self.start_shared(config, seed),
+                    Err(e) => {
+                        warn!("Can't play the game of the code {}: {}", code, e);
+                        *entry = NameEntry::new(&tr(e.label()), &code, MAX_CODE_LEN);
+                    }
+                },
+                NameEntryEvent::Cancelled => self.show_menu(),
+                NameEntryEvent::None => (),
+            },
+            Screen::Customize(customizer) => match customizer.on_keypress(keycode) {
+                CustomizeEvent::Confirmed(skin) => {
+                    self.profile.settings.skin = skin;
+                    self.profile.save();
+                    self.screen = Screen::Menu;
+                }
+                CustomizeEvent::Cancelled => self.screen = Screen::Menu,
+                CustomizeEvent::None => (),
+            },
+            Screen::CustomGame(custom) => match custom.on_keypress(keycode) {
+                CustomGameEvent::None => (),
+                event => {
+                    let settings = &mut self.profile.settings;
+                    settings.mode = custom.mode();
+                    settings.rules = custom.rules().clone();
+                    let (rules, seed) = (custom.config().rules, custom.seed());
+                    self.profile.save();
+                    self.update_menu();
+                    match event {
+                        CustomGameEvent::Play => self.start_custom_game(rules, seed),
+                        _ => self.screen = Screen::Menu,
+                    }
+                }
+            },
+            Screen::Exhibition(exhibition) => match exhibition.on_keypress(keycode) {
+                ExhibitionEvent::None => (),
+                ExhibitionEvent::Exit => self.screen = Screen::Menu,
+            },
+            Screen::Controls(menu) => match menu.on_keypress(keycode) {
+                Some(direction) => {
+                    let label = match direction {
+                        Direction::Up => "UP",
+                        Direction::Down => "DOWN",
+                        Direction::Left => "LEFT",
+                        _ => "RIGHT",
+                    };
+                    let title = tr_fmt("KEY FOR {}", &[&tr(label)]);
+                    self.modal = Some((Modal::key_capture(&title), Dialog::Rebind(direction)));
+                }
+                None if keycode == KeyCode::Escape => self.screen = Screen::Menu,
+                None => (),
+            },
+            Screen::Sound(menu) => match menu.on_keypress(keycode) {
+                Some(setting) => self.change_audio_settings(|settings| match setting {
+                    SoundSetting::Master => {
+                        settings.master = AudioSettings::next_volume(settings.master)
+                    }
+                    SoundSetting::Music => {
+                        settings.music = AudioSettings::next_volume(settings.music)
+                    }
+                    SoundSetting::Effects => {
+                        settings.effects = AudioSettings::next_volume(settings.effects)
+                    }
+                    SoundSetting::Mute => settings.muted = !settings.muted,
+                }),
+                None if keycode == KeyCode::Escape => self.screen = Screen::Menu,
+                None => (),
+            },
+            Screen::Campaigns { menu, packs } => match menu.on_keypress(keycode) {
+                Some(i) => {
+                    let pack = packs[i].clone();
+                    self.screen = self.campaign_screen(pack, String::new());
+                }
+                None if keycode == KeyCode::Escape => self.screen = Screen::Menu,
+                None => (),
+            },
+            Screen::Campaign { menu, pack, notice } => match menu.on_keypress(keycode) {
+                Some(i) => {
+                    let progress = self.profile.campaigns.get(&pack.dir);
+                    let unlocked = match progress {
+                        Some(progress) => pack.manifest.is_unlocked(i, progress),
+                        None => pack.manifest.is_unlocked(i, &PackProgress::new()),
+                    };
+                    if !unlocked {
+                        *notice = unlock_hint(pack.manifest.levels[i].unlock);
+                    } else if let Some(level) = pack.load_level(i) {
+                        let pack = pack.clone();
+                        self.play_campaign(pack, i, &level);
+                    } else {
+                        *notice = tr("THE LEVEL FAILED TO LOAD");
+                    }
+                }
+                None if keycode == KeyCode::Escape => self.show_campaigns(),
+                None => (),
+            },
+            #[cfg(any(feature = "lua", feature = "plugins"))]
+            Screen::Mods { menu, mods } => match menu.on_keypress(keycode) {
+                Some(i) => match mods[i].load() {
+                    Ok(hooks) => {
+                        self.start_game();
+                        if let Screen::Playing(state) = &mut self.screen {
+                            state.set_hooks(hooks);
+                        }
+                    }
+                    Err(e) => error!("Failed to load mod {}: {}", mods[i].name(), e),
+                },
+                None if keycode == KeyCode::Escape => self.screen = Screen::Menu,
+                None => (),
+            },
+            Screen::Scores { page } => match keycode {
+                KeyCode::ArrowLeft => *page = page.saturating_sub(1),
+                KeyCode::ArrowRight => *page = (*page + 1).min(self.leaderboard.pages() - 1),
+                KeyCode::Escape | KeyCode::Enter => self.screen = Screen::Menu,
+                _ => (),
+            },
+            Screen::HighScore {
+                entry,
+                mode,
+                difficulty,
+                score,
+            } => match entry.on_keypress(keycode) {
+                NameEntryEvent::Confirmed(name) => {
+                    let mut score = score.clone();
+                    score.name = name;
+                    let page = self.leaderboard.insert(*mode, *difficulty, score);
+                    self.leaderboard.save();
+                    self.screen = Screen::Scores { page };
+                }
+                NameEntryEvent::Cancelled => self.screen = Screen::Menu,
+                NameEntryEvent::None => (),
+            },
+        }
+        false
+    }
+
+    /// Returns `true` if the screen needs to be redrawn.
+    pub fn on_keyrelease(&mut self, keycode: KeyCode) -> bool {
+        self.input.release(keycode);
+        if console::is_chord_key(keycode) {
+            self.chord_held = false;
+        }
+        if keycode == PLAN_KEY {
+            self.show_plan = false;
+            return self.autopilot;
+        }
+        false
+    }
+
+    /// Handles a touch at the given pixel of the frame buffer. Returns the key press that it
+    /// stands for, if any.
+    pub fn on_touch(&mut self, id: u64, phase: TouchPhase, pos: Vec2) -> Option<KeyCode> {
+        self.touch.on_touch(id, phase, pos)
+    }
+
+    /// Returns `true` if the screen needs to be redrawn.
+    pub fn on_cursor_moved(&mut self, pos: Vec2) -> bool {
+        match &mut self.screen {
+            Screen::Editor(editor) => editor.on_cursor_moved(pos),
+            Screen::Online(game) => game.on_cursor_moved(pos),
+            _ => false,
+        }
+    }
+
+    /// Returns `true` if the screen needs to be redrawn.
+    pub fn on_mouse_button(&mut self, button: MouseButton, pressed: bool) -> bool {
+        self.idle_since = Instant::now();
+        match &mut self.screen {
+            Screen::Editor(editor) => editor.on_mouse_button(button, pressed),
+            Screen::Online(game) if button == MouseButton::Middle => {
+                game.on_middle_button(pressed);
+                false
+            }
+            _ => false,
+        }
+    }
+
+    /// Handles a turn of the mouse wheel by the given number of notches, positive away from the
+    /// user. Returns `true` if the screen needs to be redrawn.
+    pub fn on_mouse_wheel(&mut self, notches: f32) -> bool {
+        self.idle_since = Instant::now();
+        match &mut self.screen {
+            Screen::Editor(editor) => editor.on_mouse_wheel(notches),
+            Screen::Online(game) => game.on_mouse_wheel(notches),
+            _ => false,
+        }
+    }
+
+    /// Size of the frame in pixels: cells of `CELL_SIZE` on the board of the last game, within
+    /// the size for the default board and `MAX_CANVAS`. It stays after the game, so that the
+    /// window doesn't change its size back and forth between the games.
+    pub fn canvas_size(&self) -> (u32, u32) {
+        let default = Config::default();
+        let side = |cells: i32, default: i32| {
+            let default = default * CELL_SIZE;
+            (cells * CELL_SIZE).clamp(default, MAX_CANVAS.max(default)) as u32
+        };
+        (
+            side(self.board.0, default.width),
+            side(self.board.1, default.height),
+        )
+    }
+
+    /// Lets the developer console be opened with the backtick key.
+    pub fn enable_console(&mut self) {
+        self.console = Some(Console::default());
+    }
+
+    /// Measures the frames and the ticks from the start, to write the histograms of their times
+    /// to `path` on exit.
+    pub fn enable_perf_report(&mut self, path: &Path) {
+        self.profiler = Some(Profiler::default());
+        self.perf_report = Some(path.to_path_buf());
+    }
+
+    /// Collects the graphs for the window of `--stats`.
+    pub fn enable_live_stats(&mut self) {
+        self.live_stats = Some(LiveStats::new());
+    }
+
+    /// Lets other programs control the game over the standard input, see `remote`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn enable_remote(&mut self) {
+        self.remote = Some(Remote::new());
+    }
+
+    /// Runs the commands that arrived from the remote control, returning whether the screen has
+    /// to be redrawn.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn run_remote_commands(&mut self) -> bool {
+        let commands = match &self.remote {
+            Some(remote) => remote.commands(),
+            None => return false,
+        };
+        let mut changed = false;
+        for command in commands {
+            let answer = match command {
+                Ok(RemoteCommand::State) => {
+                    if let Some(remote) = &self.remote {
+                        remote.reply_state(&self.game_state());
+                    }
+                    continue;
+                }
+                // Answered once the next frame is saved.
+                Ok(RemoteCommand::Screenshot(path)) => {
+                    self.screenshot = Some(path);
+                    changed = true;
+                    continue;
+                }
+                Ok(command) => self.run_remote_command(command),
+                Err(reason) => Err(reason),
+            };
+            changed |= answer.is_ok();
+            if let Some(remote) = &self.remote {
+                remote.reply(answer.map(|()| "ok".to_string()));
+            }
+        }
+        changed
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn run_remote_command(&mut self, command: RemoteCommand) -> Result<(), String> {
+        let steered = self.chat_controlled() || self.autopilot;
+        match (command, &mut self.screen) {
+            (RemoteCommand::Start, Screen::Menu) => {
+                self.start_game();
+                Ok(())
+            }
+            (RemoteCommand::Start, _) => Err("not on the title screen".to_string()),
+            (_, Screen::Playing(state)) if state.world().is_over() => {
+                Err("the game is over".to_string())
+            }
+            (RemoteCommand::Turn(_), Screen::Playing(_)) if steered => {
+                Err("the snake is steered by someone else".to_string())
+            }
+            (RemoteCommand::Turn(direction), Screen::Playing(state)) => {
+                state.turn(direction);
+                Ok(())
+            }
+            (RemoteCommand::Pause, Screen::Playing(state)) => {
+                state.set_paused(true);
+                Ok(())
+            }
+            (RemoteCommand::Resume, Screen::Playing(state)) => {
+                state.set_paused(false);
+                Ok(())
+            }
+            _ => Err("no game in progress".to_string()),
+        }
+    }
+
+    /// What the remote control learns with `state?`.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn game_state(&self) -> GameState {
+        let (screen, state) = match &self.screen {
+            Screen::Menu => ("menu", None),
+            Screen::Playing(state) => ("playing", Some(state)),
+            _ => ("other", None),
+        };
+        GameState {
+            screen,
+            paused: state.is_some_and(|state| state.is_paused()),
+            over: state.is_some_and(|state| state.world().is_over()),
+            game: state.map(|state| state.view(0).snapshot()),
+        }
+    }
+
+    pub fn set_render_threads(&mut self, threads: usize) {
+        self.render_threads = threads.max(1);
+    }
+
+    pub fn set_minimized(&mut self, minimized: bool) {
+        self.minimized = minimized;
+        self.on_visibility_changed();
+    }
+
+    pub fn set_occluded(&mut self, occluded: bool) {
+        self.occluded = occluded;
+        self.on_visibility_changed();
+    }
+
+    /// Called when the system takes the surface away. The game is paused and stays paused once
+    /// the application comes back, since the player may have forgotten about it in the meantime.
+    pub fn suspend(&mut self) {
+        self.suspended = true;
+        if let Screen::Playing(state) = &mut self.screen {
+            if !state.is_paused() {
+                info!("Pausing the suspended game");
+                state.set_paused(true);
+            }
+        }
+        self.hidden_paused = false;
+        self.console_paused = false;
+    }
+
+    /// Called when the application gets a surface to draw on again.
+    pub fn resume(&mut self) {
+        self.suspended = false;
+        self.on_visibility_changed();
+    }
+
+    /// Whether the window can't be seen, so that there is no point in drawing.
+    pub fn is_hidden(&self) -> bool {
+        self.minimized || self.occluded || self.suspended
+    }
+
+    /// Pauses the game while the window is hidden, unless the player wants it to go on.
+    fn on_visibility_changed(&mut self) {
+        let hidden = self.is_hidden();
+        if !hidden {
+            // The title screen waits for the player again before the demo.
+            self.idle_since = Instant::now();
+        }
+        if let Screen::Playing(state) = &mut self.screen {
+            if hidden && !state.is_paused() && self.profile.settings.pause_hidden {
+                info!("Pausing the game while the window is hidden");
+                state.set_paused(true);
+                self.hidden_paused = true;
+            } else if !hidden && self.hidden_paused {
+                state.set_paused(false);
+                self.hidden_paused = false;
+            }
+        }
+    }
+
+    /// Opens or closes the console, pausing the game while it is open.
+    fn toggle_console(&mut self) {
+        self.console_open = !self.console_open;
+        if let Screen::Playing(state) = &mut self.screen {
+            if self.console_open && !state.is_paused() {
+                state.set_paused(true);
+                self.console_paused = true;
+            } else if !self.console_open && self.console_paused {
+                state.set_paused(false);
+                self.console_paused = false;
+            }
+        }
+    }
+
+    /// Runs a command from the console and returns its output.
+    fn run_command(&mut self, command: Command) -> String {
+        info!("Console command: {:?}", command);
+        match command {
+            Command::SetTick(ms) => {
+                self.config.tick_ms = ms;
+                self.retune_game();
+                format!("TICK {} MS", ms)
+            }
+            Command::Slow => match &mut self.screen {
+                Screen::Playing(state) => {
+                    let slow = state.time_scale() >= 1.0;
+                    state.set_time_scale(if slow { 1.0 / SLOW_MOTION } else { 1.0 });
+                    self.debug_used = true;
+                    format!("SLOW MOTION {}", if slow { "ON" } else { "OFF" })
+                }
+                _ => "NO GAME IN PROGRESS".to_string(),
+            },
+            Command::Seed(seed) => {
+                self.seed = Some(seed);
+                format!("SEED {} FROM THE NEXT GAME", seed)
+            }
+            Command::SpawnFood(pos) => match self.debug_world().map(|w| w.place_food(pos)) {
+                Some(true) => "FOOD PLACED".to_string(),
+                Some(false) => "THE CELL ISN'T EMPTY".to_string(),
+                None => "NO GAME IN PROGRESS".to_string(),
+            },
+            Command::Teleport(pos) => match self.debug_world().map(|w| w.teleport(0, pos)) {
+                Some(true) => "TELEPORTED".to_string(),
+                Some(false) => "THE SNAKE DOESN'T FIT THERE".to_string(),
+                None => "NO GAME IN PROGRESS".to_string(),
+            },
+            Command::God => match self.debug_world() {
+                Some(world) => {
+                    let god = !world.is_invulnerable(0);
+                    world.set_invulnerable(0, god);
+                    format!("GOD MODE {}", if god { "ON" } else { "OFF" })
+                }
+                None => "NO GAME IN PROGRESS".to_string(),
+            },
+            Command::Grow(segments) => match self.debug_world() {
+                Some(world) => {
+                    world.grow(0, segments);
+                    format!("GROWING BY {}", segments)
+                }
+                None => "NO GAME IN PROGRESS".to_string(),
+            },
+            Command::ClearFood => match self.debug_world() {
+                Some(world) => {
+                    world.clear_food();
+                    "FOOD CLEARED".to_string()
+                }
+                None => "NO GAME IN PROGRESS".to_string(),
+            },
+        }
+    }
+
+    /// Applies the changed parameters to the game in progress, which then isn't recorded.
+    fn retune_game(&mut self) {
+        let config = self.game_config();
+        if let Screen::Playing(state) = &mut self.screen {
+            state.retune(&config);
+            self.debug_used = true;
+        }
+    }
+
+    /// The board of the game in progress, to be changed from the console.
+    fn debug_world(&mut self) -> Option<&mut World> {
+        match &mut self.screen {
+            Screen::Playing(state) => {
+                self.debug_used = true;
+                Some(state.world_mut())
+            }
+            _ => None,
+        }
+    }
+
+    /// Keeps the title screen from starting the demo and from animating its background, for runs
+    /// where nobody is watching.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn disable_demo(&mut self) {
+        self.demo_enabled = false;
+    }
+
+    /// How many logical pixels of the window a pixel of the frame buffer takes.
+    pub fn ui_scale(&self) -> u32 {
+        self.profile.settings.ui_scale.clamp(1, MAX_UI_SCALE)
+    }
+
+    /// Called before the application exits.
+    pub fn quit(&mut self) {
+        match &mut self.screen {
+            Screen::Playing(_) => self.finish_game(),
+            Screen::Online(game) => game.leave(),
+            _ => (),
+        }
+        // Don't lose a high score when the window is closed during the name entry.
+        if let Screen::HighScore {
+            entry,
+            mode,
+            difficulty,
+            score,
+        } = &self.screen
+        {
+            let mut score = score.clone();
+            score.name = entry.text();
+            self.leaderboard.insert(*mode, *difficulty, score);
+            self.leaderboard.save();
+        }
+        if let (Some(path), Some(profiler)) = (&self.perf_report, &self.profiler) {
+            match std::fs::write(path, profiler.report()) {
+                Ok(()) => info!("Wrote the performance report to {}", path.display()),
+                Err(e) => error!("Failed to write {}: {}", path.display(), e),
+            }
+        }
+    }
+
+    /// Uses the game parameters and colors from the file instead of the defaults.
+    pub fn load_tuning(&mut self, path: &Path) {
+        match Tuning::load(path) {
+            Ok(tuning) => {
+                info!("Loaded the tuning from {}", path.display());
+                self.set_tuning(path, tuning);
+                #[cfg(feature = "hot-reload")]
+                if let Some(watcher) = &mut self.watcher {
+                    watcher.watch(path);
+                }
+            }
+            Err(e) => error!("Failed to load {}: {}", path.display(), e),
+        }
+    }
+
+    fn set_tuning(&mut self, path: &Path, tuning: Tuning) {
+        if let Some(config) = &tuning.config {
+            self.config = config.clone();
+        }
+        self.tuning = Some((path.to_path_buf(), tuning));
+        self.tune_palette();
+        let config = self.game_config();
+        if let Screen::Playing(state) = &mut self.screen {
+            state.retune(&config);
+        }
+    }
+
+    fn tune_palette(&mut self) {
+        let theme = self.profile.settings.theme.palette();
+        self.tuned_palette = match &self.tuning {
+            Some((_, tuning)) if !tuning.colors.is_empty() => Some(tuning.palette(theme)),
+            _ => None,
+        };
+    }
+
+    /// Watches the file of the level being played for changes, instead of the previous one. The
+    /// path is inside the data directory.
+    fn watch_level(&mut self, file: Option<PathBuf>) {
+        #[cfg(feature = "hot-reload")]
+        {
+            let file = file.and_then(|file| Some(storage::data_dir()?.join(file)));
+            if let Some(watcher) = &mut self.watcher {
+                if let Some(old) = &self.level_file {
+                    watcher.unwatch(old);
+                }
+                if let Some(new) = &file {
+                    watcher.watch(new);
+                }
+            }
+            self.level_file = file;
+        }
+        #[cfg(not(feature = "hot-reload"))]
+        let _ = file;
+    }
+
+    /// Applies the changes to the watched files. Returns whether the screen needs to be redrawn.
+    #[cfg(feature = "hot-reload")]
+    fn reload_changes(&mut self) -> bool {
+        let changed = match &mut self.watcher {
+            Some(watcher) => watcher.changed(),
+            None => return false,
+        };
+        let mut redraw = false;
+        for file in changed {
+            if matches!(&self.tuning, Some((path, _)) if *path == file) {
+                match Tuning::load(&file) {
+                    Ok(tuning) => {
+                        info!("Reloaded {}", file.display());
+                        self.set_tuning(&file, tuning);
+                        redraw = true;
+                    }
+                    Err(e) => error!("Failed to reload {}: {}", file.display(), e),
+                }
+            }
+            if self.level_file.as_ref() == Some(&file) {
+                let level = std::fs::read_to_string(&file)
+                    .map_err(|e| e.to_string())
+                    .and_then(|text| Level::load(&text).map_err(|e| e.to_string()));
+                match (level, &mut self.screen) {
+                    (Ok(level), Screen::Playing(state)) => {
+                        if state.set_layout(&level) {
+                            info!("Reloaded {}", file.display());
+                            redraw = true;
+                        } else {
+                            warn!("{} no longer fits the board", file.display());
+                        }
+                    }
+                    (Ok(_), _) => (),
+                    (Err(e), _) => error!("Failed to reload {}: {}", file.display(), e),
+                }
+            }
+        }
+        redraw
+    }
+
+    /// Joins the matches hosted by `snake_server` at the given address, either as a player or
+    /// as a spectator.
+    pub fn connect(&mut self, address: &str, spectator: bool) {
+        match OnlineGame::connect(
+            address,
+            self.profile.name(),
+            self.profile.settings.skin,
+            spectator,
+            self.toasts.notifier(),
+        ) {
+            Ok(game) => self.screen = Screen::Online(Box::new(game)),
+            Err(e) => error!("Failed to connect to {}: {}", address, e),
+        }
+    }
+
+    fn show_menu(&mut self) {
+        self.idle_since = Instant::now();
+        self.screen = Screen::Menu;
+    }
+
+    fn update_menu(&mut self) {
+        self.menu
+            .set_items(as_str_items(&main_menu_items(&self.profile)));
+    }
+
+    /// Applies a change to the audio settings of the profile and saves them.
+    fn change_audio_settings(&mut self, change: impl FnOnce(&mut AudioSettings)) {
+        let settings = &mut self.profile.settings.audio;
+        change(settings);
+        self.audio.set_settings(settings);
+        if let Screen::Sound(menu) = &mut self.screen {
+            menu.set_items(as_str_items(&sound_menu_items(settings)));
+        }
+        self.profile.save();
+    }
+
+    fn show_profiles(&mut self) {
+        let names = Profile::list();
+        let mut items: Vec<(String, ProfileChoice)> = names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| (name.clone(), ProfileChoice::Existing(i)))
+            .collect();
+        items.push((tr("NEW PROFILE"), ProfileChoice::New));
+        self.screen = Screen::Profiles {
+            menu: Menu::new(&tr("PROFILE"), as_str_items(&items)),
+            names,
+        };
+    }
+
+    fn show_campaigns(&mut self) {
+        let packs = Pack::list();
+        let items = campaign_items(&packs, &self.profile);
+        self.screen = Screen::Campaigns {
+            menu: Menu::new(&tr("CAMPAIGN"), as_str_items(&items)),
+            packs,
+        };
+    }
+
+    fn campaign_screen(&self, pack: Pack, notice: String) -> Screen {
+        let items = match self.profile.campaigns.get(&pack.dir) {
+            Some(progress) => campaign_level_items(&pack, progress),
+            None => campaign_level_items(&pack, &PackProgress::new()),
+        };
+        let title = pack.manifest.name.to_uppercase();
+        Screen::Campaign {
+            menu: Menu::new(&title, as_str_items(&items)),
+            pack,
+            notice,
+        }
+    }
+
+    fn switch_profile(&mut self, name: &str) {
+        self.profile.save();
+        self.profile = Profile::load(name);
+        self.audio.set_settings(&self.profile.settings.audio);
+        i18n::set_language(self.profile.settings.language);
+        self.tune_palette();
+        self.update_menu();
+        self.screen = Screen::Menu;
+    }
+
+    fn chat_controlled(&self) -> bool {
+        #[cfg(feature = "chat")]
+        return self.chat.is_some();
+        #[cfg(not(feature = "chat"))]
+        false
+    }
+
+    fn practicing(&self) -> bool {
+        matches!(&self.screen, Screen::Playing(state) if state.is_practice())
+    }
+
+    /// Configuration of a game played by the player.
+    fn game_config(&self) -> Config {
+        if let Some(config) = &self.shared {
+            return config.clone();
+        }
+        Config {
+            mode: self.profile.settings.mode,
+            difficulty: self.profile.settings.difficulty,
+            shape: self.profile.settings.grid,
+            layers: self.profile.settings.layers.clamp(1, MAX_LAYERS),
+            rules: self.custom_rules.unwrap_or_default(),
+            ..self.config.clone()
+        }
+    }
+
+    fn start_game(&mut self) {
+        let seed = self.seed.unwrap_or_else(rand::random);
+        let state = State::new(&self.game_config(), seed);
+        self.begin_game(state);
+    }
+
+    /// Plays the level from the editor, returning to the editor once the game is over.
+    fn play_test(&mut self, level: &Level) {
+        if let Screen::Editor(editor) = std::mem::replace(&mut self.screen, Screen::Menu) {
+            self.editing = Some(editor);
+        }
+        let seed = self.seed.unwrap_or_else(rand::random);
+        let state = State::with_level(&self.game_config(), level, seed);
+        self.begin_game(state);
+        let file = self.editing.as_ref().and_then(|editor| editor.file());
+        self.watch_level(file);
+    }
+
+    /// Starts the game of a code that someone shared.
+    fn start_shared(&mut self, config: Config, seed: u64) {
+        let state = State::new(&config, seed);
+        self.begin_game(state);
+        self.shared = Some(config);
+    }
+
+    /// The custom game screen with the rules that the profile last set.
+    fn custom_game(&self) -> CustomGame {
+        let seed = self.seed.unwrap_or_else(rand::random);
+        let rules = self.profile.settings.rules.clone();
+        CustomGame::new(self.game_config(), rules, seed, self.toasts.notifier())
+    }
+
+    /// Opens the custom game screen with the saved ruleset picked, for `--ruleset`.
+    pub fn open_ruleset(&mut self, name: &str) {
+        let mut custom = self.custom_game();
+        match custom.pick(name) {
+            Ok(()) => {
+                info!("Picked ruleset {}", name);
+                self.screen = Screen::CustomGame(Box::new(custom));
+            }
+            Err(e) => error!("Failed to load ruleset {}: {}", name, e),
+        }
+    }
+
+    /// Starts a game with the custom rules from the seed of the board that the custom game screen
+    /// showed.
+    fn start_custom_game(&mut self, rules: Ruleset, seed: u64) {
+        let config = Config {
+            rules,
+            ..self.game_config()
+        };
+        self.begin_game(State::new(&config, seed));
+        self.custom_rules = Some(rules);
+    }
+
+    /// Starts a game that can be rewound, with the seed shown so that it can be played again.
+    fn start_practice(&mut self) {
+        let seed = self.seed.unwrap_or_else(rand::random);
+        let mut state = State::new(&self.game_config(), seed);
+        state.set_practice();
+        self.begin_game(state);
+    }
+
+    fn start_tutorial(&mut self) {
+        let seed = self.seed.unwrap_or_else(rand::random);
+        let state = State::with_level(&self.game_config(), &Tutorial::level(), seed);
+        self.begin_game(state);
+        self.tutorial = Some(Tutorial::new());
+        self.update_tutorial();
+    }
+
+    /// Pauses the game to explain whatever has come up in the tutorial for the first time.
+    fn update_tutorial(&mut self) {
+        if let (Screen::Playing(state), Some(tutorial)) = (&mut self.screen, &mut self.tutorial) {
+            if tutorial.update(state.world()) {
+                state.set_paused(true);
+            }
+        }
+    }
+
+    fn play_campaign(&mut self, pack: Pack, index: usize, level: &Level) {
+        let seed = self.seed.unwrap_or_else(rand::random);
+        let state = State::with_level(&self.game_config(), level, seed);
+        self.begin_game(state);
+        self.watch_level(Some(pack.level_file(index)));
+        self.run = match self.run.take() {
+            Some(run) if run.pack() == pack.dir && run.next_level() == index => Some(run),
+            _ if index == 0 => Some(Run::new(&pack.dir, &self.profile.splits)),
+            _ => None,
+        };
+        self.campaign = Some((pack, index));
+    }
+
+    /// Starts the game being played over with the same seed, on the same level, leaving out the
+    /// game that was given up from the statistics.
+    fn restart_game(&mut self) {
+        let (seed, practice, time) = match &self.screen {
+            Screen::Playing(state) => (state.seed(), state.is_practice(), self.game_time(state)),
+            _ => return,
+        };
+        let level = if let Some((pack, index)) = &self.campaign {
+            match pack.load_level(*index) {
+                Some(level) => Some(level),
+                None => return,
+            }
+        } else if let Some(editor) = &self.editing {
+            Some(editor.level().clone())
+        } else if self.tutorial.is_some() {
+            Some(Tutorial::level())
+        } else {
+            None
+        };
+        info!("Restarting the game with seed {}", seed);
+        if std::mem::take(&mut self.autosaved) {
+            Autosave::remove();
+        }
+        let config = self.game_config();
+        let mut state = match &level {
+            Some(level) => State::with_level(&config, level, seed),
+            None => State::new(&config, seed),
+        };
+        if practice {
+            state.set_practice();
+        }
+        // A shared game or a custom one is the same game again.
+        let (shared, custom_rules) = (self.shared.take(), self.custom_rules.take());
+        self.begin_game(state);
+        self.shared = shared;
+        self.custom_rules = custom_rules;
+        if let Some(run) = &mut self.run {
+            run.restart(time);
+        }
+        if self.tutorial.is_some() {
+            self.tutorial = Some(Tutorial::new());
+            self.update_tutorial();
+        }
+    }
+
+    /// Seconds of game time since the start of the game.
+    fn game_time(&self, state: &State) -> f64 {
+        state.world().tick() as f64 * self.game_config().tick().as_secs_f64()
+    }
+
+    /// Whether the player has eaten enough food to complete the campaign level being played.
+    fn level_goal_reached(&self) -> bool {
+        match (&self.screen, &self.campaign) {
+            (Screen::Playing(state), Some((pack, index))) => {
+                state.world().snakes()[0].score >= pack.manifest.levels[*index].goal
+            }
+            _ => false,
+        }
+    }
+
+    /// Records the completion of the campaign level being played and returns to its pack.
+    fn complete_level(&mut self) {
+        let (pack, index) = match self.campaign.take() {
+            Some(campaign) => campaign,
+            None => return,
+        };
+        self.audio.stop_music();
+        self.audio.play(Sound::LevelClear);
+        self.watch_level(None);
+        let (time, length) = match &self.screen {
+            Screen::Playing(state) => (self.game_time(state), state.world().snakes()[0].length()),
+            _ => (0.0, 0),
+        };
+        let level = &pack.manifest.levels[index];
+        let mut notice = tr_fmt("CLEAR IN {}S", &[&time.ceil()]);
+        if let Some(par) = level.par {
+            notice = tr_fmt("CLEAR IN {}S PAR {}S", &[&time.ceil(), &par]);
+        }
+        if let Some(medal) = level.medal(time, length) {
+            notice = tr_fmt("CLEAR IN {}S {}", &[&time.ceil(), &tr(medal.name())]);
+        }
+        if self.autopilot_used {
+            notice = tr("NOT COUNTED WITH AUTOPILOT");
+            self.run = None;
+        } else {
+            let progress = self.profile.campaigns.entry(pack.dir.clone()).or_default();
+            if campaign::record_completion(progress, level, time, length) {
+                info!("Best time on {} of {}: {:.1}s", level.file, pack.dir, time);
+            }
+            if let Some(run) = &mut self.run {
+                if run.split(time, &mut self.profile.splits) {
+                    let split = speedrun::format_time(run.time(0.0));
+                    info!("Best split on {} of {}: {}", level.file, pack.dir, split);
+                }
+            }
+            self.profile.save();
+        }
+        self.autopilot = false;
+        self.idle_since = Instant::now();
+        self.screen = self.campaign_screen(pack, notice);
+    }
+
+    /// Continues the game from the last run.
+    fn resume_game(&mut self, save: &Autosave) {
+        match save.restore() {
+            Some(state) => {
+                info!("Resuming the game at tick {}", save.ticks);
+                self.begin_game(state);
+                self.autopilot_used = save.autopilot_used;
+                // The save is of this game now.
+                self.autosaved = true;
+            }
+            None => Autosave::remove(),
+        }
+    }
+
+    /// Saves the game in progress, unless it can't be replayed from its turns or doesn't count
+    /// anyway.
+    fn autosave(&mut self) {
+        self.next_autosave = Instant::now() + autosave::INTERVAL;
+        let state = match &self.screen {
+            // Only the turns of the first player are saved.
+            Screen::Playing(state)
+                if !state.is_modded()
+                    && !state.is_practice()
+                    && !state.world().is_over()
+                    && !state.world().is_coop() =>
+            {
+                state
+            }
+            _ => return,
+        };
+        if self.chat_controlled()
+            || self.debug_used
+            || self.editing.is_some()
+            || self.campaign.is_some()
+            || self.tutorial.is_some()
+            || self.tuning.is_some()
+            || self.custom_rules.is_some()
+        {
+            return;
+        }
+        Autosave {
+            config: self.game_config(),
+            seed: state.seed(),
+            ticks: state.world().tick(),
+            turns: state.turns().to_vec(),
+            score: state.world().snakes()[0].score,
+            autopilot_used: self.autopilot_used,
+        }
+        .save();
+        self.autosaved = true;
+    }
+
+    fn begin_game(&mut self, state: State) {
+        self.board = (state.world().width(), state.world().height());
+        self.screen = Screen::Playing(Box::new(state));
+        self.next_autosave = Instant::now() + autosave::INTERVAL;
+        self.autosaved = false;
+        self.autopilot = false;
+        self.autopilot_used = false;
+        self.debug_used = false;
+        self.step_requested = false;
+        self.console_paused = false;
+        self.hidden_paused = false;
+        self.last_death = None;
+        self.last_winner = None;
+        self.last_code = None;
+        self.shared = None;
+        self.custom_rules = None;
+        self.announcement = None;
+        self.effects.clear();
+        self.audio.play_music();
+    }
+
+    fn start_demo(&mut self) {
+        info!("Starting the demo");
+        let config = Config {
+            difficulty: Difficulty::Hard,
+            ..self.config.clone()
+        };
+        let state = State::with_controllers(&config, rand::random(), vec![Box::new(Autopilot)]);
+        self.screen = Screen::Demo(Box::new(state));
+    }
+
+    /// Records the current game in the statistics and returns to the menu, or asks for the
+    /// player's initials if the game made it to the leaderboard.
+    fn finish_game(&mut self) {
+        self.audio.stop_music();
+        if std::mem::take(&mut self.autosaved) {
+            Autosave::remove();
+        }
+        self.watch_level(None);
+        // Unranked games don't count towards the lifetime stats, the achievements, the replay or
+        // the leaderboard. Games steered by the chat or the autopilot, or with the debug keys used,
+        // aren't the player's own, while modded games, custom games, practice games, games with
+        // tuned parameters and tests of a level from the editor follow different rules. Campaign
+        // levels and the tutorial are left out on purpose too: their boards are made by hand and
+        // a level ends once its goal is reached, so their scores can't be compared with those
+        // of the modes. The campaign keeps its own progress in the profile instead.
+        let editing = self.editing.take();
+        let campaign = self.campaign.take();
+        let custom = self.custom_rules.take().is_some();
+        // A run only goes on through the levels that are cleared.
+        self.run = None;
+        let tutorial = self.tutorial.take();
+        if tutorial.is_some() && !self.profile.settings.tutorial_done {
+            self.profile.settings.tutorial_done = true;
+            self.profile.save();
+        }
+        let unranked = self.chat_controlled()
+            || self.autopilot_used
+            || self.debug_used
+            || editing.is_some()
+            || campaign.is_some()
+            || tutorial.is_some()
+            || custom
+            || self.tuning.is_some()
+            || self.practicing()
+            || matches!(&self.screen, Screen::Playing(state) if state.is_modded());
+        let config = self.game_config();
+        let mut endless = false;
+        if let Screen::Playing(state) = &self.screen {
+            endless = editing.is_none()
+                && campaign.is_none()
+                && tutorial.is_none()
+                && !custom
+                && !state.is_modded();
+            if endless {
+                let code = share::encode(&config, state.seed());
+                info!("Code of the game: {}", code);
+                self.last_code = Some(code);
+            }
+        }
+        self.shared = None;
+        self.autopilot = false;
+        #[cfg(feature = "chat")]
+        {
+            self.chat = None;
+        }
+        let mut next_screen = match (editing, campaign) {
+            (Some(editor), _) => Screen::Editor(editor),
+            (None, Some((pack, _))) => self.campaign_screen(pack, String::new()),
+            (None, None) => Screen::Menu,
+        };
+        if let Screen::Playing(state) = &mut self.screen {
+            state.end(EndReason::Quit);
+            if let Some(record) = state.record().filter(|_| !unranked) {
+                let stats = &self.profile.stats;
+                if stats.games_played > 0 && record.score > stats.best_score {
+                    self.toasts.post(Notice::HighScore(record.score));
+                }
+                for achievement in self.profile.record_game(record) {
+                    self.toasts.post(Notice::Achievement(achievement));
+                }
+                // Only the turns of the first player can be played again.
+                if endless && !state.world().is_coop() {
+                    storage::save(REPLAY_FILE, &Replay::of(&config, state));
+                }
+                if self
+                    .leaderboard
+                    .qualifies(record.mode, record.difficulty, record.score)
+                {
+                    let initials: String = self.profile.name().chars().take(3).collect();
+                    next_screen = Screen::HighScore {
+                        entry: NameEntry::new(&tr("NEW HIGH SCORE!"), &initials, 3),
+                        mode: record.mode,
+                        difficulty: record.difficulty,
+                        score: leaderboard::Entry {
+                            name: String::new(),
+                            score: record.score,
+                            length: record.final_length,
+                            date: record.started_at,
+                        },
+                    };
+                }
+            }
+        }
+        self.idle_since = Instant::now();
+        self.screen = next_screen;
+    }
+}
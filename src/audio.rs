@@ -0,0 +1,386 @@
+//! Sound effects and music, synthesized at startup so that the game ships no audio files. Playing
+//! a sound only hands the samples over to the mixer running on the audio thread, so the main loop
+//! never waits for the audio device.
+//!
+//! The effects can be tuned by putting the notes of some of them in `sounds.json` in the data
+//! directory, e.g. `{"eat": [{"waveform": "square", "pitch": 72, "end_pitch": 84, "duration":
+//! 0.05, "volume": 0.25, "envelope": {"attack": 0.002, "decay": 0.03, "sustain": 0.5, "release":
+//! 0.04}}]}`.
+//!
+//! Without the `audio` feature, or without a working output device, `Audio` stays silent.
+
+#[cfg(feature = "audio")]
+use crate::synth::{Envelope, Note, Waveform};
+use serde::{Deserialize, Serialize};
+
+/// Volumes change in these steps on the sound settings screen.
+const VOLUME_STEP: u8 = 10;
+
+/// The `audio` section of the profile settings. Volumes are in percent.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioSettings {
+    pub master: u8,
+    pub music: u8,
+    pub effects: u8,
+    pub muted: bool,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        AudioSettings {
+            master: 80,
+            music: 60,
+            effects: 100,
+            muted: false,
+        }
+    }
+}
+
+impl AudioSettings {
+    /// The next volume on the settings screen, going back to silence after the maximum.
+    pub fn next_volume(volume: u8) -> u8 {
+        if volume >= 100 {
+            0
+        } else {
+            (volume + VOLUME_STEP).min(100)
+        }
+    }
+
+    /// Gains of the music and of the sound effects.
+    #[cfg(feature = "audio")]
+    fn gains(&self) -> (f32, f32) {
+        if self.muted {
+            return (0.0, 0.0);
+        }
+        let master = self.master.min(100) as f32 / 100.0;
+        let gain = |volume: u8| master * volume.min(100) as f32 / 100.0;
+        (gain(self.music), gain(self.effects))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "audio", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "audio", serde(rename_all = "snake_case"))]
+pub enum Sound {
+    Eat,
+    /// Eating rotten food.
+    Spoiled,
+    /// Golden food appeared.
+    Golden,
+    /// A bomb went off.
+    Explosion,
+    Turn,
+    Death,
+    /// The board is full.
+    LevelClear,
+    MenuMove,
+    MenuSelect,
+}
+
+#[cfg(feature = "audio")]
+const ALL: [Sound; 9] = [
+    Sound::Eat,
+    Sound::Spoiled,
+    Sound::Golden,
+    Sound::Explosion,
+    Sound::Turn,
+    Sound::Death,
+    Sound::LevelClear,
+    Sound::MenuMove,
+    Sound::MenuSelect,
+];
+
+#[cfg(feature = "audio")]
+impl Sound {
+    /// The built-in version of the effect.
+    fn notes(self) -> Vec<Note> {
+        use Waveform::{Square, Triangle};
+        match self {
+            Sound::Eat => vec![Note::new(Square, 0.0, 72.0, 0.05, 0.25).slide_to(84.0)],
+            Sound::Spoiled => vec![Note::new(Square, 0.0, 67.0, 0.12, 0.25)
+                .slide_to(55.0)
+                .duty(0.25)],
+            Sound::Golden => [84.0, 88.0, 91.0]
+                .iter()
+                .enumerate()
+                .map(|(i, &pitch)| Note::new(Square, i as f32 * 0.05, pitch, 0.05, 0.2))
+                .collect(),
+            Sound::Explosion => vec![
+                Note::new(Square, 0.0, 45.0, 0.3, 0.3)
+                    .slide_to(24.0)
+                    .duty(0.5),
+                Note::new(Triangle, 0.0, 36.0, 0.3, 0.3).slide_to(20.0),
+            ],
+            Sound::Turn => vec![Note::new(Triangle, 0.0, 60.0, 0.015, 0.2)],
+            Sound::Death => vec![
+                Note::new(Square, 0.0, 64.0, 0.5, 0.25)
+                    .slide_to(40.0)
+                    .duty(0.25)
+                    .envelope(Envelope {
+                        attack: 0.005,
+                        decay: 0.2,
+                        sustain: 0.6,
+                        release: 0.2,
+                    }),
+                Note::new(Triangle, 0.0, 52.0, 0.5, 0.3).slide_to(28.0),
+            ],
+            Sound::LevelClear => [60.0, 64.0, 67.0, 72.0]
+                .iter()
+                .enumerate()
+                .map(|(i, &pitch)| {
+                    let duration = if i == 3 { 0.4 } else { 0.08 };
+                    Note::new(Square, i as f32 * 0.1, pitch, duration, 0.2)
+                })
+                .collect(),
+            Sound::MenuMove => vec![Note::new(Square, 0.0, 76.0, 0.02, 0.15).duty(0.125)],
+            Sound::MenuSelect => vec![
+                Note::new(Square, 0.0, 79.0, 0.04, 0.2),
+                Note::new(Square, 0.05, 91.0, 0.06, 0.2),
+            ],
+        }
+    }
+}
+
+#[cfg(feature = "audio")]
+pub use output::Audio;
+
+#[cfg(not(feature = "audio"))]
+pub struct Audio;
+
+#[cfg(not(feature = "audio"))]
+impl Audio {
+    pub fn new() -> Self {
+        Audio
+    }
+
+    pub fn set_settings(&self, _settings: &AudioSettings) {}
+
+    pub fn play(&self, _sound: Sound) {}
+
+    pub fn play_panned(&self, _sound: Sound, _pan: f32) {}
+
+    pub fn play_music(&self) {}
+
+    pub fn stop_music(&self) {}
+}
+
+#[cfg(feature = "audio")]
+mod output {
+    use super::{AudioSettings, Sound, ALL};
+    use crate::storage;
+    use crate::synth::{self, Note, SAMPLE_RATE};
+    use log::warn;
+    use rodio::{OutputStream, Source};
+    use std::collections::HashMap;
+    use std::sync::mpsc::{self, Receiver, Sender};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    const SOUNDS_FILE: &str = "sounds.json";
+    /// Length of the music loop.
+    const MUSIC_BARS: usize = 8;
+    const MUSIC_TEMPO: f32 = 132.0;
+
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    enum Channel {
+        /// Starts over when it ends, until it's stopped.
+        Music,
+        Effects,
+    }
+
+    /// A sound being played.
+    struct Voice {
+        samples: Arc<[f32]>,
+        position: usize,
+        channel: Channel,
+        /// From -1 for the left speaker only to 1 for the right one.
+        pan: f32,
+    }
+
+    enum Command {
+        Play(Voice),
+        StopMusic,
+        SetGains { music: f32, effects: f32 },
+    }
+
+    /// Endless stereo source that adds up all the voices that are playing. Commands arrive
+    /// through a channel, so starting a sound never locks anything on the main thread.
+    struct Mixer {
+        voices: Vec<Voice>,
+        commands: Receiver<Command>,
+        music_gain: f32,
+        effects_gain: f32,
+        /// The right channel of the current frame, which comes after the left one.
+        right: Option<f32>,
+    }
+
+    impl Iterator for Mixer {
+        type Item = f32;
+
+        fn next(&mut self) -> Option<f32> {
+            if let Some(right) = self.right.take() {
+                return Some(right);
+            }
+            for command in self.commands.try_iter() {
+                match command {
+                    Command::Play(voice) => self.voices.push(voice),
+                    Command::StopMusic => self.voices.retain(|v| v.channel != Channel::Music),
+                    Command::SetGains { music, effects } => {
+                        self.music_gain = music;
+                        self.effects_gain = effects;
+                    }
+                }
+            }
+            let (mut left, mut right) = (0.0, 0.0);
+            for voice in self.voices.iter_mut() {
+                let gain = match voice.channel {
+                    Channel::Music => self.music_gain,
+                    Channel::Effects => self.effects_gain,
+                };
+                let sample = gain * voice.samples[voice.position];
+                // Centered sounds play at full volume on both sides.
+                left += sample * (1.0 - voice.pan).min(1.0);
+                right += sample * (1.0 + voice.pan).min(1.0);
+                voice.position += 1;
+                if voice.channel == Channel::Music && voice.position == voice.samples.len() {
+                    voice.position = 0;
+                }
+            }
+            self.voices.retain(|v| v.position < v.samples.len());
+            self.right = Some(right.clamp(-1.0, 1.0));
+            Some(left.clamp(-1.0, 1.0))
+        }
+    }
+
+    impl Source for Mixer {
+        fn current_frame_len(&self) -> Option<usize> {
+            None
+        }
+
+        fn channels(&self) -> u16 {
+            2
+        }
+
+        fn sample_rate(&self) -> u32 {
+            SAMPLE_RATE
+        }
+
+        fn total_duration(&self) -> Option<Duration> {
+            None
+        }
+    }
+
+    /// Synthesizes all the effects, taking the ones defined in `sounds.json` over the built-in
+    /// ones.
+    fn synthesize() -> HashMap<Sound, Arc<[f32]>> {
+        let overrides: HashMap<Sound, Vec<Note>> = storage::load(SOUNDS_FILE);
+        ALL.iter()
+            .filter_map(|&sound| {
+                let notes = overrides
+                    .get(&sound)
+                    .cloned()
+                    .unwrap_or_else(|| sound.notes());
+                let samples = synth::render(&notes);
+                if samples.is_empty() {
+                    warn!("{:?} is silent", sound);
+                    return None;
+                }
+                Some((sound, samples.into()))
+            })
+            .collect()
+    }
+
+    pub struct Audio {
+        /// Playback stops when the stream is dropped.
+        _stream: Option<OutputStream>,
+        commands: Option<Sender<Command>>,
+        samples: HashMap<Sound, Arc<[f32]>>,
+        music: Option<Arc<[f32]>>,
+    }
+
+    impl Audio {
+        pub fn new() -> Self {
+            let (stream, handle) = match OutputStream::try_default() {
+                Ok(output) => output,
+                Err(e) => {
+                    warn!("No audio output: {}", e);
+                    return Audio {
+                        _stream: None,
+                        commands: None,
+                        samples: HashMap::new(),
+                        music: None,
+                    };
+                }
+            };
+            let (sender, receiver) = mpsc::channel();
+            let mixer = Mixer {
+                voices: Vec::new(),
+                commands: receiver,
+                music_gain: 0.0,
+                effects_gain: 0.0,
+                right: None,
+            };
+            let commands = match handle.play_raw(mixer) {
+                Ok(()) => Some(sender),
+                Err(e) => {
+                    warn!("Failed to start audio playback: {}", e);
+                    None
+                }
+            };
+            let music = synth::render(&synth::melody(rand::random(), MUSIC_BARS, MUSIC_TEMPO));
+            Audio {
+                _stream: Some(stream),
+                commands,
+                samples: synthesize(),
+                music: Some(music.into()),
+            }
+        }
+
+        fn send(&self, command: Command) {
+            if let Some(commands) = &self.commands {
+                // Only fails if the audio thread is gone, and then there's nothing to do.
+                let _ = commands.send(command);
+            }
+        }
+
+        pub fn set_settings(&self, settings: &AudioSettings) {
+            let (music, effects) = settings.gains();
+            self.send(Command::SetGains { music, effects });
+        }
+
+        pub fn play(&self, sound: Sound) {
+            self.play_panned(sound, 0.0);
+        }
+
+        /// Plays the sound shifted towards one of the speakers: -1 is the left one and 1 is the
+        /// right one.
+        pub fn play_panned(&self, sound: Sound, pan: f32) {
+            if let Some(samples) = self.samples.get(&sound) {
+                self.send(Command::Play(Voice {
+                    samples: Arc::clone(samples),
+                    position: 0,
+                    channel: Channel::Effects,
+                    pan: pan.clamp(-1.0, 1.0),
+                }));
+            }
+        }
+
+        /// Starts the background music from the beginning.
+        pub fn play_music(&self) {
+            self.stop_music();
+            if let Some(music) = &self.music {
+                self.send(Command::Play(Voice {
+                    samples: Arc::clone(music),
+                    position: 0,
+                    channel: Channel::Music,
+                    pan: 0.0,
+                }));
+            }
+        }
+
+        pub fn stop_music(&self) {
+            self.send(Command::StopMusic);
+        }
+    }
+}
@@ -0,0 +1,402 @@
+//! Steers a snake without a player, for the demo on the title screen and the autopilot. The
+//! snake takes the shortest way to food as long as it can still reach the tip of its tail
+//! afterwards, since then it can always follow the tail and can't get trapped. When there is no
+//! such way, it follows a fixed cycle through the board, and as a last resort goes wherever
+//! there is the most room.
+
+use crate::controller::{Controller, GameView};
+use crate::framebuffer::{Color, FrameBuffer};
+use crate::grid::{Direction, Tile, Vec2};
+use crate::palette::Palette;
+use crate::world::{Camera, Snake, SnakeId, World};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+
+/// A closed path through the cells of a `width` × `height` board that visits every cell once.
+/// Such a cycle only exists if one of the sides is even, so on boards with both sides odd it
+/// leaves out the top row. On the hex board, every cell touches the ones straight above and
+/// below it, so the same path works there.
+///
+/// The cycle runs right along the bottom row, then back and forth through the rows leaving
+/// out the first column, and then down the first column.
+fn cycle(width: i32, height: i32) -> Vec<Vec2> {
+    if width < 2 || height < 2 {
+        return Vec::new();
+    }
+    if height % 2 == 1 && width % 2 == 0 {
+        return cycle(height, width)
+            .into_iter()
+            .map(|Vec2(x, y)| Vec2(y, x))
+            .collect();
+    }
+    let height = height - height % 2;
+    let mut cells = Vec::with_capacity((width * height) as usize);
+    for y in 0..height {
+        if y % 2 == 0 {
+            cells.extend((1..width).map(|x| Vec2(x, y)));
+        } else {
+            cells.extend((1..width).rev().map(|x| Vec2(x, y)));
+        }
+    }
+    cells.extend((0..height).rev().map(|y| Vec2(0, y)));
+    cells
+}
+
+/// The board as seen by one snake, with its own body tracked separately so that its moves can
+/// be simulated.
+struct Board<'a> {
+    world: &'a World,
+    view: GameView<'a>,
+    /// Cells of the snake when the search started, head first.
+    own: HashSet<Vec2>,
+}
+
+impl<'a> Board<'a> {
+    fn new(view: GameView<'a>, snake: &Snake) -> Self {
+        Board {
+            world: view.world(),
+            view,
+            own: std::iter::once(snake.head)
+                .chain(snake.tail.iter().copied())
+                .collect(),
+        }
+    }
+
+    /// Whether the cell is on the board, not taken by another snake, and can be entered heading
+    /// in the direction. The snake's own body has to be checked separately.
+    fn open(&self, pos: Vec2, direction: Direction) -> bool {
+        if !self.world.can_enter(pos, direction) {
+            return false;
+        }
+        match self.world.tile(pos) {
+            None | Some(Tile::Wall) | Some(Tile::Portal) => false,
+            Some(Tile::Snake) => self.own.contains(&pos),
+            Some(_) => true,
+        }
+    }
+
+    /// Finds the shortest path from the head to any food with A*, treating the whole body of
+    /// the snake as an obstacle. The path doesn't include the head. `cost` receives the
+    /// distances from the head of the cells that the search went through.
+    fn path_to_food(&self, snake: &Snake, cost: &mut HashMap<Vec2, i32>) -> Option<Vec<Vec2>> {
+        let food: Vec<Vec2> = self.view.food().collect();
+        let topology = self.world.topology();
+        let estimate = |pos: Vec2| food.iter().map(|&f| topology.distance(pos, f)).min();
+        let mut open = BinaryHeap::new();
+        let mut came_from: HashMap<Vec2, Vec2> = HashMap::new();
+        cost.insert(snake.head, 0);
+        open.push(Reverse((estimate(snake.head)?, snake.head)));
+        while let Some(Reverse((_, pos))) = open.pop() {
+            if self.view.has_food(pos) {
+                let mut path = vec![pos];
+                while let Some(&prev) = came_from.get(path.last().unwrap()) {
+                    path.push(prev);
+                }
+                path.pop();
+                path.reverse();
+                return Some(path);
+            }
+            let next_cost = cost[&pos] + 1;
+            for &direction in self.world.directions() {
+                let next = self.world.neighbor(pos, direction);
+                if !self.open(next, direction) || self.own.contains(&next) {
+                    continue;
+                }
+                if !matches!(cost.get(&next), Some(&c) if c <= next_cost) {
+                    cost.insert(next, next_cost);
+                    came_from.insert(next, pos);
+                    open.push(Reverse((next_cost + estimate(next).unwrap_or(0), next)));
+                }
+            }
+        }
+        None
+    }
+
+    /// Moves the snake along the path and checks that from there the head can still reach the
+    /// tip of the tail.
+    fn survives(&self, snake: &Snake, path: &[Vec2]) -> bool {
+        let mut body: VecDeque<Vec2> = std::iter::once(snake.head)
+            .chain(snake.tail.iter().copied())
+            .collect();
+        for &pos in path {
+            let eats = self.view.has_food(pos);
+            if !eats {
+                body.pop_back();
+            }
+            let open = direction_to(self.world, body[0], pos).is_some_and(|d| self.open(pos, d));
+            if body.contains(&pos) || !open {
+                return false;
+            }
+            body.push_front(pos);
+        }
+        let head = body[0];
+        let tip = *body.back().unwrap();
+        if head == tip {
+            return true;
+        }
+        let body: HashSet<Vec2> = body.into_iter().collect();
+        let mut seen = HashSet::new();
+        let mut queue = VecDeque::new();
+        seen.insert(head);
+        queue.push_back(head);
+        while let Some(pos) = queue.pop_front() {
+            for &direction in self.world.directions() {
+                let next = self.world.neighbor(pos, direction);
+                if next == tip {
+                    return true;
+                }
+                if self.open(next, direction) && !body.contains(&next) && seen.insert(next) {
+                    queue.push_back(next);
+                }
+            }
+        }
+        false
+    }
+
+    /// Number of free cells that can be reached from `start`.
+    fn room(&self, start: Vec2) -> usize {
+        let mut seen = HashSet::new();
+        let mut queue = VecDeque::new();
+        seen.insert(start);
+        queue.push_back(start);
+        while let Some(pos) = queue.pop_front() {
+            for &direction in self.world.directions() {
+                let next = self.world.neighbor(pos, direction);
+                if self.open(next, direction) && !self.own.contains(&next) && seen.insert(next) {
+                    queue.push_back(next);
+                }
+            }
+        }
+        seen.len()
+    }
+}
+
+fn direction_to(world: &World, from: Vec2, to: Vec2) -> Option<Direction> {
+    world
+        .directions()
+        .iter()
+        .copied()
+        .find(|&d| world.neighbor(from, d) == to)
+}
+
+/// Reddish tint of the cells where the snake would crash or get trapped.
+pub const DANGER: Color = Color::rgb(0xE8, 0x40, 0x40);
+
+/// What the autopilot found out about the board while deciding on its next move.
+#[derive(Clone, Debug, Default)]
+pub struct Plan {
+    pub direction: Option<Direction>,
+    /// Cells that the snake is going to go through, starting next to the head.
+    pub path: Vec<Vec2>,
+    /// Distance from the head of every cell that the search for food went through.
+    pub distances: HashMap<Vec2, i32>,
+    /// Cells next to the head where the snake would crash or get trapped.
+    pub danger: Vec<Vec2>,
+}
+
+impl Plan {
+    /// Draws the plan over the board, as seen through the camera.
+    pub fn render(&self, buffer: &mut FrameBuffer, camera: &Camera, palette: &Palette) {
+        let cell = camera.cell;
+        let mut blend_cell = |pos: Vec2, color: Color, alpha: u8| {
+            if !camera.shows(pos) {
+                return;
+            }
+            let corner = camera.corner(pos);
+            buffer.blend_rectangle(
+                corner.0,
+                corner.1,
+                cell as usize,
+                cell as usize,
+                color,
+                alpha,
+            );
+        };
+        // The closer the cell is to the head, the brighter.
+        let farthest = self.distances.values().copied().max().unwrap_or(0).max(1);
+        for (&pos, &distance) in self.distances.iter() {
+            let alpha = 24 + 96 * (farthest - distance) / farthest;
+            blend_cell(pos, palette.text, alpha as u8);
+        }
+        for &pos in self.path.iter() {
+            blend_cell(pos, palette.selected, 160);
+        }
+        for &pos in self.danger.iter() {
+            blend_cell(pos, DANGER, 160);
+        }
+    }
+}
+
+/// Decides on the next move of the snake and explains it.
+pub fn plan(world: &World, snake: SnakeId) -> Plan {
+    match world.snakes().get(snake) {
+        Some(_) => plan_for(&GameView::new(world, snake)),
+        None => Plan::default(),
+    }
+}
+
+/// Like `plan`, but only goes by what the view shows.
+pub fn plan_for(view: &GameView) -> Plan {
+    let mut plan = Plan::default();
+    let (world, snake) = (view.world(), view.snake());
+    if !snake.alive {
+        return plan;
+    }
+    let board = Board::new(*view, snake);
+    let directions = world.directions();
+    let ahead = |d: Direction| world.neighbor(snake.head, d);
+    plan.danger = directions
+        .iter()
+        .map(|&d| ahead(d))
+        .filter(|&pos| world.tile(pos).is_some() && !board.survives(snake, &[pos]))
+        .collect();
+
+    if let Some(path) = board.path_to_food(snake, &mut plan.distances) {
+        if board.survives(snake, &path) {
+            plan.direction = direction_to(world, snake.head, path[0]);
+            plan.path = path;
+            return plan;
+        }
+    }
+
+    let cycle = cycle(world.width(), world.height());
+    if let Some(i) = cycle.iter().position(|&pos| pos == snake.head) {
+        let next = cycle[(i + 1) % cycle.len()];
+        if board.survives(snake, &[next]) {
+            plan.direction = direction_to(world, snake.head, next);
+            plan.path = vec![next];
+            return plan;
+        }
+    }
+
+    // Chasing the tail keeps the snake alive while it waits for the board to open up.
+    let safe = directions
+        .iter()
+        .copied()
+        .filter(|&d| board.survives(snake, &[ahead(d)]));
+    let unsafe_moves = directions.iter().copied().filter(|&d| {
+        let pos = ahead(d);
+        board.open(pos, d) && (!board.own.contains(&pos) || snake.tail.back() == Some(&pos))
+    });
+    let by_room = |&d: &Direction| board.room(ahead(d));
+    plan.direction = safe
+        .max_by_key(by_room)
+        .or_else(|| unsafe_moves.max_by_key(by_room));
+    plan.path = plan.direction.map(ahead).into_iter().collect();
+    plan
+}
+
+/// Number of free cells that the head of the snake can reach, which is how the autopilot judges
+/// the room that the snake has left. It is 0 for a dead snake.
+pub fn room(world: &World, id: SnakeId) -> usize {
+    let snake = match world.snakes().get(id).filter(|s| s.alive) {
+        Some(snake) => snake,
+        None => return 0,
+    };
+    let board = Board::new(GameView::new(world, id), snake);
+    // Not counting the head itself.
+    board.room(snake.head) - 1
+}
+
+/// Picks the direction for the next tick. Returns `None` if the snake is dead or there is no
+/// move that doesn't kill it right away.
+pub fn steer(world: &World, snake: SnakeId) -> Option<Direction> {
+    plan(world, snake).direction
+}
+
+/// Lets `steer` play the game. When every move is fatal, the snake just goes straight.
+#[derive(Default)]
+pub struct Autopilot;
+
+impl Controller for Autopilot {
+    fn decide(&mut self, view: &GameView) -> Direction {
+        plan_for(view).direction.unwrap_or(view.snake().direction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    /// A board with a single snake, with the head at `(width / 2 + 1, height / 2)` heading right
+    /// and two segments behind it, and with no food.
+    fn world(width: i32, height: i32) -> World {
+        let config = Config {
+            width,
+            height,
+            golden_food_odds: 0,
+            bomb_odds: 0,
+            ..Config::default()
+        };
+        let mut world = World::new(&config, 1, 0);
+        world.clear_food();
+        world
+    }
+
+    fn adjacent(world: &World, a: Vec2, b: Vec2) -> bool {
+        direction_to(world, a, b).is_some()
+    }
+
+    #[test]
+    fn finds_the_shortest_way_around_walls() {
+        let mut world = world(8, 8);
+        assert_eq!(world.snakes()[0].head, Vec2(5, 4));
+        for x in 2..8 {
+            world.set_wall(Vec2(x, 2), true);
+        }
+        world.place_food(Vec2(6, 0));
+        let plan = plan(&world, 0);
+        // Down, left to the gap in the wall, down through it and right to the food.
+        assert_eq!(plan.path.len(), 1 + 4 + 3 + 5);
+        assert_eq!(plan.path.last(), Some(&Vec2(6, 0)));
+        assert!(adjacent(&world, Vec2(5, 4), plan.path[0]));
+        for step in plan.path.windows(2) {
+            assert!(adjacent(&world, step[0], step[1]));
+            assert_ne!(world.tile(step[1]), Some(Tile::Wall));
+        }
+        assert_eq!(
+            plan.direction,
+            direction_to(&world, Vec2(5, 4), plan.path[0])
+        );
+    }
+
+    #[test]
+    fn stays_out_of_a_dead_end_with_food() {
+        let mut world = world(8, 8);
+        // A corridor two cells long from the head to the right edge, with food at its end.
+        for pos in [Vec2(6, 3), Vec2(7, 3), Vec2(6, 5), Vec2(7, 5)] {
+            world.set_wall(pos, true);
+        }
+        world.place_food(Vec2(7, 4));
+        let plan = plan(&world, 0);
+        // The food was found, but the snake couldn't get out after eating it.
+        assert!(plan.distances.contains_key(&Vec2(7, 4)));
+        assert!(plan.direction.is_some());
+        assert_ne!(plan.direction, Some(Direction::Right));
+        assert!(!plan.path.contains(&Vec2(6, 4)));
+        assert!(plan.danger.contains(&Vec2(6, 4)));
+    }
+
+    #[test]
+    fn follows_the_cycle_on_an_odd_board_without_food() {
+        let world = world(5, 5);
+        assert_eq!(world.snakes()[0].head, Vec2(3, 2));
+        let plan = plan(&world, 0);
+        assert_eq!(plan.path, [Vec2(4, 2)]);
+        assert_eq!(plan.direction, Some(Direction::Right));
+
+        // Without the top row, every other cell is visited once, and each one is next to the
+        // one before it, the last one next to the first.
+        let cells = cycle(5, 5);
+        assert_eq!(cells.len(), 20);
+        assert_eq!(cells.iter().collect::<HashSet<_>>().len(), 20);
+        assert!(cells
+            .iter()
+            .all(|pos| (0..5).contains(&pos.0) && (0..4).contains(&pos.1)));
+        for i in 0..cells.len() {
+            assert!(adjacent(&world, cells[i], cells[(i + 1) % cells.len()]));
+        }
+        assert!(cells.contains(&Vec2(3, 2)));
+    }
+}
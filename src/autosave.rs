@@ -0,0 +1,58 @@
+//! The game in progress, saved every few seconds until it ends, so that it can be resumed after
+//! the application was killed or crashed. Since the game is deterministic, the seed and the
+//! player's turns are enough to play it again up to the tick at which it was saved.
+
+use crate::storage;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use snake_pixels::config::Config;
+use snake_pixels::game::{State, Update};
+use snake_pixels::grid::Direction;
+use std::time::Duration;
+
+const FILE: &str = "autosave.json";
+
+pub const INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Autosave {
+    pub config: Config,
+    pub seed: u64,
+    /// Ticks played so far.
+    pub ticks: u64,
+    /// As returned by `State::turns`.
+    pub turns: Vec<(u64, Direction)>,
+    pub score: u32,
+    /// Whether the autopilot played a part of the game, which then doesn't count.
+    pub autopilot_used: bool,
+}
+
+impl Autosave {
+    /// The game left by the last run, if it didn't exit normally.
+    pub fn load() -> Option<Self> {
+        let text = storage::read_text(FILE)?;
+        serde_json::from_str(&text)
+            .map_err(|e| warn!("Failed to parse {}: {}", FILE, e))
+            .ok()
+    }
+
+    pub fn save(&self) {
+        storage::save(FILE, self);
+    }
+
+    pub fn remove() {
+        storage::remove(FILE);
+    }
+
+    /// Plays the saved game again up to the tick at which it was saved. Returns `None` if it
+    /// ends before that, e.g. when it was saved by a version with different rules.
+    pub fn restore(&self) -> Option<State> {
+        let mut state = State::new(&self.config, self.seed);
+        if state.replay(self.ticks, &self.turns) == Update::GameOver {
+            warn!("The saved game ended while restoring it");
+            return None;
+        }
+        state.take_events();
+        Some(state)
+    }
+}
@@ -0,0 +1,368 @@
+//! Headless server that hosts matches for 2 to 8 players over UDP and WebSockets. The server
+//! runs the only authoritative copy of the game: clients send their turns and replay the
+//! confirmed turns that the server broadcasts after every tick. Spectators can join to watch
+//! the matches without playing.
+
+use log::{error, info, warn};
+use snake_pixels::config::{Config, MAX_SIZE};
+use snake_pixels::grid::Direction;
+use snake_pixels::net::{
+    ClientMessage, ServerMessage, DEFAULT_PORT, HEARTBEAT_MS, MAX_PLAYERS, MAX_SPECTATORS,
+    MIN_PLAYERS,
+};
+use snake_pixels::rollback::MAX_PREDICTION;
+use snake_pixels::skin::Skin;
+use snake_pixels::transport::{Hub, Peer};
+use snake_pixels::world::World;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+/// Pause between the end of one match and the start of the next one.
+const INTERMISSION: Duration = Duration::from_secs(5);
+/// How often the lobby state is broadcast while waiting for players.
+const LOBBY_INTERVAL: Duration = Duration::from_secs(1);
+
+struct Options {
+    port: u16,
+    min_players: usize,
+    seed: Option<u64>,
+    config: Config,
+}
+
+impl Options {
+    fn from_args() -> Self {
+        let mut options = Options {
+            port: DEFAULT_PORT,
+            min_players: MIN_PLAYERS,
+            seed: None,
+            config: Config {
+                width: 31,
+                height: 31,
+                tick_ms: 150,
+                food_interval: 6,
+                ..Config::default()
+            },
+        };
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--port" => match args.next().map(|s| s.parse()) {
+                    Some(Ok(port)) => options.port = port,
+                    _ => error!("--port requires a numeric argument"),
+                },
+                "--players" => match args.next().map(|s| s.parse()) {
+                    Some(Ok(n)) if (MIN_PLAYERS..=MAX_PLAYERS).contains(&n) => {
+                        options.min_players = n
+                    }
+                    _ => error!(
+                        "--players requires a number from {} to {}",
+                        MIN_PLAYERS, MAX_PLAYERS
+                    ),
+                },
+                "--size" => match args.next().map(|s| s.parse()) {
+                    Some(Ok(size)) if (10..=MAX_SIZE).contains(&size) => {
+                        options.config.width = size;
+                        options.config.height = size;
+                    }
+                    _ => error!("--size requires a number from 10 to {}", MAX_SIZE),
+                },
+                "--seed" => match args.next().map(|s| s.parse()) {
+                    Some(Ok(seed)) => options.seed = Some(seed),
+                    _ => error!("--seed requires a numeric argument"),
+                },
+                _ => error!("Unknown argument: {}", arg),
+            }
+        }
+        options
+    }
+}
+
+struct Client {
+    peer: Peer,
+    session: u64,
+    name: String,
+    spectator: bool,
+    skin: Skin,
+    last_seen: Instant,
+}
+
+struct Match {
+    world: World,
+    seed: u64,
+    /// Session of the player controlling each snake.
+    players: Vec<u64>,
+    names: Vec<String>,
+    skins: Vec<Skin>,
+    /// Turns of every tick simulated so far.
+    history: Vec<Vec<Option<Direction>>>,
+    /// Turns for the upcoming ticks.
+    scheduled: BTreeMap<u64, Vec<Option<Direction>>>,
+    next_tick: Instant,
+}
+
+impl Match {
+    fn start_message(&self, config: &Config, snake: Option<usize>) -> ServerMessage {
+        ServerMessage::Start {
+            snake,
+            config: config.clone(),
+            seed: self.seed,
+            names: self.names.clone(),
+            skins: self.skins.clone(),
+            history: self.history.clone(),
+        }
+    }
+
+    fn schedule(&mut self, snake: usize, tick: u64, direction: Direction) {
+        let next = self.world.tick() + 1;
+        let tick = tick.clamp(next, next + MAX_PREDICTION as u64);
+        let players = self.players.len();
+        self.scheduled
+            .entry(tick)
+            .or_insert_with(|| vec![None; players])[snake] = Some(direction);
+    }
+}
+
+struct Server {
+    hub: Hub,
+    options: Options,
+    clients: Vec<Client>,
+    game: Option<Match>,
+    /// No match starts before this moment.
+    next_match: Instant,
+    next_lobby: Instant,
+}
+
+impl Server {
+    fn new(options: Options) -> std::io::Result<Self> {
+        Ok(Server {
+            hub: Hub::bind(options.port)?,
+            options,
+            clients: Vec::new(),
+            game: None,
+            next_match: Instant::now(),
+            next_lobby: Instant::now(),
+        })
+    }
+
+    fn run(&mut self) -> ! {
+        loop {
+            let wakeup = match &self.game {
+                Some(game) => game.next_tick,
+                None => self.next_lobby,
+            };
+            let timeout = wakeup.saturating_duration_since(Instant::now());
+            if let Some((peer, message)) = self.hub.receive(timeout) {
+                self.handle_message(peer, message);
+            }
+
+            let now = Instant::now();
+            self.drop_idle_clients(now);
+            match &self.game {
+                Some(game) if now >= game.next_tick => self.tick(),
+                Some(_) => (),
+                None if now >= self.next_lobby => self.lobby(now),
+                None => (),
+            }
+        }
+    }
+
+    fn handle_message(&mut self, peer: Peer, message: ClientMessage) {
+        let now = Instant::now();
+        let known = self.clients.iter().position(|c| c.peer == peer);
+        if let Some(i) = known {
+            self.clients[i].last_seen = now;
+        }
+
+        match message {
+            ClientMessage::Join {
+                name,
+                session,
+                spectator,
+                skin,
+            } => {
+                if let Some(i) = self.clients.iter().position(|c| c.session == session) {
+                    self.rejoin(i, peer, now);
+                    return;
+                }
+                let (limit, reason) = if spectator {
+                    (MAX_SPECTATORS, "NO MORE SPECTATORS")
+                } else {
+                    (MAX_PLAYERS, "SERVER FULL")
+                };
+                if self
+                    .clients
+                    .iter()
+                    .filter(|c| c.spectator == spectator)
+                    .count()
+                    >= limit
+                {
+                    let reason = reason.to_string();
+                    self.hub.send(peer, &ServerMessage::Rejected { reason });
+                    return;
+                }
+                if spectator {
+                    info!("{} is watching from {:?}", name, peer);
+                } else {
+                    info!("{} joined from {:?}", name, peer);
+                }
+                // Let them watch the match in progress.
+                if let Some(game) = &self.game {
+                    let config = &self.options.config;
+                    self.hub.send(peer, &game.start_message(config, None));
+                }
+                self.clients.push(Client {
+                    peer,
+                    session,
+                    name,
+                    spectator,
+                    skin,
+                    last_seen: now,
+                });
+                self.next_lobby = now;
+            }
+            ClientMessage::Turn { tick, direction } => {
+                if let (Some(game), Some(i)) = (&mut self.game, known) {
+                    let session = self.clients[i].session;
+                    if let Some(snake) = game.players.iter().position(|&s| s == session) {
+                        game.schedule(snake, tick, direction);
+                    }
+                }
+            }
+            ClientMessage::Ping { time } => {
+                if known.is_some() {
+                    self.hub.send(peer, &ServerMessage::Pong { time });
+                }
+            }
+            ClientMessage::Leave => {
+                if let Some(i) = known {
+                    info!("{} left", self.clients[i].name);
+                    self.clients.remove(i);
+                }
+            }
+        }
+    }
+
+    /// A known player connected again, possibly over a different transport. If they are in the
+    /// current match, they get their snake back.
+    fn rejoin(&mut self, i: usize, peer: Peer, now: Instant) {
+        let client = &mut self.clients[i];
+        info!("{} reconnected from {:?}", client.name, peer);
+        client.peer = peer;
+        client.last_seen = now;
+        if let Some(game) = &self.game {
+            let snake = game.players.iter().position(|&s| s == client.session);
+            let config = &self.options.config;
+            self.hub.send(peer, &game.start_message(config, snake));
+        }
+        self.next_lobby = now;
+    }
+
+    fn drop_idle_clients(&mut self, now: Instant) {
+        let timeout = Duration::from_millis(10 * HEARTBEAT_MS);
+        self.clients.retain(|client| {
+            let alive = now.duration_since(client.last_seen) < timeout;
+            if !alive {
+                warn!("{} timed out", client.name);
+            }
+            alive
+        });
+    }
+
+    /// Broadcasts who is waiting and starts a match once there are enough players.
+    fn lobby(&mut self, now: Instant) {
+        self.next_lobby = now + LOBBY_INTERVAL;
+        let players: Vec<String> = self
+            .clients
+            .iter()
+            .filter(|c| !c.spectator)
+            .map(|c| c.name.clone())
+            .collect();
+        if players.len() >= self.options.min_players && now >= self.next_match {
+            self.start_match();
+            return;
+        }
+        let message = ServerMessage::Lobby {
+            players,
+            needed: self.options.min_players,
+        };
+        self.broadcast(&message);
+    }
+
+    fn start_match(&mut self) {
+        let seed = self.options.seed.unwrap_or_else(rand::random);
+        let config = &self.options.config;
+        let players: Vec<&Client> = self.clients.iter().filter(|c| !c.spectator).collect();
+        info!(
+            "Starting a match for {} players with seed {}",
+            players.len(),
+            seed
+        );
+        let game = Match {
+            world: World::new(config, players.len(), seed),
+            seed,
+            players: players.iter().map(|c| c.session).collect(),
+            names: players.iter().map(|c| c.name.clone()).collect(),
+            skins: players.iter().map(|c| c.skin).collect(),
+            history: Vec::new(),
+            scheduled: BTreeMap::new(),
+            next_tick: Instant::now() + config.tick(),
+        };
+        for client in self.clients.iter() {
+            let snake = game.players.iter().position(|&s| s == client.session);
+            self.hub
+                .send(client.peer, &game.start_message(config, snake));
+        }
+        self.game = Some(game);
+    }
+
+    fn tick(&mut self) {
+        let game = self.game.as_mut().unwrap();
+        let tick = game.world.tick() + 1;
+        let turns = game
+            .scheduled
+            .remove(&tick)
+            .unwrap_or_else(|| vec![None; game.players.len()]);
+        game.world.step(&turns);
+        game.history.push(turns.clone());
+        game.next_tick += self.options.config.tick();
+        self.broadcast(&ServerMessage::Inputs { tick, turns });
+
+        let game = self.game.as_ref().unwrap();
+        if game.world.is_over() {
+            let winner = game
+                .world
+                .snakes()
+                .iter()
+                .position(|s| s.alive)
+                .map(|i| game.names[i].clone());
+            info!("Match over, winner: {:?}", winner);
+            self.broadcast(&ServerMessage::Over { winner });
+            self.game = None;
+            self.next_match = Instant::now() + INTERMISSION;
+            // Leave the final board and the winner on the screens until the next match.
+            self.next_lobby = self.next_match;
+        }
+    }
+
+    /// Sends the message to every connected client, including those that are waiting for the
+    /// next match and only watch the current one.
+    fn broadcast(&self, message: &ServerMessage) {
+        for client in self.clients.iter() {
+            self.hub.send(client.peer, message);
+        }
+    }
+}
+
+fn main() {
+    env_logger::Builder::from_env(
+        env_logger::Env::default().default_filter_or("snake_pixels=info,snake_server=info"),
+    )
+    .format_timestamp(Some(env_logger::fmt::TimestampPrecision::Millis))
+    .init();
+
+    let options = Options::from_args();
+    match Server::new(options) {
+        Ok(mut server) => server.run(),
+        Err(e) => error!("Failed to start the server: {}", e),
+    }
+}
@@ -0,0 +1,148 @@
+use serde::{Deserialize, Serialize};
+use snake_pixels::grid::Direction;
+use winit::keyboard::{Key, KeyCode, NamedKey};
+
+/// Keys steering the snake. Several keys can be bound to the same direction.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeyBindings {
+    pub up: Vec<KeyCode>,
+    pub down: Vec<KeyCode>,
+    pub left: Vec<KeyCode>,
+    pub right: Vec<KeyCode>,
+    /// Whether the digits of the numeric keypad steer too: 8 up, 2 down, 4 left and 6 right, and
+    /// on the boards with diagonals 7, 9, 1 and 3 along them. With NumLock off the keypad sends the
+    /// arrows and the keys above them instead, see `numpad_key`, which steer the same way.
+    pub numpad: bool,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        KeyBindings {
+            up: vec![KeyCode::ArrowUp],
+            down: vec![KeyCode::ArrowDown],
+            left: vec![KeyCode::ArrowLeft],
+            right: vec![KeyCode::ArrowRight],
+            numpad: true,
+        }
+    }
+}
+
+impl KeyBindings {
+    /// Keys of the second player in the co-op mode, on the other side of the keyboard from the
+    /// arrows.
+    pub fn second_player() -> Self {
+        KeyBindings {
+            up: vec![KeyCode::KeyW],
+            down: vec![KeyCode::KeyS],
+            left: vec![KeyCode::KeyA],
+            right: vec![KeyCode::KeyD],
+            numpad: false,
+        }
+    }
+
+    /// Makes the key the only one for the direction, which has to be one of the four along the
+    /// sides, and takes it away from the others.
+    pub fn bind(&mut self, direction: Direction, keycode: KeyCode) {
+        for keys in [
+            &mut self.up,
+            &mut self.down,
+            &mut self.left,
+            &mut self.right,
+        ] {
+            keys.retain(|&key| key != keycode);
+        }
+        let keys = match direction {
+            Direction::Up => &mut self.up,
+            Direction::Down => &mut self.down,
+            Direction::Left => &mut self.left,
+            Direction::Right => &mut self.right,
+            _ => return,
+        };
+        *keys = vec![keycode];
+    }
+
+    /// The keys bound to the direction, apart from those of the numeric keypad.
+    pub fn keys(&self, direction: Direction) -> &[KeyCode] {
+        match direction {
+            Direction::Up => &self.up,
+            Direction::Down => &self.down,
+            Direction::Left => &self.left,
+            Direction::Right => &self.right,
+            _ => &[],
+        }
+    }
+
+    pub fn direction(&self, keycode: KeyCode) -> Option<Direction> {
+        if self.numpad {
+            match keycode {
+                KeyCode::Numpad8 => return Some(Direction::Up),
+                KeyCode::Numpad2 => return Some(Direction::Down),
+                KeyCode::Numpad4 => return Some(Direction::Left),
+                KeyCode::Numpad6 => return Some(Direction::Right),
+                KeyCode::Numpad7 | KeyCode::Home => return Some(Direction::UpLeft),
+                KeyCode::Numpad9 | KeyCode::PageUp => return Some(Direction::UpRight),
+                KeyCode::Numpad1 | KeyCode::End => return Some(Direction::DownLeft),
+                KeyCode::Numpad3 | KeyCode::PageDown => return Some(Direction::DownRight),
+                _ => (),
+            }
+        }
+        if self.up.contains(&keycode) {
+            Some(Direction::Up)
+        } else if self.down.contains(&keycode) {
+            Some(Direction::Down)
+        } else if self.left.contains(&keycode) {
+            Some(Direction::Left)
+        } else if self.right.contains(&keycode) {
+            Some(Direction::Right)
+        } else {
+            None
+        }
+    }
+}
+
+/// Name of the key for the screen, such as `W` for `KeyW` and `ARROWUP` for `ArrowUp`.
+pub fn key_name(keycode: KeyCode) -> String {
+    let name = format!("{:?}", keycode);
+    let short = ["Key", "Digit"]
+        .iter()
+        .find_map(|prefix| name.strip_prefix(prefix))
+        .unwrap_or(&name);
+    short.to_uppercase()
+}
+
+/// The key that a key of the numeric keypad stands for, given the key that the system made of it.
+/// With NumLock off the digits act as the arrows and the keys above them, like everywhere else,
+/// so that they move through the menus too. Other keys stay as they are.
+pub fn numpad_key(keycode: KeyCode, logical: &Key) -> KeyCode {
+    let numpad = matches!(
+        keycode,
+        KeyCode::Numpad0
+            | KeyCode::Numpad1
+            | KeyCode::Numpad2
+            | KeyCode::Numpad3
+            | KeyCode::Numpad4
+            | KeyCode::Numpad5
+            | KeyCode::Numpad6
+            | KeyCode::Numpad7
+            | KeyCode::Numpad8
+            | KeyCode::Numpad9
+            | KeyCode::NumpadDecimal
+    );
+    if !numpad {
+        return keycode;
+    }
+    match logical {
+        Key::Named(NamedKey::ArrowUp) => KeyCode::ArrowUp,
+        Key::Named(NamedKey::ArrowDown) => KeyCode::ArrowDown,
+        Key::Named(NamedKey::ArrowLeft) => KeyCode::ArrowLeft,
+        Key::Named(NamedKey::ArrowRight) => KeyCode::ArrowRight,
+        Key::Named(NamedKey::Home) => KeyCode::Home,
+        Key::Named(NamedKey::End) => KeyCode::End,
+        Key::Named(NamedKey::PageUp) => KeyCode::PageUp,
+        Key::Named(NamedKey::PageDown) => KeyCode::PageDown,
+        Key::Named(NamedKey::Insert) => KeyCode::Insert,
+        Key::Named(NamedKey::Delete) => KeyCode::Delete,
+        _ => keycode,
+    }
+}
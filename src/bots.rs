@@ -0,0 +1,131 @@
+//! Registry of the policies that can steer a snake without a player, for pitting them against
+//! each other.
+
+use crate::autopilot::Autopilot;
+use crate::controller::{Controller, GameView};
+use crate::grid::Direction;
+use crate::solver::Solver;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::collections::{HashSet, VecDeque};
+
+pub struct Policy {
+    pub name: &'static str,
+    /// Creates a controller. The seed is for the policies that make random choices.
+    pub create: fn(seed: u64) -> Box<dyn Controller>,
+}
+
+pub fn policies() -> Vec<Policy> {
+    vec![
+        Policy {
+            name: "autopilot",
+            create: |_| Box::new(Autopilot),
+        },
+        Policy {
+            name: "greedy",
+            create: |_| Box::new(Greedy),
+        },
+        Policy {
+            name: "solver",
+            create: |_| Box::new(Solver::default()),
+        },
+        Policy {
+            name: "painter",
+            create: |_| Box::new(Painter),
+        },
+        Policy {
+            name: "random",
+            create: |seed| Box::new(Random::new(seed)),
+        },
+    ]
+}
+
+pub fn find(name: &str) -> Option<Policy> {
+    policies().into_iter().find(|p| p.name == name)
+}
+
+/// Directions that don't run into a wall or a snake on the next tick.
+fn safe_moves(view: &GameView) -> Vec<Direction> {
+    view.world()
+        .directions()
+        .iter()
+        .copied()
+        .filter(|&d| view.is_safe(d))
+        .collect()
+}
+
+/// Heads for the nearest food without looking further than the next tick.
+pub struct Greedy;
+
+impl Controller for Greedy {
+    fn decide(&mut self, view: &GameView) -> Direction {
+        let distance = |d: &Direction| {
+            let world = view.world();
+            let pos = world.neighbor(view.head(), *d);
+            view.food()
+                .map(|f| world.topology().distance(pos, f))
+                .min()
+                .unwrap_or(0)
+        };
+        safe_moves(view)
+            .into_iter()
+            .min_by_key(distance)
+            .unwrap_or(view.snake().direction)
+    }
+}
+
+/// Goes for the nearest cell that it hasn't painted yet, for the territory mode.
+pub struct Painter;
+
+impl Controller for Painter {
+    fn decide(&mut self, view: &GameView) -> Direction {
+        let world = view.world();
+        // Length of the way from the cell next to the head in that direction to the nearest
+        // cell that the snake doesn't own.
+        let distance = |d: &Direction| {
+            let start = world.neighbor(view.head(), *d);
+            let mut seen = HashSet::from([start]);
+            let mut queue = VecDeque::from([(start, 0)]);
+            while let Some((pos, distance)) = queue.pop_front() {
+                if view.owner(pos) != Some(view.id()) {
+                    return distance;
+                }
+                for &direction in world.directions() {
+                    let next = world.neighbor(pos, direction);
+                    let open = world.can_enter(next, direction) && !view.is_occupied(next);
+                    if open && seen.insert(next) {
+                        queue.push_back((next, distance + 1));
+                    }
+                }
+            }
+            usize::MAX
+        };
+        safe_moves(view)
+            .into_iter()
+            .min_by_key(distance)
+            .unwrap_or(view.snake().direction)
+    }
+}
+
+/// Wanders around at random, only avoiding crashing on the next tick.
+pub struct Random {
+    rng: StdRng,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Random {
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl Controller for Random {
+    fn decide(&mut self, view: &GameView) -> Direction {
+        let moves = safe_moves(view);
+        if moves.is_empty() {
+            view.snake().direction
+        } else {
+            moves[self.rng.gen_range(0..moves.len())]
+        }
+    }
+}
@@ -0,0 +1,173 @@
+//! Level packs played in order. A pack is a directory with the level files and a JSON manifest
+//! listing them, e.g.
+//!
+//! ```json
+//! {
+//!   "name": "Starter",
+//!   "levels": [
+//!     { "file": "box.txt", "name": "BOX", "goal": 10, "par": 40, "unlock": "always" },
+//!     { "file": "cross.txt", "name": "CROSS", "goal": 15 },
+//!     {
+//!       "file": "maze.txt", "name": "MAZE", "goal": 20, "unlock": { "par": 2 },
+//!       "medals": {
+//!         "bronze": { "time": 90 },
+//!         "silver": { "time": 60 },
+//!         "gold": { "time": 45, "length": 30 }
+//!       }
+//!     }
+//!   ]
+//! }
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// When a level of a pack can be played.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Unlock {
+    Always,
+    /// Once the level before it is completed.
+    #[default]
+    Previous,
+    /// Once this many levels of the pack are completed.
+    Completed(usize),
+    /// Once this many levels of the pack are completed within their par times.
+    Par(usize),
+}
+
+/// Medals for completing a level quickly or growing long on the way, from the lowest.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Medal {
+    Bronze,
+    Silver,
+    Gold,
+}
+
+impl Medal {
+    pub const ALL: [Medal; 3] = [Medal::Bronze, Medal::Silver, Medal::Gold];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Medal::Bronze => "BRONZE MEDAL",
+            Medal::Silver => "SILVER MEDAL",
+            Medal::Gold => "GOLD MEDAL",
+        }
+    }
+}
+
+/// What a completion of a level needs to win a medal. A medal without any thresholds is won by
+/// any completion.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Threshold {
+    /// Most seconds of game time.
+    pub time: Option<u32>,
+    /// Shortest length of the snake at the end.
+    pub length: Option<usize>,
+}
+
+impl Threshold {
+    pub fn is_met(&self, time: f64, length: usize) -> bool {
+        self.time.is_none_or(|limit| time <= limit as f64)
+            && self.length.is_none_or(|least| length >= least)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LevelInfo {
+    /// Name of the level file, relative to the pack.
+    pub file: String,
+    pub name: String,
+    /// Pieces of food to eat to complete the level.
+    pub goal: u32,
+    /// Seconds of game time in which the level should be completed.
+    #[serde(default)]
+    pub par: Option<u32>,
+    #[serde(default)]
+    pub unlock: Unlock,
+    /// Thresholds of the medals that can be won on the level.
+    #[serde(default)]
+    pub medals: BTreeMap<Medal, Threshold>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Manifest {
+    pub name: String,
+    pub levels: Vec<LevelInfo>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LevelProgress {
+    pub completed: bool,
+    /// Fastest completion in seconds of game time.
+    pub best_time: Option<f64>,
+    /// Best medal won in a single completion.
+    pub medal: Option<Medal>,
+}
+
+/// Progress through a pack by the level file.
+pub type PackProgress = BTreeMap<String, LevelProgress>;
+
+impl LevelInfo {
+    pub fn is_completed(&self, progress: &PackProgress) -> bool {
+        matches!(progress.get(&self.file), Some(p) if p.completed)
+    }
+
+    /// Whether the level was completed within its par time. Levels without a par time only
+    /// have to be completed.
+    pub fn is_par_beaten(&self, progress: &PackProgress) -> bool {
+        match (self.par, progress.get(&self.file)) {
+            (_, None) => false,
+            (None, Some(p)) => p.completed,
+            (Some(par), Some(p)) => matches!(p.best_time, Some(time) if time <= par as f64),
+        }
+    }
+
+    /// The best medal for completing the level in `time` seconds with a snake of `length`.
+    pub fn medal(&self, time: f64, length: usize) -> Option<Medal> {
+        Medal::ALL.iter().rev().copied().find(|medal| {
+            self.medals
+                .get(medal)
+                .is_some_and(|threshold| threshold.is_met(time, length))
+        })
+    }
+
+    pub fn best_medal(&self, progress: &PackProgress) -> Option<Medal> {
+        progress.get(&self.file).and_then(|p| p.medal)
+    }
+}
+
+impl Manifest {
+    pub fn is_unlocked(&self, level: usize, progress: &PackProgress) -> bool {
+        let count = |beaten: fn(&LevelInfo, &PackProgress) -> bool| {
+            self.levels.iter().filter(|l| beaten(l, progress)).count()
+        };
+        match self.levels[level].unlock {
+            Unlock::Always => true,
+            Unlock::Previous => level == 0 || self.levels[level - 1].is_completed(progress),
+            Unlock::Completed(n) => count(LevelInfo::is_completed) >= n,
+            Unlock::Par(n) => count(LevelInfo::is_par_beaten) >= n,
+        }
+    }
+}
+
+/// Records a completion of a level in `time` seconds with a snake of `length`, along with the medal
+/// that it won. Returns whether it is the fastest one.
+pub fn record_completion(
+    progress: &mut PackProgress,
+    level: &LevelInfo,
+    time: f64,
+    length: usize,
+) -> bool {
+    let entry = progress.entry(level.file.clone()).or_default();
+    entry.completed = true;
+    entry.medal = entry.medal.max(level.medal(time, length));
+    let best = !matches!(entry.best_time, Some(best) if best <= time);
+    if best {
+        entry.best_time = Some(time);
+    }
+    best
+}
@@ -0,0 +1,184 @@
+//! Lets a chat steer the snake: every message saying a direction is a vote, and on each tick the
+//! snake turns where the majority wants it to. Reads any IRC channel, including Twitch chat.
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use snake_pixels::font::{draw_text, text_width, LINE_HEIGHT};
+use snake_pixels::framebuffer::FrameBuffer;
+use snake_pixels::grid::Direction;
+use snake_pixels::palette::Palette;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::sync::mpsc::{self, Receiver, Sender};
+
+/// The `chat` section of the profile settings.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChatSettings {
+    /// `HOST:PORT` of the IRC server.
+    pub server: String,
+    /// Channel to read the votes from, without the leading `#`. Chat control is only offered when
+    /// this is set.
+    pub channel: String,
+    pub nick: String,
+    /// Not needed for Twitch, which lets anonymous `justinfan` users read any chat.
+    pub password: Option<String>,
+}
+
+impl Default for ChatSettings {
+    fn default() -> Self {
+        ChatSettings {
+            server: "irc.chat.twitch.tv:6667".to_string(),
+            channel: String::new(),
+            nick: "justinfan12345".to_string(),
+            password: None,
+        }
+    }
+}
+
+const DIRECTIONS: [(Direction, &str); 4] = [
+    (Direction::Up, "UP"),
+    (Direction::Down, "DOWN"),
+    (Direction::Left, "LEFT"),
+    (Direction::Right, "RIGHT"),
+];
+
+/// Parses a chat message as a vote. Accepts the direction names and their first letters.
+fn parse_vote(text: &str) -> Option<Direction> {
+    let word = text.split_whitespace().next()?.to_ascii_uppercase();
+    DIRECTIONS
+        .iter()
+        .find(|(_, name)| word == *name || (word.len() == 1 && name.starts_with(&word)))
+        .map(|&(direction, _)| direction)
+}
+
+/// Connection to the chat and the votes cast since the last tick.
+pub struct ChatControl {
+    votes: Receiver<(String, Direction)>,
+    /// The last vote of every user during the current tick.
+    ballots: HashMap<String, Direction>,
+}
+
+impl ChatControl {
+    pub fn connect(settings: &ChatSettings) -> std::io::Result<Self> {
+        let stream = TcpStream::connect(&settings.server)?;
+        let mut writer = stream.try_clone()?;
+        if let Some(password) = &settings.password {
+            write!(writer, "PASS {}\r\n", password)?;
+        }
+        write!(writer, "NICK {}\r\n", settings.nick)?;
+        write!(writer, "JOIN #{}\r\n", settings.channel.to_lowercase())?;
+        info!(
+            "Reading votes from #{} on {}",
+            settings.channel, settings.server
+        );
+
+        let (sender, votes) = mpsc::channel();
+        std::thread::spawn(move || read_chat(stream, writer, sender));
+        Ok(ChatControl {
+            votes,
+            ballots: HashMap::new(),
+        })
+    }
+
+    fn collect_votes(&mut self) {
+        for (user, direction) in self.votes.try_iter() {
+            self.ballots.insert(user, direction);
+        }
+    }
+
+    fn tally(&self) -> [usize; 4] {
+        let mut tally = [0; 4];
+        for direction in self.ballots.values() {
+            let i = DIRECTIONS.iter().position(|(d, _)| d == direction).unwrap();
+            tally[i] += 1;
+        }
+        tally
+    }
+
+    /// Closes the vote for the current tick. Returns the winning direction, or `None` if nobody
+    /// voted or there was a tie, in which case the snake keeps going straight.
+    pub fn close_vote(&mut self) -> Option<Direction> {
+        self.collect_votes();
+        let tally = self.tally();
+        self.ballots.clear();
+        let max = *tally.iter().max().unwrap();
+        if max == 0 || tally.iter().filter(|&&n| n == max).count() > 1 {
+            return None;
+        }
+        let winner = tally.iter().position(|&n| n == max).unwrap();
+        Some(DIRECTIONS[winner].0)
+    }
+
+    /// Returns whether new votes have arrived.
+    pub fn update(&mut self) -> bool {
+        let before = self.ballots.clone();
+        self.collect_votes();
+        self.ballots != before
+    }
+
+    /// Draws the current tally along the top of the screen.
+    pub fn render(&self, buffer: &mut FrameBuffer, palette: &Palette) {
+        let tally = self.tally();
+        let max = *tally.iter().max().unwrap();
+        let columns = buffer.width() as i32 / 4;
+        let y = buffer.height() as i32 - 1;
+        for (i, &(_, name)) in DIRECTIONS.iter().enumerate() {
+            let text = format!("{}{}", &name[..1], tally[i]);
+            let x = i as i32 * columns + (columns - text_width(&text)) / 2;
+            buffer.fill_rectangle(
+                x - 1,
+                y - LINE_HEIGHT + 1,
+                (text_width(&text) + 2) as usize,
+                LINE_HEIGHT as usize,
+                palette.menu_bg,
+            );
+            let color = if max > 0 && tally[i] == max {
+                palette.selected
+            } else {
+                palette.text
+            };
+            draw_text(buffer, x, y - 1, &text, 1, color);
+        }
+    }
+}
+
+/// Reads the IRC messages until the connection closes, answering the server's pings and
+/// forwarding the votes.
+fn read_chat(stream: TcpStream, mut writer: TcpStream, votes: Sender<(String, Direction)>) {
+    for line in BufReader::new(stream).lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => {
+                warn!("Lost the chat connection: {}", e);
+                return;
+            }
+        };
+        if let Some(payload) = line.strip_prefix("PING") {
+            if write!(writer, "PONG{}\r\n", payload).is_err() {
+                return;
+            }
+            continue;
+        }
+        // :user!user@user.tmi.twitch.tv PRIVMSG #channel :message
+        let mut parts = line.splitn(4, ' ');
+        let (prefix, command, text) = match (parts.next(), parts.next(), parts.nth(1)) {
+            (Some(prefix), Some(command), Some(text)) => (prefix, command, text),
+            _ => continue,
+        };
+        if command != "PRIVMSG" {
+            continue;
+        }
+        let user = prefix
+            .trim_start_matches(':')
+            .split('!')
+            .next()
+            .unwrap_or("");
+        if let Some(direction) = parse_vote(text.trim_start_matches(':')) {
+            if votes.send((user.to_string(), direction)).is_err() {
+                return;
+            }
+        }
+    }
+}
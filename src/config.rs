@@ -0,0 +1,228 @@
+use crate::ruleset::Ruleset;
+use crate::topology::Shape;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Most pieces of food on the board at once in the frenzy mode.
+const FRENZY_MAX_FOOD: u32 = 10;
+/// How many times more often the food appears in the frenzy mode.
+const FRENZY_SPEEDUP: u32 = 2;
+/// Length of a game in the territory mode, in ticks.
+const TERRITORY_TICKS: u64 = 300;
+/// Lives that the two snakes of the co-op mode share.
+pub const COOP_LIVES: u32 = 3;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Mode {
+    #[default]
+    Classic,
+    /// Lots of food at once, appearing quickly.
+    Frenzy,
+    /// Snakes paint the cells they go over, and when the time is up the one with the most cells
+    /// wins.
+    Territory,
+    /// Two players on one keyboard, sharing the score and the lives. Their snakes can go through
+    /// each other, but lose points for it.
+    CoOp,
+}
+
+impl Mode {
+    pub fn name(self) -> &'static str {
+        match self {
+            Mode::Classic => "CLASSIC",
+            Mode::Frenzy => "FRENZY",
+            Mode::Territory => "TERRITORY",
+            Mode::CoOp => "CO-OP",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            Mode::Classic => Mode::Frenzy,
+            Mode::Frenzy => Mode::Territory,
+            Mode::Territory => Mode::CoOp,
+            Mode::CoOp => Mode::Classic,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    pub fn name(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "EASY",
+            Difficulty::Normal => "NORMAL",
+            Difficulty::Hard => "HARD",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            Difficulty::Easy => Difficulty::Normal,
+            Difficulty::Normal => Difficulty::Hard,
+            Difficulty::Hard => Difficulty::Easy,
+        }
+    }
+
+    /// Multiplier applied to the duration of a tick.
+    fn tick_factor(self) -> f64 {
+        match self {
+            Difficulty::Easy => 1.5,
+            Difficulty::Normal => 1.0,
+            Difficulty::Hard => 0.6,
+        }
+    }
+}
+
+/// Smallest width and height of the board that fits the snakes of a full multiplayer match.
+pub const MIN_SIZE: i32 = 8;
+pub const MAX_SIZE: i32 = 512;
+/// Most layers of a stacked board.
+pub const MAX_LAYERS: u32 = 4;
+/// Largest `food_spread`, beyond which the weights of the cells on the largest boards wouldn't fit
+/// in 64 bits.
+pub const MAX_FOOD_SPREAD: u32 = 2;
+
+/// Parameters that affect the gameplay.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub mode: Mode,
+    pub difficulty: Difficulty,
+    pub width: i32,
+    pub height: i32,
+    /// Duration of a tick at normal difficulty.
+    pub tick_ms: u64,
+    /// Number of ticks between two pieces of food appearing, while there are few of them.
+    pub food_interval: u32,
+    /// Most pieces of food on the board at once, or 0 for no limit. The closer the board gets to
+    /// the limit, the longer it takes for more food to appear.
+    pub max_food: u32,
+    /// How strongly the food prefers the cells far from the heads of the snakes. A cell is picked
+    /// with the probability proportional to the length of the way to it from the nearest head to
+    /// this power, so with 0 every reachable cell is as likely.
+    pub food_spread: u32,
+    /// Number of ticks that food stays fresh, or 0 for food that never goes bad. After that it
+    /// rots, and a snake eating it gets shorter instead of longer. Rotten food disappears after
+    /// half as many ticks again.
+    pub food_lifetime: u32,
+    /// Golden food, worth more points but disappearing soon, appears on one tick in that many on
+    /// average, or never if it's 0.
+    pub golden_food_odds: u32,
+    /// A bomb, which clears the walls around it when eaten, appears on one tick in that many on
+    /// average, or never if it's 0. Boards without walls get no bombs.
+    pub bomb_odds: u32,
+    /// Number of ticks after the player's snake appears or comes back during which the game
+    /// speeds up from half the speed to the full one, or 0 to go at full speed right away.
+    pub slow_start: u32,
+    /// Shape of the cells of the board, see `topology`. Left out of the saved parameters while
+    /// it is square, so that it doesn't change the hash of the older ones.
+    #[serde(skip_serializing_if = "Shape::is_square")]
+    pub shape: Shape,
+    /// Number of boards of `width` × `height` stacked on top of each other and connected by
+    /// ladders, see `World::layers`. Left out of the saved parameters while there is one.
+    #[serde(skip_serializing_if = "is_one")]
+    pub layers: u32,
+    /// Rules of a custom game. Left out of the saved parameters while they are the standard ones.
+    #[serde(skip_serializing_if = "Ruleset::is_standard")]
+    pub rules: Ruleset,
+}
+
+fn is_one(layers: &u32) -> bool {
+    *layers == 1
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            mode: Mode::Classic,
+            difficulty: Difficulty::Normal,
+            width: 15,
+            height: 15,
+            tick_ms: 400,
+            food_interval: 4,
+            max_food: 0,
+            food_spread: 1,
+            food_lifetime: 60,
+            golden_food_odds: 200,
+            bomb_odds: 100,
+            slow_start: 6,
+            shape: Shape::Square,
+            layers: 1,
+            rules: Ruleset::default(),
+        }
+    }
+}
+
+impl Config {
+    pub fn tick(&self) -> Duration {
+        Duration::from_millis(self.tick_ms).mul_f64(self.difficulty.tick_factor())
+    }
+
+    /// The interval between two pieces of food and their limit, as the mode changes them.
+    pub fn food_pacing(&self) -> (u32, u32) {
+        match self.mode {
+            Mode::Classic | Mode::Territory | Mode::CoOp => (self.food_interval, self.max_food),
+            Mode::Frenzy => (self.food_interval / FRENZY_SPEEDUP, FRENZY_MAX_FOOD),
+        }
+    }
+
+    /// Number of ticks after which the game ends, if the mode has a timer.
+    pub fn time_limit(&self) -> Option<u64> {
+        match self.mode {
+            Mode::Classic | Mode::Frenzy | Mode::CoOp => None,
+            Mode::Territory => Some(TERRITORY_TICKS),
+        }
+    }
+
+    /// Checks that a game can be played with these parameters, e.g. after reading them from a
+    /// file or from the network.
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, size) in [("width", self.width), ("height", self.height)] {
+            if !(MIN_SIZE..=MAX_SIZE).contains(&size) {
+                return Err(format!(
+                    "The {} of the board should be from {} to {}, not {}",
+                    name, MIN_SIZE, MAX_SIZE, size
+                ));
+            }
+        }
+        if !(1..=MAX_LAYERS).contains(&self.layers) {
+            return Err(format!(
+                "The board should have from 1 to {} layers, not {}",
+                MAX_LAYERS, self.layers
+            ));
+        }
+        if self.tick_ms == 0 {
+            return Err("The duration of a tick should be positive".to_string());
+        }
+        if self.food_spread > MAX_FOOD_SPREAD {
+            return Err(format!(
+                "The spread of the food should be at most {}, not {}",
+                MAX_FOOD_SPREAD, self.food_spread
+            ));
+        }
+        self.rules.validate()
+    }
+
+    /// Short fingerprint of the parameters, stable across runs and platforms, so that sessions
+    /// played with identical settings can be grouped together.
+    pub fn hash(&self) -> String {
+        let serialized = serde_json::to_string(self).unwrap();
+        // 64-bit FNV-1a
+        let mut hash: u64 = 0xcbf29ce484222325;
+        for byte in serialized.bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+        format!("{:016x}", hash)
+    }
+}
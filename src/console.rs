@@ -0,0 +1,221 @@
+//! Drop-down developer console, opened with the backtick key when the game runs with `--debug`.
+//! Up and down go through the previous commands, `help` lists the rest. The cheats can also be
+//! toggled with Ctrl and a key without opening the console.
+
+use crate::name_entry::keycode_char;
+use snake_pixels::font::{draw_text, text_width, GLYPH_HEIGHT, LINE_HEIGHT};
+use snake_pixels::framebuffer::FrameBuffer;
+use snake_pixels::grid::Vec2;
+use snake_pixels::palette::Palette;
+use std::collections::VecDeque;
+use winit::keyboard::KeyCode;
+
+pub const TOGGLE_KEY: KeyCode = KeyCode::Backquote;
+
+const MAX_INPUT_LEN: usize = 28;
+const MAX_HISTORY: usize = 50;
+const MAX_OUTPUT: usize = 20;
+
+/// Segments that the snake grows by with the key chord.
+const CHORD_GROWTH: u32 = 10;
+
+const HELP: [&str; 10] = [
+    "HELP",
+    "SPAWN_FOOD X Y",
+    "SET_TICK MS",
+    "TELEPORT X Y",
+    "GOD (CTRL+G)",
+    "GROW N (CTRL+E)",
+    "CLEAR_FOOD (CTRL+F)",
+    "SLOW (CTRL+T)",
+    "SEED N",
+    "CLEAR",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+    SpawnFood(Vec2),
+    /// Duration of a tick at normal difficulty in milliseconds.
+    SetTick(u64),
+    Teleport(Vec2),
+    /// Toggles the invulnerability of the player's snake.
+    God,
+    /// Makes the player's snake longer by this many segments over the next ticks.
+    Grow(u32),
+    /// Takes all the food off the board.
+    ClearFood,
+    /// Toggles slow motion.
+    Slow,
+    /// Seed of the next games.
+    Seed(u64),
+}
+
+impl Command {
+    /// Parses a line of the console, case-insensitively. The error is meant for the console.
+    pub fn parse(line: &str) -> Result<Command, String> {
+        let words: Vec<String> = line.split_whitespace().map(str::to_uppercase).collect();
+        let number = |i: usize| -> Result<u64, String> {
+            match words.get(i).map(|word| word.parse()) {
+                Some(Ok(n)) => Ok(n),
+                Some(Err(_)) => Err(format!("NOT A NUMBER: {}", words[i])),
+                None => Err(format!("{} NEEDS MORE ARGUMENTS", words[0])),
+            }
+        };
+        let cell = || -> Result<Vec2, String> {
+            let (x, y) = (number(1)?, number(2)?);
+            Ok(Vec2(
+                x.min(i32::MAX as u64) as i32,
+                y.min(i32::MAX as u64) as i32,
+            ))
+        };
+        let (command, args) = match words.first().map(String::as_str) {
+            Some("SPAWN_FOOD") => (Command::SpawnFood(cell()?), 2),
+            Some("SET_TICK") => (Command::SetTick(number(1)?), 1),
+            Some("TELEPORT") => (Command::Teleport(cell()?), 2),
+            Some("GOD") => (Command::God, 0),
+            Some("GROW") => (Command::Grow(number(1)?.min(u32::MAX as u64) as u32), 1),
+            Some("CLEAR_FOOD") => (Command::ClearFood, 0),
+            Some("SLOW") => (Command::Slow, 0),
+            Some("SEED") => (Command::Seed(number(1)?), 1),
+            Some(word) => return Err(format!("UNKNOWN COMMAND {}", word)),
+            None => return Err(String::new()),
+        };
+        if words.len() > args + 1 {
+            return Err(format!("{} TAKES {} ARGUMENTS", words[0], args));
+        }
+        Ok(command)
+    }
+
+    /// The cheat run by pressing the key while holding Ctrl.
+    pub fn chord(keycode: KeyCode) -> Option<Command> {
+        match keycode {
+            KeyCode::KeyG => Some(Command::God),
+            KeyCode::KeyE => Some(Command::Grow(CHORD_GROWTH)),
+            KeyCode::KeyF => Some(Command::ClearFood),
+            KeyCode::KeyT => Some(Command::Slow),
+            _ => None,
+        }
+    }
+}
+
+pub fn is_chord_key(keycode: KeyCode) -> bool {
+    matches!(keycode, KeyCode::ControlLeft | KeyCode::ControlRight)
+}
+
+pub enum ConsoleEvent {
+    None,
+    Run(Command),
+    Close,
+}
+
+#[derive(Default)]
+pub struct Console {
+    input: String,
+    /// Entered lines, the latest last.
+    history: Vec<String>,
+    /// Position in the history of the line being shown in the input, or `None` for a new one.
+    browsing: Option<usize>,
+    /// Lines printed so far, the latest last.
+    output: VecDeque<String>,
+}
+
+impl Console {
+    pub fn print(&mut self, line: impl Into<String>) {
+        if self.output.len() == MAX_OUTPUT {
+            self.output.pop_front();
+        }
+        self.output.push_back(line.into());
+    }
+
+    fn browse(&mut self, index: Option<usize>) {
+        self.browsing = index;
+        self.input = match index {
+            Some(i) => self.history[i].clone(),
+            None => String::new(),
+        };
+    }
+
+    pub fn on_keypress(&mut self, keycode: KeyCode) -> ConsoleEvent {
+        match keycode {
+            TOGGLE_KEY | KeyCode::Escape => return ConsoleEvent::Close,
+            KeyCode::Enter | KeyCode::NumpadEnter => {
+                let line = std::mem::take(&mut self.input);
+                self.browsing = None;
+                if line.trim().is_empty() {
+                    return ConsoleEvent::None;
+                }
+                self.print(format!("> {}", line));
+                if self.history.last() != Some(&line) {
+                    if self.history.len() == MAX_HISTORY {
+                        self.history.remove(0);
+                    }
+                    self.history.push(line.clone());
+                }
+                match line.trim().to_uppercase().as_str() {
+                    "CLEAR" => self.output.clear(),
+                    "HELP" => {
+                        for command in HELP.iter() {
+                            self.print(*command);
+                        }
+                    }
+                    _ => match Command::parse(&line) {
+                        Ok(command) => return ConsoleEvent::Run(command),
+                        Err(e) => self.print(e),
+                    },
+                }
+            }
+            KeyCode::ArrowUp if !self.history.is_empty() => {
+                let index = match self.browsing {
+                    Some(i) => i.saturating_sub(1),
+                    None => self.history.len() - 1,
+                };
+                self.browse(Some(index));
+            }
+            KeyCode::ArrowDown => match self.browsing {
+                Some(i) if i + 1 < self.history.len() => self.browse(Some(i + 1)),
+                _ => self.browse(None),
+            },
+            KeyCode::Backspace => {
+                self.input.pop();
+            }
+            _ => {
+                let c = match keycode {
+                    KeyCode::Space => Some(' '),
+                    // There is no shift, and commands use underscores rather than dashes.
+                    KeyCode::Minus => Some('_'),
+                    _ => keycode_char(keycode),
+                };
+                if let Some(c) = c.filter(|_| self.input.len() < MAX_INPUT_LEN) {
+                    self.input.push(c);
+                }
+            }
+        }
+        ConsoleEvent::None
+    }
+
+    /// Draws the console over the upper half of the screen.
+    pub fn render(&self, buffer: &mut FrameBuffer, palette: &Palette) {
+        let top = buffer.height() as i32;
+        let bottom = top / 2;
+        buffer.fill_rectangle(
+            0,
+            bottom,
+            buffer.width(),
+            (top - bottom) as usize,
+            palette.menu_bg,
+        );
+        buffer.fill_rectangle(0, bottom, buffer.width(), 1, palette.dim_text);
+        let prompt = format!("> {}", self.input);
+        let y = bottom + 2 + GLYPH_HEIGHT;
+        draw_text(buffer, 1, y, &prompt, 1, palette.selected);
+        let cursor = 1 + text_width(&prompt) + 1;
+        buffer.fill_rectangle(cursor, y - GLYPH_HEIGHT - 1, 3, 1, palette.selected);
+        for (i, line) in self.output.iter().rev().enumerate() {
+            let y = y + (i as i32 + 1) * LINE_HEIGHT;
+            if y > top {
+                break;
+            }
+            draw_text(buffer, 1, y, line, 1, palette.text);
+        }
+    }
+}
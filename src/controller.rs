@@ -0,0 +1,248 @@
+//! Whatever steers a snake: the player at the keyboard, a recorded game, a remote player or a
+//! bot. `State` asks the controller of every snake for its direction before each tick.
+
+use crate::grid::{Direction, Tile, Vec2};
+use crate::world::{Snake, SnakeId, World};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::mpsc::{self, Receiver, Sender};
+
+/// The game as seen by the controller of one snake, or by anything else that only needs to look
+/// at the board: the remote control, the training environment and the tests. It borrows the
+/// world, so it costs nothing to make; `snapshot` copies what it shows.
+#[derive(Clone, Copy)]
+pub struct GameView<'a> {
+    world: &'a World,
+    snake: SnakeId,
+    /// Distance from the head beyond which the food and the territory are hidden, if any.
+    sight: Option<i32>,
+}
+
+impl<'a> GameView<'a> {
+    pub fn new(world: &'a World, snake: SnakeId) -> Self {
+        GameView {
+            world,
+            snake,
+            sight: None,
+        }
+    }
+
+    /// The same view, showing no food beyond `radius` cells from the head, and every cell there
+    /// as if the snake owned it already.
+    pub fn with_sight(self, radius: i32) -> Self {
+        GameView {
+            sight: Some(radius),
+            ..self
+        }
+    }
+
+    pub fn sight(&self) -> Option<i32> {
+        self.sight
+    }
+
+    /// Whether the view shows what is in the cell.
+    pub fn sees(&self, pos: Vec2) -> bool {
+        self.sight
+            .is_none_or(|radius| self.world.topology().distance(self.head(), pos) <= radius)
+    }
+
+    pub fn world(&self) -> &'a World {
+        self.world
+    }
+
+    /// The snake being steered.
+    pub fn id(&self) -> SnakeId {
+        self.snake
+    }
+
+    pub fn snake(&self) -> &'a Snake {
+        &self.world.snakes()[self.snake]
+    }
+
+    /// The tick that the decision is for.
+    pub fn next_tick(&self) -> u64 {
+        self.world.tick() + 1
+    }
+
+    pub fn tick(&self) -> u64 {
+        self.world.tick()
+    }
+
+    pub fn width(&self) -> i32 {
+        self.world.width()
+    }
+
+    pub fn height(&self) -> i32 {
+        self.world.height()
+    }
+
+    pub fn head(&self) -> Vec2 {
+        self.snake().head
+    }
+
+    /// Where the head moves on the next tick, unless the snake turns.
+    pub fn velocity(&self) -> Vec2 {
+        let Vec2(x, y) = self.world.neighbor(self.head(), self.snake().direction);
+        Vec2(x - self.head().0, y - self.head().1)
+    }
+
+    pub fn score(&self) -> u32 {
+        self.snake().score
+    }
+
+    /// Positions of the fresh food in sight.
+    pub fn food(&self) -> impl Iterator<Item = Vec2> + 'a {
+        let view = *self;
+        self.world.food().filter(move |&pos| view.sees(pos))
+    }
+
+    /// Whether there is fresh food in sight in the cell.
+    pub fn has_food(&self, pos: Vec2) -> bool {
+        self.world.tile(pos) == Some(Tile::Food) && self.sees(pos)
+    }
+
+    /// The snake that painted the cell last, in the territory mode. Out of sight, that is always
+    /// the snake being steered.
+    pub fn owner(&self, pos: Vec2) -> Option<SnakeId> {
+        let on_board = self.world.tile(pos).is_some();
+        if self.world.has_territory() && on_board && !self.sees(pos) {
+            Some(self.snake)
+        } else {
+            self.world.owner(pos)
+        }
+    }
+
+    /// Whether the cell is off the board or taken by a wall, a portal or a snake.
+    pub fn is_occupied(&self, pos: Vec2) -> bool {
+        matches!(
+            self.world.tile(pos),
+            None | Some(Tile::Wall) | Some(Tile::Portal) | Some(Tile::Snake)
+        )
+    }
+
+    /// Whether moving in the direction doesn't run into a wall or a snake on the next tick. The
+    /// tip of the snake's own tail is safe, since it moves away.
+    pub fn is_safe(&self, direction: Direction) -> bool {
+        let pos = self.world.neighbor(self.head(), direction);
+        if !self.world.can_enter(pos, direction) {
+            return false;
+        }
+        !self.is_occupied(pos) || Some(pos) == self.snake().tail.back().copied()
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        let snake = self.snake();
+        let occupied = (0..self.height())
+            .flat_map(|y| (0..self.width()).map(move |x| Vec2(x, y)))
+            .map(|pos| self.is_occupied(pos))
+            .collect();
+        Snapshot {
+            width: self.width(),
+            height: self.height(),
+            tick: self.tick(),
+            occupied,
+            head: snake.head,
+            velocity: self.velocity(),
+            tail: snake.tail.iter().copied().collect(),
+            alive: snake.alive,
+            score: snake.score,
+            food: self.food().collect(),
+        }
+    }
+}
+
+/// A copy of what a `GameView` shows, e.g. to send it to another program.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub width: i32,
+    pub height: i32,
+    pub tick: u64,
+    /// `width` × `height` cells row by row from the bottom up, see `GameView::is_occupied`.
+    pub occupied: Vec<bool>,
+    pub head: Vec2,
+    pub velocity: Vec2,
+    /// Segments from the one right behind the head to the tip of the tail.
+    pub tail: Vec<Vec2>,
+    pub alive: bool,
+    pub score: u32,
+    pub food: Vec<Vec2>,
+}
+
+pub trait Controller {
+    /// The direction in which the snake moves on the next tick.
+    fn decide(&mut self, view: &GameView) -> Direction;
+
+    /// A direction chosen by the player. Only controllers that follow the player use it.
+    fn input(&mut self, _direction: Direction) {}
+
+    /// The player's direction waiting for the next tick, if any.
+    fn pending(&self) -> Option<Direction> {
+        None
+    }
+}
+
+/// Follows the player. If several directions are chosen during one tick, the last one wins.
+#[derive(Default)]
+pub struct Keyboard {
+    turn: Option<Direction>,
+}
+
+impl Controller for Keyboard {
+    fn decide(&mut self, view: &GameView) -> Direction {
+        self.turn.take().unwrap_or(view.snake().direction)
+    }
+
+    fn input(&mut self, direction: Direction) {
+        self.turn = Some(direction);
+    }
+
+    fn pending(&self) -> Option<Direction> {
+        self.turn
+    }
+}
+
+/// Repeats the turns of a recorded game.
+pub struct Replay {
+    turns: BTreeMap<u64, Direction>,
+}
+
+impl Replay {
+    /// `turns` holds the new direction of the snake by the tick at which it was taken.
+    pub fn new(turns: impl IntoIterator<Item = (u64, Direction)>) -> Self {
+        Replay {
+            turns: turns.into_iter().collect(),
+        }
+    }
+}
+
+impl Controller for Replay {
+    fn decide(&mut self, view: &GameView) -> Direction {
+        self.turns
+            .get(&view.next_tick())
+            .copied()
+            .unwrap_or(view.snake().direction)
+    }
+}
+
+/// Steers by the directions received from another thread, typically the one talking to a
+/// remote player. The latest direction received before the tick wins.
+pub struct Remote {
+    receiver: Receiver<Direction>,
+}
+
+impl Remote {
+    /// Creates the controller together with the sender for its directions.
+    pub fn channel() -> (Sender<Direction>, Self) {
+        let (sender, receiver) = mpsc::channel();
+        (sender, Remote { receiver })
+    }
+}
+
+impl Controller for Remote {
+    fn decide(&mut self, view: &GameView) -> Direction {
+        self.receiver
+            .try_iter()
+            .last()
+            .unwrap_or(view.snake().direction)
+    }
+}
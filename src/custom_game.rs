@@ -0,0 +1,376 @@
+//! Screen where players set up a custom game: the mode and the rules that they want for it, over
+//! a preview of the board that the game starts on. Every mode keeps rules of its own, so that
+//! e.g. the territory mode can be walled off while the classic one wraps around. The mode and
+//! its rules can be saved as a ruleset and picked again later, see `presets`. In the modes with
+//! bots the rules include the handicaps of the bots.
+
+use crate::i18n::{tr, tr_fmt};
+use crate::modal::{Modal, ModalEvent};
+use crate::presets::{self, Preset, MAX_NAME_LEN};
+use crate::toasts::{Notice, Notifier};
+use log::warn;
+use snake_pixels::config::{Config, Mode};
+use snake_pixels::font::{draw_text_centered, LINE_HEIGHT};
+use snake_pixels::framebuffer::FrameBuffer;
+use snake_pixels::game::State;
+use snake_pixels::grid::Vec2;
+use snake_pixels::handicap::{MAX_HEAD_START, MAX_REACTION_DELAY};
+use snake_pixels::palette::Palette;
+use snake_pixels::ruleset::{Ruleset, MAX_OBSTACLES, OBSTACLES_STEP};
+use snake_pixels::world::{Camera, World};
+use std::collections::BTreeMap;
+use winit::keyboard::KeyCode;
+
+/// Vertical distance between the rows, which are packed tightly for all of them to fit.
+const ROW_HEIGHT: i32 = LINE_HEIGHT;
+/// Sight of the bots in cells, 0 for the whole board, in the order of the handicap growing.
+const BLIND_RADII: [u32; 5] = [0, 10, 7, 5, 3];
+/// Step in which the head start changes.
+const HEAD_START_STEP: u32 = 2;
+/// Thickness of the frame around the preview in cells, which shows where the board wraps.
+const PREVIEW_BORDER: i32 = 1;
+/// Opacity of the background of the menu over the preview, which keeps the text readable.
+const SHADE_ALPHA: u8 = 170;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Row {
+    Ruleset,
+    Mode,
+    Wrap,
+    Obstacles,
+    Speed,
+    RottenFood,
+    GoldenFood,
+    Bombs,
+    ReactionDelay,
+    BlindRadius,
+    HeadStart,
+}
+
+const ROWS: [Row; 8] = [
+    Row::Ruleset,
+    Row::Mode,
+    Row::Wrap,
+    Row::Obstacles,
+    Row::Speed,
+    Row::RottenFood,
+    Row::GoldenFood,
+    Row::Bombs,
+];
+
+/// Rows of the modes in which the player competes with bots.
+const HANDICAP_ROWS: [Row; 3] = [Row::ReactionDelay, Row::BlindRadius, Row::HeadStart];
+
+/// A dialog shown over the screen.
+enum Prompt {
+    None,
+    /// Entering the name to save the ruleset under.
+    Save(Modal),
+    /// Whether to save the ruleset over the one with the name.
+    Overwrite(Modal, String),
+}
+
+pub enum CustomGameEvent {
+    None,
+    /// Play the game shown in the preview.
+    Play,
+    Cancelled,
+}
+
+pub struct CustomGame {
+    /// Parameters of the game other than the mode and the rules.
+    base: Config,
+    mode: Mode,
+    rules: BTreeMap<Mode, Ruleset>,
+    /// Seed of the board in the preview, which the game starts from.
+    seed: u64,
+    preview: World,
+    row: usize,
+    /// Names of the saved rulesets.
+    presets: Vec<String>,
+    /// Index in `presets` of the ruleset that was last picked or saved, until anything changes.
+    preset: Option<usize>,
+    prompt: Prompt,
+    notifier: Notifier,
+}
+
+impl CustomGame {
+    pub fn new(
+        base: Config,
+        rules: BTreeMap<Mode, Ruleset>,
+        seed: u64,
+        notifier: Notifier,
+    ) -> Self {
+        let mode = base.mode;
+        let config = Config {
+            rules: rules.get(&mode).copied().unwrap_or_default(),
+            ..base.clone()
+        };
+        CustomGame {
+            base,
+            mode,
+            rules,
+            seed,
+            preview: preview(&config, seed),
+            row: 0,
+            presets: presets::list(),
+            preset: None,
+            prompt: Prompt::None,
+            notifier,
+        }
+    }
+
+    /// Picks the saved ruleset with the name, setting its mode and the rules of that mode.
+    pub fn pick(&mut self, name: &str) -> Result<(), String> {
+        let preset = presets::load(name)?;
+        self.mode = preset.mode;
+        self.rules.insert(preset.mode, preset.rules);
+        self.preset = self.presets.iter().position(|n| n == name);
+        self.update_preview();
+        Ok(())
+    }
+
+    /// Picks the next or the previous saved ruleset, skipping any that fail to load.
+    fn cycle_presets(&mut self, up: bool) {
+        let len = self.presets.len();
+        for i in 1..=len {
+            let index = match self.preset {
+                Some(current) if up => (current + i) % len,
+                Some(current) => (current + len - i) % len,
+                None if up => i - 1,
+                None => len - i,
+            };
+            let name = self.presets[index].clone();
+            match self.pick(&name) {
+                Ok(()) => return,
+                Err(e) => warn!("Failed to load ruleset {}: {}", name, e),
+            }
+        }
+    }
+
+    fn save(&mut self, name: String) {
+        let preset = Preset {
+            mode: self.mode,
+            rules: self.rules.get(&self.mode).copied().unwrap_or_default(),
+        };
+        if presets::save(&name, &preset) {
+            self.notifier.post(Notice::RulesetSaved(name.clone()));
+            if !self.presets.contains(&name) {
+                self.presets.push(name.clone());
+                self.presets.sort();
+            }
+            self.preset = self.presets.iter().position(|n| *n == name);
+        }
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// The rules of every mode, as last set.
+    pub fn rules(&self) -> &BTreeMap<Mode, Ruleset> {
+        &self.rules
+    }
+
+    /// Parameters of the game in the preview.
+    pub fn config(&self) -> Config {
+        Config {
+            mode: self.mode,
+            rules: self.rules.get(&self.mode).copied().unwrap_or_default(),
+            ..self.base.clone()
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    fn rows(&self) -> Vec<Row> {
+        let handicap = if State::bots(self.mode) > 0 {
+            &HANDICAP_ROWS[..]
+        } else {
+            &[]
+        };
+        ROWS.iter().chain(handicap).copied().collect()
+    }
+
+    fn update_preview(&mut self) {
+        self.preview = preview(&self.config(), self.seed);
+    }
+
+    /// Changes the value in the selected row by one step, up or down.
+    fn change(&mut self, up: bool) {
+        let row = self.rows()[self.row];
+        if row == Row::Ruleset {
+            return self.cycle_presets(up);
+        }
+        self.preset = None;
+        let rules = self.rules.entry(self.mode).or_default();
+        let handicap = &mut rules.handicap;
+        match row {
+            Row::Ruleset => unreachable!(),
+            Row::Mode => self.mode = self.mode.next(),
+            Row::Wrap => rules.wrap = !rules.wrap,
+            Row::Obstacles => {
+                let steps = MAX_OBSTACLES / OBSTACLES_STEP + 1;
+                let step = if up { 1 } else { steps - 1 };
+                rules.obstacles =
+                    (rules.obstacles / OBSTACLES_STEP + step) % steps * OBSTACLES_STEP;
+            }
+            Row::Speed => rules.speed = rules.speed.next(),
+            Row::RottenFood => rules.rotten_food = !rules.rotten_food,
+            Row::GoldenFood => rules.golden_food = !rules.golden_food,
+            Row::Bombs => rules.bombs = !rules.bombs,
+            Row::ReactionDelay => {
+                let steps = MAX_REACTION_DELAY + 1;
+                let step = if up { 1 } else { steps - 1 };
+                handicap.reaction_delay = (handicap.reaction_delay + step) % steps;
+            }
+            Row::BlindRadius => {
+                let len = BLIND_RADII.len();
+                let step = if up { 1 } else { len - 1 };
+                // A radius set by hand in a ruleset file goes back to the first one.
+                let current = BLIND_RADII.iter().position(|&r| r == handicap.blind_radius);
+                handicap.blind_radius = BLIND_RADII[current.map_or(0, |i| (i + step) % len)];
+            }
+            Row::HeadStart => {
+                let steps = MAX_HEAD_START / HEAD_START_STEP + 1;
+                let step = if up { 1 } else { steps - 1 };
+                handicap.head_start =
+                    (handicap.head_start / HEAD_START_STEP + step) % steps * HEAD_START_STEP;
+            }
+        }
+    }
+
+    pub fn on_keypress(&mut self, keycode: KeyCode) -> CustomGameEvent {
+        match &mut self.prompt {
+            Prompt::Save(modal) => {
+                match modal.on_keypress(keycode) {
+                    ModalEvent::Entered(name) if self.presets.contains(&name) => {
+                        let question = tr_fmt("OVERWRITE {}?", &[&name]);
+                        self.prompt = Prompt::Overwrite(Modal::confirm(&question), name);
+                    }
+                    ModalEvent::Entered(name) => {
+                        self.save(name);
+                        self.prompt = Prompt::None;
+                    }
+                    ModalEvent::Cancelled => self.prompt = Prompt::None,
+                    _ => (),
+                }
+                return CustomGameEvent::None;
+            }
+            Prompt::Overwrite(modal, name) => {
+                match modal.on_keypress(keycode) {
+                    ModalEvent::Confirmed => {
+                        let name = std::mem::take(name);
+                        self.save(name);
+                        self.prompt = Prompt::None;
+                    }
+                    ModalEvent::Cancelled => self.prompt = Prompt::None,
+                    _ => (),
+                }
+                return CustomGameEvent::None;
+            }
+            Prompt::None => (),
+        }
+        let rows = self.rows().len();
+        match keycode {
+            KeyCode::Enter | KeyCode::NumpadEnter => return CustomGameEvent::Play,
+            KeyCode::Escape => return CustomGameEvent::Cancelled,
+            KeyCode::ArrowUp => self.row = (self.row + rows - 1) % rows,
+            KeyCode::ArrowDown => self.row = (self.row + 1) % rows,
+            KeyCode::ArrowRight | KeyCode::Space => self.change(true),
+            KeyCode::ArrowLeft => self.change(false),
+            KeyCode::Tab => self.seed = rand::random(),
+            KeyCode::KeyS => {
+                let name = self.preset.map_or("", |i| self.presets[i].as_str());
+                let modal = Modal::name(&tr("SAVE RULESET"), name, MAX_NAME_LEN);
+                self.prompt = Prompt::Save(modal);
+                return CustomGameEvent::None;
+            }
+            _ => return CustomGameEvent::None,
+        }
+        self.update_preview();
+        CustomGameEvent::None
+    }
+
+    fn label(&self, row: Row) -> String {
+        let rules = self.rules.get(&self.mode).copied().unwrap_or_default();
+        let on_off = |on: bool| tr(if on { "ON" } else { "OFF" });
+        match row {
+            Row::Ruleset => {
+                let name = match self.preset {
+                    Some(i) => self.presets[i].to_uppercase(),
+                    None => tr("NONE"),
+                };
+                tr_fmt("RULESET: {}", &[&name])
+            }
+            Row::Mode => tr_fmt("MODE: {}", &[&tr(self.mode.name())]),
+            Row::Wrap => tr_fmt("WRAP: {}", &[&on_off(rules.wrap)]),
+            Row::Obstacles => tr_fmt("OBSTACLES: {}%", &[&rules.obstacles]),
+            Row::Speed => tr_fmt("SPEED: {}", &[&tr(rules.speed.name())]),
+            Row::RottenFood => tr_fmt("ROTTEN FOOD: {}", &[&on_off(rules.rotten_food)]),
+            Row::GoldenFood => tr_fmt("GOLDEN FOOD: {}", &[&on_off(rules.golden_food)]),
+            Row::Bombs => tr_fmt("BOMBS: {}", &[&on_off(rules.bombs)]),
+            Row::ReactionDelay => tr_fmt("AI DELAY: {}", &[&rules.handicap.reaction_delay]),
+            Row::BlindRadius => {
+                let sight = match rules.handicap.blind_radius {
+                    0 => tr("ALL"),
+                    radius => radius.to_string(),
+                };
+                tr_fmt("AI SIGHT: {}", &[&sight])
+            }
+            Row::HeadStart => tr_fmt("HEAD START: +{}", &[&rules.handicap.head_start]),
+        }
+    }
+
+    pub fn render(&self, buffer: &mut FrameBuffer, palette: &Palette) {
+        buffer.clear(palette.menu_bg);
+        render_board(buffer, palette, &self.preview);
+        let (width, top) = (buffer.width(), buffer.height());
+        buffer.blend_rectangle(0, 0, width, top, palette.menu_bg, SHADE_ALPHA);
+
+        let top = top as i32;
+        draw_text_centered(buffer, top - 12, &tr("CUSTOM GAME"), 2, palette.text);
+        let mut y = top - 26;
+        for (i, row) in self.rows().into_iter().enumerate() {
+            if i == self.row {
+                let label = format!("< {} >", self.label(row));
+                draw_text_centered(buffer, y, &label, 1, palette.selected);
+            } else {
+                draw_text_centered(buffer, y, &self.label(row), 1, palette.text);
+            }
+            y -= ROW_HEIGHT;
+        }
+
+        let hints = tr("ENTER: PLAY  ESC: CANCEL");
+        draw_text_centered(buffer, 16, &hints, 1, palette.dim_text);
+        let hints = tr("S: SAVE  TAB: NEW BOARD");
+        draw_text_centered(buffer, 9, &hints, 1, palette.dim_text);
+        if let Prompt::Save(modal) | Prompt::Overwrite(modal, _) = &self.prompt {
+            modal.render(buffer, palette);
+        }
+    }
+}
+
+/// Draws the whole board with its frame, as large as it fits on the screen. Returns the camera
+/// that it was drawn through.
+pub fn render_board(buffer: &mut FrameBuffer, palette: &Palette, world: &World) -> Camera {
+    let (width, height) = world.extent();
+    let framed = (width + 2 * PREVIEW_BORDER, height + 2 * PREVIEW_BORDER);
+    let camera = Camera::fit(buffer, framed);
+    let margin = PREVIEW_BORDER * camera.cell;
+    let camera = Camera {
+        origin: camera.origin + Vec2(margin, margin),
+        ..camera
+    };
+    let camera = world.focus(camera.with_shape(world.shape()));
+    world.render_with_camera(buffer, palette, &camera, &[]);
+    world.render_border(buffer, palette, &camera, PREVIEW_BORDER);
+    camera
+}
+
+/// The board of a game with the parameters, with all of its snakes.
+fn preview(config: &Config, seed: u64) -> World {
+    World::new(config, State::snakes(config.mode), seed)
+}
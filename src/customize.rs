@@ -0,0 +1,203 @@
+//! Screen where players pick how their snake looks, with a preview of the snake on top. The
+//! color is picked with bars of hue, saturation and value, which show how the color would
+//! change along them.
+
+use crate::i18n::{tr, tr_fmt};
+use snake_pixels::font::{draw_text, draw_text_centered};
+use snake_pixels::framebuffer::{Color, FrameBuffer};
+use snake_pixels::grid::{Direction, Vec2};
+use snake_pixels::palette::Palette;
+use snake_pixels::skin::{Hsv, Skin};
+use winit::keyboard::KeyCode;
+
+/// Length of the snake in the preview.
+const PREVIEW_LENGTH: i32 = 8;
+const PREVIEW_CELL: i32 = 8;
+const HUE_STEP: u16 = 10;
+const PERCENT_STEP: u8 = 5;
+/// Space between the sides of the screen and the bars.
+const MARGIN: i32 = 4;
+/// Space for the letter in front of each bar.
+const LABEL_WIDTH: i32 = 8;
+const BAR_HEIGHT: i32 = 5;
+/// Vertical distance between the rows.
+const ROW_HEIGHT: i32 = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Row {
+    Head,
+    Pattern,
+    Color,
+    Hue,
+    Saturation,
+    Value,
+}
+
+const ROWS: [Row; 6] = [
+    Row::Head,
+    Row::Pattern,
+    Row::Color,
+    Row::Hue,
+    Row::Saturation,
+    Row::Value,
+];
+
+pub enum CustomizeEvent {
+    None,
+    Confirmed(Skin),
+    Cancelled,
+}
+
+pub struct Customizer {
+    skin: Skin,
+    row: usize,
+}
+
+impl Customizer {
+    pub fn new(skin: Skin) -> Self {
+        Customizer { skin, row: 0 }
+    }
+
+    /// Changes the value in the selected row by one step, up or down.
+    fn change(&mut self, up: bool) {
+        let skin = &mut self.skin;
+        let step = |value: u8| {
+            if up {
+                (value + PERCENT_STEP).min(100)
+            } else {
+                value.saturating_sub(PERCENT_STEP)
+            }
+        };
+        match ROWS[self.row] {
+            Row::Head => skin.head = skin.head.next(),
+            Row::Pattern => skin.pattern = skin.pattern.next(),
+            Row::Color => skin.custom_color = !skin.custom_color,
+            Row::Hue => {
+                let hues = Hsv::MAX_HUE + 1;
+                let step = if up { HUE_STEP } else { hues - HUE_STEP };
+                skin.color.hue = (skin.color.hue + step) % hues;
+            }
+            Row::Saturation => skin.color.saturation = step(skin.color.saturation),
+            Row::Value => skin.color.value = step(skin.color.value),
+        }
+        // Picking a color means wanting it rather than the theme's.
+        if matches!(ROWS[self.row], Row::Hue | Row::Saturation | Row::Value) {
+            skin.custom_color = true;
+        }
+    }
+
+    pub fn on_keypress(&mut self, keycode: KeyCode) -> CustomizeEvent {
+        match keycode {
+            KeyCode::Enter | KeyCode::NumpadEnter => return CustomizeEvent::Confirmed(self.skin),
+            KeyCode::Escape => return CustomizeEvent::Cancelled,
+            KeyCode::ArrowUp => self.row = (self.row + ROWS.len() - 1) % ROWS.len(),
+            KeyCode::ArrowDown => self.row = (self.row + 1) % ROWS.len(),
+            KeyCode::ArrowRight | KeyCode::Space => self.change(true),
+            KeyCode::ArrowLeft => self.change(false),
+            _ => (),
+        }
+        CustomizeEvent::None
+    }
+
+    pub fn render(&self, buffer: &mut FrameBuffer, palette: &Palette) {
+        buffer.clear(palette.menu_bg);
+        let top = buffer.height() as i32;
+        draw_text_centered(buffer, top - 12, &tr("SNAKE"), 2, palette.text);
+        self.render_preview(buffer, palette, top - 32);
+
+        let skin = &self.skin;
+        let mut y = top - 32 - 2 * ROW_HEIGHT;
+        for (i, &row) in ROWS.iter().enumerate() {
+            let selected = i == self.row;
+            let color = if selected {
+                palette.selected
+            } else {
+                palette.text
+            };
+            let label = match row {
+                Row::Head => tr_fmt("HEAD: {}", &[&tr(skin.head.name())]),
+                Row::Pattern => tr_fmt("PATTERN: {}", &[&tr(skin.pattern.name())]),
+                Row::Color => {
+                    let name = if skin.custom_color { "CUSTOM" } else { "THEME" };
+                    tr_fmt("COLOR: {}", &[&tr(name)])
+                }
+                Row::Hue | Row::Saturation | Row::Value => {
+                    render_bar(buffer, y, row, skin.color, color);
+                    y -= ROW_HEIGHT;
+                    continue;
+                }
+            };
+            let label = if selected {
+                format!("< {} >", label)
+            } else {
+                label
+            };
+            draw_text_centered(buffer, y, &label, 1, color);
+            y -= ROW_HEIGHT;
+        }
+
+        draw_text_centered(buffer, 16, &tr("ENTER: OK"), 1, palette.dim_text);
+        draw_text_centered(buffer, 9, &tr("ESC: CANCEL"), 1, palette.dim_text);
+    }
+
+    /// A short snake going right on a strip of the board, with the top at `y`.
+    fn render_preview(&self, buffer: &mut FrameBuffer, palette: &Palette, y: i32) {
+        let width = PREVIEW_LENGTH * PREVIEW_CELL;
+        let left = (buffer.width() as i32 - width) / 2;
+        let bottom = y - PREVIEW_CELL;
+        buffer.fill_rectangle(
+            left,
+            bottom,
+            width as usize,
+            PREVIEW_CELL as usize,
+            palette.bg,
+        );
+        let colors = self
+            .skin
+            .colors()
+            .unwrap_or_else(|| palette.snake_colors(0));
+        let corner = |i: i32| Vec2(left + i * PREVIEW_CELL, bottom);
+        let head = PREVIEW_LENGTH - 1;
+        for i in 0..head {
+            let segment = (head - 1 - i) as usize;
+            self.skin
+                .draw_segment(buffer, corner(i), PREVIEW_CELL, segment, colors);
+        }
+        let direction = Direction::Right;
+        self.skin
+            .draw_head(buffer, corner(head), PREVIEW_CELL, direction, colors.0);
+    }
+}
+
+/// Draws the bar of the component of the color in the row, with a letter in front of it in
+/// `label_color`. The bar goes through the colors that differ only in the component, and has a
+/// mark at the current value. The tops of both are at `y`.
+fn render_bar(buffer: &mut FrameBuffer, y: i32, row: Row, color: Hsv, label_color: Color) {
+    let (label, max, value) = match row {
+        Row::Hue => ("H", Hsv::MAX_HUE, color.hue),
+        Row::Saturation => ("S", Hsv::MAX_SATURATION as u16, color.saturation as u16),
+        _ => ("V", Hsv::MAX_VALUE as u16, color.value as u16),
+    };
+    let with = |x: u16| match row {
+        Row::Hue => Hsv { hue: x, ..color },
+        Row::Saturation => Hsv {
+            saturation: x as u8,
+            ..color
+        },
+        _ => Hsv {
+            value: x as u8,
+            ..color
+        },
+    };
+    draw_text(buffer, MARGIN, y, &tr(label), 1, label_color);
+    let left = MARGIN + LABEL_WIDTH;
+    let width = buffer.width() as i32 - left - MARGIN;
+    let bottom = y - BAR_HEIGHT;
+    for i in 0..width {
+        let x = (i * max as i32 / (width - 1)) as u16;
+        buffer.fill_rectangle(left + i, bottom, 1, BAR_HEIGHT as usize, with(x).to_color());
+    }
+    let mark = left + value as i32 * (width - 1) / max as i32;
+    let height = (BAR_HEIGHT + 2) as usize;
+    buffer.fill_rectangle(mark, bottom - 1, 1, height, label_color);
+}
@@ -0,0 +1,551 @@
+//! Level editor. The left mouse button paints the cells with the chosen tool and the right one
+//! erases them. The tools are picked in the bar at the top or with the keys 1 to 9, 0 and O, and R
+//! turns the gates and the conveyors that their tools put down. Z or Backspace undoes the last
+//! change, Enter plays the level if it passes the checks, S saves it under a name and L loads a
+//! saved one. The mouse wheel zooms in on the level and dragging with the middle button moves
+//! around it.
+
+use crate::i18n::{tr, tr_fmt};
+use crate::menu::Menu;
+use crate::modal::{Modal, ModalEvent};
+use crate::storage;
+use crate::toasts::{Notice, Notifier};
+use crate::viewport::Viewport;
+use log::{error, info};
+use snake_pixels::font::{draw_text, GLYPH_HEIGHT};
+use snake_pixels::framebuffer::{Color, FrameBuffer};
+use snake_pixels::grid::{Direction, Vec2};
+use snake_pixels::level::{Cell, Level, LevelError};
+use snake_pixels::palette::Palette;
+use snake_pixels::topology::Shape;
+use snake_pixels::world::{render_arrow, render_flag, Camera};
+use std::path::PathBuf;
+use web_time::Instant;
+use winit::event::MouseButton;
+use winit::keyboard::KeyCode;
+
+const LEVELS_DIR: &str = "levels";
+const EXTENSION: &str = "txt";
+const MAX_NAME_LEN: usize = 8;
+/// Height of the tool bar at the top of the screen, in pixels.
+const TOOLBAR: i32 = 12;
+/// Size of a tool button and the distance between the starts of two buttons.
+const BUTTON: i32 = 8;
+const BUTTON_STEP: i32 = 10;
+const MAX_UNDO: usize = 100;
+/// Color marking the cell where the level check failed.
+const ERROR: Color = Color::rgb(0xE0, 0x30, 0x30);
+
+fn level_path(name: &str) -> PathBuf {
+    PathBuf::from(LEVELS_DIR).join(format!("{}.{}", name, EXTENSION))
+}
+
+/// Names of the saved levels.
+pub fn saved_levels() -> Vec<String> {
+    storage::list_files(LEVELS_DIR, EXTENSION)
+}
+
+/// Loads a saved level, logging the reason if it can't.
+pub fn load_level(name: &str) -> Option<Level> {
+    let text = storage::read_text(level_path(name))?;
+    Level::parse(&text)
+        .map_err(|e| error!("Failed to load level {}: {}", name, e))
+        .ok()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Tool {
+    Wall,
+    Portal,
+    Start,
+    FoodSpawner,
+    Erase,
+    Mud,
+    Boost,
+    Gate,
+    Conveyor,
+    Checkpoint,
+    Opening,
+}
+
+impl Tool {
+    const ALL: [Tool; 11] = [
+        Tool::Wall,
+        Tool::Portal,
+        Tool::Start,
+        Tool::FoodSpawner,
+        Tool::Erase,
+        Tool::Mud,
+        Tool::Boost,
+        Tool::Gate,
+        Tool::Conveyor,
+        Tool::Checkpoint,
+        Tool::Opening,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Tool::Wall => "WALL",
+            Tool::Portal => "PORTAL",
+            Tool::Start => "START",
+            Tool::FoodSpawner => "FOOD",
+            Tool::Erase => "ERASE",
+            Tool::Mud => "MUD",
+            Tool::Boost => "BOOST",
+            Tool::Gate => "GATE",
+            Tool::Conveyor => "CONVEYOR",
+            Tool::Checkpoint => "CHECKPOINT",
+            Tool::Opening => "OPENING",
+        }
+    }
+
+    /// The cell that the tool paints, with `portal` as the number of a new portal and `turn` as
+    /// the direction of a new gate or conveyor.
+    fn paints(self, portal: u8, turn: Direction) -> Cell {
+        match self {
+            Tool::Wall => Cell::Wall,
+            Tool::Portal => Cell::Portal(portal),
+            Tool::Start => Cell::Start,
+            Tool::FoodSpawner => Cell::FoodSpawner,
+            Tool::Erase => Cell::Empty,
+            Tool::Mud => Cell::Mud,
+            Tool::Boost => Cell::Boost,
+            Tool::Gate => Cell::Gate(turn),
+            Tool::Conveyor => Cell::Conveyor(turn),
+            Tool::Checkpoint => Cell::Checkpoint,
+            Tool::Opening => Cell::Opening,
+        }
+    }
+
+    /// Whether dragging the mouse keeps painting with the tool. Portals and starts are placed
+    /// one at a time.
+    fn drags(self) -> bool {
+        !matches!(self, Tool::Portal | Tool::Start)
+    }
+}
+
+fn cell_color(cell: Cell, palette: &Palette) -> Color {
+    match cell {
+        Cell::Empty => palette.bg,
+        Cell::Wall => palette.wall,
+        Cell::Start => palette.head,
+        Cell::FoodSpawner => palette.food,
+        Cell::Portal(_) => palette.portal,
+        Cell::Mud => palette.mud,
+        Cell::Boost => palette.boost,
+        Cell::Gate(_) => palette.bg,
+        Cell::Conveyor(_) => palette.conveyor,
+        Cell::Checkpoint => palette.bg,
+        Cell::Opening => palette.bg,
+    }
+}
+
+/// Draws the cell into the square of `size` × `size` pixels with the bottom left corner at
+/// `corner`.
+fn render_cell(buffer: &mut FrameBuffer, palette: &Palette, corner: Vec2, size: i32, cell: Cell) {
+    let Vec2(x, y) = corner;
+    buffer.fill_rectangle(
+        x,
+        y,
+        size as usize,
+        size as usize,
+        cell_color(cell, palette),
+    );
+    match cell {
+        Cell::Portal(n) => {
+            let y = y + (size - GLYPH_HEIGHT) / 2 + GLYPH_HEIGHT;
+            draw_text(buffer, x + (size - 3) / 2, y, &n.to_string(), 1, palette.bg);
+        }
+        Cell::Gate(direction) => render_arrow(buffer, corner, size, direction, palette.gate),
+        Cell::Conveyor(direction) => render_arrow(buffer, corner, size, direction, palette.bg),
+        Cell::Checkpoint => render_flag(buffer, corner, size, palette.checkpoint),
+        Cell::Opening => {
+            // A gap in a piece of the border, which stands in for the edge that it opens.
+            let (side, gap) = ((size / 4).max(1), (size / 3).max(1));
+            for x in [x, x + size - side] {
+                buffer.fill_rectangle(x, y, side as usize, gap as usize, palette.border);
+                let top = y + size - gap;
+                buffer.fill_rectangle(x, top, side as usize, gap as usize, palette.border);
+            }
+        }
+        _ => (),
+    }
+}
+
+/// A dialog shown over the editor.
+enum Prompt {
+    None,
+    /// Entering the name to save the level under.
+    Save(Modal),
+    /// Whether to save the level over the one with the name.
+    Overwrite(Modal, String),
+    /// Choosing a level to load, by its index in the names.
+    Load(Menu<usize>, Vec<String>),
+}
+
+pub enum EditorEvent {
+    None,
+    /// Play the level to try it out.
+    PlayTest(Level),
+    Exit,
+}
+
+pub struct Editor {
+    level: Level,
+    /// Name under which the level was last saved or loaded.
+    name: String,
+    tool: Tool,
+    /// The way that the gates and the conveyors put down with their tools point.
+    turn: Direction,
+    /// Levels before the last changes, the latest last.
+    undo: Vec<Level>,
+    /// The level at the start of the current mouse stroke.
+    stroke: Option<Level>,
+    /// What the held mouse button paints as the mouse moves.
+    painting: Option<Cell>,
+    /// Position of the mouse in the buffer.
+    cursor: Option<Vec2>,
+    prompt: Prompt,
+    /// Why the level couldn't be played, shown until the next action.
+    error: Option<LevelError>,
+    /// Size of the buffer the editor is drawn on, in pixels.
+    canvas: (i32, i32),
+    viewport: Viewport,
+    notifier: Notifier,
+}
+
+impl Editor {
+    pub fn new(width: i32, height: i32, canvas: (i32, i32), notifier: Notifier) -> Self {
+        Editor {
+            level: Level::new(width, height),
+            name: String::new(),
+            tool: Tool::Wall,
+            turn: Direction::Right,
+            undo: Vec::new(),
+            stroke: None,
+            painting: None,
+            cursor: None,
+            prompt: Prompt::None,
+            error: None,
+            canvas,
+            viewport: Viewport::new(),
+            notifier,
+        }
+    }
+
+    /// Path of the level's file inside the data directory, once it has been saved or loaded.
+    pub fn file(&self) -> Option<PathBuf> {
+        (!self.name.is_empty()).then(|| level_path(&self.name))
+    }
+
+    pub fn level(&self) -> &Level {
+        &self.level
+    }
+
+    /// Shows the level below the tool bar, zoomed in and moved around with the mouse.
+    fn camera(&self) -> Camera {
+        let size = (self.level.width(), self.level.height());
+        self.viewport.camera(self.fitted_camera(), size)
+    }
+
+    /// Shows the level below the tool bar with the largest cells that fit.
+    fn fitted_camera(&self) -> Camera {
+        let (width, height) = (self.level.width(), self.level.height());
+        let board_height = self.canvas.1 - TOOLBAR;
+        let cell = (self.canvas.0 / width.max(1))
+            .min(board_height / height.max(1))
+            .max(1);
+        Camera {
+            origin: Vec2(
+                (self.canvas.0 - width * cell) / 2,
+                (board_height - height * cell) / 2,
+            ),
+            cell,
+            border: 0,
+            shape: Shape::Square,
+            rows: None,
+            still: false,
+        }
+    }
+
+    /// The tool whose button is under a pixel of the buffer.
+    fn button_at(&self, pixel: Vec2) -> Option<Tool> {
+        let top = self.canvas.1 - 2;
+        if pixel.1 >= top || pixel.1 < top - BUTTON || pixel.0 < 2 {
+            return None;
+        }
+        let i = (pixel.0 - 2) / BUTTON_STEP;
+        let within = (pixel.0 - 2) % BUTTON_STEP < BUTTON;
+        Tool::ALL.get(i as usize).copied().filter(|_| within)
+    }
+
+    /// The cell of the level under the mouse.
+    fn hovered(&self) -> Option<Vec2> {
+        let cursor = self.cursor.filter(|pos| pos.1 < self.canvas.1 - TOOLBAR)?;
+        let pos = self.camera().cell_at(cursor);
+        self.level.get(pos).map(|_| pos)
+    }
+
+    fn paint(&mut self, cell: Cell) {
+        if let Some(pos) = self.hovered() {
+            self.level.set(pos, cell);
+        }
+    }
+
+    fn push_undo(&mut self, level: Level) {
+        if self.undo.len() == MAX_UNDO {
+            self.undo.remove(0);
+        }
+        self.undo.push(level);
+    }
+
+    /// Returns whether the screen needs to be redrawn.
+    pub fn on_cursor_moved(&mut self, pos: Vec2) -> bool {
+        if self.cursor == Some(pos) {
+            return false;
+        }
+        self.cursor = Some(pos);
+        if self.viewport.on_cursor_moved(pos) {
+            return true;
+        }
+        if let Some(cell) = self.painting {
+            self.paint(cell);
+        }
+        true
+    }
+
+    /// Returns whether the screen needs to be redrawn.
+    pub fn on_mouse_button(&mut self, button: MouseButton, pressed: bool) -> bool {
+        if !matches!(self.prompt, Prompt::None) {
+            return false;
+        }
+        if button == MouseButton::Middle {
+            self.viewport.on_middle_button(pressed);
+            return false;
+        }
+        self.error = None;
+        if !pressed {
+            self.painting = None;
+            if let Some(before) = self.stroke.take() {
+                if before != self.level {
+                    self.push_undo(before);
+                }
+            }
+            return false;
+        }
+        if let Some(tool) = self.cursor.and_then(|pos| self.button_at(pos)) {
+            self.tool = tool;
+            return true;
+        }
+        let (tool, cell) = match button {
+            MouseButton::Left => match self.level.next_portal() {
+                Some(n) => (self.tool, self.tool.paints(n, self.turn)),
+                // Every portal is taken.
+                None if self.tool == Tool::Portal => return false,
+                None => (self.tool, self.tool.paints(0, self.turn)),
+            },
+            MouseButton::Right => (Tool::Erase, Cell::Empty),
+            _ => return false,
+        };
+        self.stroke = Some(self.level.clone());
+        if tool.drags() {
+            self.painting = Some(cell);
+        }
+        self.paint(cell);
+        true
+    }
+
+    /// Returns whether the screen needs to be redrawn.
+    pub fn on_mouse_wheel(&mut self, notches: f32) -> bool {
+        if !matches!(self.prompt, Prompt::None) {
+            return false;
+        }
+        self.viewport.on_wheel(notches);
+        true
+    }
+
+    /// Moves the view on towards where the mouse sent it. Returns whether the screen needs to be
+    /// redrawn.
+    pub fn update(&mut self) -> bool {
+        self.viewport.update()
+    }
+
+    pub fn next_frame(&self) -> Option<Instant> {
+        self.viewport.next_frame()
+    }
+
+    fn save(&mut self, name: String) {
+        if storage::write_text(level_path(&name), &self.level.to_text()) {
+            info!("Saved level {}", name);
+            self.notifier.post(Notice::LevelSaved(name.clone()));
+            self.name = name;
+        }
+    }
+
+    pub fn on_keypress(&mut self, keycode: KeyCode) -> EditorEvent {
+        self.error = None;
+        match &mut self.prompt {
+            Prompt::Save(modal) => {
+                match modal.on_keypress(keycode) {
+                    // Saving again under the same name is what the player expects.
+                    ModalEvent::Entered(name)
+                        if name != self.name && saved_levels().contains(&name) =>
+                    {
+                        let question = tr_fmt("OVERWRITE {}?", &[&name]);
+                        self.prompt = Prompt::Overwrite(Modal::confirm(&question), name);
+                    }
+                    ModalEvent::Entered(name) => {
+                        self.save(name);
+                        self.prompt = Prompt::None;
+                    }
+                    ModalEvent::Cancelled => self.prompt = Prompt::None,
+                    _ => (),
+                }
+                return EditorEvent::None;
+            }
+            Prompt::Overwrite(modal, name) => {
+                match modal.on_keypress(keycode) {
+                    ModalEvent::Confirmed => {
+                        let name = std::mem::take(name);
+                        self.save(name);
+                        self.prompt = Prompt::None;
+                    }
+                    ModalEvent::Cancelled => self.prompt = Prompt::None,
+                    _ => (),
+                }
+                return EditorEvent::None;
+            }
+            Prompt::Load(menu, names) => {
+                match menu.on_keypress(keycode) {
+                    Some(i) => {
+                        let name = names[i].clone();
+                        if let Some(level) = load_level(&name) {
+                            let before = std::mem::replace(&mut self.level, level);
+                            self.push_undo(before);
+                            self.name = name;
+                        }
+                        self.prompt = Prompt::None;
+                    }
+                    None if keycode == KeyCode::Escape => self.prompt = Prompt::None,
+                    None => (),
+                }
+                return EditorEvent::None;
+            }
+            Prompt::None => (),
+        }
+        match keycode {
+            KeyCode::Digit1 => self.tool = Tool::Wall,
+            KeyCode::Digit2 => self.tool = Tool::Portal,
+            KeyCode::Digit3 => self.tool = Tool::Start,
+            KeyCode::Digit4 => self.tool = Tool::FoodSpawner,
+            KeyCode::Digit5 => self.tool = Tool::Erase,
+            KeyCode::Digit6 => self.tool = Tool::Mud,
+            KeyCode::Digit7 => self.tool = Tool::Boost,
+            KeyCode::Digit8 => self.tool = Tool::Gate,
+            KeyCode::Digit9 => self.tool = Tool::Conveyor,
+            KeyCode::Digit0 => self.tool = Tool::Checkpoint,
+            KeyCode::KeyO => self.tool = Tool::Opening,
+            KeyCode::KeyR => self.turn = self.turn.clockwise(),
+            KeyCode::KeyZ | KeyCode::Backspace => {
+                if let Some(level) = self.undo.pop() {
+                    self.level = level;
+                }
+            }
+            KeyCode::Enter | KeyCode::NumpadEnter => match self.level.validate() {
+                Ok(()) => return EditorEvent::PlayTest(self.level.clone()),
+                Err(e) => {
+                    info!("The level can't be played: {}", e);
+                    self.error = Some(e);
+                }
+            },
+            KeyCode::KeyS => {
+                self.prompt = Prompt::Save(Modal::name(&tr("SAVE LEVEL"), &self.name, MAX_NAME_LEN))
+            }
+            KeyCode::KeyL => {
+                let names = saved_levels();
+                if !names.is_empty() {
+                    let items: Vec<(&str, usize)> = names
+                        .iter()
+                        .enumerate()
+                        .map(|(i, name)| (name.as_str(), i))
+                        .collect();
+                    let menu = Menu::new(&tr("LOAD LEVEL"), items);
+                    self.prompt = Prompt::Load(menu, names);
+                }
+            }
+            KeyCode::Escape => return EditorEvent::Exit,
+            _ => (),
+        }
+        EditorEvent::None
+    }
+
+    pub fn render(&self, buffer: &mut FrameBuffer, palette: &Palette) {
+        match &self.prompt {
+            Prompt::Load(menu, _) => return menu.render(buffer, palette),
+            Prompt::Save(_) | Prompt::Overwrite(..) | Prompt::None => (),
+        }
+        buffer.clear(palette.menu_bg);
+        let Camera { origin, cell, .. } = self.camera();
+        for (pos, c) in self.level.cells() {
+            let corner = Vec2(origin.0 + pos.0 * cell, origin.1 + pos.1 * cell);
+            render_cell(buffer, palette, corner, cell, c);
+        }
+        // The rooms of a level made of several are stacked, with a line where one meets the next.
+        for room in 1..self.level.rooms().len() as i32 {
+            let y = origin.1 + room * self.level.room_height() * cell;
+            let width = (self.level.width() * cell) as usize;
+            buffer.fill_rectangle(origin.0, y, width, 1, palette.border);
+        }
+        if let Some(pos) = self.hovered() {
+            let (x, y) = (origin.0 + pos.0 * cell, origin.1 + pos.1 * cell);
+            buffer.blend_rectangle(x, y, cell as usize, cell as usize, palette.selected, 96);
+        }
+        if let Some(at) = self.error.and_then(|e| e.at) {
+            let pos = self.level.cell_at(at);
+            let (x, y) = (origin.0 + pos.0 * cell, origin.1 + pos.1 * cell);
+            buffer.blend_rectangle(x, y, cell as usize, cell as usize, ERROR, 192);
+        }
+
+        // The level zoomed in reaches under the tool bar.
+        let bar = self.canvas.1 - TOOLBAR;
+        buffer.fill_rectangle(
+            0,
+            bar,
+            self.canvas.0 as usize,
+            TOOLBAR as usize,
+            palette.menu_bg,
+        );
+
+        let top = self.canvas.1 - 2;
+        for (i, &tool) in Tool::ALL.iter().enumerate() {
+            let x = 2 + i as i32 * BUTTON_STEP;
+            let y = top - BUTTON;
+            if tool == self.tool {
+                let size = (BUTTON + 2) as usize;
+                buffer.fill_rectangle(x - 1, y - 1, size, size, palette.selected);
+            }
+            render_cell(
+                buffer,
+                palette,
+                Vec2(x, y),
+                BUTTON,
+                tool.paints(1, self.turn),
+            );
+        }
+        let x = 2 + Tool::ALL.len() as i32 * BUTTON_STEP + 2;
+        match self.error {
+            Some(e) => draw_text(
+                buffer,
+                x,
+                top - 1,
+                &tr(e.problem.label()),
+                1,
+                palette.selected,
+            ),
+            None => draw_text(buffer, x, top - 1, &tr(self.tool.name()), 1, palette.text),
+        }
+        if let Prompt::Save(modal) | Prompt::Overwrite(modal, _) = &self.prompt {
+            modal.render(buffer, palette);
+        }
+    }
+}
@@ -0,0 +1,94 @@
+//! Short animations over the board, which play out in real time rather than in ticks, so that
+//! they stay smooth however slow the game is. Through a still camera they keep still and only
+//! fade out.
+
+use snake_pixels::framebuffer::{Color, FrameBuffer};
+use snake_pixels::grid::Vec2;
+use snake_pixels::world::{Camera, BOMB_RADIUS};
+use std::time::Duration;
+use web_time::Instant;
+
+/// How long the ring of an explosion takes to reach the edge of the blast.
+const BLAST_TIME: Duration = Duration::from_millis(400);
+/// Time between two frames of an animation.
+const FRAME: Duration = Duration::from_millis(33);
+
+/// The ring of an explosion, growing from the bomb.
+struct Blast {
+    center: Vec2,
+    start: Instant,
+}
+
+#[derive(Default)]
+pub struct Effects {
+    blasts: Vec<Blast>,
+}
+
+impl Effects {
+    /// Starts the ring of the explosion of the bomb on the cell.
+    pub fn add_blast(&mut self, center: Vec2) {
+        self.blasts.push(Blast {
+            center,
+            start: Instant::now(),
+        });
+    }
+
+    pub fn clear(&mut self) {
+        self.blasts.clear();
+    }
+
+    /// Drops the animations that have ended. Returns whether the screen needs to be redrawn,
+    /// which it does while any of them plays and once more after the last one ends.
+    pub fn update(&mut self) -> bool {
+        let playing = !self.blasts.is_empty();
+        self.blasts
+            .retain(|blast| blast.start.elapsed() < BLAST_TIME);
+        playing
+    }
+
+    /// When the next frame of the animations is due, if any of them still plays.
+    pub fn next_frame(&self) -> Option<Instant> {
+        let playing = self
+            .blasts
+            .iter()
+            .any(|blast| blast.start.elapsed() < BLAST_TIME);
+        playing.then(|| Instant::now() + FRAME)
+    }
+
+    pub fn render(&self, buffer: &mut FrameBuffer, camera: &Camera, color: Color) {
+        let cell = camera.cell;
+        for blast in self
+            .blasts
+            .iter()
+            .filter(|blast| camera.shows(blast.center))
+        {
+            let progress = blast.start.elapsed().as_secs_f32() / BLAST_TIME.as_secs_f32();
+            if progress >= 1.0 {
+                continue;
+            }
+            let center = camera.corner(blast.center) + Vec2(cell / 2, cell / 2);
+            let reach = (BOMB_RADIUS * cell) as f32 + cell as f32 / 2.0;
+            let radius = if camera.still {
+                reach as i32
+            } else {
+                (progress * reach) as i32
+            };
+            // Fades out as it grows.
+            let alpha = (255.0 * (1.0 - progress)) as u8;
+            render_ring(buffer, center, radius, color, alpha);
+        }
+    }
+}
+
+/// Blends the pixels at the distance of about `radius` from `center` with the color.
+fn render_ring(buffer: &mut FrameBuffer, center: Vec2, radius: i32, color: Color, alpha: u8) {
+    let (inner, outer) = ((radius - 1).max(0).pow(2), radius.pow(2));
+    for dy in -radius..=radius {
+        for dx in -radius..=radius {
+            let distance = dx * dx + dy * dy;
+            if distance > inner && distance <= outer {
+                buffer.blend_rectangle(center.0 + dx, center.1 + dy, 1, 1, color, alpha);
+            }
+        }
+    }
+}
@@ -0,0 +1,386 @@
+//! Bot matches for watching: two to four bot policies play game after game against each other
+//! at a chosen speed while their wins are counted, for fun or to compare them. The matches can
+//! be paused, and the board shown as the autopilot reads it from the seat of any of the bots:
+//! the way to food that it would take, how far each cell is and where the snake would crash.
+
+use crate::custom_game::render_board;
+use crate::i18n::{tr, tr_fmt};
+use log::info;
+use snake_pixels::autopilot;
+use snake_pixels::bots::{self, Policy};
+use snake_pixels::config::{Config, Mode};
+use snake_pixels::font::{draw_text, draw_text_centered, text_width, GLYPH_HEIGHT, LINE_HEIGHT};
+use snake_pixels::framebuffer::FrameBuffer;
+use snake_pixels::palette::Palette;
+use snake_pixels::tournament::Match;
+use snake_pixels::world::SnakeId;
+use std::time::Duration;
+use web_time::Instant;
+use winit::keyboard::KeyCode;
+
+/// Most bots in a match, as many as there are starting places that don't crowd each other.
+const MAX_BOTS: usize = 4;
+/// How many times faster than in a game of the player the ticks can go.
+const SPEEDS: [u32; 5] = [1, 2, 4, 16, 64];
+/// Speed of the matches at first, by its index in `SPEEDS`.
+const DEFAULT_SPEED: usize = 2;
+/// How long the end of a match stays on the screen at the usual speed.
+const BETWEEN_MATCHES: Duration = Duration::from_secs(1);
+/// Longest time spent on the ticks of a single update. Beyond it the ticks are dropped, so that
+/// the slower policies at the highest speeds don't hold up the drawing.
+const UPDATE_BUDGET: Duration = Duration::from_millis(25);
+/// Vertical distance between the rows of the lineup.
+const ROW_HEIGHT: i32 = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Row {
+    /// The policy of the bot in a seat.
+    Bot(usize),
+    Mode,
+    Speed,
+}
+
+const ROWS: [Row; 6] = [
+    Row::Bot(0),
+    Row::Bot(1),
+    Row::Bot(2),
+    Row::Bot(3),
+    Row::Mode,
+    Row::Speed,
+];
+
+pub enum ExhibitionEvent {
+    None,
+    Exit,
+}
+
+/// The matches between the bots of the lineup, with their results so far.
+struct Series {
+    /// Index in `policies` of the policy of every snake.
+    seats: Vec<usize>,
+    game: Match,
+    /// Seed of the current match. Every next match gets the next one.
+    seed: u64,
+    wins: Vec<u32>,
+    draws: u32,
+    paused: bool,
+    /// The snake whose view of the board is shown.
+    inspected: Option<SnakeId>,
+    next_tick: Instant,
+}
+
+impl Series {
+    /// Counts the result of the match that just ended.
+    fn score(&mut self, policies: &[Policy]) {
+        let winner = self.game.winner();
+        match winner {
+            Some(id) => self.wins[id] += 1,
+            None => self.draws += 1,
+        }
+        let winner = winner.map_or("nobody", |id| policies[self.seats[id]].name);
+        info!("Bot match {} won by {}", self.seed, winner);
+    }
+}
+
+pub struct Exhibition {
+    config: Config,
+    policies: Vec<Policy>,
+    /// Index in `policies` of the policy in every seat. The first two are never empty.
+    lineup: [Option<usize>; MAX_BOTS],
+    mode: Mode,
+    /// Index in `SPEEDS`.
+    speed: usize,
+    row: usize,
+    /// The matches being watched, or `None` while the lineup is set up.
+    series: Option<Series>,
+}
+
+impl Exhibition {
+    pub fn new(config: Config) -> Self {
+        // The co-op mode shares the lives between the snakes, which doesn't make a contest.
+        let mode = match config.mode {
+            Mode::CoOp => Mode::Classic,
+            mode => mode,
+        };
+        Exhibition {
+            config,
+            policies: bots::policies(),
+            lineup: [Some(0), Some(1), None, None],
+            mode,
+            speed: DEFAULT_SPEED,
+            row: 0,
+            series: None,
+        }
+    }
+
+    /// Duration of a tick at the chosen speed.
+    fn tick(&self) -> Duration {
+        self.config.tick() / SPEEDS[self.speed]
+    }
+
+    fn start(&mut self) {
+        let seats: Vec<usize> = self.lineup.iter().flatten().copied().collect();
+        let seed = rand::random();
+        info!("Starting bot matches with seed {}", seed);
+        self.series = Some(Series {
+            game: self.new_match(&seats, seed),
+            wins: vec![0; seats.len()],
+            seats,
+            seed,
+            draws: 0,
+            paused: false,
+            inspected: None,
+            next_tick: Instant::now() + self.tick(),
+        });
+    }
+
+    fn new_match(&self, seats: &[usize], seed: u64) -> Match {
+        let config = Config {
+            mode: self.mode,
+            ..self.config.clone()
+        };
+        let controllers = seats
+            .iter()
+            .enumerate()
+            .map(|(i, &p)| (self.policies[p].create)(seed.wrapping_add(i as u64)))
+            .collect();
+        Match::new(&config, controllers, seed)
+    }
+
+    /// Plays the ticks that are due. Returns whether the screen needs to be redrawn.
+    pub fn update(&mut self) -> bool {
+        let tick = self.tick();
+        let between = BETWEEN_MATCHES / SPEEDS[self.speed];
+        let start = Instant::now();
+        let mut series = match self.series.take() {
+            Some(series) if !series.paused && series.next_tick <= start => series,
+            series => {
+                self.series = series;
+                return false;
+            }
+        };
+        while series.next_tick <= start {
+            if series.game.is_over() {
+                series.seed = series.seed.wrapping_add(1);
+                series.game = self.new_match(&series.seats, series.seed);
+            } else {
+                series.game.step();
+            }
+            series.next_tick += tick;
+            if series.game.is_over() {
+                series.score(&self.policies);
+                series.next_tick += between;
+            }
+            if start.elapsed() >= UPDATE_BUDGET {
+                series.next_tick = series.next_tick.max(Instant::now());
+                break;
+            }
+        }
+        self.series = Some(series);
+        true
+    }
+
+    pub fn next_wakeup(&self) -> Option<Instant> {
+        match &self.series {
+            Some(series) if !series.paused => Some(series.next_tick),
+            _ => None,
+        }
+    }
+
+    /// Changes the value in the selected row of the lineup by one step, up or down.
+    fn change(&mut self, up: bool) {
+        match ROWS[self.row] {
+            Row::Bot(seat) => {
+                // The empty seat comes before the first policy, for the seats that can be empty.
+                let first = if seat < 2 { 1 } else { 0 };
+                let choices = self.policies.len() + 1;
+                let current = self.lineup[seat].map_or(0, |p| p + 1);
+                let step = if up { 1 } else { choices - first - 1 };
+                let next = (current - first + step) % (choices - first) + first;
+                self.lineup[seat] = next.checked_sub(1);
+            }
+            Row::Mode => {
+                self.mode = match self.mode.next() {
+                    Mode::CoOp => Mode::CoOp.next(),
+                    mode => mode,
+                }
+            }
+            Row::Speed => self.change_speed(up),
+        }
+    }
+
+    fn change_speed(&mut self, up: bool) {
+        self.speed = if up {
+            (self.speed + 1).min(SPEEDS.len() - 1)
+        } else {
+            self.speed.saturating_sub(1)
+        };
+        let tick = self.tick();
+        if let Some(series) = &mut self.series {
+            series.next_tick = series.next_tick.min(Instant::now() + tick);
+        }
+    }
+
+    pub fn on_keypress(&mut self, keycode: KeyCode) -> ExhibitionEvent {
+        if self.series.is_some() {
+            return self.on_match_keypress(keycode);
+        }
+        match keycode {
+            KeyCode::Enter | KeyCode::NumpadEnter => self.start(),
+            KeyCode::Escape => return ExhibitionEvent::Exit,
+            KeyCode::ArrowUp => self.row = (self.row + ROWS.len() - 1) % ROWS.len(),
+            KeyCode::ArrowDown => self.row = (self.row + 1) % ROWS.len(),
+            KeyCode::ArrowRight | KeyCode::Space => self.change(true),
+            KeyCode::ArrowLeft => self.change(false),
+            _ => (),
+        }
+        ExhibitionEvent::None
+    }
+
+    fn on_match_keypress(&mut self, keycode: KeyCode) -> ExhibitionEvent {
+        match keycode {
+            KeyCode::ArrowRight | KeyCode::ArrowUp => self.change_speed(true),
+            KeyCode::ArrowLeft | KeyCode::ArrowDown => self.change_speed(false),
+            _ => (),
+        }
+        let tick = self.tick();
+        let series = match &mut self.series {
+            Some(series) => series,
+            None => return ExhibitionEvent::None,
+        };
+        let seats = series.seats.len();
+        match keycode {
+            KeyCode::Space | KeyCode::KeyP => {
+                series.paused = !series.paused;
+                series.next_tick = Instant::now() + tick;
+            }
+            // Steps through the match a tick at a time, to follow the decisions.
+            KeyCode::Period if series.paused && !series.game.is_over() => {
+                series.game.step();
+                if series.game.is_over() {
+                    series.score(&self.policies);
+                }
+            }
+            KeyCode::Digit1 | KeyCode::Digit2 | KeyCode::Digit3 | KeyCode::Digit4 => {
+                let seat = match keycode {
+                    KeyCode::Digit1 => 0,
+                    KeyCode::Digit2 => 1,
+                    KeyCode::Digit3 => 2,
+                    _ => 3,
+                };
+                if seat < seats {
+                    let shown = series.inspected == Some(seat);
+                    series.inspected = if shown { None } else { Some(seat) };
+                }
+            }
+            KeyCode::Tab => {
+                series.inspected = match series.inspected {
+                    None => Some(0),
+                    Some(seat) if seat + 1 < seats => Some(seat + 1),
+                    Some(_) => None,
+                }
+            }
+            // Back to the lineup, which ends the series.
+            KeyCode::Escape => self.series = None,
+            _ => (),
+        }
+        ExhibitionEvent::None
+    }
+
+    fn label(&self, row: Row) -> String {
+        match row {
+            Row::Bot(seat) => {
+                let name = match self.lineup[seat] {
+                    Some(p) => self.policies[p].name.to_uppercase(),
+                    None => tr("NONE"),
+                };
+                tr_fmt("BOT {}: {}", &[&(seat + 1), &name])
+            }
+            Row::Mode => tr_fmt("MODE: {}", &[&tr(self.mode.name())]),
+            Row::Speed => tr_fmt("SPEED: {}", &[&format!("{}X", SPEEDS[self.speed])]),
+        }
+    }
+
+    pub fn render(&self, buffer: &mut FrameBuffer, palette: &Palette) {
+        buffer.clear(palette.menu_bg);
+        match &self.series {
+            Some(series) => self.render_series(buffer, palette, series),
+            None => self.render_lineup(buffer, palette),
+        }
+    }
+
+    fn render_lineup(&self, buffer: &mut FrameBuffer, palette: &Palette) {
+        let top = buffer.height() as i32;
+        draw_text_centered(buffer, top - 12, &tr("BOT MATCH"), 2, palette.text);
+        let mut y = top - 28;
+        for (i, &row) in ROWS.iter().enumerate() {
+            if i == self.row {
+                let label = format!("< {} >", self.label(row));
+                draw_text_centered(buffer, y, &label, 1, palette.selected);
+            } else {
+                draw_text_centered(buffer, y, &self.label(row), 1, palette.text);
+            }
+            y -= ROW_HEIGHT;
+        }
+        draw_text_centered(buffer, 16, &tr("ENTER: START"), 1, palette.dim_text);
+        draw_text_centered(buffer, 9, &tr("ESC: CANCEL"), 1, palette.dim_text);
+    }
+
+    /// Draws the match with the wins of every bot in its color in the top left corner, and the
+    /// speed and the keys at the bottom.
+    fn render_series(&self, buffer: &mut FrameBuffer, palette: &Palette, series: &Series) {
+        let world = series.game.world();
+        let camera = render_board(buffer, palette, world);
+        if let Some(seat) = series.inspected {
+            autopilot::plan(world, seat).render(buffer, &camera, palette);
+        }
+
+        let (width, top) = (buffer.width() as i32, buffer.height() as i32 - 1);
+        let mut y = top;
+        for (i, (&p, &wins)) in series.seats.iter().zip(series.wins.iter()).enumerate() {
+            let marker = if series.inspected == Some(i) { ">" } else { "" };
+            let text = format!(
+                "{}{} {}",
+                marker,
+                self.policies[p].name.to_uppercase(),
+                wins
+            );
+            let (color, _) = palette.snake_colors(i);
+            draw_text(buffer, 1, y, &text, 1, color);
+            y -= LINE_HEIGHT;
+        }
+        let draws = tr_fmt("DRAWS {}", &[&series.draws]);
+        draw_text(buffer, 1, y, &draws, 1, palette.dim_text);
+        let played = series.wins.iter().sum::<u32>() + series.draws;
+        let text = tr_fmt("GAMES {}", &[&played]);
+        draw_text(
+            buffer,
+            width - text_width(&text) - 1,
+            top,
+            &text,
+            1,
+            palette.dim_text,
+        );
+
+        let y = GLYPH_HEIGHT + 1;
+        let speed = if series.paused {
+            tr("PAUSED")
+        } else {
+            format!("{}X", SPEEDS[self.speed])
+        };
+        draw_text(buffer, 1, y, &speed, 1, palette.text);
+        let hint = if series.paused {
+            tr("1-4: INSPECT")
+        } else {
+            tr("SPACE: PAUSE")
+        };
+        draw_text(
+            buffer,
+            width - text_width(&hint) - 1,
+            y,
+            &hint,
+            1,
+            palette.dim_text,
+        );
+    }
+}
@@ -0,0 +1,141 @@
+//! Tiny 3x5 pixel font for menus and the HUD.
+
+use crate::framebuffer::{Color, FrameBuffer};
+
+pub const GLYPH_WIDTH: i32 = 3;
+pub const GLYPH_HEIGHT: i32 = 5;
+/// Horizontal distance between the starts of two consecutive characters.
+pub const ADVANCE: i32 = GLYPH_WIDTH + 1;
+/// Vertical distance between the tops of two consecutive lines.
+pub const LINE_HEIGHT: i32 = GLYPH_HEIGHT + 2;
+
+const ACUTE: u8 = 0b001;
+const GRAVE: u8 = 0b100;
+const DIAERESIS: u8 = 0b101;
+const TILDE: u8 = 0b111;
+
+/// Splits an accented letter into the letter without the accent and the accent, which is a row
+/// of bits like those of the glyph. Letters without an accent have no bits set in it.
+fn split_accent(c: char) -> (char, u8) {
+    match c.to_uppercase().next().unwrap_or(c) {
+        'Á' => ('A', ACUTE),
+        'À' => ('A', GRAVE),
+        'Ä' => ('A', DIAERESIS),
+        'É' => ('E', ACUTE),
+        'È' => ('E', GRAVE),
+        'Í' => ('I', ACUTE),
+        'Ñ' => ('N', TILDE),
+        'Ó' => ('O', ACUTE),
+        'Ö' => ('O', DIAERESIS),
+        'Ú' => ('U', ACUTE),
+        'Ü' => ('U', DIAERESIS),
+        _ => (c, 0),
+    }
+}
+
+/// Rows of the glyph from top to bottom, with the most significant of the three bits being the
+/// leftmost pixel. Lowercase letters are drawn as uppercase, unknown characters as `?`.
+fn glyph(c: char) -> [u8; 5] {
+    match c.to_ascii_uppercase() {
+        'A' => [0b111, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b111, 0b100, 0b100, 0b100, 0b111],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b111, 0b100, 0b101, 0b101, 0b111],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b111],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        'P' => [0b111, 0b101, 0b111, 0b100, 0b100],
+        'Q' => [0b111, 0b101, 0b101, 0b111, 0b001],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b011, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        ' ' => [0b000, 0b000, 0b000, 0b000, 0b000],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        ',' => [0b000, 0b000, 0b000, 0b010, 0b100],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '+' => [0b000, 0b010, 0b111, 0b010, 0b000],
+        '/' => [0b001, 0b001, 0b010, 0b100, 0b100],
+        '%' => [0b101, 0b001, 0b010, 0b100, 0b101],
+        '!' => [0b010, 0b010, 0b010, 0b000, 0b010],
+        '?' => [0b111, 0b001, 0b011, 0b000, 0b010],
+        '<' => [0b001, 0b010, 0b100, 0b010, 0b001],
+        '>' => [0b100, 0b010, 0b001, 0b010, 0b100],
+        '=' => [0b000, 0b111, 0b000, 0b111, 0b000],
+        '(' => [0b010, 0b100, 0b100, 0b100, 0b010],
+        ')' => [0b010, 0b001, 0b001, 0b001, 0b010],
+        '#' => [0b101, 0b111, 0b101, 0b111, 0b101],
+        '_' => [0b000, 0b000, 0b000, 0b000, 0b111],
+        '\'' => [0b010, 0b010, 0b000, 0b000, 0b000],
+        '*' => [0b101, 0b010, 0b101, 0b000, 0b000],
+        'ß' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        '¡' => [0b010, 0b000, 0b010, 0b010, 0b010],
+        '¿' => [0b010, 0b000, 0b110, 0b100, 0b111],
+        _ => glyph('?'),
+    }
+}
+
+/// Width of the rendered text in pixels at scale 1.
+pub fn text_width(text: &str) -> i32 {
+    let chars = text.chars().count() as i32;
+    if chars == 0 {
+        0
+    } else {
+        chars * ADVANCE - 1
+    }
+}
+
+/// Draws a single line of text with its top left corner at `(x, y)`. Every pixel of the font is
+/// drawn as a `scale`×`scale` square. Accents are drawn in the row above the top, in the space
+/// between the lines.
+pub fn draw_text(buffer: &mut FrameBuffer, x: i32, y: i32, text: &str, scale: i32, color: Color) {
+    let mut x = x;
+    for c in text.chars() {
+        let (c, accent) = split_accent(c);
+        let rows = std::iter::once((-1, accent)).chain((0..).zip(glyph(c)));
+        for (row, bits) in rows {
+            for col in 0..GLYPH_WIDTH {
+                if bits & (1 << (GLYPH_WIDTH - 1 - col)) != 0 {
+                    buffer.fill_rectangle(
+                        x + col * scale,
+                        y - (row + 1) * scale,
+                        scale as usize,
+                        scale as usize,
+                        color,
+                    );
+                }
+            }
+        }
+        x += ADVANCE * scale;
+    }
+}
+
+/// Draws text horizontally centered in the buffer.
+pub fn draw_text_centered(buffer: &mut FrameBuffer, y: i32, text: &str, scale: i32, color: Color) {
+    let x = (buffer.width() as i32 - text_width(text) * scale) / 2;
+    draw_text(buffer, x, y, text, scale, color);
+}
@@ -0,0 +1,383 @@
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Color(u32);
+
+impl Color {
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Color::rgba(r, g, b, 255)
+    }
+
+    /// A color with the opacity `a`, from 0 for transparent to 255 for opaque. The frame buffer
+    /// stores it as it is, while the GPU blends the quads over what is under them.
+    pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Color(r as u32 | ((g as u32) << 8) | ((b as u32) << 16) | ((a as u32) << 24))
+    }
+
+    pub const fn as_rgba_u32(self) -> u32 {
+        self.0
+    }
+
+    /// Parses a color written as `#RRGGBB`.
+    pub fn parse(text: &str) -> Option<Self> {
+        let hex = text.strip_prefix('#')?;
+        if hex.len() != 6 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+        let value = u32::from_str_radix(hex, 16).ok()?;
+        Some(Color::rgb(
+            (value >> 16) as u8,
+            (value >> 8) as u8,
+            value as u8,
+        ))
+    }
+
+    pub const fn components(self) -> (u8, u8, u8) {
+        (self.0 as u8, (self.0 >> 8) as u8, (self.0 >> 16) as u8)
+    }
+
+    /// Puts `other` over this color with the opacity `alpha`, from 0 for transparent to 255 for
+    /// opaque.
+    pub fn blend(self, other: Color, alpha: u8) -> Color {
+        let (r0, g0, b0) = self.components();
+        let (r1, g1, b1) = other.components();
+        let mix = |c0: u8, c1: u8| {
+            ((c0 as u32 * (255 - alpha as u32) + c1 as u32 * alpha as u32) / 255) as u8
+        };
+        Color::rgb(mix(r0, r1), mix(g0, g1), mix(b0, b1))
+    }
+}
+
+fn clamp<T: PartialOrd>(input: T, min: T, max: T) -> T {
+    if input < min {
+        min
+    } else if input > max {
+        max
+    } else {
+        input
+    }
+}
+
+/// The part of `start..start + len` that lies within `0..size`, as `(start, end)`. The end is not
+/// after the start, even if the range is completely outside.
+fn clip(start: i32, len: usize, size: usize) -> (usize, usize) {
+    let end = (start as i64).saturating_add(len.min(i64::MAX as usize) as i64);
+    let size = size as i64;
+    let start = clamp(start as i64, 0, size);
+    (start as usize, clamp(end, start, size) as usize)
+}
+
+/// Anything that rectangles of a solid color can be drawn on, in the coordinates of a
+/// `FrameBuffer`: the buffer itself, or the quads that a renderer draws on the GPU.
+pub trait Fill {
+    /// Fills the rectangle covering columns `x0..x0 + w` and rows `y0..y0 + h`.
+    fn fill_rectangle(&mut self, x0: i32, y0: i32, w: usize, h: usize, color: Color);
+}
+
+impl Fill for FrameBuffer {
+    fn fill_rectangle(&mut self, x0: i32, y0: i32, w: usize, h: usize, color: Color) {
+        FrameBuffer::fill_rectangle(self, x0, y0, w, h, color)
+    }
+}
+
+/// CPU-side image that the game draws into. Coordinates have the origin in the bottom left
+/// corner with y pointing up. All primitives clip to the buffer bounds, so drawing partially or
+/// completely outside of the buffer is allowed.
+///
+/// A buffer may hold only a band of the rows of the image, see `draw_in_bands`. It still takes
+/// the coordinates of the whole image and ignores what is drawn outside of its rows.
+pub struct FrameBuffer {
+    width: usize,
+    height: usize,
+    /// The rows `bottom..top` of the image are in `data`.
+    bottom: usize,
+    top: usize,
+    data: Vec<u32>,
+    /// Kept between the frames drawn in bands, so that they aren't allocated every time.
+    bands: Vec<FrameBuffer>,
+}
+
+impl FrameBuffer {
+    pub fn new(width: usize, height: usize) -> Self {
+        FrameBuffer::band(width, height, 0, height)
+    }
+
+    fn band(width: usize, height: usize, bottom: usize, top: usize) -> Self {
+        FrameBuffer {
+            width,
+            height,
+            bottom,
+            top,
+            data: vec![0; width * (top - bottom)],
+            bands: Vec::new(),
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// The rows of the image from `.0` to `.1` that the buffer holds, see `draw_in_bands`.
+    pub fn rows(&self) -> (i32, i32) {
+        (self.bottom as i32, self.top as i32)
+    }
+
+    fn index(&self, x: i32, y: i32) -> Option<usize> {
+        if x < 0 || y < 0 {
+            return None;
+        }
+        let (x, y) = (x as usize, y as usize);
+        if x >= self.width || y < self.bottom || y >= self.top {
+            return None;
+        }
+        Some(self.row(y) + x)
+    }
+
+    /// Index of the start of the row in `data`.
+    fn row(&self, y: usize) -> usize {
+        self.width * (self.top - y - 1)
+    }
+
+    /// The part of the rows `y0..y0 + h` that this buffer holds.
+    fn clip_rows(&self, y0: i32, h: usize) -> (usize, usize) {
+        let (start, end) = clip(y0, h, self.top);
+        (start.max(self.bottom), end.max(self.bottom))
+    }
+
+    /// The pixels row by row from the top, each as returned by `Color::as_rgba_u32`.
+    pub fn pixels(&self) -> &[u32] {
+        &self.data
+    }
+
+    pub fn clear(&mut self, color: Color) {
+        self.data.fill(color.as_rgba_u32())
+    }
+
+    pub fn pixel(&self, x: i32, y: i32) -> Option<Color> {
+        self.index(x, y).map(|i| Color(self.data[i]))
+    }
+
+    pub fn set_pixel(&mut self, x: i32, y: i32, color: Color) {
+        if let Some(i) = self.index(x, y) {
+            self.data[i] = color.as_rgba_u32();
+        }
+    }
+
+    /// Fills the rectangle covering columns `x0..x0 + w` and rows `y0..y0 + h`.
+    pub fn fill_rectangle(&mut self, x0: i32, y0: i32, w: usize, h: usize, color: Color) {
+        let (x_start, x_end) = clip(x0, w, self.width);
+        let (y_start, y_end) = self.clip_rows(y0, h);
+        for y in y_start..y_end {
+            let row = self.row(y);
+            self.data[row + x_start..row + x_end].fill(color.as_rgba_u32());
+        }
+    }
+
+    /// Like `fill_rectangle`, but puts the color over what is already there with the opacity
+    /// `alpha`.
+    pub fn blend_rectangle(
+        &mut self,
+        x0: i32,
+        y0: i32,
+        w: usize,
+        h: usize,
+        color: Color,
+        alpha: u8,
+    ) {
+        let (x_start, x_end) = clip(x0, w, self.width);
+        let (y_start, y_end) = self.clip_rows(y0, h);
+        for y in y_start..y_end {
+            let row = self.row(y);
+            for pixel in self.data[row + x_start..row + x_end].iter_mut() {
+                *pixel = Color(*pixel).blend(color, alpha).as_rgba_u32();
+            }
+        }
+    }
+
+    /// Draws the frame in `threads` bands of rows at once, on the threads of a pool that stays
+    /// around between the frames, and copies the bands into the buffer once they are all done.
+    /// `draw` is called for every band and has to draw the whole frame in the same way each time,
+    /// since each band keeps only its own rows. It can skip what lies outside of `rows`.
+    pub fn draw_in_bands(&mut self, threads: usize, draw: impl Fn(&mut FrameBuffer) + Sync) {
+        // There are no threads in the browser.
+        if threads <= 1 || cfg!(target_arch = "wasm32") || self.top - self.bottom < threads {
+            draw(self);
+            return;
+        }
+        let (width, height) = (self.width, self.height);
+        let (bottom, rows) = (self.bottom, self.top - self.bottom);
+        let edge = |i: usize| bottom + rows * i / threads;
+        let fits = |(i, band): (usize, &FrameBuffer)| {
+            band.width == width && band.height == height && band.bottom == edge(i)
+        };
+        if self.bands.len() != threads || !self.bands.iter().enumerate().all(fits) {
+            self.bands = (0..threads)
+                .map(|i| FrameBuffer::band(width, height, edge(i), edge(i + 1)))
+                .collect();
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            use rayon::prelude::*;
+            self.bands.par_iter_mut().for_each(&draw);
+        }
+        for band in self.bands.iter() {
+            let start = self.row(band.top - 1);
+            self.data[start..start + band.data.len()].copy_from_slice(&band.data);
+        }
+    }
+
+    /// Copies the buffer into an RGBA8 frame of the same dimensions.
+    pub fn copy_to(&self, frame: &mut [u8]) {
+        debug_assert_eq!(frame.len(), self.data.len() * 4);
+        for (dst, src) in frame.chunks_exact_mut(4).zip(self.data.iter()) {
+            dst.copy_from_slice(&src.to_le_bytes());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLACK: Color = Color::rgb(0, 0, 0);
+    const WHITE: Color = Color::rgb(255, 255, 255);
+    const RED: Color = Color::rgb(200, 0, 0);
+
+    /// The pixels of the given color, from the bottom left.
+    fn pixels_of(buffer: &FrameBuffer, color: Color) -> Vec<(i32, i32)> {
+        let (width, height) = (buffer.width() as i32, buffer.height() as i32);
+        (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .filter(|&(x, y)| buffer.pixel(x, y) == Some(color))
+            .collect()
+    }
+
+    fn rectangle(xs: std::ops::Range<i32>, ys: std::ops::Range<i32>) -> Vec<(i32, i32)> {
+        ys.flat_map(|y| xs.clone().map(move |x| (x, y))).collect()
+    }
+
+    #[test]
+    fn set_pixel_counts_rows_from_the_bottom() {
+        let mut buffer = FrameBuffer::new(3, 2);
+        buffer.clear(BLACK);
+        buffer.set_pixel(2, 0, RED);
+        assert_eq!(buffer.pixel(2, 0), Some(RED));
+        // The last pixel of the data is the bottom right one.
+        assert_eq!(buffer.pixels()[5], RED.as_rgba_u32());
+        assert_eq!(pixels_of(&buffer, RED), vec![(2, 0)]);
+    }
+
+    #[test]
+    fn set_pixel_outside_is_ignored() {
+        let mut buffer = FrameBuffer::new(3, 2);
+        buffer.clear(BLACK);
+        for (x, y) in [(-1, 0), (0, -1), (3, 0), (0, 2), (i32::MIN, i32::MAX)] {
+            buffer.set_pixel(x, y, RED);
+            assert_eq!(buffer.pixel(x, y), None);
+        }
+        assert!(pixels_of(&buffer, RED).is_empty());
+    }
+
+    #[test]
+    fn fill_rectangle_inside() {
+        let mut buffer = FrameBuffer::new(8, 6);
+        buffer.fill_rectangle(1, 2, 3, 2, RED);
+        assert_eq!(pixels_of(&buffer, RED), rectangle(1..4, 2..4));
+    }
+
+    #[test]
+    fn fill_rectangle_on_the_edge() {
+        let mut buffer = FrameBuffer::new(8, 6);
+        buffer.fill_rectangle(6, 4, 5, 5, RED);
+        assert_eq!(pixels_of(&buffer, RED), rectangle(6..8, 4..6));
+    }
+
+    #[test]
+    fn fill_rectangle_with_negative_coordinates() {
+        let mut buffer = FrameBuffer::new(8, 6);
+        buffer.fill_rectangle(-2, -3, 4, 5, RED);
+        assert_eq!(pixels_of(&buffer, RED), rectangle(0..2, 0..2));
+    }
+
+    #[test]
+    fn fill_rectangle_larger_than_the_buffer() {
+        let mut buffer = FrameBuffer::new(8, 6);
+        buffer.fill_rectangle(-10, -10, 100, usize::MAX, RED);
+        assert_eq!(pixels_of(&buffer, RED), rectangle(0..8, 0..6));
+    }
+
+    #[test]
+    fn fill_rectangle_outside_draws_nothing() {
+        let mut buffer = FrameBuffer::new(8, 6);
+        buffer.clear(BLACK);
+        buffer.fill_rectangle(-5, 0, 5, 6, RED);
+        buffer.fill_rectangle(8, 0, 3, 6, RED);
+        buffer.fill_rectangle(0, 6, 8, 3, RED);
+        buffer.fill_rectangle(2, 2, 0, 3, RED);
+        assert!(pixels_of(&buffer, RED).is_empty());
+    }
+
+    #[test]
+    fn blend_rectangle_mixes_with_what_is_there() {
+        let mut buffer = FrameBuffer::new(4, 4);
+        buffer.clear(BLACK);
+        buffer.blend_rectangle(0, 0, 2, 2, WHITE, 255);
+        buffer.blend_rectangle(2, 0, 2, 2, WHITE, 0);
+        buffer.blend_rectangle(0, 2, 2, 2, WHITE, 51);
+        assert_eq!(pixels_of(&buffer, WHITE), rectangle(0..2, 0..2));
+        assert_eq!(
+            pixels_of(&buffer, Color::rgb(51, 51, 51)),
+            rectangle(0..2, 2..4)
+        );
+        assert_eq!(pixels_of(&buffer, BLACK), rectangle(2..4, 0..4));
+    }
+
+    #[test]
+    fn blend_rectangle_clips() {
+        let mut buffer = FrameBuffer::new(4, 4);
+        buffer.clear(BLACK);
+        buffer.blend_rectangle(-3, 3, 5, 10, WHITE, 255);
+        assert_eq!(pixels_of(&buffer, WHITE), rectangle(0..2, 3..4));
+        buffer.blend_rectangle(-100, -100, 1000, 1000, WHITE, 255);
+        assert_eq!(pixels_of(&buffer, WHITE), rectangle(0..4, 0..4));
+    }
+
+    #[test]
+    fn clear_fills_everything() {
+        let mut buffer = FrameBuffer::new(5, 3);
+        buffer.set_pixel(1, 1, RED);
+        buffer.clear(WHITE);
+        assert_eq!(pixels_of(&buffer, WHITE), rectangle(0..5, 0..3));
+    }
+
+    #[test]
+    fn copy_to_writes_rgba_from_the_top() {
+        let mut buffer = FrameBuffer::new(2, 2);
+        buffer.clear(BLACK);
+        buffer.set_pixel(0, 1, Color::rgb(1, 2, 3));
+        buffer.set_pixel(1, 0, Color::rgb(4, 5, 6));
+        let mut frame = vec![0; 16];
+        buffer.copy_to(&mut frame);
+        assert_eq!(
+            frame,
+            [1, 2, 3, 255, 0, 0, 0, 255, 0, 0, 0, 255, 4, 5, 6, 255]
+        );
+    }
+
+    #[test]
+    fn drawing_in_bands_matches_drawing_at_once() {
+        let draw = |buffer: &mut FrameBuffer| {
+            buffer.clear(BLACK);
+            buffer.fill_rectangle(-1, 2, 5, 7, RED);
+            buffer.blend_rectangle(2, -2, 9, 5, WHITE, 100);
+            buffer.set_pixel(6, 9, WHITE);
+        };
+        let mut whole = FrameBuffer::new(7, 10);
+        draw(&mut whole);
+        let mut banded = FrameBuffer::new(7, 10);
+        banded.draw_in_bands(3, draw);
+        assert_eq!(banded.pixels(), whole.pixels());
+    }
+}
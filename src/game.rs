@@ -0,0 +1,535 @@
+use crate::bots::Painter;
+use crate::config::{Config, Mode};
+use crate::controller::{Controller, GameView, Keyboard};
+use crate::grid::Direction;
+use crate::hooks::Hooks;
+use crate::level::Level;
+use crate::palette::Palette;
+use crate::renderer::Renderer;
+use crate::rollback::Snapshots;
+use crate::ruleset::SpeedCurve;
+use crate::skin::Skin;
+use crate::telemetry::{EndReason, GameRecord, Session};
+use crate::world::{Camera, Event, SnakeId, World, GOLDEN_POINTS};
+use log::info;
+use std::collections::VecDeque;
+use std::time::Duration;
+use web_time::Instant;
+
+/// Size of a board cell in pixels.
+pub const CELL_SIZE: i32 = 8;
+
+/// Number of past ticks kept for looking back at how the game ended.
+const HISTORY: usize = 8;
+/// Number of ticks that the practice mode goes back with every rewind.
+pub const REWIND_TICKS: usize = 20;
+/// Number of past ticks kept for rewinding in the practice mode.
+const REWIND_HISTORY: usize = 10 * REWIND_TICKS;
+/// How many times longer the first tick after the player's snake appears is, see
+/// `Config::slow_start`.
+const SLOW_START_FACTOR: f64 = 2.0;
+/// How many times shorter the ticks are while the player sprints.
+const SPRINT_FACTOR: u32 = 2;
+/// Range of `State::set_time_scale`.
+pub const MIN_TIME_SCALE: f32 = 0.25;
+pub const MAX_TIME_SCALE: f32 = 8.0;
+/// Number of bots that the player competes with in the territory mode.
+const TERRITORY_OPPONENTS: usize = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Update {
+    Idle,
+    Changed,
+    GameOver,
+}
+
+/// A game played in real time on top of the tick-based `World`, with a controller for every
+/// snake. The first snake is the player's.
+pub struct State {
+    seed: u64,
+    tick: Duration,
+    /// Number of ticks over which the ticks get as short as `tick` after the player's snake
+    /// appears.
+    slow_start: u32,
+    /// How the ticks get shorter as the player scores.
+    speed: SpeedCurve,
+    /// The tick at which the player's snake appeared last.
+    spawned_at: u64,
+    next_update: Instant,
+    controllers: Vec<Box<dyn Controller>>,
+    world: World,
+    session: Session,
+    /// Events since the last call to `take_events`.
+    events: Vec<Event>,
+    /// The world before each of the last `HISTORY` ticks, oldest first.
+    history: VecDeque<World>,
+    /// The world before each of the last `REWIND_HISTORY` ticks in the practice mode.
+    rewinds: Option<Snapshots<World>>,
+    /// Custom rules of a modded game.
+    hooks: Option<Box<dyn Hooks>>,
+    /// Whether the ticks only advance with `step`.
+    paused: bool,
+    /// Whether the player's snake sprints, see `set_sprinting`.
+    sprinting: bool,
+    /// How many times faster than usual the ticks go, see `set_time_scale`.
+    time_scale: f32,
+    /// The directions taken by the first snake whenever it turned, by the tick at which they
+    /// took effect.
+    turns: Vec<(u64, Direction)>,
+}
+
+impl State {
+    /// Number of the snakes in a game of the mode started with `new`.
+    pub fn snakes(mode: Mode) -> usize {
+        match mode {
+            Mode::CoOp => 2,
+            Mode::Classic | Mode::Frenzy | Mode::Territory => 1 + State::bots(mode),
+        }
+    }
+
+    /// Number of the bots that the player competes with in a game of the mode started with
+    /// `new`.
+    pub fn bots(mode: Mode) -> usize {
+        match mode {
+            Mode::Territory => TERRITORY_OPPONENTS,
+            Mode::Classic | Mode::Frenzy | Mode::CoOp => 0,
+        }
+    }
+
+    /// A game steered from the keyboard, against bots in the territory mode, by two players in
+    /// the co-op mode and alone otherwise. The bots are handicapped as the rules say.
+    pub fn new(config: &Config, seed: u64) -> Self {
+        let mut controllers: Vec<Box<dyn Controller>> = vec![Box::new(Keyboard::default())];
+        if config.mode == Mode::CoOp {
+            controllers.push(Box::new(Keyboard::default()));
+        }
+        let handicap = config.rules.handicap;
+        let bots = State::bots(config.mode);
+        for _ in 0..bots {
+            controllers.push(handicap.hinder(Box::new(Painter)));
+        }
+        let mut state = State::with_controllers(config, seed, controllers);
+        if bots > 0 && handicap.head_start > 0 {
+            state.world.grow(0, handicap.head_start);
+        }
+        state
+    }
+
+    /// A game with a snake for every controller.
+    pub fn with_controllers(
+        config: &Config,
+        seed: u64,
+        controllers: Vec<Box<dyn Controller>>,
+    ) -> Self {
+        let world = World::new(config, controllers.len(), seed);
+        State::with_world(config, seed, world, controllers)
+    }
+
+    /// A single-player game on a hand-made level, steered from the keyboard.
+    pub fn with_level(config: &Config, level: &Level, seed: u64) -> Self {
+        let world = World::from_level(config, level, 1, seed);
+        State::with_world(config, seed, world, vec![Box::new(Keyboard::default())])
+    }
+
+    fn with_world(
+        config: &Config,
+        seed: u64,
+        world: World,
+        controllers: Vec<Box<dyn Controller>>,
+    ) -> Self {
+        info!("Starting a game with seed {}", seed);
+        let mut state = State {
+            seed,
+            tick: config.tick(),
+            slow_start: config.slow_start,
+            speed: config.rules.speed,
+            spawned_at: world.tick(),
+            next_update: Instant::now(),
+            world,
+            controllers,
+            session: Session::new(config, seed),
+            events: Vec::new(),
+            history: VecDeque::with_capacity(HISTORY),
+            rewinds: None,
+            hooks: None,
+            paused: false,
+            sprinting: false,
+            time_scale: 1.0,
+            turns: Vec::new(),
+        };
+        state.next_update += state.next_tick();
+        state
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    /// For changes to the board outside of the rules, e.g. from the developer console.
+    pub fn world_mut(&mut self) -> &mut World {
+        &mut self.world
+    }
+
+    /// The game as seen by one of the snakes.
+    pub fn view(&self, snake: SnakeId) -> GameView<'_> {
+        GameView::new(&self.world, snake)
+    }
+
+    /// Time until the game ends, if the mode has a timer.
+    pub fn time_left(&self) -> Option<Duration> {
+        let ticks = self.world.ticks_left()?;
+        Some(self.tick * ticks as u32)
+    }
+
+    pub fn length(&self) -> usize {
+        self.world.snakes()[0].length()
+    }
+
+    pub fn end(&mut self, reason: EndReason) {
+        let length = self.length();
+        // In the territory mode the cells are what counts, and in the co-op mode the points of
+        // both snakes, with the penalties.
+        if let Some(territory) = self.world.territory() {
+            self.session.set_score(territory[0] as u32);
+        } else if self.world.is_coop() {
+            self.session
+                .set_score(self.world.snakes().iter().map(|s| s.score).sum());
+        }
+        self.session.finish(reason, length);
+    }
+
+    /// Record of the game, available once it has ended.
+    pub fn record(&self) -> Option<&GameRecord> {
+        self.session.record()
+    }
+
+    pub fn update(&mut self) -> Update {
+        if self.paused || self.world.is_over() || Instant::now() < self.next_update {
+            return Update::Idle;
+        }
+        self.step()
+    }
+
+    /// Advances the game by exactly one tick, whether it is paused or not.
+    pub fn step(&mut self) -> Update {
+        self.session.on_tick();
+        let world = &self.world;
+        let turns: Vec<Option<Direction>> = self
+            .controllers
+            .iter_mut()
+            .enumerate()
+            .map(|(id, controller)| {
+                let view = GameView::new(world, id);
+                view.snake().alive.then(|| controller.decide(&view))
+            })
+            .collect();
+        // Only the turns that the board has are recorded, as the world makes them.
+        let topology = self.world.topology();
+        if let (Some(&Some(direction)), Some(snake)) = (turns.first(), self.world.snakes().first())
+        {
+            match topology.resolve(direction, snake.direction) {
+                Some(turn) if turn != snake.direction => {
+                    self.turns.push((self.world.tick() + 1, turn))
+                }
+                _ => (),
+            }
+        }
+        if self.history.len() == HISTORY {
+            self.history.pop_front();
+        }
+        self.history.push_back(self.world.clone());
+        if let Some(rewinds) = self.rewinds.as_mut() {
+            rewinds.push(self.world.clone());
+        }
+        let events = self.world.step(&turns);
+        if let Some(hooks) = self.hooks.as_mut() {
+            for event in events.iter() {
+                match *event {
+                    Event::Ate { snake, pos } | Event::AteGolden { snake, pos } => {
+                        hooks.on_food_eaten(&mut self.world, snake, pos)
+                    }
+                    Event::Died { snake, reason, .. } => {
+                        hooks.on_death(&mut self.world, snake, reason)
+                    }
+                    Event::FoodSpawned { pos } => hooks.on_food_spawned(&mut self.world, pos),
+                    _ => (),
+                }
+            }
+            hooks.on_tick(&mut self.world);
+        }
+        self.events.extend_from_slice(&events);
+        let coop = self.world.is_coop();
+        for event in events {
+            match event {
+                Event::Ate { snake, pos } if snake == 0 || coop => {
+                    let points = match self.hooks.as_mut() {
+                        Some(hooks) => hooks.food_value(&mut self.world, pos),
+                        None => 1,
+                    };
+                    self.session.on_food_eaten(points);
+                }
+                Event::AteGolden { snake, .. } if snake == 0 || coop => {
+                    self.session.on_food_eaten(GOLDEN_POINTS)
+                }
+                Event::Ate { .. } | Event::AteRotten { .. } | Event::AteGolden { .. } => (),
+                Event::Died { .. } if !self.world.is_over() => (),
+                Event::Died { reason, .. } => {
+                    self.end(reason);
+                    return Update::GameOver;
+                }
+                Event::BoardFull => {
+                    self.end(EndReason::BoardFull);
+                    return Update::GameOver;
+                }
+                Event::TimeUp => {
+                    self.end(EndReason::TimeUp);
+                    return Update::GameOver;
+                }
+                Event::Respawned { snake, .. } if snake == 0 || coop => {
+                    self.spawned_at = self.world.tick()
+                }
+                Event::FoodSpawned { .. }
+                | Event::Dropped { .. }
+                | Event::GoldenFoodSpawned { .. }
+                | Event::GoldenFoodExpired { .. }
+                | Event::Exploded { .. }
+                | Event::PassedThrough { .. }
+                | Event::CheckpointReached { .. }
+                | Event::Respawned { .. } => (),
+            }
+        }
+        self.next_update = Instant::now() + self.next_tick();
+        Update::Changed
+    }
+
+    /// How long the coming tick lasts: longer with the head of the player's snake in mud and
+    /// shorter with it on a boost pad or with the points it scored under a speed curve, and
+    /// longer still right after the snake appeared. All of
+    /// that is shorter while the player sprints, and all of it is scaled by the time scale.
+    fn next_tick(&self) -> Duration {
+        let tick = self.unhurried_tick();
+        let tick = if self.sprinting {
+            tick / SPRINT_FACTOR
+        } else {
+            tick
+        };
+        tick.div_f32(self.time_scale)
+    }
+
+    fn unhurried_tick(&self) -> Duration {
+        let zone = self
+            .world
+            .snakes()
+            .first()
+            .and_then(|snake| self.world.zone(snake.head));
+        let tick = match zone {
+            Some(zone) => zone.tick(self.tick),
+            None => self.tick,
+        };
+        // The diagonal moves of the player's snake are longer, and so take longer, and the speed
+        // may follow the score.
+        let tick = match self.world.snakes().first() {
+            Some(snake) => tick
+                .mul_f64(self.world.topology().step_length(snake.direction))
+                .mul_f64(self.speed.tick_factor(snake.score)),
+            None => tick,
+        };
+        let ramp = self.slow_start as u64;
+        let since = self.world.tick().saturating_sub(self.spawned_at);
+        if since >= ramp {
+            return tick;
+        }
+        let left = (ramp - since) as f64 / ramp as f64;
+        tick.mul_f64(1.0 + (SLOW_START_FACTOR - 1.0) * left)
+    }
+
+    /// The turns of the player's snake so far, by the tick at which each took effect. Together
+    /// with the seed and the parameters they are enough to play the game again with `replay`.
+    pub fn turns(&self) -> &[(u64, Direction)] {
+        &self.turns
+    }
+
+    /// Plays the game up to the tick `ticks` at once, turning the player's snake as in `turns`,
+    /// e.g. to restore a game saved with the ticks and the turns that it had. Returns the update
+    /// of the last tick.
+    pub fn replay(&mut self, ticks: u64, turns: &[(u64, Direction)]) -> Update {
+        let mut turns = turns.iter().peekable();
+        let mut update = Update::Idle;
+        while self.world.tick() < ticks && update != Update::GameOver {
+            let tick = self.world.tick() + 1;
+            while let Some(&&(at, direction)) = turns.peek() {
+                if at > tick {
+                    break;
+                }
+                if at == tick {
+                    self.turn(direction);
+                }
+                turns.next();
+            }
+            update = self.step();
+        }
+        update
+    }
+
+    /// The world before each of the last few ticks, oldest first.
+    pub fn history(&self) -> &VecDeque<World> {
+        &self.history
+    }
+
+    /// Hands over what happened in the game since the previous call, for the effects that
+    /// accompany the game.
+    pub fn take_events(&mut self) -> Vec<Event> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// The earliest moment at which `update` will have something to do, unless the game is
+    /// paused.
+    pub fn next_wakeup(&self) -> Instant {
+        self.next_update
+    }
+
+    pub fn render(&self, renderer: &mut dyn Renderer, palette: &Palette) {
+        self.world.render(renderer.buffer_mut(), palette);
+    }
+
+    pub fn render_with_camera(
+        &self,
+        renderer: &mut dyn Renderer,
+        palette: &Palette,
+        camera: &Camera,
+        skins: &[Skin],
+    ) {
+        self.world
+            .render_with_camera(renderer.buffer_mut(), palette, camera, skins);
+    }
+
+    /// Stops the ticks, apart from those made with `step`, or resumes them.
+    pub fn set_paused(&mut self, paused: bool) {
+        if self.paused && !paused {
+            self.next_update = Instant::now() + self.next_tick();
+        }
+        self.paused = paused;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Makes the ticks `SPRINT_FACTOR` times shorter, while the player holds the key of the
+    /// direction in which the snake moves. Starting to sprint brings the next tick closer.
+    pub fn set_sprinting(&mut self, sprinting: bool) {
+        if sprinting == self.sprinting {
+            return;
+        }
+        self.sprinting = sprinting;
+        if sprinting {
+            self.next_update = self.next_update.min(Instant::now() + self.next_tick());
+        }
+    }
+
+    /// Makes the ticks `scale` times faster, from `MIN_TIME_SCALE` to `MAX_TIME_SCALE`, to watch a
+    /// game closely or to get through it quickly. Only the ticks are affected: the game is drawn
+    /// as often as before and plays out the same.
+    pub fn set_time_scale(&mut self, scale: f32) {
+        let scale = scale.clamp(MIN_TIME_SCALE, MAX_TIME_SCALE);
+        if scale == self.time_scale {
+            return;
+        }
+        let now = Instant::now();
+        // The part of the tick that is left is stretched or squeezed along with it.
+        let left = self.next_update.saturating_duration_since(now);
+        self.next_update = now + left.mul_f32(self.time_scale / scale);
+        self.time_scale = scale;
+    }
+
+    pub fn time_scale(&self) -> f32 {
+        self.time_scale
+    }
+
+    /// The player's direction for the snake waiting for the next tick, if any.
+    pub fn pending_turn(&self, snake: SnakeId) -> Option<Direction> {
+        self.controllers[snake].pending()
+    }
+
+    /// Passes the player's choice to the controller of the first snake. The turn takes effect
+    /// on the next tick.
+    pub fn turn(&mut self, direction: Direction) {
+        self.turn_player(0, direction);
+    }
+
+    /// Passes the choice of one of the players sharing the keyboard to the controller of their
+    /// snake.
+    pub fn turn_player(&mut self, snake: SnakeId, direction: Direction) {
+        // A direction that the board doesn't have doesn't replace a turn already chosen.
+        let heading = self.world.snakes()[snake].direction;
+        if self.world.topology().resolve(direction, heading).is_some() {
+            self.controllers[snake].input(direction);
+        }
+    }
+
+    /// Applies changed parameters to the game in progress. The size of the board only changes
+    /// with the next game.
+    pub fn retune(&mut self, config: &Config) {
+        self.tick = config.tick();
+        self.slow_start = config.slow_start;
+        let (interval, max_food) = config.food_pacing();
+        self.world.set_food_pacing(interval, max_food);
+        self.world.set_food_spread(config.food_spread);
+        self.world.set_food_lifetime(config.food_lifetime);
+        self.world.set_golden_food_odds(config.golden_food_odds);
+        self.world.set_bomb_odds(config.bomb_odds);
+    }
+
+    /// Swaps the layout of the board for that of the level. Returns whether the level fits.
+    pub fn set_layout(&mut self, level: &Level) -> bool {
+        self.world.set_layout(level)
+    }
+
+    /// Adds custom rules to the game, which should not have started yet.
+    pub fn set_hooks(&mut self, mut hooks: Box<dyn Hooks>) {
+        hooks.on_start(&mut self.world);
+        self.hooks = Some(hooks);
+    }
+
+    /// Makes it a practice game, which can be rewound but doesn't count.
+    pub fn set_practice(&mut self) {
+        self.rewinds = Some(Snapshots::new(REWIND_HISTORY));
+    }
+
+    pub fn is_practice(&self) -> bool {
+        self.rewinds.is_some()
+    }
+
+    /// Takes a practice game back by `REWIND_TICKS` ticks, or to the earliest tick that it still
+    /// has, even if the game is already over. Returns whether it went back.
+    pub fn rewind(&mut self) -> bool {
+        let world = match self.rewinds.as_mut().and_then(|r| r.rewind(REWIND_TICKS)) {
+            Some(world) => world,
+            None => return false,
+        };
+        let tick = world.tick();
+        info!("Rewinding from tick {} to {}", self.world.tick(), tick);
+        self.world = world;
+        self.turns.retain(|&(at, _)| at <= tick);
+        self.history.clear();
+        self.events.clear();
+        // The player gets to look around again, as after coming back to life.
+        self.spawned_at = tick;
+        self.next_update = Instant::now() + self.next_tick();
+        true
+    }
+
+    /// Whether the game is played by custom rules.
+    pub fn is_modded(&self) -> bool {
+        self.hooks.is_some()
+    }
+
+    /// Hands the snake over to another controller, for example to the autopilot.
+    pub fn set_controller(&mut self, snake: SnakeId, controller: Box<dyn Controller>) {
+        self.controllers[snake] = controller;
+    }
+}
@@ -0,0 +1,114 @@
+//! Size, position and fullscreen state of the window, remembered between sessions.
+
+use crate::storage;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+use winit::event_loop::EventLoopWindowTarget;
+use winit::monitor::MonitorHandle;
+use winit::window::{Fullscreen, Window, WindowBuilder};
+
+const FILE_NAME: &str = "window.json";
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowGeometry {
+    /// Inner size in physical pixels, while not in fullscreen.
+    pub size: Option<(u32, u32)>,
+    /// Outer position in physical pixels, while not in fullscreen.
+    pub position: Option<(i32, i32)>,
+    /// Name of the monitor the window was on.
+    pub monitor: Option<String>,
+    pub fullscreen: bool,
+}
+
+/// Whether the point is inside the monitor.
+fn contains(monitor: &MonitorHandle, (x, y): (i32, i32)) -> bool {
+    let origin = monitor.position();
+    let size = monitor.size();
+    x >= origin.x
+        && y >= origin.y
+        && x < origin.x + size.width as i32
+        && y < origin.y + size.height as i32
+}
+
+impl WindowGeometry {
+    pub fn load() -> Self {
+        storage::load(FILE_NAME)
+    }
+
+    pub fn save(&self) {
+        storage::save(FILE_NAME, self);
+    }
+
+    /// Whether there is a saved size, which then takes precedence over the UI scale.
+    pub fn has_size(&self) -> bool {
+        self.size.is_some()
+    }
+
+    /// Restores the saved geometry as far as it still makes sense: the window only goes back to
+    /// its position if that is on the same monitor, and is never bigger than the monitor.
+    pub fn apply<T>(
+        &self,
+        builder: WindowBuilder,
+        elwt: &EventLoopWindowTarget<T>,
+    ) -> WindowBuilder {
+        let monitor = self.monitor.as_ref().and_then(|name| {
+            let monitor = elwt
+                .available_monitors()
+                .find(|m| m.name().as_ref() == Some(name));
+            if monitor.is_none() {
+                warn!(
+                    "Monitor {} is gone, not restoring the window position",
+                    name
+                );
+            }
+            monitor
+        });
+        let mut builder = builder;
+        if let Some((width, height)) = self.size {
+            let bounds = monitor
+                .clone()
+                .or_else(|| elwt.primary_monitor())
+                .map(|m| m.size());
+            let (width, height) = match bounds {
+                Some(bounds) => (width.min(bounds.width), height.min(bounds.height)),
+                None => (width, height),
+            };
+            builder = builder.with_inner_size(PhysicalSize::new(width, height));
+        }
+        if let (Some(monitor), Some(position)) = (&monitor, self.position) {
+            if contains(monitor, position) {
+                builder = builder.with_position(PhysicalPosition::new(position.0, position.1));
+            }
+        }
+        if self.fullscreen {
+            builder = builder.with_fullscreen(Some(Fullscreen::Borderless(monitor)));
+        }
+        builder
+    }
+
+    pub fn on_resized(&mut self, window: &Window, size: PhysicalSize<u32>) {
+        if window.fullscreen().is_none() && window.is_minimized() != Some(true) {
+            self.size = Some((size.width, size.height));
+        }
+    }
+
+    pub fn on_moved(&mut self, window: &Window, position: PhysicalPosition<i32>) {
+        if window.fullscreen().is_none() && window.is_minimized() != Some(true) {
+            self.position = Some((position.x, position.y));
+        }
+    }
+
+    /// Records the final state of the window and saves it.
+    pub fn on_exit(&mut self, window: &Window) {
+        self.on_resized(window, window.inner_size());
+        if let Ok(position) = window.outer_position() {
+            self.on_moved(window, position);
+        }
+        self.fullscreen = window.fullscreen().is_some();
+        self.monitor = window.current_monitor().and_then(|m| m.name());
+        info!("Saving the window geometry: {:?}", self);
+        self.save();
+    }
+}
@@ -0,0 +1,357 @@
+//! Draws the quads of `Renderer::layers_mut` on the GPU as instances of a single square, over the
+//! frame that `pixels` scales to the window. The cells of the board don't have to go through the
+//! buffer then, which is what takes the time on large boards.
+
+use pixels::wgpu;
+use pixels::{Pixels, PixelsContext};
+use snake_pixels::renderer::Quad;
+use std::borrow::Cow;
+
+/// Size of a quad in the instance buffer: the position and the size as four floats, and the
+/// color as four bytes.
+const QUAD_SIZE: wgpu::BufferAddress = 20;
+/// Quads that fit into the instance buffer at first. It grows as needed.
+const INITIAL_QUADS: wgpu::BufferAddress = 1024;
+
+const SHADER: &str = "
+struct Frame {
+    size: vec2<f32>,
+};
+
+@group(0) @binding(0) var<uniform> frame: Frame;
+
+struct Vertex {
+    @builtin(position) position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+};
+
+// The colors are in sRGB like the pixels of the frame, which the texture turns linear.
+fn linear(c: vec3<f32>) -> vec3<f32> {
+    return select(pow((c + 0.055) / 1.055, vec3<f32>(2.4)), c / 12.92, c <= vec3<f32>(0.04045));
+}
+
+@vertex
+fn vs_main(
+    @builtin(vertex_index) corner: u32,
+    @location(0) rect: vec4<f32>,
+    @location(1) color: vec4<f32>,
+) -> Vertex {
+    let offset = vec2<f32>(f32(corner & 1u), f32(corner >> 1u));
+    let pixel = rect.xy + offset * rect.zw;
+    var out: Vertex;
+    // The frame has y going up, like the clip space.
+    out.position = vec4<f32>(pixel / frame.size * 2.0 - 1.0, 0.0, 1.0);
+    out.color = vec4<f32>(linear(color.rgb), color.a);
+    return out;
+}
+
+@fragment
+fn fs_main(in: Vertex) -> @location(0) vec4<f32> {
+    return in.color;
+}
+";
+
+pub struct QuadPipeline {
+    pipeline: wgpu::RenderPipeline,
+    /// The size of the frame, to place the quads in it.
+    frame: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    instances: wgpu::Buffer,
+    /// Number of quads that fit into `instances`.
+    capacity: wgpu::BufferAddress,
+    /// The quads of the next frame.
+    pub quads: Vec<Quad>,
+}
+
+impl QuadPipeline {
+    pub fn new(pixels: &Pixels) -> Self {
+        QuadPipeline::with_format(pixels.device(), pixels.render_texture_format())
+    }
+
+    /// The pipeline drawing into textures of the format.
+    fn with_format(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("quads"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(SHADER)),
+        });
+        let frame = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("frame"),
+            size: 16,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("quads"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("quads"),
+            layout: &layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: frame.as_entire_binding(),
+            }],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("quads"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let attributes = [
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32x4,
+                offset: 0,
+                shader_location: 0,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Unorm8x4,
+                offset: 16,
+                shader_location: 1,
+            },
+        ];
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("quads"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: QUAD_SIZE,
+                    step_mode: wgpu::VertexStepMode::Instance,
+                    attributes: &attributes,
+                }],
+            },
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleStrip,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    // The quads of the overlays and the paint are translucent.
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+        });
+        QuadPipeline {
+            pipeline,
+            frame,
+            bind_group,
+            instances: create_instances(device, INITIAL_QUADS),
+            capacity: INITIAL_QUADS,
+            quads: Vec::new(),
+        }
+    }
+
+    /// Draws the quads over the frame of `width` × `height` pixels that the scaling renderer
+    /// has drawn into `target`, and forgets them.
+    pub fn render(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &wgpu::TextureView,
+        context: &PixelsContext,
+        size: (usize, usize),
+    ) {
+        // Where the scaling renderer has put the frame in the window.
+        let clip = context.scaling_renderer.clip_rect();
+        let (device, queue) = (&context.device, &context.queue);
+        self.draw(encoder, target, device, queue, size, clip);
+    }
+
+    /// Draws the quads over the frame of `width` × `height` pixels, which takes the rectangle
+    /// `clip` of `target`, and forgets them.
+    fn draw(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &wgpu::TextureView,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        (width, height): (usize, usize),
+        (x, y, w, h): (u32, u32, u32, u32),
+    ) {
+        if self.quads.is_empty() {
+            return;
+        }
+        let count = self.quads.len() as wgpu::BufferAddress;
+        if count > self.capacity {
+            self.capacity = count.next_power_of_two();
+            self.instances = create_instances(device, self.capacity);
+        }
+        let mut data = Vec::with_capacity(self.quads.len() * QUAD_SIZE as usize);
+        for quad in self.quads.drain(..) {
+            for value in [quad.x, quad.y, quad.width, quad.height] {
+                data.extend_from_slice(&value.to_le_bytes());
+            }
+            data.extend_from_slice(&quad.color.as_rgba_u32().to_le_bytes());
+        }
+        queue.write_buffer(&self.instances, 0, &data);
+        let mut size = [0; 16];
+        size[..4].copy_from_slice(&(width as f32).to_le_bytes());
+        size[4..8].copy_from_slice(&(height as f32).to_le_bytes());
+        queue.write_buffer(&self.frame, 0, &size);
+
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("quads"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        pass.set_viewport(x as f32, y as f32, w as f32, h as f32, 0.0, 1.0);
+        pass.set_scissor_rect(x, y, w, h);
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.set_vertex_buffer(0, self.instances.slice(..));
+        pass.draw(0..4, 0..count as u32);
+    }
+}
+
+fn create_instances(device: &wgpu::Device, quads: wgpu::BufferAddress) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("quads"),
+        size: quads * QUAD_SIZE,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use snake_pixels::framebuffer::Color;
+
+    const WIDTH: u32 = 4;
+    const HEIGHT: u32 = 2;
+    /// Rows of the copy of a texture have to be aligned to this many bytes.
+    const ROW: u32 = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+
+    /// Any adapter, which may be a software one such as llvmpipe.
+    fn device() -> Option<(wgpu::Device, wgpu::Queue)> {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+        let adapter =
+            pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default()))?;
+        pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor::default(), None)).ok()
+    }
+
+    /// Draws the quads over a black frame and reads its pixels back, the top row first, as
+    /// `[r, g, b, a]`.
+    fn draw(quads: &[Quad]) -> Option<Vec<[u8; 4]>> {
+        let (device, queue) = device()?;
+        let format = wgpu::TextureFormat::Rgba8UnormSrgb;
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: wgpu::Extent3d {
+                width: WIDTH,
+                height: HEIGHT,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let output = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: (ROW * HEIGHT) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let mut pipeline = QuadPipeline::with_format(&device, format);
+        pipeline.quads = quads.to_vec();
+        let mut encoder = device.create_command_encoder(&Default::default());
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        let size = (WIDTH as usize, HEIGHT as usize);
+        let clip = (0, 0, WIDTH, HEIGHT);
+        pipeline.draw(&mut encoder, &view, &device, &queue, size, clip);
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &output,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(ROW),
+                    rows_per_image: None,
+                },
+            },
+            texture.size(),
+        );
+        queue.submit([encoder.finish()]);
+        let data = output.slice(..);
+        data.map_async(wgpu::MapMode::Read, |result| result.unwrap());
+        device.poll(wgpu::Maintain::Wait);
+        let data = data.get_mapped_range();
+        let pixels = data
+            .chunks(ROW as usize)
+            .flat_map(|row| row[..4 * WIDTH as usize].chunks(4))
+            .map(|pixel| [pixel[0], pixel[1], pixel[2], pixel[3]])
+            .collect();
+        Some(pixels)
+    }
+
+    fn quad(x: f32, color: Color) -> Quad {
+        Quad {
+            x,
+            y: 0.0,
+            width: 2.0,
+            height: HEIGHT as f32,
+            color,
+        }
+    }
+
+    #[test]
+    fn translucent_quads_blend_with_the_frame() {
+        let quads = [
+            quad(0.0, Color::rgb(200, 0, 0)),
+            quad(2.0, Color::rgba(255, 255, 255, 128)),
+        ];
+        let Some(pixels) = draw(&quads) else {
+            eprintln!("No adapter to draw the quads with");
+            return;
+        };
+        for row in pixels.chunks(WIDTH as usize) {
+            assert_eq!(row[0], [200, 0, 0, 255]);
+            assert_eq!(row[1], [200, 0, 0, 255]);
+            // Half of white over black in linear light, which is brighter than the middle of
+            // the sRGB scale.
+            for pixel in &row[2..] {
+                assert!((180..=195).contains(&pixel[0]), "{:?}", pixel);
+                assert_eq!(pixel[0], pixel[1]);
+                assert_eq!(pixel[0], pixel[2]);
+            }
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub struct Vec2(pub i32, pub i32);
+
+impl std::ops::AddAssign for Vec2 {
+    fn add_assign(&mut self, other: Self) {
+        self.0 += other.0;
+        self.1 += other.1;
+    }
+}
+
+impl std::ops::Add for Vec2 {
+    type Output = Vec2;
+
+    fn add(self, other: Self) -> Self {
+        Vec2(self.0 + other.0, self.1 + other.1)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+    /// The diagonals, which only some boards have, see `topology`.
+    UpLeft,
+    UpRight,
+    DownLeft,
+    DownRight,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 4] = [
+        Direction::Up,
+        Direction::Down,
+        Direction::Left,
+        Direction::Right,
+    ];
+
+    pub fn vec(self) -> Vec2 {
+        match self {
+            Direction::Up => Vec2(0, 1),
+            Direction::Down => Vec2(0, -1),
+            Direction::Left => Vec2(-1, 0),
+            Direction::Right => Vec2(1, 0),
+            Direction::UpLeft => Vec2(-1, 1),
+            Direction::UpRight => Vec2(1, 1),
+            Direction::DownLeft => Vec2(-1, -1),
+            Direction::DownRight => Vec2(1, -1),
+        }
+    }
+
+    /// The direction after a quarter turn clockwise.
+    pub fn clockwise(self) -> Self {
+        match self {
+            Direction::Up => Direction::Right,
+            Direction::Right => Direction::Down,
+            Direction::Down => Direction::Left,
+            Direction::Left => Direction::Up,
+            Direction::UpLeft => Direction::UpRight,
+            Direction::UpRight => Direction::DownRight,
+            Direction::DownRight => Direction::DownLeft,
+            Direction::DownLeft => Direction::UpLeft,
+        }
+    }
+
+    pub fn opposite(self) -> Self {
+        self.clockwise().clockwise()
+    }
+
+    /// Distances of the pixel `(x, y)` of a square from its back, as seen heading in the
+    /// direction, and from its side, where `last` is the coordinate of its last pixel. The
+    /// distances along the diagonals are halved, to stay within the square.
+    pub fn along_across(self, x: i32, y: i32, last: i32) -> (i32, i32) {
+        match self {
+            Direction::Right => (x, y),
+            Direction::Left => (last - x, y),
+            Direction::Up => (y, x),
+            Direction::Down => (last - y, x),
+            Direction::UpRight => ((x + y) / 2, (x - y + last) / 2),
+            Direction::UpLeft => ((last - x + y) / 2, (x + y) / 2),
+            Direction::DownRight => ((x + last - y) / 2, (x + y) / 2),
+            Direction::DownLeft => ((2 * last - x - y) / 2, (x - y + last) / 2),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Tile {
+    Empty,
+    Snake,
+    Food,
+    /// Food that was left too long, see `Config::food_lifetime`.
+    RottenFood,
+    /// See `World::golden_food`.
+    GoldenFood,
+    /// See `World::bombs`.
+    Bomb,
+    Wall,
+    /// Leads to the other end of the portal, see `World::from_level`.
+    Portal,
+}
+
+const NOT_FREE: usize = usize::MAX;
+
+/// Contents of every cell of the board, together with the list of empty cells, so that both
+/// collision checks and picking a random empty cell are O(1).
+#[derive(Clone)]
+pub struct Grid {
+    width: i32,
+    height: i32,
+    tiles: Vec<Tile>,
+    free: Vec<Vec2>,
+    // For every cell, its position in `free` or `NOT_FREE`.
+    free_index: Vec<usize>,
+}
+
+impl Grid {
+    pub fn new(width: i32, height: i32) -> Self {
+        let size = (width * height) as usize;
+        Grid {
+            width,
+            height,
+            tiles: vec![Tile::Empty; size],
+            free: (0..height)
+                .flat_map(|y| (0..width).map(move |x| Vec2(x, y)))
+                .collect(),
+            free_index: (0..size).collect(),
+        }
+    }
+
+    fn index(&self, pos: Vec2) -> Option<usize> {
+        if pos.0 < 0 || pos.0 >= self.width || pos.1 < 0 || pos.1 >= self.height {
+            None
+        } else {
+            Some((pos.1 * self.width + pos.0) as usize)
+        }
+    }
+
+    /// Returns `None` for positions outside of the board.
+    pub fn get(&self, pos: Vec2) -> Option<Tile> {
+        self.index(pos).map(|i| self.tiles[i])
+    }
+
+    pub fn set(&mut self, pos: Vec2, tile: Tile) {
+        let i = self.index(pos).expect("position outside of the grid");
+        let was_empty = self.tiles[i] == Tile::Empty;
+        self.tiles[i] = tile;
+
+        if was_empty && tile != Tile::Empty {
+            let free_pos = self.free_index[i];
+            self.free.swap_remove(free_pos);
+            if let Some(&moved) = self.free.get(free_pos) {
+                let moved_idx = self.index(moved).unwrap();
+                self.free_index[moved_idx] = free_pos;
+            }
+            self.free_index[i] = NOT_FREE;
+        } else if !was_empty && tile == Tile::Empty {
+            self.free_index[i] = self.free.len();
+            self.free.push(pos);
+        }
+    }
+
+    /// The empty cells, in no particular order.
+    pub fn free_cells(&self) -> &[Vec2] {
+        &self.free
+    }
+
+    pub fn random_free<R: Rng>(&self, rng: &mut R) -> Option<Vec2> {
+        if self.free.is_empty() {
+            None
+        } else {
+            Some(self.free[rng.gen_range(0..self.free.len())])
+        }
+    }
+}
@@ -0,0 +1,227 @@
+//! Environment for training agents to play the game, modelled after OpenAI Gym: `reset` starts
+//! a new single-player game and `step` advances it by one tick with the agent's move. Nothing
+//! runs in real time, so the games go as fast as the agent can decide.
+
+use crate::config::Config;
+use crate::controller::GameView;
+use crate::grid::{Direction, Vec2};
+use crate::world::{Event, World};
+
+/// Reward for eating a piece of food.
+pub const FOOD_REWARD: f32 = 1.0;
+/// Reward for dying.
+pub const DEATH_REWARD: f32 = -1.0;
+/// Reward for filling the board, which ends the game as a win.
+pub const WIN_REWARD: f32 = 10.0;
+
+/// How the agent sees the board.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    /// Three planes of `height` × `width` cells of the whole board, all the layers included,
+    /// holding ones where the cells are taken by the head, the rest of the snake and the food.
+    /// Rows go from the bottom up.
+    Grid,
+    /// `FEATURES_PER_DIRECTION` numbers for each direction in which the snakes can go on the
+    /// board and one more, describing the surroundings of the head, see `features`.
+    Features,
+}
+
+/// Numbers in an observation with `Encoding::Features` for each direction of the board.
+pub const FEATURES_PER_DIRECTION: usize = 3;
+
+/// Observation of the board as a flat array together with its shape.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Observation {
+    pub shape: Vec<usize>,
+    pub data: Vec<f32>,
+}
+
+pub struct Env {
+    config: Config,
+    encoding: Encoding,
+    /// Seed of the next game. Each game gets the next one, so the sequence of games is
+    /// reproducible.
+    seed: u64,
+    /// Ticks without food after which the game is cut short, so that an agent that has learned
+    /// to go round in circles doesn't get stuck forever.
+    max_hungry_ticks: u64,
+    world: World,
+    hungry_ticks: u64,
+}
+
+impl Env {
+    pub fn new(config: &Config, encoding: Encoding, seed: u64) -> Self {
+        let world = World::new(config, 1, seed);
+        Env {
+            config: config.clone(),
+            encoding,
+            seed: seed.wrapping_add(1),
+            max_hungry_ticks: (world.width() * world.height()) as u64 * 2,
+            world,
+            hungry_ticks: 0,
+        }
+    }
+
+    /// The game being played, for rendering or for agents that want to look at it directly.
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    pub fn observation_shape(&self) -> Vec<usize> {
+        match self.encoding {
+            Encoding::Grid => vec![3, self.world.height() as usize, self.world.width() as usize],
+            Encoding::Features => {
+                vec![FEATURES_PER_DIRECTION * self.world.directions().len() + 1]
+            }
+        }
+    }
+
+    /// Starts a new game.
+    pub fn reset(&mut self) -> Observation {
+        self.world = World::new(&self.config, 1, self.seed);
+        self.seed = self.seed.wrapping_add(1);
+        self.hungry_ticks = 0;
+        self.observe()
+    }
+
+    /// Moves the snake in the given direction. Returns what the agent sees afterwards, the
+    /// reward for the move, and whether the game is over. Once it is over, the next call has to
+    /// be to `reset`.
+    pub fn step(&mut self, action: Direction) -> (Observation, f32, bool) {
+        let mut reward = 0.0;
+        self.hungry_ticks += 1;
+        for event in self.world.step(&[Some(action)]) {
+            match event {
+                Event::Ate { .. } | Event::AteGolden { .. } => {
+                    reward += FOOD_REWARD;
+                    self.hungry_ticks = 0;
+                }
+                Event::AteRotten { .. } => reward -= FOOD_REWARD,
+                Event::Died { .. } => reward += DEATH_REWARD,
+                Event::BoardFull => reward += WIN_REWARD,
+                Event::TimeUp => (),
+                Event::FoodSpawned { .. }
+                | Event::Dropped { .. }
+                | Event::GoldenFoodSpawned { .. }
+                | Event::GoldenFoodExpired { .. }
+                | Event::Exploded { .. }
+                | Event::PassedThrough { .. }
+                | Event::CheckpointReached { .. }
+                | Event::Respawned { .. } => (),
+            }
+        }
+        let done = self.world.is_over() || self.hungry_ticks >= self.max_hungry_ticks;
+        (self.observe(), reward, done)
+    }
+
+    fn observe(&self) -> Observation {
+        let data = match self.encoding {
+            Encoding::Grid => self.grid(),
+            Encoding::Features => self.features(),
+        };
+        Observation {
+            shape: self.observation_shape(),
+            data,
+        }
+    }
+
+    fn grid(&self) -> Vec<f32> {
+        let (width, height) = (self.world.width() as usize, self.world.height() as usize);
+        let plane = width * height;
+        let mut data = vec![0.0; 3 * plane];
+        let index = |pos: Vec2| pos.1 as usize * width + pos.0 as usize;
+        let view = GameView::new(&self.world, 0);
+        if self.world.tile(view.head()).is_some() {
+            data[index(view.head())] = 1.0;
+        }
+        for &pos in view.snake().tail.iter() {
+            data[plane + index(pos)] = 1.0;
+        }
+        for pos in view.food() {
+            data[2 * plane + index(pos)] = 1.0;
+        }
+        data
+    }
+
+    /// For each of the directions of the board in the order of `World::directions`: whether
+    /// moving there is fatal, whether it is the current direction, and whether it brings the
+    /// head closer to the nearest food. The last number is the length of the snake relative to
+    /// the size of the board.
+    fn features(&self) -> Vec<f32> {
+        let view = GameView::new(&self.world, 0);
+        let (head, directions) = (view.head(), self.world.directions());
+        let topology = self.world.topology();
+        let nearest = view.food().min_by_key(|&f| topology.distance(head, f));
+        let food_towards = |d: Direction| match nearest {
+            Some(food) => {
+                let next = self.world.neighbor(head, d);
+                topology.distance(next, food) < topology.distance(head, food)
+            }
+            None => false,
+        };
+        let flag = |b: bool| if b { 1.0 } else { 0.0 };
+        let mut data = Vec::with_capacity(FEATURES_PER_DIRECTION * directions.len() + 1);
+        data.extend(directions.iter().map(|&d| flag(!view.is_safe(d))));
+        data.extend(
+            directions
+                .iter()
+                .map(|&d| flag(d == view.snake().direction)),
+        );
+        data.extend(directions.iter().map(|&d| flag(food_towards(d))));
+        let cells = self.world.width() * self.world.height();
+        data.push(view.snake().length() as f32 / cells as f32);
+        data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::topology::Shape;
+
+    /// Plays a few ticks going straight and checks that every observation has its shape.
+    fn play(config: &Config, encoding: Encoding) {
+        let mut env = Env::new(config, encoding, 1);
+        let shape = env.observation_shape();
+        let mut observation = env.reset();
+        for _ in 0..10 {
+            assert_eq!(observation.shape, shape);
+            assert_eq!(observation.data.len(), shape.iter().product::<usize>());
+            let direction = GameView::new(env.world(), 0).snake().direction;
+            let (next, _, done) = env.step(direction);
+            if done {
+                break;
+            }
+            observation = next;
+        }
+    }
+
+    #[test]
+    fn grid_covers_every_layer() {
+        let config = Config {
+            layers: 2,
+            ..Config::default()
+        };
+        play(&config, Encoding::Grid);
+        let env = Env::new(&config, Encoding::Grid, 1);
+        let height = (config.height * 2) as usize;
+        assert_eq!(
+            env.observation_shape(),
+            vec![3, height, config.width as usize]
+        );
+    }
+
+    #[test]
+    fn features_cover_every_direction_of_the_board() {
+        for (shape, directions) in [(Shape::Square, 4), (Shape::Diagonal, 8), (Shape::Hex, 6)] {
+            let config = Config {
+                shape,
+                ..Config::default()
+            };
+            play(&config, Encoding::Features);
+            let env = Env::new(&config, Encoding::Features, 1);
+            let length = FEATURES_PER_DIRECTION * directions + 1;
+            assert_eq!(env.observation_shape(), vec![length]);
+        }
+    }
+}
@@ -0,0 +1,111 @@
+//! Handicaps that even out a game against bots: the bots react late and see only around their
+//! heads, and the player's snake starts longer. The bots are hindered by wrapping their
+//! controllers, so the rules of the game stay the same for every snake.
+
+use crate::controller::{Controller, GameView};
+use crate::grid::Direction;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Highest reaction delay of the bots, in ticks.
+pub const MAX_REACTION_DELAY: u32 = 5;
+/// Most segments that the player's snake can start with on top of the usual ones.
+pub const MAX_HEAD_START: u32 = 10;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Handicap {
+    /// Number of ticks after which the decisions of the bots take effect.
+    pub reaction_delay: u32,
+    /// Distance from the head in cells beyond which the bots see nothing, or 0 if they see the
+    /// whole board.
+    pub blind_radius: u32,
+    /// Segments that the player's snake grows by at the start of the game.
+    pub head_start: u32,
+}
+
+impl Handicap {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.reaction_delay > MAX_REACTION_DELAY {
+            return Err(format!(
+                "The bots can react at most {} ticks late, not {}",
+                MAX_REACTION_DELAY, self.reaction_delay
+            ));
+        }
+        if self.head_start > MAX_HEAD_START {
+            return Err(format!(
+                "The head start can be at most {} segments, not {}",
+                MAX_HEAD_START, self.head_start
+            ));
+        }
+        Ok(())
+    }
+
+    /// Wraps the controller of a bot in the handicaps that apply to it.
+    pub fn hinder(&self, mut bot: Box<dyn Controller>) -> Box<dyn Controller> {
+        if self.blind_radius > 0 {
+            bot = Box::new(Blinkered::new(bot, self.blind_radius));
+        }
+        if self.reaction_delay > 0 {
+            bot = Box::new(Delayed::new(bot, self.reaction_delay));
+        }
+        bot
+    }
+}
+
+/// Makes the decisions of a controller take effect a number of ticks late. Until then the snake
+/// keeps going the way it did.
+pub struct Delayed {
+    inner: Box<dyn Controller>,
+    delay: usize,
+    /// The decisions not yet taken, the oldest first.
+    decisions: VecDeque<Direction>,
+}
+
+impl Delayed {
+    pub fn new(inner: Box<dyn Controller>, delay: u32) -> Self {
+        Delayed {
+            inner,
+            delay: delay as usize,
+            decisions: VecDeque::new(),
+        }
+    }
+}
+
+impl Controller for Delayed {
+    fn decide(&mut self, view: &GameView) -> Direction {
+        self.decisions.push_back(self.inner.decide(view));
+        let heading = view.snake().direction;
+        if self.decisions.len() <= self.delay {
+            return heading;
+        }
+        match self.decisions.pop_front() {
+            // A turn meant for where the snake was a few ticks ago may lead straight back, which
+            // no bot would choose.
+            Some(direction) if direction != heading.opposite() => direction,
+            _ => heading,
+        }
+    }
+}
+
+/// Shows a controller only what is near the head of its snake. Beyond `radius` cells there is no
+/// food, and in the territory mode every cell looks as if the snake had painted it already.
+pub struct Blinkered {
+    inner: Box<dyn Controller>,
+    radius: i32,
+}
+
+impl Blinkered {
+    pub fn new(inner: Box<dyn Controller>, radius: u32) -> Self {
+        Blinkered {
+            inner,
+            radius: radius as i32,
+        }
+    }
+}
+
+impl Controller for Blinkered {
+    fn decide(&mut self, view: &GameView) -> Direction {
+        self.inner.decide(&view.with_sight(self.radius))
+    }
+}
@@ -0,0 +1,112 @@
+//! Overlay for tuning the spawner of the food and the layouts of the levels, toggled with F4: a
+//! heatmap of the cells where food appeared, or where the head of the player's snake went, in
+//! all the games of the session on boards of the same size. The more often, the brighter.
+
+use snake_pixels::font::{draw_text, text_width, GLYPH_HEIGHT};
+use snake_pixels::framebuffer::{Color, FrameBuffer};
+use snake_pixels::grid::Vec2;
+use snake_pixels::palette::Palette;
+use snake_pixels::world::{Camera, Event, World};
+use std::collections::HashMap;
+use winit::keyboard::KeyCode;
+
+/// Switches from no heatmap to the one of the food, to the one of the snake, and back.
+pub const TOGGLE_KEY: KeyCode = KeyCode::F4;
+
+/// Opacity of the cells that were visited the least and the most.
+const MIN_ALPHA: u8 = 40;
+const MAX_ALPHA: u8 = 200;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Layer {
+    Food,
+    Travel,
+}
+
+/// How often something happened on every cell of one size of the board.
+#[derive(Default)]
+struct Counts {
+    food: HashMap<Vec2, u32>,
+    travel: HashMap<Vec2, u32>,
+}
+
+#[derive(Default)]
+pub struct Heatmap {
+    /// The counts by the width and the height of the board.
+    boards: HashMap<(i32, i32), Counts>,
+    shown: Option<Layer>,
+}
+
+impl Heatmap {
+    /// Counts the food that appeared in a tick of the player's game, and the cell where the head
+    /// of the player's snake is after it.
+    pub fn record(&mut self, world: &World, events: &[Event]) {
+        let counts = self
+            .boards
+            .entry((world.width(), world.height()))
+            .or_default();
+        for event in events {
+            if let Event::FoodSpawned { pos } | Event::GoldenFoodSpawned { pos, .. } = *event {
+                *counts.food.entry(pos).or_insert(0) += 1;
+            }
+        }
+        if let Some(snake) = world.snakes().first().filter(|snake| snake.alive) {
+            *counts.travel.entry(snake.head).or_insert(0) += 1;
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.shown = match self.shown {
+            None => Some(Layer::Food),
+            Some(Layer::Food) => Some(Layer::Travel),
+            Some(Layer::Travel) => None,
+        };
+    }
+
+    pub fn is_shown(&self) -> bool {
+        self.shown.is_some()
+    }
+
+    /// Draws the heatmap of the board of the world over it, and in the bottom right corner what
+    /// it shows and the total of its counts.
+    pub fn render(
+        &self,
+        buffer: &mut FrameBuffer,
+        palette: &Palette,
+        camera: &Camera,
+        world: &World,
+    ) {
+        let layer = match self.shown {
+            Some(layer) => layer,
+            None => return,
+        };
+        let counts = self.boards.get(&(world.width(), world.height()));
+        let (counts, color, name) = match layer {
+            Layer::Food => (counts.map(|c| &c.food), palette.food, "FOOD"),
+            Layer::Travel => (counts.map(|c| &c.travel), palette.selected, "TRAVEL"),
+        };
+        let total: u32 = counts.map_or(0, |counts| counts.values().sum());
+        if let Some(counts) = counts {
+            render_counts(buffer, camera, counts, color);
+        }
+        let text = format!("{} {}", name, total);
+        let x = buffer.width() as i32 - text_width(&text) - 1;
+        draw_text(buffer, x, GLYPH_HEIGHT + 1, &text, 1, palette.text);
+    }
+}
+
+/// Tints every cell with a count, the more the higher the count is compared to the highest one.
+fn render_counts(
+    buffer: &mut FrameBuffer,
+    camera: &Camera,
+    counts: &HashMap<Vec2, u32>,
+    color: Color,
+) {
+    let most = counts.values().copied().max().unwrap_or(0).max(1);
+    let cell = camera.cell as usize;
+    for (&pos, &count) in counts.iter().filter(|(&pos, _)| camera.shows(pos)) {
+        let alpha = MIN_ALPHA as u32 + (MAX_ALPHA - MIN_ALPHA) as u32 * count / most;
+        let corner = camera.corner(pos);
+        buffer.blend_rectangle(corner.0, corner.1, cell, cell, color, alpha as u8);
+    }
+}
@@ -0,0 +1,26 @@
+//! Custom rules layered on top of a game. `State` calls the hooks after every tick, and they may
+//! change the world, e.g. to build walls or put food on the board.
+
+use crate::grid::Vec2;
+use crate::telemetry::EndReason;
+use crate::world::{SnakeId, World};
+
+pub trait Hooks {
+    /// Called once before the first tick, typically to lay out the level.
+    fn on_start(&mut self, _world: &mut World) {}
+
+    /// Called after every tick.
+    fn on_tick(&mut self, _world: &mut World) {}
+
+    fn on_food_eaten(&mut self, _world: &mut World, _snake: SnakeId, _pos: Vec2) {}
+
+    /// Called when food appears on its own, but not for the food put on the board by the hooks.
+    fn on_food_spawned(&mut self, _world: &mut World, _pos: Vec2) {}
+
+    /// Points for the player's snake eating the food at `pos`.
+    fn food_value(&mut self, _world: &mut World, _pos: Vec2) -> u32 {
+        1
+    }
+
+    fn on_death(&mut self, _world: &mut World, _snake: SnakeId, _reason: EndReason) {}
+}
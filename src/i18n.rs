@@ -0,0 +1,127 @@
+//! Translations of the menus and the HUD. The English text doubles as the key, so it is shown
+//! whenever a translation is missing. The translations are files of lines `TEXT = TRANSLATION`,
+//! built into the game for every language. A file `locales/<code>.txt` in the data directory
+//! overrides the built-in translation line by line, so that translators can try their changes
+//! without rebuilding the game. The developer console and the frame-time overlay aren't
+//! translated.
+
+use crate::storage;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::RwLock;
+
+const LOCALES_DIR: &str = "locales";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Language {
+    #[default]
+    English,
+    German,
+    Spanish,
+}
+
+impl Language {
+    const ALL: [Language; 3] = [Language::English, Language::German, Language::Spanish];
+
+    /// ISO 639-1 code, which names the file of the translation.
+    pub fn code(self) -> &'static str {
+        match self {
+            Language::English => "en",
+            Language::German => "de",
+            Language::Spanish => "es",
+        }
+    }
+
+    /// Name of the language in the language itself.
+    pub fn name(self) -> &'static str {
+        match self {
+            Language::English => "ENGLISH",
+            Language::German => "DEUTSCH",
+            Language::Spanish => "ESPAÑOL",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        let i = Language::ALL.iter().position(|&l| l == self).unwrap();
+        Language::ALL[(i + 1) % Language::ALL.len()]
+    }
+
+    fn builtin(self) -> &'static str {
+        match self {
+            Language::English => "",
+            Language::German => include_str!("../locales/de.txt"),
+            Language::Spanish => include_str!("../locales/es.txt"),
+        }
+    }
+}
+
+type Catalog = HashMap<String, String>;
+
+/// Parses the lines `TEXT = TRANSLATION` of a translation, skipping empty lines and comments
+/// starting with `#`. `name` is only used in the warnings about lines that are neither.
+fn parse(text: &str, name: &str) -> Catalog {
+    let mut catalog = Catalog::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match line.split_once('=') {
+            Some((key, value)) => {
+                catalog.insert(key.trim().to_string(), value.trim().to_string());
+            }
+            None => warn!("{}:{}: Expected TEXT = TRANSLATION", name, i + 1),
+        }
+    }
+    catalog
+}
+
+/// Catalogs to look the text up in, the first one that has it wins.
+static CATALOGS: RwLock<Vec<Catalog>> = RwLock::new(Vec::new());
+
+/// Switches all the text to `language`, falling back from the file in the data directory to the
+/// built-in translation and then to English.
+pub fn set_language(language: Language) {
+    info!("Switching to {}", language.code());
+    let mut catalogs = Vec::new();
+    if language != Language::English {
+        let path = [LOCALES_DIR, &format!("{}.txt", language.code())]
+            .iter()
+            .collect::<std::path::PathBuf>();
+        if let Some(text) = storage::read_text(&path) {
+            info!("Using the translation from {}", path.display());
+            catalogs.push(parse(&text, &path.to_string_lossy()));
+        }
+        catalogs.push(parse(language.builtin(), language.code()));
+    }
+    *CATALOGS.write().unwrap() = catalogs;
+}
+
+/// The text in the current language.
+pub fn tr(text: &str) -> String {
+    CATALOGS
+        .read()
+        .unwrap()
+        .iter()
+        .find_map(|catalog| catalog.get(text))
+        .map_or(text, |translation| translation.as_str())
+        .to_string()
+}
+
+/// Translates a text with `{}` in place of every argument, which the translation may move.
+pub fn tr_fmt(text: &str, args: &[&dyn Display]) -> String {
+    let translation = tr(text);
+    let mut parts = translation.split("{}");
+    let mut result = parts.next().unwrap_or("").to_string();
+    let mut args = args.iter();
+    for part in parts {
+        if let Some(arg) = args.next() {
+            result.push_str(&arg.to_string());
+        }
+        result.push_str(part);
+    }
+    result
+}
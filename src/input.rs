@@ -0,0 +1,43 @@
+//! Which keys are held down. The keyboard repeats a held key after a while, which would queue the
+//! same turn over and over, and some platforms don't mark the repeats, so they are told apart by
+//! the keys that haven't been released yet. Holding the key of the direction in which the snake
+//! already moves makes it sprint, if the player wants.
+
+use crate::bindings::KeyBindings;
+use snake_pixels::grid::Direction;
+use winit::keyboard::KeyCode;
+
+#[derive(Default)]
+pub struct InputState {
+    /// The keys held down, in the order in which they were pressed.
+    held: Vec<KeyCode>,
+}
+
+impl InputState {
+    /// Records a key going down. Returns `false` if it is already held, so that this is only a
+    /// repeat.
+    pub fn press(&mut self, keycode: KeyCode) -> bool {
+        if self.held.contains(&keycode) {
+            return false;
+        }
+        self.held.push(keycode);
+        true
+    }
+
+    pub fn release(&mut self, keycode: KeyCode) {
+        self.held.retain(|&held| held != keycode);
+    }
+
+    /// Forgets all the keys, e.g. when the window loses the focus and won't hear them released.
+    pub fn clear(&mut self) {
+        self.held.clear();
+    }
+
+    /// The direction of the key pressed last among those held, if any is bound to one.
+    pub fn held_direction(&self, bindings: &KeyBindings) -> Option<Direction> {
+        self.held
+            .iter()
+            .rev()
+            .find_map(|&keycode| bindings.direction(keycode))
+    }
+}
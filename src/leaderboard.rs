@@ -0,0 +1,141 @@
+use crate::i18n::tr;
+use crate::storage;
+use serde::{Deserialize, Serialize};
+use snake_pixels::config::{Difficulty, Mode};
+use snake_pixels::font::{draw_text, draw_text_centered, text_width, LINE_HEIGHT};
+use snake_pixels::framebuffer::FrameBuffer;
+use snake_pixels::palette::Palette;
+
+const FILE_NAME: &str = "leaderboard.json";
+const MAX_ENTRIES: usize = 10;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Entry {
+    pub name: String,
+    pub score: u32,
+    pub length: usize,
+    /// Seconds since the Unix epoch.
+    pub date: u64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Table {
+    mode: Mode,
+    difficulty: Difficulty,
+    /// Sorted by score, highest first.
+    entries: Vec<Entry>,
+}
+
+/// Best scores on this machine, with a separate table for each mode and difficulty. Shared by
+/// all profiles.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Leaderboard {
+    /// Sorted by mode, then difficulty.
+    tables: Vec<Table>,
+}
+
+impl Leaderboard {
+    pub fn load() -> Self {
+        let mut leaderboard: Leaderboard = storage::load(FILE_NAME);
+        leaderboard
+            .tables
+            .sort_by_key(|table| (table.mode, table.difficulty));
+        leaderboard
+    }
+
+    pub fn save(&self) {
+        storage::save(FILE_NAME, self)
+    }
+
+    fn table(&self, mode: Mode, difficulty: Difficulty) -> Option<&Table> {
+        self.tables
+            .iter()
+            .find(|table| table.mode == mode && table.difficulty == difficulty)
+    }
+
+    /// Whether a game with this score would make it into the top 10.
+    pub fn qualifies(&self, mode: Mode, difficulty: Difficulty, score: u32) -> bool {
+        if score == 0 {
+            return false;
+        }
+        match self.table(mode, difficulty) {
+            Some(table) => {
+                table.entries.len() < MAX_ENTRIES
+                    || table.entries.iter().any(|entry| entry.score < score)
+            }
+            None => true,
+        }
+    }
+
+    /// Adds the entry and returns the index of its table, for `render`.
+    pub fn insert(&mut self, mode: Mode, difficulty: Difficulty, entry: Entry) -> usize {
+        let index = match self
+            .tables
+            .binary_search_by_key(&(mode, difficulty), |table| (table.mode, table.difficulty))
+        {
+            Ok(index) => index,
+            Err(index) => {
+                self.tables.insert(
+                    index,
+                    Table {
+                        mode,
+                        difficulty,
+                        entries: Vec::new(),
+                    },
+                );
+                index
+            }
+        };
+        let entries = &mut self.tables[index].entries;
+        // Among equal scores, the older entry stays ahead.
+        let position = entries
+            .iter()
+            .position(|other| other.score < entry.score)
+            .unwrap_or(entries.len());
+        entries.insert(position, entry);
+        entries.truncate(MAX_ENTRIES);
+        index
+    }
+
+    /// Number of tables, each shown on its own page.
+    pub fn pages(&self) -> usize {
+        self.tables.len().max(1)
+    }
+
+    pub fn render(&self, buffer: &mut FrameBuffer, palette: &Palette, page: usize) {
+        buffer.clear(palette.menu_bg);
+        let top = buffer.height() as i32;
+        let width = buffer.width() as i32;
+        draw_text_centered(buffer, top - 4, &tr("SCORES"), 2, palette.text);
+        draw_text_centered(buffer, 9, &tr("< >: PAGE  ESC: BACK"), 1, palette.dim_text);
+
+        let table = match self.tables.get(page) {
+            Some(table) => table,
+            None => {
+                let text = tr("NO SCORES YET");
+                draw_text_centered(buffer, top / 2, &text, 1, palette.dim_text);
+                return;
+            }
+        };
+
+        let subtitle = format!("{} {}", tr(table.mode.name()), tr(table.difficulty.name()));
+        draw_text_centered(buffer, top - 18, &subtitle, 1, palette.selected);
+
+        let mut y = top - 28;
+        for (i, entry) in table.entries.iter().enumerate() {
+            draw_text(buffer, 4, y, &format!("{:>2}", i + 1), 1, palette.dim_text);
+            draw_text(buffer, 16, y, &entry.name, 1, palette.text);
+            let score = entry.score.to_string();
+            draw_text(
+                buffer,
+                width - 4 - text_width(&score),
+                y,
+                &score,
+                1,
+                palette.text,
+            );
+            y -= LINE_HEIGHT;
+        }
+    }
+}
@@ -0,0 +1,677 @@
+//! Hand-made boards. A level is stored as text with a character for every cell, top row first:
+//!
+//! - `.` an empty cell,
+//! - `#` a wall,
+//! - `S` where a snake starts, with its head on this cell,
+//! - `F` a food spawner, where the food appears whenever one of them is free,
+//! - `1` to `9` a portal: a snake entering one of the two cells with the same digit comes out
+//!   next to the other one, heading the same way,
+//! - `~` mud, which slows the game down while the head of the player's snake is in it,
+//! - `+` a boost pad, which speeds the game up while the head of the player's snake is on it,
+//! - `^`, `v`, `<` and `>` a one-way gate, which the snakes can only enter heading the way that
+//!   it points,
+//! - `U`, `D`, `L` and `R` a conveyor going up, down, left or right, which carries a snake whose
+//!   head moves onto it one more cell its way,
+//! - `C` a checkpoint: a snake that dies comes back with the length that it had when its head last
+//!   touched one,
+//! - `O` an opening in the border, on the edge of the room: a snake that leaves the room across
+//!   it comes into the room next to it on that side, or back on the opposite side of the same
+//!   room if there is none.
+//!
+//! A level can be made of several rooms of the same size, each of them starting with a header
+//! line `= NAME` and the exits of the room, such as `= HALL right:CAVE up:TOWER`. An exit leads
+//! from the openings on that side of the room to the same place on the opposite side of the other
+//! room. The snakes start in the rooms where their start cells are.
+//!
+//! `Level::load` checks that the level can be played, so that a broken level is reported when it
+//! is loaded rather than misbehaving in the middle of a game.
+
+use crate::grid::{Direction, Vec2};
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+
+/// Number of different portal pairs, one for each digit.
+pub const PORTALS: u8 = 9;
+/// The start of the header line of a room.
+const ROOM_HEADER: char = '=';
+
+/// The sides of a room as they are named in the exits in its header.
+const SIDES: [(Direction, &str); 4] = [
+    (Direction::Up, "up"),
+    (Direction::Down, "down"),
+    (Direction::Left, "left"),
+    (Direction::Right, "right"),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cell {
+    Empty,
+    Wall,
+    Start,
+    FoodSpawner,
+    /// One end of the portal pair with the given number, from 1 to `PORTALS`.
+    Portal(u8),
+    Mud,
+    Boost,
+    /// A one-way gate that can be entered only heading in the direction.
+    Gate(Direction),
+    Conveyor(Direction),
+    Checkpoint,
+    /// An open cell on the edge of the level, through which the snakes wrap around the board.
+    Opening,
+}
+
+/// Cells of the body of a new snake behind its head.
+fn body(head: Vec2, direction: Direction) -> impl Iterator<Item = Vec2> {
+    let back = Vec2(-direction.vec().0, -direction.vec().1);
+    vec![head + back, head + back + back].into_iter()
+}
+
+impl Cell {
+    /// Whether a snake can move onto the cell.
+    fn is_open(self) -> bool {
+        matches!(
+            self,
+            Cell::Empty
+                | Cell::Start
+                | Cell::FoodSpawner
+                | Cell::Mud
+                | Cell::Boost
+                | Cell::Gate(_)
+                | Cell::Conveyor(_)
+                | Cell::Checkpoint
+                | Cell::Opening
+        )
+    }
+
+    fn from_char(c: char) -> Option<Cell> {
+        let cell = match c {
+            '.' => Cell::Empty,
+            '#' => Cell::Wall,
+            'S' => Cell::Start,
+            'F' => Cell::FoodSpawner,
+            '1'..='9' => Cell::Portal(c as u8 - b'0'),
+            '~' => Cell::Mud,
+            '+' => Cell::Boost,
+            '^' => Cell::Gate(Direction::Up),
+            'v' => Cell::Gate(Direction::Down),
+            '<' => Cell::Gate(Direction::Left),
+            '>' => Cell::Gate(Direction::Right),
+            'U' => Cell::Conveyor(Direction::Up),
+            'D' => Cell::Conveyor(Direction::Down),
+            'L' => Cell::Conveyor(Direction::Left),
+            'R' => Cell::Conveyor(Direction::Right),
+            'C' => Cell::Checkpoint,
+            'O' => Cell::Opening,
+            _ => return None,
+        };
+        Some(cell)
+    }
+
+    fn to_char(self) -> char {
+        match self {
+            Cell::Empty => '.',
+            Cell::Wall => '#',
+            Cell::Start => 'S',
+            Cell::FoodSpawner => 'F',
+            Cell::Portal(n) => (b'0' + n) as char,
+            Cell::Mud => '~',
+            Cell::Boost => '+',
+            Cell::Gate(Direction::Up) => '^',
+            Cell::Gate(Direction::Down) => 'v',
+            Cell::Gate(Direction::Left) => '<',
+            Cell::Gate(Direction::Right) => '>',
+            Cell::Conveyor(Direction::Up) => 'U',
+            Cell::Conveyor(Direction::Down) => 'D',
+            Cell::Conveyor(Direction::Left) => 'L',
+            Cell::Conveyor(Direction::Right) => 'R',
+            Cell::Gate(_) | Cell::Conveyor(_) => {
+                unreachable!("the levels only have gates and conveyors along the sides")
+            }
+            Cell::Checkpoint => 'C',
+            Cell::Opening => 'O',
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Problem {
+    UnknownCell(char),
+    /// A row that is not as long as the first one.
+    RowLength {
+        expected: usize,
+        found: usize,
+    },
+    Empty,
+    NoStart,
+    /// The two cells behind the start aren't free in any direction, or are taken by another
+    /// snake.
+    NoRoomForBody,
+    /// A portal with one end or more than two.
+    UnpairedPortal(u8),
+    /// A cell that the snake can't get to from its start.
+    Unreachable,
+    /// An opening that isn't on the edge of its room.
+    InnerOpening,
+    /// A room with a different number of rows than the first one.
+    RoomSize,
+    /// An exit that isn't a side and the name of another room.
+    BadExit,
+    /// A room with the same name as one before it.
+    DuplicateRoom,
+}
+
+impl Problem {
+    /// Short description for the screen.
+    pub fn label(self) -> &'static str {
+        match self {
+            Problem::UnknownCell(_) => "UNKNOWN CELL",
+            Problem::RowLength { .. } => "BAD ROW LENGTH",
+            Problem::Empty => "EMPTY LEVEL",
+            Problem::NoStart => "NO START",
+            Problem::NoRoomForBody => "NO ROOM AT START",
+            Problem::UnpairedPortal(_) => "UNPAIRED PORTAL",
+            Problem::Unreachable => "UNREACHABLE CELL",
+            Problem::InnerOpening => "OPENING NOT AT EDGE",
+            Problem::RoomSize => "BAD ROOM SIZE",
+            Problem::BadExit => "BAD EXIT",
+            Problem::DuplicateRoom => "DUPLICATE ROOM",
+        }
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Problem::UnknownCell(c) => write!(f, "unknown cell {:?}", c),
+            Problem::RowLength { expected, found } => {
+                write!(f, "the row is {} cells long instead of {}", found, expected)
+            }
+            Problem::Empty => write!(f, "the level is empty"),
+            Problem::NoStart => write!(f, "there is no start cell"),
+            Problem::NoRoomForBody => write!(f, "there is no room for the body of the snake"),
+            Problem::UnpairedPortal(n) => write!(f, "portal {} needs exactly two ends", n),
+            Problem::Unreachable => write!(f, "the cell can't be reached from the start"),
+            Problem::InnerOpening => write!(f, "the opening isn't on the edge of its room"),
+            Problem::RoomSize => write!(f, "the room isn't as high as the first one"),
+            Problem::BadExit => write!(f, "the exit isn't a side and the name of a room"),
+            Problem::DuplicateRoom => write!(f, "there is a room with the same name before"),
+        }
+    }
+}
+
+/// A problem with a level, at the place where it is in the text.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LevelError {
+    pub problem: Problem,
+    /// Line and column of the offending character, both counting from 1, or `None` if the
+    /// problem is with the level as a whole.
+    pub at: Option<(usize, usize)>,
+}
+
+impl fmt::Display for LevelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.at {
+            Some((line, column)) => write!(f, "line {}, column {}: {}", line, column, self.problem),
+            None => write!(f, "{}", self.problem),
+        }
+    }
+}
+
+impl std::error::Error for LevelError {}
+
+/// One of the rooms of a level, see the module documentation.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Room {
+    /// The name in the header, empty in a level without headers.
+    pub name: String,
+    /// The sides of the room that lead to other rooms, with the indices of the rooms.
+    pub exits: Vec<(Direction, usize)>,
+}
+
+impl Room {
+    /// The room that the openings on the side lead to, if it's not this one.
+    pub fn exit(&self, side: Direction) -> Option<usize> {
+        self.exits
+            .iter()
+            .find(|&&(direction, _)| direction == side)
+            .map(|&(_, room)| room)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Level {
+    width: i32,
+    /// Height of all the rooms together.
+    height: i32,
+    /// Row by row from the bottom, with the first room at the bottom and the others above it.
+    cells: Vec<Cell>,
+    rooms: Vec<Room>,
+    room_height: i32,
+}
+
+impl Level {
+    /// An empty level with a single room.
+    pub fn new(width: i32, height: i32) -> Self {
+        Level {
+            width,
+            height,
+            cells: vec![Cell::Empty; (width * height) as usize],
+            rooms: vec![Room::default()],
+            room_height: height,
+        }
+    }
+
+    /// Reads a level and checks that it can be played.
+    pub fn load(text: &str) -> Result<Self, LevelError> {
+        let level = Level::parse(text)?;
+        level.validate()?;
+        Ok(level)
+    }
+
+    /// Reads a level without checking it, e.g. to carry on editing it. Empty lines at the end
+    /// are ignored.
+    pub fn parse(text: &str) -> Result<Self, LevelError> {
+        let lines: Vec<&str> = text.trim_end().lines().map(str::trim_end).collect();
+        let headers = lines
+            .first()
+            .is_some_and(|line| line.starts_with(ROOM_HEADER));
+        // The rows of every room with the number of the line before them, which is its header in
+        // a level with several rooms.
+        let mut sections: Vec<(usize, Vec<&str>)> = Vec::new();
+        for (i, &line) in lines.iter().enumerate() {
+            match sections.last_mut() {
+                Some((_, rows)) if !(headers && line.starts_with(ROOM_HEADER)) => rows.push(line),
+                _ if headers => sections.push((i + 1, Vec::new())),
+                _ => sections.push((i, vec![line])),
+            }
+        }
+        let (start, rows) = sections
+            .first()
+            .map_or((0, &[][..]), |(i, rows)| (*i, &rows[..]));
+        let width = rows.first().map_or(0, |row| row.chars().count());
+        if width == 0 {
+            return Err(LevelError {
+                problem: Problem::Empty,
+                at: headers.then_some((start, 1)),
+            });
+        }
+        let room_height = rows.len() as i32;
+        let mut level = Level::new(width as i32, room_height * sections.len() as i32);
+        level.room_height = room_height;
+        level.rooms = vec![Room::default(); sections.len()];
+        for (room, (start, rows)) in sections.iter().enumerate() {
+            if rows.len() as i32 != room_height {
+                return Err(LevelError {
+                    problem: Problem::RoomSize,
+                    at: Some((*start, 1)),
+                });
+            }
+            for (i, row) in rows.iter().enumerate() {
+                let line = start + i + 1;
+                let found = row.chars().count();
+                if found != width {
+                    return Err(LevelError {
+                        problem: Problem::RowLength {
+                            expected: width,
+                            found,
+                        },
+                        at: Some((line, found.min(width) + 1)),
+                    });
+                }
+                let y = (room as i32 + 1) * room_height - 1 - i as i32;
+                for (x, c) in row.chars().enumerate() {
+                    let cell = Cell::from_char(c).ok_or(LevelError {
+                        problem: Problem::UnknownCell(c),
+                        at: Some((line, x + 1)),
+                    })?;
+                    level.set(Vec2(x as i32, y), cell);
+                }
+            }
+        }
+        if headers {
+            let headers: Vec<(usize, &str)> = sections
+                .iter()
+                .map(|&(line, _)| (line, lines[line - 1]))
+                .collect();
+            level.read_headers(&headers)?;
+        }
+        Ok(level)
+    }
+
+    /// Names the rooms and connects them by the exits in their headers, given with the numbers
+    /// of their lines.
+    fn read_headers(&mut self, headers: &[(usize, &str)]) -> Result<(), LevelError> {
+        let headers: Vec<(usize, Vec<&str>)> = headers
+            .iter()
+            .map(|&(line, header)| {
+                let words = header[ROOM_HEADER.len_utf8()..].split_whitespace();
+                (line, words.collect())
+            })
+            .collect();
+        for (room, (line, words)) in headers.iter().enumerate() {
+            let name = words.first().copied().unwrap_or_default();
+            if self.rooms[..room].iter().any(|other| other.name == name) {
+                return Err(LevelError {
+                    problem: Problem::DuplicateRoom,
+                    at: Some((*line, 1)),
+                });
+            }
+            self.rooms[room].name = name.to_string();
+        }
+        for (room, (line, words)) in headers.iter().enumerate() {
+            for word in words.iter().skip(1) {
+                let exit = word.split_once(':').and_then(|(side, name)| {
+                    let side = SIDES.iter().find(|&&(_, s)| s == side)?.0;
+                    let to = self.rooms.iter().position(|other| other.name == name)?;
+                    (to != room).then_some((side, to))
+                });
+                match exit {
+                    Some(exit) => self.rooms[room].exits.push(exit),
+                    None => {
+                        return Err(LevelError {
+                            problem: Problem::BadExit,
+                            at: Some((*line, 1)),
+                        })
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that there is a start with room for the body of the snake, that the portals come
+    /// in pairs, that the openings are on the edge, and that all the open cells can be reached
+    /// from the first start.
+    pub fn validate(&self) -> Result<(), LevelError> {
+        let error = |problem: Problem, pos: Vec2| LevelError {
+            problem,
+            at: Some(self.text_position(pos)),
+        };
+        let starts = self.starts();
+        let first = *starts.first().ok_or(LevelError {
+            problem: Problem::NoStart,
+            at: None,
+        })?;
+        let mut bodies = HashSet::new();
+        for &start in starts.iter() {
+            let direction = self
+                .start_direction(start)
+                .ok_or_else(|| error(Problem::NoRoomForBody, start))?;
+            for pos in body(start, direction) {
+                if !bodies.insert(pos) {
+                    return Err(error(Problem::NoRoomForBody, start));
+                }
+            }
+        }
+        for n in 1..=PORTALS {
+            let ends: Vec<Vec2> = self.positions(Cell::Portal(n)).collect();
+            if !ends.is_empty() && ends.len() != 2 {
+                return Err(error(Problem::UnpairedPortal(n), ends[0]));
+            }
+        }
+        if let Some(pos) = self
+            .positions(Cell::Opening)
+            .find(|&pos| !self.on_room_edge(pos))
+        {
+            return Err(error(Problem::InnerOpening, pos));
+        }
+
+        let exits: Vec<(Vec2, Vec2)> = self
+            .portals()
+            .into_iter()
+            .flat_map(|(a, b)| vec![(a, b), (b, a)])
+            .collect();
+        // The cell where a move from `pos` in the direction ends, if the snake survives it.
+        let step = |pos: Vec2, direction: Direction| {
+            let mut next = self.step(pos, direction)?;
+            if let Some(&(_, exit)) = exits.iter().find(|&&(entry, _)| entry == next) {
+                next = self.step(exit, direction)?;
+            }
+            let open = match self.get(next) {
+                Some(Cell::Gate(gate)) => gate == direction,
+                Some(c) => c.is_open(),
+                None => false,
+            };
+            open.then_some(next)
+        };
+        let mut reached = HashSet::new();
+        let mut queue = VecDeque::new();
+        reached.insert(first);
+        queue.push_back(first);
+        while let Some(pos) = queue.pop_front() {
+            for direction in IntoIterator::into_iter(Direction::ALL) {
+                let next = match step(pos, direction) {
+                    Some(next) => next,
+                    None => continue,
+                };
+                // The snake passes over a conveyor, which carries it one more cell.
+                let stop = match self.get(next) {
+                    Some(Cell::Conveyor(push)) => {
+                        reached.insert(next);
+                        step(next, push)
+                    }
+                    _ => Some(next),
+                };
+                if let Some(stop) = stop.filter(|&stop| reached.insert(stop)) {
+                    queue.push_back(stop);
+                }
+            }
+        }
+        // Reported top to bottom, left to right, like the text.
+        let mut unreachable: Vec<Vec2> = self
+            .cells()
+            .filter(|&(pos, cell)| cell.is_open() && !reached.contains(&pos))
+            .map(|(pos, _)| pos)
+            .collect();
+        unreachable.sort_by_key(|pos| self.text_position(*pos));
+        match unreachable.first() {
+            Some(&pos) => Err(error(Problem::Unreachable, pos)),
+            None => Ok(()),
+        }
+    }
+
+    /// Number of lines in the text before the rows of every room, for its header.
+    fn header_lines(&self) -> i32 {
+        (self.rooms.len() > 1) as i32
+    }
+
+    /// Line and column of the character for the cell in the text, both counting from 1.
+    pub fn text_position(&self, pos: Vec2) -> (usize, usize) {
+        let room = self.room(pos) as i32;
+        let row = (room + 1) * self.room_height - pos.1;
+        let line = room * (self.room_height + self.header_lines()) + self.header_lines() + row;
+        (line as usize, pos.0 as usize + 1)
+    }
+
+    /// The cell for the character at the line and column in the text, both counting from 1. The
+    /// header of a room stands for its top row.
+    pub fn cell_at(&self, (line, column): (usize, usize)) -> Vec2 {
+        let lines = self.room_height + self.header_lines();
+        let (room, row) = ((line as i32 - 1) / lines, (line as i32 - 1) % lines);
+        let row = (row - self.header_lines()).max(0);
+        Vec2(column as i32 - 1, (room + 1) * self.room_height - 1 - row)
+    }
+
+    /// The direction in which a snake starting at `head` fits, with the cells of its body
+    /// behind the head free. Right is tried first, then left, up and down.
+    pub fn start_direction(&self, head: Vec2) -> Option<Direction> {
+        IntoIterator::into_iter([
+            Direction::Right,
+            Direction::Left,
+            Direction::Up,
+            Direction::Down,
+        ])
+        .find(|&direction| {
+            body(head, direction).all(|pos| {
+                self.room(pos) == self.room(head)
+                    && matches!(
+                        self.get(pos),
+                        Some(
+                            Cell::Empty
+                                | Cell::FoodSpawner
+                                | Cell::Mud
+                                | Cell::Boost
+                                | Cell::Checkpoint
+                                | Cell::Opening
+                        )
+                    )
+            })
+        })
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = String::with_capacity(((self.width + 1) * self.height) as usize);
+        for (i, room) in self.rooms.iter().enumerate() {
+            if self.rooms.len() > 1 {
+                text.push(ROOM_HEADER);
+                text.push(' ');
+                text.push_str(&room.name);
+                for &(side, to) in room.exits.iter() {
+                    let side = SIDES.iter().find(|&&(s, _)| s == side).map_or("", |s| s.1);
+                    text.push_str(&format!(" {}:{}", side, self.rooms[to].name));
+                }
+                text.push('\n');
+            }
+            let bottom = i as i32 * self.room_height;
+            for y in (bottom..bottom + self.room_height).rev() {
+                text.extend((0..self.width).map(|x| self.cells[self.index(Vec2(x, y))].to_char()));
+                text.push('\n');
+            }
+        }
+        text
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    pub fn rooms(&self) -> &[Room] {
+        &self.rooms
+    }
+
+    pub fn room_height(&self) -> i32 {
+        self.room_height
+    }
+
+    /// The index of the room that the cell is in.
+    pub fn room(&self, pos: Vec2) -> usize {
+        pos.1.div_euclid(self.room_height).max(0) as usize
+    }
+
+    fn index(&self, pos: Vec2) -> usize {
+        (pos.1 * self.width + pos.0) as usize
+    }
+
+    fn contains(&self, pos: Vec2) -> bool {
+        0 <= pos.0 && pos.0 < self.width && 0 <= pos.1 && pos.1 < self.height
+    }
+
+    fn on_room_edge(&self, pos: Vec2) -> bool {
+        let row = pos.1.rem_euclid(self.room_height);
+        pos.0 == 0 || pos.0 == self.width - 1 || row == 0 || row == self.room_height - 1
+    }
+
+    /// Where a snake moving from `pos` in the direction gets to, or `None` if it runs into the
+    /// edge of its room.
+    fn step(&self, pos: Vec2, direction: Direction) -> Option<Vec2> {
+        if let Some(exit) = self.exit(pos, direction) {
+            return Some(exit);
+        }
+        let next = pos + direction.vec();
+        (self.contains(next) && self.room(next) == self.room(pos)).then_some(next)
+    }
+
+    /// Where a snake leaving its room through the opening at `pos` in the direction comes in: at
+    /// the same place on the opposite side of the room that the exit on that side leads to, or
+    /// of the same room if there is no exit. `None` if there is no opening at `pos` or the move
+    /// stays in the room.
+    pub fn exit(&self, pos: Vec2, direction: Direction) -> Option<Vec2> {
+        if self.get(pos) != Some(Cell::Opening) {
+            return None;
+        }
+        let room = self.room(pos);
+        let Vec2(x, y) = pos + direction.vec();
+        let row = y - room as i32 * self.room_height;
+        if (0..self.width).contains(&x) && (0..self.room_height).contains(&row) {
+            return None;
+        }
+        let to = self.rooms[room].exit(direction).unwrap_or(room) as i32;
+        Some(Vec2(
+            x.rem_euclid(self.width),
+            to * self.room_height + row.rem_euclid(self.room_height),
+        ))
+    }
+
+    /// The openings in the border, see `Cell::Opening`.
+    pub fn openings(&self) -> Vec<Vec2> {
+        self.positions(Cell::Opening).collect()
+    }
+
+    /// The cell at the position, or `None` outside of the level.
+    pub fn get(&self, pos: Vec2) -> Option<Cell> {
+        if self.contains(pos) {
+            Some(self.cells[self.index(pos)])
+        } else {
+            None
+        }
+    }
+
+    /// Changes the cell at the position. Positions outside of the level are ignored.
+    pub fn set(&mut self, pos: Vec2, cell: Cell) {
+        if self.contains(pos) {
+            let i = self.index(pos);
+            self.cells[i] = cell;
+        }
+    }
+
+    /// All the cells with their positions, row by row from the bottom.
+    pub fn cells(&self) -> impl Iterator<Item = (Vec2, Cell)> + '_ {
+        let width = self.width;
+        self.cells
+            .iter()
+            .enumerate()
+            .map(move |(i, &cell)| (Vec2(i as i32 % width, i as i32 / width), cell))
+    }
+
+    fn positions(&self, cell: Cell) -> impl Iterator<Item = Vec2> + '_ {
+        self.cells()
+            .filter(move |&(_, c)| c == cell)
+            .map(|(pos, _)| pos)
+    }
+
+    /// Starting cells of the snakes, in the order of the snakes.
+    pub fn starts(&self) -> Vec<Vec2> {
+        self.positions(Cell::Start).collect()
+    }
+
+    pub fn food_spawners(&self) -> Vec<Vec2> {
+        self.positions(Cell::FoodSpawner).collect()
+    }
+
+    /// Pairs of connected portal cells. Portals without a pair are left out.
+    pub fn portals(&self) -> Vec<(Vec2, Vec2)> {
+        (1..=PORTALS)
+            .filter_map(|n| {
+                let mut ends = self.positions(Cell::Portal(n));
+                match (ends.next(), ends.next(), ends.next()) {
+                    (Some(a), Some(b), None) => Some((a, b)),
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+
+    /// The number for a new portal cell: the portal that is missing its other end, or else the
+    /// first one that isn't used. `None` if all of them are taken.
+    pub fn next_portal(&self) -> Option<u8> {
+        let count = |n: u8| self.positions(Cell::Portal(n)).count();
+        (1..=PORTALS)
+            .find(|&n| count(n) == 1)
+            .or_else(|| (1..=PORTALS).find(|&n| count(n) == 0))
+    }
+}
@@ -0,0 +1,30 @@
+//! Game logic and rendering primitives shared by the game and `snake_server`. Nothing here
+//! depends on a window.
+
+pub mod autopilot;
+pub mod bots;
+pub mod campaign;
+pub mod config;
+pub mod controller;
+pub mod font;
+pub mod framebuffer;
+pub mod game;
+pub mod grid;
+pub mod gym;
+pub mod handicap;
+pub mod hooks;
+pub mod level;
+pub mod net;
+pub mod palette;
+pub mod renderer;
+pub mod replay;
+pub mod rollback;
+pub mod ruleset;
+pub mod share;
+pub mod skin;
+pub mod solver;
+pub mod telemetry;
+pub mod topology;
+pub mod tournament;
+pub mod transport;
+pub mod world;
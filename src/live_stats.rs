@@ -0,0 +1,131 @@
+//! Graphs for the window that `--stats` opens next to the board: the length of the player's
+//! snake over the latest ticks, the frame rate over the latest seconds, and the room that the
+//! autopilot sees around the head of the snake, which drops as it gets trapped.
+
+use snake_pixels::autopilot;
+use snake_pixels::font::{draw_text, text_width, LINE_HEIGHT};
+use snake_pixels::framebuffer::{Color, FrameBuffer};
+use snake_pixels::game::State;
+use snake_pixels::palette::Palette;
+use std::collections::VecDeque;
+use std::time::Duration;
+use web_time::Instant;
+
+/// Size of the frame buffer of the window.
+pub const WIDTH: u32 = 128;
+pub const HEIGHT: u32 = 96;
+
+/// Number of the latest samples that the graphs show, one column of pixels each.
+const SAMPLES: usize = WIDTH as usize - 4;
+
+/// Height of a graph in pixels, with the largest sample reaching the top.
+const GRAPH_HEIGHT: i32 = 22;
+
+/// Height of a graph together with its caption.
+const PANEL_HEIGHT: i32 = HEIGHT as i32 / 3;
+
+/// Adds a sample to a graph, forgetting the oldest one if there are too many.
+fn push(samples: &mut VecDeque<u32>, value: u32) {
+    if samples.len() == SAMPLES {
+        samples.pop_front();
+    }
+    samples.push_back(value);
+}
+
+pub struct LiveStats {
+    /// Tick of the game when the latest sample was taken, to tell the next tick and the next
+    /// game from the same one.
+    tick: Option<u64>,
+    lengths: VecDeque<u32>,
+    room: VecDeque<u32>,
+    /// Frames drawn in each of the latest seconds.
+    fps: VecDeque<u32>,
+    /// Frames drawn since `second` started.
+    frames: u32,
+    second: Instant,
+}
+
+impl LiveStats {
+    pub fn new() -> Self {
+        LiveStats {
+            tick: None,
+            lengths: VecDeque::new(),
+            room: VecDeque::new(),
+            fps: VecDeque::new(),
+            frames: 0,
+            second: Instant::now(),
+        }
+    }
+
+    /// Samples the game once per tick. An earlier tick means that a new game has started, and
+    /// the graphs of the old one are cleared.
+    pub fn add_tick(&mut self, state: &State) {
+        let world = state.world();
+        let tick = world.tick();
+        match self.tick {
+            Some(last) if last == tick => return,
+            Some(last) if last > tick => {
+                self.lengths.clear();
+                self.room.clear();
+            }
+            _ => (),
+        }
+        self.tick = Some(tick);
+        push(&mut self.lengths, state.length() as u32);
+        push(&mut self.room, autopilot::room(world, 0) as u32);
+    }
+
+    /// Counts a frame of the board, adding a sample of the frame rate once a second.
+    pub fn add_frame(&mut self) {
+        self.frames += 1;
+        let elapsed = self.second.elapsed();
+        if elapsed < Duration::from_secs(1) {
+            return;
+        }
+        // Over a long pause with no frames the rate is averaged over the whole time.
+        let fps = self.frames as f64 / elapsed.as_secs_f64();
+        push(&mut self.fps, fps.round() as u32);
+        self.frames = 0;
+        self.second = Instant::now();
+    }
+
+    /// Draws the three graphs one under the other, each with its latest sample in the caption.
+    pub fn render(&self, buffer: &mut FrameBuffer, palette: &Palette) {
+        buffer.clear(palette.menu_bg);
+        let graphs = [
+            ("LENGTH", &self.lengths, palette.head),
+            ("FPS", &self.fps, palette.food),
+            ("ROOM", &self.room, palette.portal),
+        ];
+        for (i, &(name, samples, color)) in graphs.iter().enumerate() {
+            let top = buffer.height() as i32 - 1 - i as i32 * PANEL_HEIGHT;
+            render_graph(buffer, top, name, samples, color, palette);
+        }
+    }
+}
+
+/// Draws a graph of `samples` with its caption below `top`, newest sample on the right.
+fn render_graph(
+    buffer: &mut FrameBuffer,
+    top: i32,
+    name: &str,
+    samples: &VecDeque<u32>,
+    color: Color,
+    palette: &Palette,
+) {
+    let left = 2;
+    draw_text(buffer, left, top, name, 1, palette.text);
+    if let Some(latest) = samples.back() {
+        let value = latest.to_string();
+        let x = buffer.width() as i32 - left - text_width(&value);
+        draw_text(buffer, x, top, &value, 1, color);
+    }
+    let bottom = top - LINE_HEIGHT - GRAPH_HEIGHT;
+    buffer.fill_rectangle(left, bottom - 1, SAMPLES, 1, palette.dim_text);
+    let max = samples.iter().copied().max().unwrap_or(0).max(1);
+    for (i, &value) in samples.iter().rev().enumerate() {
+        let x = left + (SAMPLES - 1 - i) as i32;
+        let height = (value as u64 * GRAPH_HEIGHT as u64).div_ceil(max as u64);
+        buffer.fill_rectangle(x, bottom, 1, height as usize, color);
+    }
+}
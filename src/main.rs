@@ -1,72 +1,107 @@
+mod achievements;
+mod app;
+mod audio;
+mod autosave;
+mod bindings;
+#[cfg(feature = "chat")]
+mod chat;
+mod console;
+mod custom_game;
+mod customize;
+mod editor;
+mod effects;
+mod exhibition;
+mod geometry;
+mod gpu;
+mod heatmap;
+mod i18n;
+mod input;
+mod leaderboard;
+mod live_stats;
+mod menu;
+mod modal;
+#[cfg(any(feature = "lua", feature = "plugins"))]
+mod mods;
+mod name_entry;
+mod online;
+mod packs;
+#[cfg(feature = "plugins")]
+mod plugins;
+mod presets;
+mod profile;
+mod profiler;
+#[cfg(not(target_arch = "wasm32"))]
+mod remote;
+mod screensaver;
+#[cfg(feature = "lua")]
+mod scripting;
+mod speedrun;
+mod stats;
+mod storage;
+#[cfg(feature = "audio")]
+mod synth;
+#[cfg(not(target_arch = "wasm32"))]
+mod terminal;
+mod toasts;
+mod touch;
+mod tuning;
+mod tutorial;
+mod viewport;
+
+use app::{App, TITLE};
+use geometry::WindowGeometry;
+use gpu::QuadPipeline;
 use log::{debug, error, info};
 use pixels::{Pixels, PixelsBuilder, SurfaceTexture};
-use rand::Rng;
-use std::{
-    cell::Cell,
-    collections::{HashSet, VecDeque},
-    time::{Duration, Instant},
-};
+use profile::Profile;
+use snake_pixels::config::Config;
+use snake_pixels::framebuffer::FrameBuffer;
+use snake_pixels::grid::Vec2;
+use snake_pixels::renderer::{Quad, Renderer};
+use snake_pixels::tournament::Format;
+use std::{collections::VecDeque, error::Error, path::PathBuf, time::Duration};
+use web_time::Instant;
 use winit::{
-    dpi::PhysicalSize,
-    event::{ElementState, Event, KeyboardInput, StartCause, VirtualKeyCode, WindowEvent},
-    event_loop::{ControlFlow, EventLoop},
+    dpi::{LogicalSize, PhysicalPosition, PhysicalSize},
+    event::{ElementState, Event, KeyEvent, MouseScrollDelta, StartCause, Touch, WindowEvent},
+    event_loop::{ControlFlow, EventLoop, EventLoopWindowTarget},
+    keyboard::{KeyCode, PhysicalKey},
     window::{Window, WindowBuilder},
 };
 
-#[derive(Clone, Copy)]
-struct Color(u32);
-
-impl Color {
-    const fn rgb(r: u8, g: u8, b: u8) -> Self {
-        Color(r as u32 | ((g as u32) << 8) | ((b as u32) << 16) | 0xFF000000)
-    }
-
-    const fn as_rgba_u32(self) -> u32 {
-        self.0
-    }
-}
-
-fn clamp<T: PartialOrd>(input: T, min: T, max: T) -> T {
-    if input < min {
-        min
-    } else if input > max {
-        max
-    } else {
-        input
-    }
-}
-
-fn pixels_slice_u32_mut(pixels: &mut Pixels) -> &mut [u32] {
-    unsafe {
-        let (_, pixel_array, _) = pixels.get_frame().align_to_mut::<u32>();
-        pixel_array
-    }
-}
-
 struct Canvas {
-    width: usize,
-    height: usize,
+    buffer: FrameBuffer,
     pixels: Pixels,
+    /// Draws the cells of the board with `--gpu`.
+    quads: Option<QuadPipeline>,
     frame_times: VecDeque<Instant>,
+    fps_update: Instant,
 }
 
 impl Canvas {
-    fn new(window: &Window, width: u32, height: u32) -> Result<Self, pixels::Error> {
+    async fn new(
+        window: &Window,
+        width: u32,
+        height: u32,
+        vsync: bool,
+        gpu: bool,
+    ) -> Result<Self, pixels::Error> {
         let window_size = window.inner_size();
         let pixels = PixelsBuilder::new(
             width,
             height,
             SurfaceTexture::new(window_size.width, window_size.height, window),
         )
-        .enable_vsync(true)
-        .present_mode(wgpu::PresentMode::Immediate)
-        .build()?;
+        .enable_vsync(vsync)
+        .build_async()
+        .await?;
 
         Ok(Canvas {
-            width: width as usize,
-            height: height as usize,
+            buffer: FrameBuffer::new(width as usize, height as usize),
+            quads: gpu.then(|| QuadPipeline::new(&pixels)),
             pixels,
             frame_times: VecDeque::new(),
+            fps_update: Instant::now(),
         })
     }
 
@@ -83,304 +118,844 @@ impl Canvas {
         self.frame_times.len() as f32
     }
 
-    fn draw(&mut self) -> Result<(), ()> {
-        self.update_fps();
-        self.pixels.render().map_err(|e| {
-            error!("Pixels error: {}", e);
-        })
+    /// The pixel of the buffer at a position in the window, clamped to the buffer.
+    fn buffer_pos(&self, x: f64, y: f64) -> Vec2 {
+        let (x, y) = self
+            .pixels
+            .window_pos_to_pixel((x as f32, y as f32))
+            .unwrap_or_else(|(x, y)| (x.max(0) as usize, y.max(0) as usize));
+        // The buffer has y pointing up.
+        Vec2(x as i32, self.buffer.height() as i32 - 1 - y as i32)
     }
 
-    fn clear(&mut self, color: Color) {
-        pixels_slice_u32_mut(&mut self.pixels).fill(color.as_rgba_u32())
+    fn resize_surface(&mut self, width: u32, height: u32) {
+        if let Err(e) = self.pixels.resize_surface(width, height) {
+            error!("Failed to resize surface: {}", e);
+        }
     }
 
-    fn set_pixel(&mut self, x: i32, y: i32, color: Color) {
-        if x < 0 || y < 0 {
+    /// Changes the size of the frame buffer, keeping the window and the surface.
+    fn resize_buffer(&mut self, width: u32, height: u32) {
+        if let Err(e) = self.pixels.resize_buffer(width, height) {
+            error!("Failed to resize the buffer: {}", e);
             return;
         }
-        let (x, y) = (x as usize, y as usize);
-        if x >= self.width || y >= self.height {
-            return;
-        }
-
-        pixels_slice_u32_mut(&mut self.pixels)[self.width * (self.height - y - 1) + x] =
-            color.as_rgba_u32()
+        info!("Frame buffer resized to ({}, {})", width, height);
+        self.buffer = FrameBuffer::new(width as usize, height as usize);
     }
 
-    fn fill_rectangle(&mut self, x0: i32, y0: i32, w: usize, h: usize, color: Color) {
-        let x0 = clamp(x0, 0, self.width as i32) as usize;
-        let y0 = clamp(y0, 0, self.height as i32) as usize;
-        let h = if h > y0 { y0 } else { h };
+    fn buffer_size(&self) -> (u32, u32) {
+        (self.buffer.width() as u32, self.buffer.height() as u32)
+    }
+}
 
-        let mut offset = self.width * (self.height - y0 - 1) + x0;
+impl Renderer for Canvas {
+    fn buffer_mut(&mut self) -> &mut FrameBuffer {
+        &mut self.buffer
+    }
 
-        let slice = pixels_slice_u32_mut(&mut self.pixels);
-        for _ in 0..h {
-            slice[offset..offset + w].fill(color.as_rgba_u32());
-            offset += self.width;
-        }
+    fn layers_mut(&mut self) -> (&mut FrameBuffer, Option<&mut Vec<Quad>>) {
+        let quads = self.quads.as_mut().map(|pipeline| &mut pipeline.quads);
+        (&mut self.buffer, quads)
     }
 
-    fn resize_surface(&mut self, width: u32, height: u32) {
-        self.pixels.resize_surface(width, height);
+    fn present(&mut self) -> Result<(), Box<dyn Error>> {
+        self.update_fps();
+        if Instant::now() > self.fps_update {
+            info!("FPS: {}", self.fps());
+            self.fps_update = Instant::now() + Duration::from_secs(1);
+        }
+        self.buffer.copy_to(self.pixels.frame_mut());
+        match &mut self.quads {
+            Some(pipeline) => {
+                let size = (self.buffer.width(), self.buffer.height());
+                self.pixels.render_with(|encoder, target, context| {
+                    context.scaling_renderer.render(encoder, target);
+                    pipeline.render(encoder, target, context, size);
+                    Ok(())
+                })?
+            }
+            None => self.pixels.render()?,
+        }
+        Ok(())
     }
 }
 
-#[derive(Clone, Copy, Debug, Default, Hash)]
-struct Vec2(i32, i32);
+/// Size of a pixel of the graphs of `--stats` in logical pixels of their window.
+const STATS_SCALE: u32 = 3;
 
-impl std::ops::AddAssign for Vec2 {
-    fn add_assign(&mut self, other: Self) {
-        self.0 += other.0;
-        self.1 += other.1;
-    }
+/// The second window of `--stats`, which shows the graphs of `App::render_live_stats`.
+struct StatsWindow {
+    window: Window,
+    buffer: FrameBuffer,
+    pixels: Pixels,
 }
 
-impl std::ops::Add for Vec2 {
-    type Output = Vec2;
-
-    fn add(self, other: Self) -> Self {
-        Vec2(self.0 + other.0, self.1 + other.1)
+impl StatsWindow {
+    /// Opens the window to the right of `board`, the window with the board.
+    async fn new<T>(
+        event_loop: &EventLoopWindowTarget<T>,
+        board: &Window,
+    ) -> Result<Self, Box<dyn Error>> {
+        let (width, height) = (live_stats::WIDTH, live_stats::HEIGHT);
+        let mut builder = WindowBuilder::new()
+            .with_title(format!("{} - {}", TITLE, i18n::tr("STATS")))
+            .with_inner_size(LogicalSize::new(width * STATS_SCALE, height * STATS_SCALE))
+            .with_min_inner_size(PhysicalSize::new(width, height));
+        if let Ok(position) = board.outer_position() {
+            let x = position.x + board.outer_size().width as i32;
+            builder = builder.with_position(PhysicalPosition::new(x, position.y));
+        }
+        let window = builder.build(event_loop)?;
+        let window_size = window.inner_size();
+        let pixels = PixelsBuilder::new(
+            width,
+            height,
+            SurfaceTexture::new(window_size.width, window_size.height, &window),
+        )
+        .build_async()
+        .await?;
+        Ok(StatsWindow {
+            buffer: FrameBuffer::new(width as usize, height as usize),
+            pixels,
+            window,
+        })
     }
 }
 
-impl std::cmp::PartialEq for Vec2 {
-    fn eq(&self, other: &Self) -> bool {
-        self.0 == other.0 && self.1 == other.1
+impl Renderer for StatsWindow {
+    fn buffer_mut(&mut self) -> &mut FrameBuffer {
+        &mut self.buffer
     }
-}
 
-impl std::cmp::Eq for Vec2 {}
-
-const BG_COLOR: Color = Color::rgb(0x48, 0xB2, 0xE8);
-const HEAD_COLOR: Color = Color::rgb(0x4E, 0x38, 0xE8);
-const TAIL_COLOR: Color = Color::rgb(0x5E, 0x48, 0xE8);
-const FOOD_COLOR: Color = Color::rgb(0x9E, 0x28, 0xE8);
-
-struct State {
-    tick: Duration,
-    food_tick: Duration,
-    next_update: Instant,
-    next_food: Instant,
-    fps_update: Cell<Instant>,
-
-    width: i32,
-    height: i32,
-    v: Vec2,
-    head: Vec2,
-    tail: Vec<Vec2>,
-    food: HashSet<Vec2>,
+    fn present(&mut self) -> Result<(), Box<dyn Error>> {
+        self.buffer.copy_to(self.pixels.frame_mut());
+        self.pixels.render()?;
+        Ok(())
+    }
 }
 
-impl State {
-    fn new() -> Self {
-        let tick = Duration::from_millis(400);
-        let food_tick = Duration::from_millis(1500);
-        State {
-            tick,
-            next_update: Instant::now() + tick,
-            food_tick,
-            next_food: Instant::now() + food_tick,
-            fps_update: Cell::new(Instant::now()),
-            width: 15,
-            height: 15,
-            v: Vec2(1, 0),
-            head: Vec2(8, 7),
-            tail: vec![Vec2(7, 7), Vec2(6, 7)],
-            food: HashSet::new(),
-        }
-    }
-
-    fn update(&mut self) -> bool {
-        if Instant::now() > self.next_update {
-            if self.step() {
-                return true;
+/// Handles the events of the window of `--stats`. Closing it only closes the graphs, and the game
+/// goes on.
+fn handle_stats_event(event: &WindowEvent, app: &App, stats: &mut Option<StatsWindow>) {
+    let window = match stats {
+        Some(window) => window,
+        None => return,
+    };
+    match event {
+        WindowEvent::RedrawRequested => {
+            if let Err(e) = app.render_live_stats(window) {
+                error!("Failed to draw the stats: {}", e);
+                *stats = None;
             }
-            self.next_update = Instant::now() + self.tick;
         }
-
-        if self.food.is_empty() || Instant::now() > self.next_food {
-            if self.add_food() {
-                return true;
+        WindowEvent::Resized(PhysicalSize { width, height }) if *width > 0 && *height > 0 => {
+            if let Err(e) = window.pixels.resize_surface(*width, *height) {
+                error!("Failed to resize the surface of the stats: {}", e);
             }
-            self.next_food = Instant::now() + self.food_tick;
         }
+        WindowEvent::CloseRequested => {
+            info!("Closing the stats");
+            *stats = None;
+        }
+        _ => (),
+    }
+}
 
-        false
+/// Hands a key press to the application with `press`, which returns whether to exit.
+fn handle_keypress<T>(
+    app: &mut App,
+    window: &Window,
+    elwt: &EventLoopWindowTarget<T>,
+    press: impl FnOnce(&mut App) -> bool,
+) {
+    let ui_scale = app.ui_scale();
+    if press(app) {
+        app.quit();
+        elwt.exit();
+        return;
     }
+    if app.ui_scale() != ui_scale {
+        let _ = window.request_inner_size(window_size(app, window.scale_factor()));
+    }
+    window.request_redraw();
+}
 
-    fn step(&mut self) -> bool {
-        let new_head = self.head + self.v;
+struct Options {
+    /// Render continuously instead of only when the game state changes, for benchmarking.
+    uncapped: bool,
+    /// Draw the cells of the board on the GPU instead of into the frame buffer.
+    gpu: bool,
+    seed: Option<u64>,
+    profile: Option<String>,
+    /// Address of a `snake_server` to join instead of showing the menu.
+    connect: Option<String>,
+    /// Join the server as a spectator.
+    spectate: bool,
+    /// Play in the terminal instead of a window.
+    tui: bool,
+    /// Run without a window, e.g. to keep a spectator connected to a server.
+    headless: bool,
+    /// Instead of playing, let the bots play `games` games against each other and print the
+    /// results.
+    tournament: bool,
+    games: u64,
+    format: Format,
+    /// File with the game parameters and colors to use instead of the defaults.
+    config: Option<PathBuf>,
+    /// Enable the developer console.
+    debug: bool,
+    /// Instead of playing, write the history of games of the profile to this file, as JSON if
+    /// it ends with `.json` and as CSV otherwise.
+    export_stats: Option<PathBuf>,
+    /// Take commands from the standard input and answer them on the standard output, see
+    /// `remote`.
+    remote: bool,
+    /// Open a second window with graphs of the game, see `live_stats`.
+    stats: bool,
+    /// Write the histograms of the frame times and the tick latency to this file on exit, see
+    /// `profiler`.
+    perf_report: Option<PathBuf>,
+    /// Number of threads that draw the large boards, see `FrameBuffer::draw_in_bands`.
+    render_threads: usize,
+    /// Instead of playing, play the game of this replay again and print whether it ends with the
+    /// result that the replay claims, see `replay`.
+    verify: Option<PathBuf>,
+    /// Name of a saved ruleset to open the custom game screen with, see `presets`.
+    ruleset: Option<String>,
+}
 
-        if new_head.0 < 0 || new_head.0 >= self.width ||
-           new_head.1 < 0 || new_head.1 >= self.height ||
-           self.tail[0..self.tail.len() - 1].contains(&new_head) {
-            return true;
+impl Options {
+    fn from_args() -> Self {
+        let mut options = Options {
+            uncapped: false,
+            gpu: false,
+            seed: None,
+            profile: None,
+            connect: None,
+            spectate: false,
+            tui: false,
+            headless: false,
+            tournament: false,
+            games: 100,
+            format: Format::Text,
+            config: None,
+            debug: false,
+            export_stats: None,
+            remote: false,
+            stats: false,
+            perf_report: None,
+            render_threads: 1,
+            verify: None,
+            ruleset: None,
+        };
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--uncapped" => options.uncapped = true,
+                "--gpu" => options.gpu = true,
+                "--tui" => options.tui = true,
+                "--headless" => options.headless = true,
+                "--tournament" => options.tournament = true,
+                "--debug" => options.debug = true,
+                "--remote" => options.remote = true,
+                "--stats" => options.stats = true,
+                "--games" => match args.next().map(|s| s.parse()) {
+                    Some(Ok(games)) => options.games = games,
+                    _ => error!("--games requires a numeric argument"),
+                },
+                "--render-threads" => match args.next().map(|s| s.parse()) {
+                    Some(Ok(threads)) if threads > 0 => options.render_threads = threads,
+                    _ => error!("--render-threads requires a positive number"),
+                },
+                "--format" => match args.next().as_deref().and_then(Format::parse) {
+                    Some(format) => options.format = format,
+                    None => error!("--format requires one of text, csv or json"),
+                },
+                "--seed" => match args.next().map(|s| s.parse()) {
+                    Some(Ok(seed)) => options.seed = Some(seed),
+                    _ => error!("--seed requires a numeric argument"),
+                },
+                "--profile" => match args.next() {
+                    Some(name) if Profile::is_valid_name(&name) => options.profile = Some(name),
+                    _ => error!(
+                        "--profile requires a name of up to {} uppercase letters and digits",
+                        profile::MAX_NAME_LEN
+                    ),
+                },
+                "--config" => match args.next() {
+                    Some(path) => options.config = Some(PathBuf::from(path)),
+                    None => error!("--config requires a file"),
+                },
+                "--export-stats" => match args.next() {
+                    Some(path) => options.export_stats = Some(PathBuf::from(path)),
+                    None => error!("--export-stats requires a file"),
+                },
+                "--ruleset" => match args.next() {
+                    Some(name) => options.ruleset = Some(name),
+                    None => error!("--ruleset requires a name"),
+                },
+                "--perf-report" => match args.next() {
+                    Some(path) => options.perf_report = Some(PathBuf::from(path)),
+                    None => error!("--perf-report requires a file"),
+                },
+                "--verify" => match args.next() {
+                    Some(path) => options.verify = Some(PathBuf::from(path)),
+                    None => error!("--verify requires a replay file"),
+                },
+                "--connect" | "--spectate" => match args.next() {
+                    Some(address) => {
+                        options.connect = Some(address);
+                        options.spectate = arg == "--spectate";
+                    }
+                    None => error!("{} requires a server address", arg),
+                },
+                _ => error!("Unknown argument: {}", arg),
+            }
         }
+        #[cfg(target_arch = "wasm32")]
+        options.read_page_address();
+        options
+    }
 
-        if self.food.contains(&new_head) {
-            self.tail.push(Vec2(0, 0));
-            self.food.remove(&new_head);
+    /// The browser has no command line, so the server to join comes from the address of the
+    /// page, as in `?connect=ws://HOST:PORT` or `?spectate=ws://HOST:PORT`.
+    #[cfg(target_arch = "wasm32")]
+    fn read_page_address(&mut self) {
+        let query = web_sys::window()
+            .and_then(|page| page.location().search().ok())
+            .and_then(|search| web_sys::UrlSearchParams::new_with_str(&search).ok());
+        let query = match query {
+            Some(query) => query,
+            None => return,
+        };
+        if let Some(address) = query.get("connect") {
+            self.connect = Some(address);
+        } else if let Some(address) = query.get("spectate") {
+            self.connect = Some(address);
+            self.spectate = true;
         }
+    }
 
-        for i in (0..(self.tail.len() - 1)).rev() {
-            self.tail[i + 1] = self.tail[i];
+    fn control_flow(&self, app: &App) -> ControlFlow {
+        if self.uncapped && !app.is_hidden() {
+            ControlFlow::Poll
+        } else if let Some(wakeup) = app.next_wakeup() {
+            ControlFlow::WaitUntil(wakeup)
+        } else {
+            ControlFlow::Wait
         }
-        self.tail[0] = self.head;
-        self.head += self.v;
-        false
     }
+}
 
-    fn add_food(&mut self) -> bool {
-        let total_nodes = self.width * self.height;
-        if self.tail.len() + self.food.len() + 2 >= total_nodes as usize {
-            return true;
-        }
+fn set_control_flow<T>(elwt: &EventLoopWindowTarget<T>, control_flow: ControlFlow) {
+    if elwt.control_flow() != control_flow {
+        debug!("Setting ControlFlow {:?}", control_flow);
+    }
+    elwt.set_control_flow(control_flow);
+}
 
-        loop {
-            let idx = rand::thread_rng().gen_range(0..total_nodes);
-            let pos = Vec2(idx % self.width, idx / self.width);
-            if !self.food.contains(&pos) && !self.tail.contains(&pos) && pos != self.head {
-                self.food.insert(pos);
-                return false;
-            }
-        }
+/// Size of the window that shows every pixel of the frame buffer as a `ui_scale`×`ui_scale` square
+/// of logical pixels. It's rounded to whole physical pixels, so that the image stays sharp.
+fn window_size(app: &App, scale_factor: f64) -> PhysicalSize<u32> {
+    let (width, height) = app.canvas_size();
+    let scale = ((app.ui_scale() as f64 * scale_factor).round() as u32).max(1);
+    PhysicalSize::new(width * scale, height * scale)
+}
+
+/// Rebuilds the frame buffer when the application needs another size of it, e.g. for a larger
+/// board, and fits the window to it.
+fn fit_canvas(app: &App, canvas: &mut Canvas, window: &Window) {
+    let (width, height) = app.canvas_size();
+    if canvas.buffer_size() == (width, height) {
+        return;
     }
+    canvas.resize_buffer(width, height);
+    window.set_min_inner_size(Some(PhysicalSize::new(width, height)));
+    let _ = window.request_inner_size(window_size(app, window.scale_factor()));
+}
 
-    fn render(&self, canvas: &mut Canvas) {
-        canvas.clear(BG_COLOR);
-        canvas.set_pixel(self.head.0, self.head.1, HEAD_COLOR);
-        for pos in self.tail.iter() {
-            canvas.set_pixel(pos.0, pos.1, TAIL_COLOR);
+/// Scrolling on a touchpad by this many pixels counts as a notch of the mouse wheel.
+const WHEEL_NOTCH_PIXELS: f32 = 40.0;
+
+fn handle_window_event<T>(
+    event: &mut WindowEvent,
+    elwt: &EventLoopWindowTarget<T>,
+    app: &mut App,
+    canvas: &mut Option<Canvas>,
+    window: &Window,
+    geometry: &mut WindowGeometry,
+) {
+    match event {
+        // Drawing into a window that nobody sees would only waste the battery, and the surface
+        // of a minimized window can't be drawn into at all.
+        WindowEvent::RedrawRequested if app.is_hidden() => (),
+        WindowEvent::RedrawRequested => {
+            if let Some(canvas) = canvas {
+                fit_canvas(app, canvas, window);
+                if let Err(e) = app.render(canvas) {
+                    error!("Failed to draw: {}", e);
+                    elwt.exit();
+                }
+                if let Some(title) = app.window_title() {
+                    window.set_title(&title);
+                }
+            }
         }
-        for pos in self.food.iter() {
-            canvas.set_pixel(pos.0, pos.1, FOOD_COLOR);
+        WindowEvent::Resized(PhysicalSize { width, height }) => {
+            info!("Window resized to ({}, {})", width, height);
+            // Some platforms make minimized windows zero-sized instead of reporting them.
+            let minimized = *width == 0 || *height == 0;
+            app.set_minimized(minimized);
+            if minimized {
+                return;
+            }
+            if let Some(canvas) = canvas {
+                canvas.resize_surface(*width, *height);
+            }
+            geometry.on_resized(window, PhysicalSize::new(*width, *height));
+            window.request_redraw();
         }
-        if Instant::now() > self.fps_update.get() {
-            info!("FPS: {}", canvas.fps());
-            self.fps_update.set(Instant::now() + Duration::from_secs(1))
+        WindowEvent::Occluded(occluded) => {
+            info!("Window occluded: {}", occluded);
+            app.set_occluded(*occluded);
+            if !*occluded {
+                window.request_redraw();
+            }
         }
-    }
-
-    fn on_keypress(&mut self, keycode: VirtualKeyCode) {
-        match keycode {
-            VirtualKeyCode::Right => {
-                self.v = Vec2(1, 0);
+        WindowEvent::Moved(position) => geometry.on_moved(window, *position),
+        WindowEvent::Focused(false) => app.on_focus_lost(),
+        WindowEvent::ScaleFactorChanged {
+            scale_factor,
+            inner_size_writer,
+        } => {
+            info!("Scale factor changed to {}", scale_factor);
+            let size = window_size(app, *scale_factor);
+            if let Err(e) = inner_size_writer.request_inner_size(size) {
+                error!("Failed to resize the window: {}", e);
+            }
+        }
+        WindowEvent::CloseRequested => {
+            app.quit();
+            elwt.exit()
+        }
+        WindowEvent::KeyboardInput {
+            event:
+                KeyEvent {
+                    physical_key: PhysicalKey::Code(keycode),
+                    logical_key,
+                    state: ElementState::Pressed,
+                    repeat,
+                    ..
+                },
+            ..
+        } => {
+            // The back button on Android.
+            let keycode = match keycode {
+                KeyCode::BrowserBack => KeyCode::Escape,
+                keycode => bindings::numpad_key(*keycode, logical_key),
+            };
+            let repeat = *repeat;
+            handle_keypress(app, window, elwt, |app| app.on_key_down(keycode, repeat))
+        }
+        WindowEvent::KeyboardInput {
+            event:
+                KeyEvent {
+                    physical_key: PhysicalKey::Code(keycode),
+                    logical_key,
+                    state: ElementState::Released,
+                    ..
+                },
+            ..
+        } => {
+            if app.on_keyrelease(bindings::numpad_key(*keycode, logical_key)) {
+                window.request_redraw();
             }
-            VirtualKeyCode::Up => {
-                self.v = Vec2(0, 1);
+        }
+        WindowEvent::KeyboardInput { .. } => (),
+        WindowEvent::CursorMoved { position, .. } => {
+            if let Some(canvas) = canvas {
+                if app.on_cursor_moved(canvas.buffer_pos(position.x, position.y)) {
+                    window.request_redraw();
+                }
             }
-            VirtualKeyCode::Left => {
-                self.v = Vec2(-1, 0);
+        }
+        WindowEvent::MouseInput { state, button, .. } => {
+            if app.on_mouse_button(*button, *state == ElementState::Pressed) {
+                window.request_redraw();
             }
-            VirtualKeyCode::Down => {
-                self.v = Vec2(0, -1);
+        }
+        WindowEvent::MouseWheel { delta, .. } => {
+            let notches = match delta {
+                MouseScrollDelta::LineDelta(_, lines) => *lines,
+                MouseScrollDelta::PixelDelta(pixels) => pixels.y as f32 / WHEEL_NOTCH_PIXELS,
+            };
+            if app.on_mouse_wheel(notches) {
+                window.request_redraw();
             }
-            _ => (),
         }
-    }
-}
-
-fn handle_keypress(keycode: VirtualKeyCode, state: &mut State) -> Option<ControlFlow> {
-    match keycode {
-        VirtualKeyCode::Escape => Some(ControlFlow::Exit),
-        x => {
-            state.on_keypress(x);
-            None
+        WindowEvent::Touch(Touch {
+            id,
+            phase,
+            location,
+            ..
+        }) => {
+            if let Some(canvas) = canvas {
+                let pos = canvas.buffer_pos(location.x, location.y);
+                match app.on_touch(*id, *phase, pos) {
+                    Some(keycode) => {
+                        handle_keypress(app, window, elwt, |app| app.on_keypress(keycode))
+                    }
+                    None => window.request_redraw(),
+                }
+            }
         }
+        WindowEvent::Ime(ime) => debug!("IME: {:?}", ime),
+        _ => debug!("WindowEvent:  {:?}", event),
     }
 }
 
 fn handle_event<T: std::fmt::Debug + 'static>(
     event: Event<T>,
-    state: &mut State,
-    canvas: &mut Canvas,
-) -> Option<ControlFlow> {
-    match &event {
+    elwt: &EventLoopWindowTarget<T>,
+    app: &mut App,
+    canvas: &mut Option<Canvas>,
+    window: &Window,
+    geometry: &mut WindowGeometry,
+    options: &Options,
+) {
+    match event {
         Event::NewEvents(StartCause::Init) => {
             info!("Initializing events");
-            Some(ControlFlow::Poll)
-        }
-        Event::NewEvents(StartCause::Poll) | Event::NewEvents(StartCause::WaitCancelled { .. }) => {
-            if state.update() {
-                return Some(ControlFlow::Exit)
-            }
-            state.render(canvas);
-            if canvas.draw().is_err() {
-                Some(ControlFlow::Exit)
-            } else {
-                None
-            }
-        }
-        Event::NewEvents(_) => {
-            debug!("Event: {:?}", event);
-            None
+            set_control_flow(elwt, options.control_flow(app));
         }
+        // The game advances in `AboutToWait`, once the input that woke the loop is handled.
+        Event::NewEvents(_) => (),
         Event::WindowEvent {
-            event: window_event,
+            event: mut window_event,
             ..
-        } => {
-            debug!("WindowEvent:  {:?}", window_event);
-            match window_event {
-                WindowEvent::Resized(PhysicalSize { width, height }) => {
-                    info!("Window resized to ({}, {})", width, height);
-                    canvas.resize_surface(*width, *height);
-                    None
+        } => handle_window_event(&mut window_event, elwt, app, canvas, window, geometry),
+        Event::DeviceEvent { .. } => (),
+        Event::LoopExiting => geometry.on_exit(window),
+        // Android takes the surface away while the application is in the background.
+        Event::Suspended => {
+            info!("Suspended");
+            app.suspend();
+            // The surface is about to be destroyed, and drawing into it would fail.
+            *canvas = None;
+        }
+        Event::Resumed => {
+            info!("Resumed");
+            #[cfg(not(target_arch = "wasm32"))]
+            if canvas.is_none() {
+                info!("Recreating the canvas");
+                let (width, height) = app.canvas_size();
+                match pollster::block_on(Canvas::new(
+                    window,
+                    width,
+                    height,
+                    !options.uncapped,
+                    options.gpu,
+                )) {
+                    Ok(new_canvas) => *canvas = Some(new_canvas),
+                    Err(e) => {
+                        error!("Failed to create the canvas: {}", e);
+                        elwt.exit();
+                        return;
+                    }
                 }
-                WindowEvent::CloseRequested => Some(ControlFlow::Exit),
-                WindowEvent::KeyboardInput {
-                    input:
-                        KeyboardInput {
-                            virtual_keycode: Some(keycode),
-                            state: ElementState::Pressed,
-                            ..
-                        },
-                    ..
-                } => handle_keypress(*keycode, state),
-                WindowEvent::KeyboardInput { .. } => None,
-                _ => None,
             }
+            app.resume();
+            window.request_redraw();
         }
-        Event::RedrawRequested(_) => {
-            debug!("RedrawRequested");
-            state.render(canvas);
-            if canvas.draw().is_err() {
-                Some(ControlFlow::Exit)
-            } else {
-                None
+        // All the events of this iteration of the loop have been handled, so the game advances
+        // with all the input that came in them. Frames are only drawn in `RedrawRequested`,
+        // which the platform times itself, as browsers and macOS do with their display refresh.
+        Event::AboutToWait => {
+            let changed = app.update();
+            if changed || (options.uncapped && !app.is_hidden()) {
+                window.request_redraw();
             }
+            set_control_flow(elwt, options.control_flow(app));
         }
-        Event::DeviceEvent { .. } => None,
-        Event::MainEventsCleared => None,
-        Event::RedrawEventsCleared => None,
-        _ => {
-            debug!("Event:  {:?}", event);
-            None
-        }
+        _ => debug!("Event:  {:?}", event),
     }
 }
 
+/// Puts the window's canvas on the page.
+#[cfg(target_arch = "wasm32")]
+fn attach_canvas(window: &Window) {
+    use winit::platform::web::WindowExtWebSys;
+
+    let canvas = window.canvas().expect("the window has no canvas");
+    web_sys::window()
+        .and_then(|page| page.document())
+        .and_then(|document| document.body())
+        .and_then(|body| body.append_child(&canvas).ok())
+        .expect("failed to add the canvas to the page");
+}
+
 fn main() {
-    env_logger::Builder::from_env(
-        env_logger::Env::default().default_filter_or("snake_pixels=debug"),
-    )
-    .format_timestamp(Some(env_logger::fmt::TimestampPrecision::Micros))
-    .init();
+    #[cfg(target_arch = "wasm32")]
+    {
+        std::panic::set_hook(Box::new(console_error_panic_hook::hook));
+        console_log::init_with_level(log::Level::Debug).expect("failed to initialize logging");
+        wasm_bindgen_futures::spawn_local(run(EventLoop::new().unwrap(), Options::from_args()));
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        // The log would scribble over the game in the terminal, unless it's redirected.
+        let tui = std::env::args().any(|arg| arg == "--tui");
+        env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(if tui {
+            "off"
+        } else {
+            "snake_pixels=debug"
+        }))
+        .format_timestamp(Some(env_logger::fmt::TimestampPrecision::Micros))
+        .init();
+        let options = Options::from_args();
+        if let Some(path) = &options.export_stats {
+            export_stats(&options, path);
+        } else if let Some(path) = &options.verify {
+            verify_replay(path);
+        } else if options.tournament {
+            run_tournament(&options);
+        } else if options.tui {
+            run_in_terminal(&options);
+        } else if options.headless {
+            run_headless(&options);
+        } else {
+            pollster::block_on(run(EventLoop::new().unwrap(), options));
+        }
+    }
+}
+
+fn create_app(options: &Options) -> App {
+    let mut app = App::new(Config::default(), options.seed, options.profile.as_deref());
+    if let Some(path) = &options.config {
+        app.load_tuning(path);
+    }
+    if options.debug {
+        app.enable_console();
+    }
+    if let Some(path) = &options.perf_report {
+        app.enable_perf_report(path);
+    }
+    app.set_render_threads(options.render_threads);
+    if let Some(name) = &options.ruleset {
+        app.open_ruleset(name);
+    }
+    if let Some(address) = &options.connect {
+        app.connect(address, options.spectate);
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    if options.remote {
+        if options.tui {
+            error!("--remote can't be used with --tui, which needs the standard input");
+        } else {
+            app.enable_remote();
+        }
+    }
+    app
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn run_in_terminal(options: &Options) {
+    info!("Starting up in the terminal");
+    if let Err(e) = terminal::run(create_app(options)) {
+        error!("Terminal error: {}", e);
+        eprintln!("Failed to run in the terminal: {}", e);
+    }
+}
+
+/// Plays the tournament between all the bots and prints the results to stdout.
+#[cfg(not(target_arch = "wasm32"))]
+fn run_tournament(options: &Options) {
+    use snake_pixels::tournament;
+
+    let policies = snake_pixels::bots::policies();
+    info!(
+        "Playing {} games between every pair of {} bots",
+        options.games,
+        policies.len()
+    );
+    let standings = tournament::run(
+        &Config::default(),
+        &policies,
+        options.games,
+        options.seed.unwrap_or(0),
+    );
+    print!("{}", tournament::format(&standings, options.format));
+}
+
+/// Writes the history of games of the profile to `path`.
+#[cfg(not(target_arch = "wasm32"))]
+fn export_stats(options: &Options, path: &std::path::Path) {
+    use snake_pixels::telemetry::{self, ExportFormat};
+
+    let profile = match &options.profile {
+        Some(name) => Profile::load(name),
+        None => Profile::load_last(),
+    };
+    let text = telemetry::export(&profile.history, ExportFormat::from_path(path));
+    match std::fs::write(path, text) {
+        Ok(()) => println!(
+            "Exported {} games of {} to {}",
+            profile.history.len(),
+            profile.name(),
+            path.display()
+        ),
+        Err(e) => {
+            error!("Failed to write {}: {}", path.display(), e);
+            eprintln!("Failed to write {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// Plays the game of the replay again and prints the verdict. Exits with an error if the replay
+/// can't be read or its result doesn't hold up.
+#[cfg(not(target_arch = "wasm32"))]
+fn verify_replay(path: &std::path::Path) {
+    use snake_pixels::replay::Replay;
+
+    let replay: Replay = match std::fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|text| serde_json::from_str(&text).map_err(|e| e.to_string()))
+    {
+        Ok(replay) => replay,
+        Err(e) => {
+            error!("Failed to read {}: {}", path.display(), e);
+            eprintln!("Failed to read {}: {}", path.display(), e);
+            std::process::exit(2);
+        }
+    };
+    let verdict = replay.verify();
+    if verdict.is_valid() {
+        println!(
+            "{}: valid, score {} and length {} after {} ticks",
+            path.display(),
+            replay.score,
+            replay.length,
+            replay.ticks
+        );
+    } else {
+        println!(
+            "{}: rejected, the replay claims the score {} and the length {}, but {}",
+            path.display(),
+            replay.score,
+            replay.length,
+            verdict
+        );
+        std::process::exit(1);
+    }
+}
+
+/// Runs the application without any display until it has nothing left to do, which only
+/// happens after leaving the game it was started with.
+#[cfg(not(target_arch = "wasm32"))]
+fn run_headless(options: &Options) {
+    use snake_pixels::renderer::NullRenderer;
+
+    info!("Starting up without a display");
+    let mut app = create_app(options);
+    let (width, height) = app.canvas_size();
+    let mut renderer = NullRenderer::new(width as usize, height as usize);
+    app.disable_demo();
+    while let Some(wakeup) = app.next_wakeup() {
+        std::thread::sleep(wakeup.saturating_duration_since(Instant::now()));
+        if app.update() {
+            if let Err(e) = app.render(&mut renderer) {
+                error!("Failed to draw: {}", e);
+                break;
+            }
+        }
+    }
+    app.quit();
+    info!("Drew {} frames", renderer.frames());
+}
+
+/// Creating the surface is asynchronous in the browser, so the whole setup is.
+async fn run(event_loop: EventLoop<()>, options: Options) {
     info!("Starting up");
 
-    let event_loop = EventLoop::new();
-    let window = WindowBuilder::new().build(&event_loop).unwrap();
+    let mut app = create_app(&options);
+
+    let (width, height) = app.canvas_size();
+    let mut geometry = WindowGeometry::load();
+    let builder = WindowBuilder::new()
+        .with_title(TITLE)
+        .with_min_inner_size(PhysicalSize::new(width, height));
+    let window = geometry
+        .apply(builder, &event_loop)
+        .build(&event_loop)
+        .unwrap();
+    if !geometry.has_size() {
+        // The scale factor is only known once the window exists.
+        let _ = window.request_inner_size(window_size(&app, window.scale_factor()));
+    }
     info!("Created window");
+    #[cfg(target_arch = "wasm32")]
+    attach_canvas(&window);
 
-    let mut canvas = Canvas::new(&window, 15, 15).unwrap();
+    // On Android there is no surface to draw on until the application is resumed.
+    let mut canvas = if cfg!(target_os = "android") {
+        None
+    } else {
+        Some(
+            Canvas::new(&window, width, height, !options.uncapped, options.gpu)
+                .await
+                .unwrap(),
+        )
+    };
     info!("Initialized canvas");
 
-    let mut state = State::new();
+    let mut stats = None;
+    if options.stats {
+        match StatsWindow::new(&event_loop, &window).await {
+            Ok(window) => {
+                app.enable_live_stats();
+                stats = Some(window);
+            }
+            Err(e) => error!("Failed to open the stats: {}", e),
+        }
+    }
 
-    event_loop.run(move |event, _, control_flow| {
-        handle_event(event, &mut state, &mut canvas).map(|cf| {
-            debug!("Setting ControlFlow {:?}", cf);
-            *control_flow = cf
-        });
-    });
+    let handler = move |event, elwt: &EventLoopWindowTarget<()>| {
+        match &event {
+            Event::WindowEvent { window_id, event }
+                if stats
+                    .as_ref()
+                    .is_some_and(|stats| stats.window.id() == *window_id) =>
+            {
+                return handle_stats_event(event, &app, &mut stats);
+            }
+            // The graphs follow the board.
+            Event::WindowEvent {
+                event: WindowEvent::RedrawRequested,
+                ..
+            } => {
+                if let Some(stats) = &stats {
+                    stats.window.request_redraw();
+                }
+            }
+            _ => (),
+        }
+        handle_event(
+            event,
+            elwt,
+            &mut app,
+            &mut canvas,
+            &window,
+            &mut geometry,
+            &options,
+        )
+    };
+    // The browser runs its own event loop, so `run` would never return there.
+    #[cfg(target_arch = "wasm32")]
+    {
+        use winit::platform::web::EventLoopExtWebSys;
+        event_loop.spawn(handler);
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    event_loop.run(handler).unwrap();
 }
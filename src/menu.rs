@@ -0,0 +1,105 @@
+use snake_pixels::font::{draw_text_centered, text_width, GLYPH_HEIGHT, LINE_HEIGHT};
+use snake_pixels::framebuffer::FrameBuffer;
+use snake_pixels::palette::Palette;
+use winit::keyboard::KeyCode;
+
+/// Vertical list of items navigated with the arrow keys and chosen with Enter.
+pub struct Menu<T> {
+    title: String,
+    items: Vec<(String, T)>,
+    selected: usize,
+}
+
+impl<T: Copy> Menu<T> {
+    pub fn new(title: &str, items: Vec<(&str, T)>) -> Self {
+        Menu {
+            title: title.to_string(),
+            items: items
+                .into_iter()
+                .map(|(label, item)| (label.to_string(), item))
+                .collect(),
+            selected: 0,
+        }
+    }
+
+    /// Replaces the items, keeping the selection at the same position.
+    pub fn set_items(&mut self, items: Vec<(&str, T)>) {
+        self.items = items
+            .into_iter()
+            .map(|(label, item)| (label.to_string(), item))
+            .collect();
+        self.selected = self.selected.min(self.items.len() - 1);
+    }
+
+    /// Returns the chosen item when Enter or Space is pressed.
+    pub fn on_keypress(&mut self, keycode: KeyCode) -> Option<T> {
+        match keycode {
+            KeyCode::ArrowUp => {
+                self.selected = (self.selected + self.items.len() - 1) % self.items.len();
+                None
+            }
+            KeyCode::ArrowDown => {
+                self.selected = (self.selected + 1) % self.items.len();
+                None
+            }
+            KeyCode::Enter | KeyCode::NumpadEnter | KeyCode::Space => {
+                Some(self.items[self.selected].1)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn render(&self, buffer: &mut FrameBuffer, palette: &Palette) {
+        buffer.clear(palette.menu_bg);
+        self.render_over(buffer, palette);
+    }
+
+    /// Draws the title and the items over what is already in the buffer.
+    pub fn render_over(&self, buffer: &mut FrameBuffer, palette: &Palette) {
+        let top = buffer.height() as i32;
+        draw_text_centered(buffer, top - 12, &self.title, 2, palette.text);
+        for (i, y) in self.rows(buffer) {
+            let color = if i == self.selected {
+                palette.selected
+            } else {
+                palette.text
+            };
+            draw_text_centered(buffer, y, &self.label(i), 1, color);
+        }
+    }
+
+    /// The label of the item as shown, with arrows around the selected one.
+    fn label(&self, i: usize) -> String {
+        let label = &self.items[i].0;
+        if i == self.selected {
+            format!("> {} <", label)
+        } else {
+            label.clone()
+        }
+    }
+
+    /// The items on the screen with the top of their lines and the right edge of their labels,
+    /// for drawing something next to them.
+    pub fn visible(&self, buffer: &FrameBuffer) -> Vec<(T, i32, i32)> {
+        self.rows(buffer)
+            .map(|(i, y)| {
+                let width = text_width(&self.label(i));
+                (
+                    self.items[i].1,
+                    y,
+                    (buffer.width() as i32 - width) / 2 + width,
+                )
+            })
+            .collect()
+    }
+
+    /// Indices of the items on the screen with the top of their lines. Scrolls when the items
+    /// don't fit above the two status lines at the bottom.
+    fn rows(&self, buffer: &FrameBuffer) -> impl Iterator<Item = (usize, i32)> {
+        let top = buffer.height() as i32;
+        let rows = ((top - 28 - (GLYPH_HEIGHT + 2 * LINE_HEIGHT)) / 8 + 1).max(1) as usize;
+        let first = (self.selected + 1).saturating_sub(rows);
+        let end = (first + rows).min(self.items.len());
+        (first..end).map(move |i| (i, top - 28 - (i - first) as i32 * 8))
+    }
+}
@@ -0,0 +1,169 @@
+//! Dialogs drawn in a box over the screen, which take all the keys until they are closed: a
+//! question to answer with yes or no, a name to enter and a key to press.
+
+use crate::i18n::tr;
+use crate::name_entry::{NameEntry, NameEntryEvent};
+use snake_pixels::font::{draw_text, draw_text_centered, text_width, LINE_HEIGHT};
+use snake_pixels::framebuffer::FrameBuffer;
+use snake_pixels::palette::Palette;
+use winit::keyboard::KeyCode;
+
+/// Opacity of the menu background over the screen behind the dialog.
+const SHADE_ALPHA: u8 = 160;
+/// Space between the border of the box and its text.
+const PADDING: i32 = 4;
+
+pub enum ModalEvent {
+    None,
+    /// Yes to the question.
+    Confirmed,
+    /// No to the question, or Escape in any dialog.
+    Cancelled,
+    Entered(String),
+    Captured(KeyCode),
+}
+
+enum Kind {
+    /// Whether Yes has the focus rather than No.
+    Confirm {
+        yes: bool,
+    },
+    Name(NameEntry),
+    KeyCapture,
+}
+
+pub struct Modal {
+    title: String,
+    kind: Kind,
+}
+
+impl Modal {
+    /// Asks the question, with No focused so that a stray Enter doesn't do anything rash.
+    pub fn confirm(question: &str) -> Self {
+        Modal {
+            title: question.to_string(),
+            kind: Kind::Confirm { yes: false },
+        }
+    }
+
+    /// Asks for a name of up to `max_len` characters, see `NameEntry`.
+    pub fn name(title: &str, initial: &str, max_len: usize) -> Self {
+        Modal {
+            title: title.to_string(),
+            kind: Kind::Name(NameEntry::new(title, initial, max_len)),
+        }
+    }
+
+    /// Waits for any key other than Escape.
+    pub fn key_capture(title: &str) -> Self {
+        Modal {
+            title: title.to_string(),
+            kind: Kind::KeyCapture,
+        }
+    }
+
+    pub fn on_keypress(&mut self, keycode: KeyCode) -> ModalEvent {
+        match &mut self.kind {
+            Kind::Confirm { yes } => match keycode {
+                KeyCode::ArrowLeft | KeyCode::ArrowRight | KeyCode::Tab => *yes = !*yes,
+                KeyCode::Enter | KeyCode::NumpadEnter | KeyCode::Space if *yes => {
+                    return ModalEvent::Confirmed
+                }
+                KeyCode::Enter | KeyCode::NumpadEnter | KeyCode::Space => {
+                    return ModalEvent::Cancelled
+                }
+                KeyCode::KeyY => return ModalEvent::Confirmed,
+                KeyCode::KeyN | KeyCode::Escape => return ModalEvent::Cancelled,
+                _ => (),
+            },
+            Kind::Name(entry) => match entry.on_keypress(keycode) {
+                NameEntryEvent::Confirmed(name) => return ModalEvent::Entered(name),
+                NameEntryEvent::Cancelled => return ModalEvent::Cancelled,
+                NameEntryEvent::None => (),
+            },
+            Kind::KeyCapture if keycode == KeyCode::Escape => return ModalEvent::Cancelled,
+            Kind::KeyCapture => return ModalEvent::Captured(keycode),
+        }
+        ModalEvent::None
+    }
+
+    /// Shades the screen and draws the box in the middle of it.
+    pub fn render(&self, buffer: &mut FrameBuffer, palette: &Palette) {
+        let (width, height) = (buffer.width() as i32, buffer.height() as i32);
+        buffer.blend_rectangle(
+            0,
+            0,
+            width as usize,
+            height as usize,
+            palette.menu_bg,
+            SHADE_ALPHA,
+        );
+
+        let hints = match &self.kind {
+            Kind::Confirm { .. } => vec![],
+            Kind::Name(_) => vec![tr("ENTER: OK"), tr("ESC: CANCEL")],
+            Kind::KeyCapture => vec![tr("PRESS A KEY"), tr("ESC: CANCEL")],
+        };
+        // The line with the answers or the name, between the title and the hints.
+        let (middle, middle_width) = match &self.kind {
+            Kind::Confirm { .. } => (LINE_HEIGHT, text_width(&answers())),
+            Kind::Name(entry) => (LINE_HEIGHT + 3, entry.field_width(1)),
+            Kind::KeyCapture => (0, 0),
+        };
+        let content_width = hints
+            .iter()
+            .map(|hint| text_width(hint))
+            .chain([text_width(&self.title), middle_width])
+            .max()
+            .unwrap_or(0);
+        let box_width = (content_width + 2 * PADDING).min(width);
+        let box_height = 2 * PADDING + LINE_HEIGHT + middle + hints.len() as i32 * LINE_HEIGHT;
+        let (left, bottom) = ((width - box_width) / 2, (height - box_height) / 2);
+        buffer.fill_rectangle(
+            left,
+            bottom,
+            box_width as usize,
+            box_height as usize,
+            palette.selected,
+        );
+        buffer.fill_rectangle(
+            left + 1,
+            bottom + 1,
+            (box_width - 2) as usize,
+            (box_height - 2) as usize,
+            palette.menu_bg,
+        );
+
+        let mut y = bottom + box_height - PADDING;
+        draw_text_centered(buffer, y, &self.title, 1, palette.text);
+        y -= LINE_HEIGHT;
+        match &self.kind {
+            Kind::Confirm { yes } => {
+                let (yes_text, no_text) = (tr("YES"), tr("NO"));
+                let x = (width - text_width(&answers())) / 2;
+                let focused = |focus: bool| {
+                    if focus {
+                        palette.selected
+                    } else {
+                        palette.dim_text
+                    }
+                };
+                draw_text(buffer, x, y, &yes_text, 1, focused(*yes));
+                let x = x + text_width(&answers()) - text_width(&no_text);
+                draw_text(buffer, x, y, &no_text, 1, focused(!*yes));
+            }
+            Kind::Name(entry) => entry.render_field(buffer, palette, y, 1),
+            Kind::KeyCapture => (),
+        }
+        y -= middle;
+        for hint in hints.iter() {
+            draw_text_centered(buffer, y, hint, 1, palette.dim_text);
+            y -= LINE_HEIGHT;
+        }
+    }
+}
+
+/// The answers to the question as they are laid out.
+fn answers() -> String {
+    format!("{}    {}", tr("YES"), tr("NO"))
+}
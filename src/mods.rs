@@ -0,0 +1,44 @@
+//! Mods installed in the data directory, whichever way they are written.
+
+#[cfg(feature = "plugins")]
+use crate::plugins::Plugin;
+#[cfg(feature = "lua")]
+use crate::scripting::Script;
+use snake_pixels::hooks::Hooks;
+
+pub enum Mod {
+    #[cfg(feature = "lua")]
+    Script(String),
+    #[cfg(feature = "plugins")]
+    Plugin(String),
+}
+
+impl Mod {
+    /// The installed mods: the Lua scripts, then the WebAssembly plugins.
+    pub fn list() -> Vec<Mod> {
+        let mut mods = Vec::new();
+        #[cfg(feature = "lua")]
+        mods.extend(Script::list().into_iter().map(Mod::Script));
+        #[cfg(feature = "plugins")]
+        mods.extend(Plugin::list().into_iter().map(Mod::Plugin));
+        mods
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            #[cfg(feature = "lua")]
+            Mod::Script(name) => name,
+            #[cfg(feature = "plugins")]
+            Mod::Plugin(name) => name,
+        }
+    }
+
+    pub fn load(&self) -> Result<Box<dyn Hooks>, String> {
+        match self {
+            #[cfg(feature = "lua")]
+            Mod::Script(name) => Ok(Box::new(Script::load(name)?)),
+            #[cfg(feature = "plugins")]
+            Mod::Plugin(name) => Ok(Box::new(Plugin::load(name)?)),
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use crate::i18n::tr;
+use snake_pixels::font::{draw_text, draw_text_centered, text_width, ADVANCE, GLYPH_HEIGHT};
+use snake_pixels::framebuffer::FrameBuffer;
+use snake_pixels::palette::Palette;
+use winit::keyboard::KeyCode;
+
+/// Characters that can be entered, in the order they are cycled through with the arrow keys.
+const ALPHABET: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+
+pub enum NameEntryEvent {
+    None,
+    Confirmed(String),
+    Cancelled,
+}
+
+pub fn keycode_char(keycode: KeyCode) -> Option<char> {
+    use KeyCode::*;
+    let c = match keycode {
+        KeyA => 'A',
+        KeyB => 'B',
+        KeyC => 'C',
+        KeyD => 'D',
+        KeyE => 'E',
+        KeyF => 'F',
+        KeyG => 'G',
+        KeyH => 'H',
+        KeyI => 'I',
+        KeyJ => 'J',
+        KeyK => 'K',
+        KeyL => 'L',
+        KeyM => 'M',
+        KeyN => 'N',
+        KeyO => 'O',
+        KeyP => 'P',
+        KeyQ => 'Q',
+        KeyR => 'R',
+        KeyS => 'S',
+        KeyT => 'T',
+        KeyU => 'U',
+        KeyV => 'V',
+        KeyW => 'W',
+        KeyX => 'X',
+        KeyY => 'Y',
+        KeyZ => 'Z',
+        Digit0 | Numpad0 => '0',
+        Digit1 | Numpad1 => '1',
+        Digit2 | Numpad2 => '2',
+        Digit3 | Numpad3 => '3',
+        Digit4 | Numpad4 => '4',
+        Digit5 | Numpad5 => '5',
+        Digit6 | Numpad6 => '6',
+        Digit7 | Numpad7 => '7',
+        Digit8 | Numpad8 => '8',
+        Digit9 | Numpad9 => '9',
+        _ => return None,
+    };
+    Some(c)
+}
+
+/// Arcade-style name input: characters can be typed directly or picked with the up and down
+/// arrows at the cursor position.
+pub struct NameEntry {
+    title: String,
+    chars: Vec<char>,
+    cursor: usize,
+    max_len: usize,
+}
+
+impl NameEntry {
+    pub fn new(title: &str, initial: &str, max_len: usize) -> Self {
+        NameEntry {
+            title: title.to_string(),
+            chars: initial.chars().take(max_len).collect(),
+            cursor: 0,
+            max_len,
+        }
+    }
+
+    /// The text entered so far.
+    pub fn text(&self) -> String {
+        self.chars.iter().collect()
+    }
+
+    fn cycle(&mut self, delta: usize) {
+        if self.cursor == self.chars.len() {
+            self.chars.push('A');
+            if delta == 1 {
+                return;
+            }
+        }
+        let alphabet: Vec<char> = ALPHABET.chars().collect();
+        let current = alphabet
+            .iter()
+            .position(|&c| c == self.chars[self.cursor])
+            .unwrap_or(0);
+        self.chars[self.cursor] = alphabet[(current + delta) % alphabet.len()];
+    }
+
+    fn type_char(&mut self, c: char) {
+        if self.cursor < self.chars.len() {
+            self.chars[self.cursor] = c;
+        } else {
+            self.chars.push(c);
+        }
+        self.cursor = (self.cursor + 1).min(self.max_len - 1);
+    }
+
+    pub fn on_keypress(&mut self, keycode: KeyCode) -> NameEntryEvent {
+        let alphabet_len = ALPHABET.len();
+        match keycode {
+            KeyCode::Enter | KeyCode::NumpadEnter => {
+                if !self.chars.is_empty() {
+                    return NameEntryEvent::Confirmed(self.text());
+                }
+            }
+            KeyCode::Escape => return NameEntryEvent::Cancelled,
+            KeyCode::ArrowUp => self.cycle(1),
+            KeyCode::ArrowDown => self.cycle(alphabet_len - 1),
+            KeyCode::ArrowLeft => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::ArrowRight => {
+                if self.cursor < self.chars.len() && self.cursor + 1 < self.max_len {
+                    self.cursor += 1;
+                }
+            }
+            KeyCode::Backspace => {
+                self.chars.pop();
+                self.cursor = self.cursor.min(self.chars.len());
+            }
+            _ => {
+                if let Some(c) = keycode_char(keycode) {
+                    self.type_char(c);
+                }
+            }
+        }
+        NameEntryEvent::None
+    }
+
+    pub fn render(&self, buffer: &mut FrameBuffer, palette: &Palette) {
+        buffer.clear(palette.menu_bg);
+        let top = buffer.height() as i32;
+        draw_text_centered(buffer, top - 12, &self.title, 1, palette.text);
+
+        // Long entries, such as the codes of games, get smaller letters.
+        let scale = if self.field_width(2) <= buffer.width() as i32 {
+            2
+        } else {
+            1
+        };
+        let y = top / 2 + GLYPH_HEIGHT * scale / 2;
+        self.render_field(buffer, palette, y, scale);
+
+        draw_text_centered(buffer, 16, &tr("ENTER: OK"), 1, palette.dim_text);
+        draw_text_centered(buffer, 9, &tr("ESC: CANCEL"), 1, palette.dim_text);
+    }
+
+    /// Width in pixels of the text with all of its characters entered.
+    pub fn field_width(&self, scale: i32) -> i32 {
+        text_width(&"_".repeat(self.max_len)) * scale
+    }
+
+    /// Draws the text entered so far centered horizontally with its top at `y`, over the blanks
+    /// for the characters, the one at the cursor highlighted.
+    pub fn render_field(&self, buffer: &mut FrameBuffer, palette: &Palette, y: i32, scale: i32) {
+        let text = self.text();
+        let x = (buffer.width() as i32 - self.field_width(scale)) / 2;
+        draw_text(buffer, x, y, &text, scale, palette.selected);
+        for i in 0..self.max_len as i32 {
+            let color = if i == self.cursor as i32 {
+                palette.selected
+            } else {
+                palette.dim_text
+            };
+            buffer.fill_rectangle(
+                x + i * ADVANCE * scale,
+                y - GLYPH_HEIGHT * scale - 3,
+                (ADVANCE * scale - scale) as usize,
+                1,
+                color,
+            );
+        }
+    }
+}
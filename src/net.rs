@@ -0,0 +1,106 @@
+//! Protocol between `snake_server` and the clients. Every message is a single JSON object,
+//! carried by one of the transports in `transport`.
+
+use crate::config::Config;
+use crate::grid::Direction;
+use crate::skin::Skin;
+use crate::world::SnakeId;
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_PORT: u16 = 7878;
+pub const MIN_PLAYERS: usize = 2;
+pub const MAX_PLAYERS: usize = 8;
+pub const MAX_SPECTATORS: usize = 32;
+/// Clients ping the server this often, and are dropped after ten times as long without a
+/// message.
+pub const HEARTBEAT_MS: u64 = 1000;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    /// `session` is picked at random by the client, so that it can take its snake back after
+    /// reconnecting, possibly from a different address. Spectators never get a snake and don't
+    /// count towards the players needed to start a match.
+    Join {
+        name: String,
+        session: u64,
+        #[serde(default)]
+        spectator: bool,
+        #[serde(default)]
+        skin: Skin,
+    },
+    /// Asks for the turn to happen on the given tick. Turns that arrive too late happen on the
+    /// next tick that the server simulates.
+    Turn {
+        tick: u64,
+        direction: Direction,
+    },
+    /// `time` is in milliseconds on the client's own clock and is sent back in `Pong`.
+    Ping {
+        time: u64,
+    },
+    Leave,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    /// Sent to everyone while waiting for enough players to start a match.
+    Lobby {
+        players: Vec<String>,
+        needed: usize,
+    },
+    /// Sent to each client when a match starts, or when they connect in the middle of one. The
+    /// board is `World::new(config, names.len(), seed)` followed by the turns of every tick so
+    /// far in `history`. `snake` is the one that the client controls, if any.
+    Start {
+        snake: Option<SnakeId>,
+        config: Config,
+        seed: u64,
+        names: Vec<String>,
+        /// How the snakes look, in the same order as the names.
+        #[serde(default)]
+        skins: Vec<Skin>,
+        history: Vec<Vec<Option<Direction>>>,
+    },
+    /// The turns of every snake with which the server simulated the tick.
+    Inputs {
+        tick: u64,
+        turns: Vec<Option<Direction>>,
+    },
+    Over {
+        winner: Option<String>,
+    },
+    Rejected {
+        reason: String,
+    },
+    Pong {
+        time: u64,
+    },
+}
+
+impl ServerMessage {
+    /// Checks that a match can be played as described by the message, so that a broken server
+    /// can't bring the client down.
+    pub fn validate(&self) -> Result<(), String> {
+        if let ServerMessage::Start {
+            snake,
+            config,
+            names,
+            ..
+        } = self
+        {
+            config.validate()?;
+            if names.is_empty() || names.len() > MAX_PLAYERS {
+                return Err(format!("A match can't have {} players", names.len()));
+            }
+            match snake {
+                Some(snake) if *snake >= names.len() => {
+                    return Err(format!("There is no snake {}", snake));
+                }
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+}
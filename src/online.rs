@@ -0,0 +1,387 @@
+use crate::i18n::{tr, tr_fmt};
+use crate::toasts::{Notice, Notifier};
+use crate::viewport::Viewport;
+use log::{info, warn};
+use snake_pixels::font::{draw_text, draw_text_centered, text_width, LINE_HEIGHT};
+use snake_pixels::framebuffer::FrameBuffer;
+use snake_pixels::grid::{Direction, Vec2};
+use snake_pixels::net::{ClientMessage, ServerMessage, HEARTBEAT_MS};
+use snake_pixels::palette::Palette;
+use snake_pixels::rollback::Rollback;
+use snake_pixels::skin::Skin;
+use snake_pixels::transport::{self, Transport};
+use snake_pixels::world::{render_board, Camera, SnakeId, World};
+use std::time::Duration;
+use web_time::Instant;
+use winit::keyboard::KeyCode;
+
+/// How often the connection is checked for new messages.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+/// Silence from the server after which the connection is considered lost.
+const CONNECTION_TIMEOUT: Duration = Duration::from_millis(3 * HEARTBEAT_MS);
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(2);
+/// Magnification when a spectator zooms in with the keyboard.
+const ZOOM: f32 = 4.0;
+
+/// A match hosted by `snake_server`. The client runs its own copy of the simulation ahead of the
+/// server and corrects it whenever the server confirms different inputs than predicted.
+pub struct OnlineGame {
+    address: String,
+    name: String,
+    skin: Skin,
+    /// Lets the server recognize us after a reconnect.
+    session: u64,
+    spectator: bool,
+    transport: Option<Box<dyn Transport>>,
+    /// Our snake in the current match, or `None` while waiting or watching.
+    snake: Option<SnakeId>,
+    game: Option<Rollback>,
+    names: Vec<String>,
+    skins: Vec<Skin>,
+    show_scores: bool,
+    /// Spectators can zoom in on a part of a large board and move around.
+    viewport: Viewport,
+    tick: Duration,
+    next_tick: Instant,
+    status: Vec<String>,
+    /// Players in the lobby as of the last message about it.
+    lobby: Vec<String>,
+    notifier: Notifier,
+    /// Reference point of the timestamps in pings.
+    clock: Instant,
+    latency: Option<Duration>,
+    last_received: Instant,
+    next_ping: Instant,
+    next_reconnect: Instant,
+}
+
+impl OnlineGame {
+    /// `address` is either `HOST:PORT` for UDP or a `ws://` URL.
+    pub fn connect(
+        address: &str,
+        name: &str,
+        skin: Skin,
+        spectator: bool,
+        notifier: Notifier,
+    ) -> std::io::Result<Self> {
+        let transport = transport::connect(address)?;
+        let now = Instant::now();
+        let mut game = OnlineGame {
+            address: address.to_string(),
+            name: name.to_string(),
+            skin,
+            session: rand::random(),
+            spectator,
+            transport: Some(transport),
+            snake: None,
+            game: None,
+            names: Vec::new(),
+            skins: Vec::new(),
+            show_scores: spectator,
+            viewport: Viewport::new(),
+            tick: Duration::from_millis(HEARTBEAT_MS),
+            next_tick: now,
+            status: vec![tr("CONNECTING")],
+            lobby: Vec::new(),
+            notifier,
+            clock: now,
+            latency: None,
+            last_received: now,
+            next_ping: now,
+            next_reconnect: now,
+        };
+        info!("Joining the server at {}", address);
+        game.join();
+        Ok(game)
+    }
+
+    fn send(&mut self, message: &ClientMessage) {
+        if let Some(transport) = &mut self.transport {
+            if let Err(e) = transport.send(message) {
+                warn!("Lost the connection to the server: {}", e);
+                self.transport = None;
+            }
+        }
+    }
+
+    fn join(&mut self) {
+        let message = ClientMessage::Join {
+            name: self.name.clone(),
+            session: self.session,
+            spectator: self.spectator,
+            skin: self.skin,
+        };
+        self.send(&message);
+    }
+
+    fn reconnect(&mut self, now: Instant) {
+        self.next_reconnect = now + RECONNECT_INTERVAL;
+        self.latency = None;
+        self.status = vec![tr("RECONNECTING")];
+        match transport::connect(&self.address) {
+            Ok(transport) => {
+                info!("Reconnecting to {}", self.address);
+                self.transport = Some(transport);
+                self.last_received = now;
+                self.join();
+            }
+            Err(e) => warn!("Failed to reconnect to {}: {}", self.address, e),
+        }
+    }
+
+    /// Handles the messages that arrived since the last call and moves the view of a spectator.
+    /// Returns whether anything changed.
+    pub fn update(&mut self) -> bool {
+        let moved = self.viewport.update();
+        self.receive() || moved
+    }
+
+    fn receive(&mut self) -> bool {
+        let now = Instant::now();
+        if self.transport.is_none() || now.duration_since(self.last_received) > CONNECTION_TIMEOUT {
+            if now < self.next_reconnect {
+                return false;
+            }
+            self.reconnect(now);
+            return true;
+        }
+
+        if now >= self.next_ping {
+            let time = now.duration_since(self.clock).as_millis() as u64;
+            self.send(&ClientMessage::Ping { time });
+            self.next_ping = now + Duration::from_millis(HEARTBEAT_MS);
+        }
+
+        let mut changed = false;
+        loop {
+            let message = match self.transport.as_mut().map(|t| t.receive()) {
+                Some(Ok(Some(message))) => message,
+                Some(Ok(None)) | None => break,
+                Some(Err(e)) => {
+                    warn!("Lost the connection to the server: {}", e);
+                    self.transport = None;
+                    break;
+                }
+            };
+            self.last_received = now;
+            changed = true;
+            if let Err(e) = message.validate() {
+                warn!("Invalid message from the server: {}", e);
+                self.status = vec![tr("INVALID MATCH")];
+                continue;
+            }
+            match message {
+                ServerMessage::Lobby { players, needed } => {
+                    self.game = None;
+                    // Those who were there before us didn't just join.
+                    let joined = players.iter().filter(|&name| !self.lobby.contains(name));
+                    for name in joined.filter(|_| !self.lobby.is_empty()) {
+                        if *name != self.name {
+                            self.notifier.post(Notice::PlayerJoined(name.clone()));
+                        }
+                    }
+                    self.status = vec![tr_fmt("PLAYERS {}/{}", &[&players.len(), &needed])];
+                    self.status.extend(players.iter().cloned());
+                    self.lobby = players;
+                }
+                ServerMessage::Start {
+                    snake,
+                    config,
+                    seed,
+                    names,
+                    skins,
+                    history,
+                } => {
+                    let mut game = Rollback::new(World::new(&config, names.len(), seed), snake);
+                    for (tick, turns) in history.iter().enumerate() {
+                        game.confirm(tick as u64 + 1, turns);
+                    }
+                    self.game = Some(game);
+                    self.names = names;
+                    self.skins = skins;
+                    self.snake = snake;
+                    self.tick = config.tick();
+                    self.next_tick = now;
+                    self.status = match snake {
+                        Some(_) => Vec::new(),
+                        None => vec![tr("WATCHING")],
+                    };
+                }
+                ServerMessage::Inputs { tick, turns } => {
+                    if let Some(game) = &mut self.game {
+                        game.confirm(tick, &turns);
+                    }
+                }
+                ServerMessage::Over { winner } => {
+                    if let Some(game) = &self.game {
+                        info!("Match over after {} rollbacks", game.rollbacks());
+                    }
+                    self.snake = None;
+                    self.status = vec![match winner {
+                        Some(name) => tr_fmt("{} WINS", &[&name]),
+                        None => tr("DRAW"),
+                    }];
+                }
+                ServerMessage::Rejected { reason } => {
+                    warn!("Rejected by the server: {}", reason);
+                    self.status = vec![reason];
+                }
+                ServerMessage::Pong { time } => {
+                    let sent = self.clock + Duration::from_millis(time);
+                    self.latency = Some(now.saturating_duration_since(sent));
+                }
+            }
+        }
+
+        // Stay far enough ahead of the server for our turns to reach it in time.
+        let lead = self.latency.map_or(0, |latency| {
+            (latency.as_millis() / self.tick.as_millis().max(1)) as u64
+        }) + 1;
+        if let Some(game) = &mut self.game {
+            let tick = game.world().tick();
+            while game.world().tick() < game.confirmed_tick() + lead {
+                let before = game.world().tick();
+                game.advance();
+                if game.world().tick() == before {
+                    break;
+                }
+            }
+            if now >= self.next_tick {
+                game.advance();
+                self.next_tick = now + self.tick;
+            }
+            changed |= game.world().tick() != tick;
+        }
+        changed
+    }
+
+    pub fn next_wakeup(&self) -> Instant {
+        Instant::now() + POLL_INTERVAL
+    }
+
+    pub fn turn(&mut self, direction: Direction) {
+        if let (Some(game), Some(_)) = (&mut self.game, self.snake) {
+            let tick = game.turn(direction);
+            self.send(&ClientMessage::Turn { tick, direction });
+        }
+    }
+
+    /// Handles the keys for the scoreboard and the camera. Returns `false` for keys that it
+    /// doesn't use.
+    pub fn on_keypress(&mut self, keycode: KeyCode) -> bool {
+        match keycode {
+            KeyCode::Tab => self.show_scores = !self.show_scores,
+            KeyCode::KeyZ if self.spectator && self.viewport.is_zoomed() => self.viewport.reset(),
+            KeyCode::KeyZ if self.spectator => self.viewport.zoom(ZOOM),
+            KeyCode::ArrowUp if self.viewport.is_zoomed() => self.viewport.pan(Vec2(0, 1)),
+            KeyCode::ArrowDown if self.viewport.is_zoomed() => self.viewport.pan(Vec2(0, -1)),
+            KeyCode::ArrowLeft if self.viewport.is_zoomed() => self.viewport.pan(Vec2(-1, 0)),
+            KeyCode::ArrowRight if self.viewport.is_zoomed() => self.viewport.pan(Vec2(1, 0)),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Returns whether the screen needs to be redrawn.
+    pub fn on_cursor_moved(&mut self, pos: Vec2) -> bool {
+        self.viewport.on_cursor_moved(pos)
+    }
+
+    pub fn on_middle_button(&mut self, pressed: bool) {
+        if self.spectator {
+            self.viewport.on_middle_button(pressed);
+        }
+    }
+
+    /// Returns whether the screen needs to be redrawn.
+    pub fn on_mouse_wheel(&mut self, notches: f32) -> bool {
+        if self.spectator {
+            self.viewport.on_wheel(notches);
+        }
+        self.spectator
+    }
+
+    /// Our snake is drawn in the palette's own colors, so it swaps places with the first one.
+    fn color_index(&self, snake: SnakeId) -> usize {
+        match self.snake {
+            Some(ours) if snake == ours => 0,
+            Some(ours) if snake == 0 => ours,
+            _ => snake,
+        }
+    }
+
+    pub fn leave(&mut self) {
+        self.send(&ClientMessage::Leave);
+    }
+
+    pub fn render(&self, buffer: &mut FrameBuffer, palette: &Palette) {
+        match &self.game {
+            Some(game) => {
+                let world = game.world();
+                let mut snakes = world.snakes().to_vec();
+                let mut skins = self.skins.clone();
+                skins.resize(snakes.len(), Skin::default());
+                if let Some(snake) = self.snake {
+                    snakes.swap(0, snake);
+                    skins.swap(0, snake);
+                }
+                let size = world.extent();
+                let camera = Camera::fit(buffer, size).with_shape(world.shape());
+                let camera = world.focus(self.viewport.camera(camera, size));
+                render_board(
+                    buffer,
+                    palette,
+                    &camera,
+                    (world.width(), world.height()),
+                    &snakes,
+                    &skins,
+                    world.objects(),
+                );
+                if self.show_scores {
+                    self.render_scores(buffer, palette, world);
+                }
+            }
+            None => buffer.clear(palette.menu_bg),
+        }
+
+        let mut y = buffer.height() as i32 - 20;
+        for line in self.status.iter() {
+            draw_text_centered(buffer, y, line, 1, palette.text);
+            y -= LINE_HEIGHT;
+        }
+
+        if let Some(latency) = self.latency {
+            let text = format!("{}MS", latency.as_millis());
+            let x = buffer.width() as i32 - text_width(&text) - 1;
+            draw_text(
+                buffer,
+                x,
+                buffer.height() as i32,
+                &text,
+                1,
+                palette.dim_text,
+            );
+        }
+    }
+
+    /// Names and scores of the snakes in the corner, best first.
+    fn render_scores(&self, buffer: &mut FrameBuffer, palette: &Palette, world: &World) {
+        let mut order: Vec<SnakeId> = (0..world.snakes().len()).collect();
+        order.sort_by_key(|&i| std::cmp::Reverse(world.snakes()[i].score));
+        let mut y = buffer.height() as i32 - 1;
+        for i in order {
+            let snake = &world.snakes()[i];
+            let color = if snake.alive {
+                let skin = self.skins.get(i).and_then(|skin| skin.colors());
+                skin.unwrap_or_else(|| palette.snake_colors(self.color_index(i)))
+                    .0
+            } else {
+                palette.dim_text
+            };
+            let name = self.names.get(i).map_or("?", String::as_str);
+            let line = format!("{} {}", name, snake.score);
+            draw_text(buffer, 1, y, &line, 1, color);
+            y -= LINE_HEIGHT;
+        }
+    }
+}
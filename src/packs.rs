@@ -0,0 +1,64 @@
+//! Campaign level packs in `campaigns/<pack>/` inside the data directory, each with a
+//! `campaign.json` manifest next to its level files.
+
+use crate::storage;
+use log::error;
+use snake_pixels::campaign::Manifest;
+use snake_pixels::level::Level;
+use std::path::PathBuf;
+
+const CAMPAIGNS_DIR: &str = "campaigns";
+const MANIFEST_FILE: &str = "campaign.json";
+
+#[derive(Clone, Debug)]
+pub struct Pack {
+    /// Name of the directory of the pack, under which its progress is saved.
+    pub dir: String,
+    pub manifest: Manifest,
+}
+
+impl Pack {
+    fn path(dir: &str, file: &str) -> PathBuf {
+        [CAMPAIGNS_DIR, dir, file].iter().collect()
+    }
+
+    /// Path of the file of a level inside the data directory.
+    pub fn level_file(&self, level: usize) -> PathBuf {
+        Pack::path(&self.dir, &self.manifest.levels[level].file)
+    }
+
+    /// The installed packs with a readable manifest, logging the reason for the rest.
+    pub fn list() -> Vec<Pack> {
+        storage::list_dirs(CAMPAIGNS_DIR)
+            .into_iter()
+            .filter_map(|dir| {
+                let text = storage::read_text(Pack::path(&dir, MANIFEST_FILE))?;
+                match serde_json::from_str::<Manifest>(&text) {
+                    Ok(manifest) if !manifest.levels.is_empty() => Some(Pack { dir, manifest }),
+                    Ok(_) => {
+                        error!("Campaign {} has no levels", dir);
+                        None
+                    }
+                    Err(e) => {
+                        error!("Failed to parse the manifest of campaign {}: {}", dir, e);
+                        None
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// Loads a level of the pack, logging the reason if it can't.
+    pub fn load_level(&self, level: usize) -> Option<Level> {
+        let file = &self.manifest.levels[level].file;
+        let text = storage::read_text(self.level_file(level))?;
+        Level::load(&text)
+            .map_err(|e| {
+                error!(
+                    "Failed to load level {} of campaign {}: {}",
+                    file, self.dir, e
+                )
+            })
+            .ok()
+    }
+}
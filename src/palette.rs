@@ -0,0 +1,218 @@
+use crate::framebuffer::Color;
+use serde::{Deserialize, Serialize};
+
+/// Colors used for rendering all screens.
+#[derive(Clone)]
+pub struct Palette {
+    pub bg: Color,
+    pub head: Color,
+    pub tail: Color,
+    pub food: Color,
+    pub rotten_food: Color,
+    pub golden_food: Color,
+    pub bomb: Color,
+    pub wall: Color,
+    pub portal: Color,
+    pub mud: Color,
+    pub boost: Color,
+    pub gate: Color,
+    pub conveyor: Color,
+    pub checkpoint: Color,
+    /// The frame around the board.
+    pub border: Color,
+
+    pub menu_bg: Color,
+    pub text: Color,
+    pub dim_text: Color,
+    pub selected: Color,
+    pub bar: Color,
+}
+
+const CLASSIC: Palette = Palette {
+    bg: Color::rgb(0x48, 0xB2, 0xE8),
+    head: Color::rgb(0x4E, 0x38, 0xE8),
+    tail: Color::rgb(0x5E, 0x48, 0xE8),
+    food: Color::rgb(0x9E, 0x28, 0xE8),
+    rotten_food: Color::rgb(0x78, 0x70, 0x38),
+    golden_food: Color::rgb(0xFF, 0xD0, 0x20),
+    bomb: Color::rgb(0x20, 0x20, 0x28),
+    wall: Color::rgb(0x28, 0x50, 0x78),
+    portal: Color::rgb(0xF0, 0x90, 0x30),
+    mud: Color::rgb(0x6A, 0x58, 0x38),
+    boost: Color::rgb(0xF0, 0xF0, 0x70),
+    gate: Color::rgb(0x28, 0x50, 0x78),
+    conveyor: Color::rgb(0x38, 0x80, 0xB0),
+    checkpoint: Color::rgb(0xE8, 0x40, 0x40),
+    border: Color::rgb(0x70, 0x58, 0xC0),
+
+    menu_bg: Color::rgb(0x20, 0x18, 0x48),
+    text: Color::rgb(0xD8, 0xD8, 0xF0),
+    dim_text: Color::rgb(0x80, 0x80, 0xA8),
+    selected: Color::rgb(0xFF, 0xD8, 0x40),
+    bar: Color::rgb(0x9E, 0x28, 0xE8),
+};
+
+const NIGHT: Palette = Palette {
+    bg: Color::rgb(0x10, 0x14, 0x20),
+    head: Color::rgb(0x50, 0xE0, 0x70),
+    tail: Color::rgb(0x30, 0xA0, 0x48),
+    food: Color::rgb(0xF0, 0x50, 0x40),
+    rotten_food: Color::rgb(0x78, 0x68, 0x30),
+    golden_food: Color::rgb(0xF8, 0xD8, 0x30),
+    bomb: Color::rgb(0xE0, 0xE0, 0xE8),
+    wall: Color::rgb(0x48, 0x50, 0x60),
+    portal: Color::rgb(0x40, 0xA0, 0xF0),
+    mud: Color::rgb(0x50, 0x40, 0x28),
+    boost: Color::rgb(0x60, 0xD0, 0xF0),
+    gate: Color::rgb(0x90, 0x98, 0xA8),
+    conveyor: Color::rgb(0x30, 0x38, 0x48),
+    checkpoint: Color::rgb(0xD0, 0x60, 0x50),
+    border: Color::rgb(0x30, 0x38, 0x48),
+
+    menu_bg: Color::rgb(0x08, 0x0A, 0x10),
+    text: Color::rgb(0xC0, 0xC8, 0xC0),
+    dim_text: Color::rgb(0x60, 0x68, 0x60),
+    selected: Color::rgb(0x50, 0xE0, 0x70),
+    bar: Color::rgb(0xF0, 0x50, 0x40),
+};
+
+const GAMEBOY: Palette = Palette {
+    bg: Color::rgb(0x9B, 0xBC, 0x0F),
+    head: Color::rgb(0x0F, 0x38, 0x0F),
+    tail: Color::rgb(0x30, 0x62, 0x30),
+    food: Color::rgb(0x30, 0x62, 0x30),
+    rotten_food: Color::rgb(0x8B, 0xAC, 0x0F),
+    golden_food: Color::rgb(0x0F, 0x38, 0x0F),
+    bomb: Color::rgb(0x0F, 0x38, 0x0F),
+    wall: Color::rgb(0x0F, 0x38, 0x0F),
+    portal: Color::rgb(0x8B, 0xAC, 0x0F),
+    mud: Color::rgb(0x30, 0x62, 0x30),
+    boost: Color::rgb(0x0F, 0x38, 0x0F),
+    gate: Color::rgb(0x0F, 0x38, 0x0F),
+    conveyor: Color::rgb(0x8B, 0xAC, 0x0F),
+    checkpoint: Color::rgb(0x30, 0x62, 0x30),
+    border: Color::rgb(0x30, 0x62, 0x30),
+
+    menu_bg: Color::rgb(0x0F, 0x38, 0x0F),
+    text: Color::rgb(0x9B, 0xBC, 0x0F),
+    dim_text: Color::rgb(0x8B, 0xAC, 0x0F),
+    selected: Color::rgb(0xE0, 0xF0, 0x80),
+    bar: Color::rgb(0x8B, 0xAC, 0x0F),
+};
+
+/// The most distinct colors for players with low vision.
+const HIGH_CONTRAST: Palette = Palette {
+    bg: Color::rgb(0x00, 0x00, 0x00),
+    head: Color::rgb(0xFF, 0xFF, 0x00),
+    tail: Color::rgb(0xFF, 0xFF, 0xFF),
+    food: Color::rgb(0x00, 0xFF, 0x00),
+    rotten_food: Color::rgb(0xFF, 0x80, 0x00),
+    golden_food: Color::rgb(0xFF, 0xFF, 0x00),
+    bomb: Color::rgb(0xFF, 0x00, 0x00),
+    wall: Color::rgb(0x80, 0x80, 0x80),
+    portal: Color::rgb(0xFF, 0x00, 0xFF),
+    mud: Color::rgb(0x80, 0x40, 0x00),
+    boost: Color::rgb(0x00, 0xFF, 0xFF),
+    gate: Color::rgb(0xC0, 0xC0, 0xC0),
+    conveyor: Color::rgb(0x00, 0x80, 0xFF),
+    checkpoint: Color::rgb(0xFF, 0x00, 0xFF),
+    border: Color::rgb(0xC0, 0xC0, 0xC0),
+
+    // Also around the board, where it has to stand out from the black of the board.
+    menu_bg: Color::rgb(0x38, 0x38, 0x38),
+    text: Color::rgb(0xFF, 0xFF, 0xFF),
+    dim_text: Color::rgb(0xC0, 0xC0, 0xC0),
+    selected: Color::rgb(0xFF, 0xFF, 0x00),
+    bar: Color::rgb(0x00, 0xFF, 0x00),
+};
+
+/// Head and tail colors of the opponents in a multiplayer game. The first snake uses the colors
+/// of the palette.
+const OPPONENTS: [(Color, Color); 7] = [
+    (Color::rgb(0xE8, 0x40, 0x40), Color::rgb(0xB8, 0x30, 0x30)),
+    (Color::rgb(0xF0, 0xC0, 0x20), Color::rgb(0xC0, 0x98, 0x18)),
+    (Color::rgb(0x30, 0xC0, 0x50), Color::rgb(0x28, 0x98, 0x40)),
+    (Color::rgb(0xF0, 0x80, 0x20), Color::rgb(0xC0, 0x68, 0x18)),
+    (Color::rgb(0xF0, 0xF0, 0xF0), Color::rgb(0xC0, 0xC0, 0xC0)),
+    (Color::rgb(0x20, 0x20, 0x20), Color::rgb(0x48, 0x48, 0x48)),
+    (Color::rgb(0xE0, 0x60, 0xC0), Color::rgb(0xB0, 0x48, 0x98)),
+];
+
+impl Palette {
+    /// Colors for the high-contrast mode, which replace those of any theme.
+    pub fn high_contrast() -> &'static Palette {
+        &HIGH_CONTRAST
+    }
+
+    /// Head and tail colors of the snake with the given index.
+    pub fn snake_colors(&self, snake: usize) -> (Color, Color) {
+        match snake {
+            0 => (self.head, self.tail),
+            _ => OPPONENTS[(snake - 1) % OPPONENTS.len()],
+        }
+    }
+
+    /// Replaces the color named like its field. Returns whether there is such a color.
+    pub fn set_color(&mut self, name: &str, color: Color) -> bool {
+        let field = match name {
+            "bg" => &mut self.bg,
+            "head" => &mut self.head,
+            "tail" => &mut self.tail,
+            "food" => &mut self.food,
+            "rotten_food" => &mut self.rotten_food,
+            "golden_food" => &mut self.golden_food,
+            "bomb" => &mut self.bomb,
+            "wall" => &mut self.wall,
+            "portal" => &mut self.portal,
+            "mud" => &mut self.mud,
+            "boost" => &mut self.boost,
+            "gate" => &mut self.gate,
+            "conveyor" => &mut self.conveyor,
+            "checkpoint" => &mut self.checkpoint,
+            "border" => &mut self.border,
+            "menu_bg" => &mut self.menu_bg,
+            "text" => &mut self.text,
+            "dim_text" => &mut self.dim_text,
+            "selected" => &mut self.selected,
+            "bar" => &mut self.bar,
+            _ => return false,
+        };
+        *field = color;
+        true
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Theme {
+    #[default]
+    Classic,
+    Night,
+    Gameboy,
+}
+
+impl Theme {
+    pub fn palette(self) -> &'static Palette {
+        match self {
+            Theme::Classic => &CLASSIC,
+            Theme::Night => &NIGHT,
+            Theme::Gameboy => &GAMEBOY,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Theme::Classic => "CLASSIC",
+            Theme::Night => "NIGHT",
+            Theme::Gameboy => "GAMEBOY",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            Theme::Classic => Theme::Night,
+            Theme::Night => Theme::Gameboy,
+            Theme::Gameboy => Theme::Classic,
+        }
+    }
+}
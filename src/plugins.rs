@@ -0,0 +1,239 @@
+//! Game mods compiled to WebAssembly, loaded from `plugins/<name>.wasm` in the data directory.
+//! They run sandboxed: a plugin sees nothing of the game but the functions below, and every call
+//! into it gets a limited amount of fuel, so that a broken plugin can't hang the game. The file
+//! is read when the game starts, so a rebuilt plugin is picked up without restarting.
+//!
+//! A plugin exports `snake_abi_version() -> i32`, which has to return `ABI_VERSION`, and any of
+//!
+//! - `on_start()` before the first tick and `on_tick(tick: i64)` after every tick,
+//! - `on_food_eaten(snake: i32, x: i32, y: i32)`,
+//! - `on_food_spawned(x: i32, y: i32)` when food appears on its own,
+//! - `on_death(snake: i32, reason: i32)`, with the reason numbered as in `reason_code`,
+//! - `food_value(x: i32, y: i32) -> i32`, the points for the player eating that food.
+//!
+//! It may import from the module `snake`:
+//!
+//! - `width() -> i32` and `height() -> i32`, the size of the board,
+//! - `tile(x: i32, y: i32) -> i32`, one of the `TILE_*` codes,
+//! - `spawn_food(x: i32, y: i32) -> i32` and `remove_food(x: i32, y: i32) -> i32`,
+//! - `set_tile(x: i32, y: i32, tile: i32) -> i32` with `TILE_EMPTY` or `TILE_WALL`.
+//!
+//! The last three return 1 if the board changed and 0 otherwise, following the rules of
+//! `World::place_food`, `World::remove_food` and `World::set_wall`. Cells are counted from 0 at
+//! the bottom left, and snakes from 0 with the player's first.
+
+use crate::storage;
+use log::{error, info};
+use snake_pixels::grid::{Tile, Vec2};
+use snake_pixels::hooks::Hooks;
+use snake_pixels::telemetry::EndReason;
+use snake_pixels::world::{SnakeId, World};
+use std::path::Path;
+use wasmtime::{Caller, Config, Engine, Instance, Linker, Module, Store, WasmParams, WasmResults};
+
+/// Version of the interface between the game and the plugins. It changes whenever a function
+/// that plugins rely on changes its meaning or signature.
+pub const ABI_VERSION: i32 = 1;
+
+pub const TILE_OUTSIDE: i32 = -1;
+pub const TILE_EMPTY: i32 = 0;
+pub const TILE_SNAKE: i32 = 1;
+pub const TILE_FOOD: i32 = 2;
+pub const TILE_WALL: i32 = 3;
+pub const TILE_PORTAL: i32 = 4;
+pub const TILE_ROTTEN_FOOD: i32 = 5;
+pub const TILE_GOLDEN_FOOD: i32 = 6;
+pub const TILE_BOMB: i32 = 7;
+
+const PLUGINS_DIR: &str = "plugins";
+const EXTENSION: &str = "wasm";
+/// Fuel for a single call into a plugin, roughly the number of instructions it may run.
+const FUEL: u64 = 1_000_000;
+
+fn tile_code(tile: Option<Tile>) -> i32 {
+    match tile {
+        None => TILE_OUTSIDE,
+        Some(Tile::Empty) => TILE_EMPTY,
+        Some(Tile::Snake) => TILE_SNAKE,
+        Some(Tile::Food) => TILE_FOOD,
+        Some(Tile::Wall) => TILE_WALL,
+        Some(Tile::Portal) => TILE_PORTAL,
+        Some(Tile::RottenFood) => TILE_ROTTEN_FOOD,
+        Some(Tile::GoldenFood) => TILE_GOLDEN_FOOD,
+        Some(Tile::Bomb) => TILE_BOMB,
+    }
+}
+
+fn reason_code(reason: EndReason) -> i32 {
+    match reason {
+        EndReason::Wall => 0,
+        EndReason::Tail => 1,
+        EndReason::Snake => 2,
+        EndReason::BoardFull => 3,
+        EndReason::Quit => 4,
+        EndReason::TimeUp => 5,
+    }
+}
+
+/// State seen by the functions that the game gives to the plugin. The world is only there while
+/// a function of the plugin runs.
+struct Host {
+    world: Option<World>,
+}
+
+/// Applies a change to the world on behalf of the plugin. Returns 1 if the board changed.
+fn change(mut caller: Caller<'_, Host>, change: impl FnOnce(&mut World) -> bool) -> i32 {
+    match caller.data_mut().world.as_mut() {
+        Some(world) => change(world) as i32,
+        None => 0,
+    }
+}
+
+fn link(linker: &mut Linker<Host>) -> wasmtime::Result<()> {
+    linker.func_wrap("snake", "width", |caller: Caller<'_, Host>| {
+        caller.data().world.as_ref().map_or(0, World::width)
+    })?;
+    linker.func_wrap("snake", "height", |caller: Caller<'_, Host>| {
+        caller.data().world.as_ref().map_or(0, World::height)
+    })?;
+    linker.func_wrap(
+        "snake",
+        "tile",
+        |caller: Caller<'_, Host>, x: i32, y: i32| {
+            tile_code(
+                caller
+                    .data()
+                    .world
+                    .as_ref()
+                    .and_then(|w| w.tile(Vec2(x, y))),
+            )
+        },
+    )?;
+    linker.func_wrap(
+        "snake",
+        "spawn_food",
+        |caller: Caller<'_, Host>, x: i32, y: i32| change(caller, |w| w.place_food(Vec2(x, y))),
+    )?;
+    linker.func_wrap(
+        "snake",
+        "remove_food",
+        |caller: Caller<'_, Host>, x: i32, y: i32| change(caller, |w| w.remove_food(Vec2(x, y))),
+    )?;
+    linker.func_wrap(
+        "snake",
+        "set_tile",
+        |caller: Caller<'_, Host>, x: i32, y: i32, tile: i32| match tile {
+            TILE_EMPTY => change(caller, |w| w.set_wall(Vec2(x, y), false)),
+            TILE_WALL => change(caller, |w| w.set_wall(Vec2(x, y), true)),
+            _ => 0,
+        },
+    )?;
+    Ok(())
+}
+
+pub struct Plugin {
+    name: String,
+    store: Store<Host>,
+    instance: Instance,
+    /// Set after the first error, so that a broken plugin doesn't flood the log on every tick.
+    failed: bool,
+}
+
+impl Plugin {
+    /// Names of the installed plugins.
+    pub fn list() -> Vec<String> {
+        storage::list_files(PLUGINS_DIR, EXTENSION)
+    }
+
+    /// Compiles the plugin and checks that it was built for this version of the game.
+    pub fn load(name: &str) -> Result<Self, String> {
+        let path = Path::new(PLUGINS_DIR).join(format!("{}.{}", name, EXTENSION));
+        let bytes =
+            storage::read_bytes(&path).ok_or_else(|| format!("{} not found", path.display()))?;
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config).map_err(|e| e.to_string())?;
+        let module = Module::new(&engine, &bytes).map_err(|e| e.to_string())?;
+        let mut linker = Linker::new(&engine);
+        link(&mut linker).map_err(|e| e.to_string())?;
+        let mut store = Store::new(&engine, Host { world: None });
+        store.set_fuel(FUEL).map_err(|e| e.to_string())?;
+        let instance = linker
+            .instantiate(&mut store, &module)
+            .map_err(|e| e.to_string())?;
+        let version = instance
+            .get_typed_func::<(), i32>(&mut store, "snake_abi_version")
+            .and_then(|version| version.call(&mut store, ()))
+            .map_err(|e| format!("no ABI version: {}", e))?;
+        if version != ABI_VERSION {
+            return Err(format!(
+                "built for ABI version {}, the game has {}",
+                version, ABI_VERSION
+            ));
+        }
+        info!("Loaded plugin {}", name);
+        Ok(Plugin {
+            name: name.to_string(),
+            store,
+            instance,
+            failed: false,
+        })
+    }
+
+    /// Calls a function of the plugin if it is exported, giving it access to a copy of the world
+    /// that replaces the world once the call returns.
+    fn call<P, R>(&mut self, world: &mut World, function: &str, params: P) -> Option<R>
+    where
+        P: WasmParams,
+        R: WasmResults,
+    {
+        if self.failed {
+            return None;
+        }
+        let func = self.instance.get_func(&mut self.store, function)?;
+        self.store.data_mut().world = Some(world.clone());
+        let result = func.typed::<P, R>(&self.store).and_then(|func| {
+            self.store.set_fuel(FUEL)?;
+            func.call(&mut self.store, params)
+        });
+        if let Some(changed) = self.store.data_mut().world.take() {
+            *world = changed;
+        }
+        match result {
+            Ok(result) => Some(result),
+            Err(e) => {
+                error!("Plugin {} failed in {}: {}", self.name, function, e);
+                self.failed = true;
+                None
+            }
+        }
+    }
+}
+
+impl Hooks for Plugin {
+    fn on_start(&mut self, world: &mut World) {
+        self.call::<(), ()>(world, "on_start", ());
+    }
+
+    fn on_tick(&mut self, world: &mut World) {
+        let tick = world.tick() as i64;
+        self.call::<i64, ()>(world, "on_tick", tick);
+    }
+
+    fn on_food_eaten(&mut self, world: &mut World, snake: SnakeId, pos: Vec2) {
+        self.call::<(i32, i32, i32), ()>(world, "on_food_eaten", (snake as i32, pos.0, pos.1));
+    }
+
+    fn on_food_spawned(&mut self, world: &mut World, pos: Vec2) {
+        self.call::<(i32, i32), ()>(world, "on_food_spawned", (pos.0, pos.1));
+    }
+
+    fn food_value(&mut self, world: &mut World, pos: Vec2) -> u32 {
+        self.call::<(i32, i32), i32>(world, "food_value", (pos.0, pos.1))
+            .map_or(1, |points| points.max(0) as u32)
+    }
+
+    fn on_death(&mut self, world: &mut World, snake: SnakeId, reason: EndReason) {
+        self.call::<(i32, i32), ()>(world, "on_death", (snake as i32, reason_code(reason)));
+    }
+}
@@ -0,0 +1,72 @@
+//! Named rulesets of custom games, so that house rules can be passed around: TOML files in
+//! `rulesets/` inside the data directory with the mode and the rules of `Ruleset`, any of which
+//! can be left out for the standard one, e.g. `rulesets/MAZE.toml`:
+//!
+//! ```toml
+//! mode = "territory"
+//! obstacles = 15
+//! speed = "rising"
+//! bombs = false
+//!
+//! [handicap]
+//! reaction_delay = 2
+//! ```
+
+use crate::storage;
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use snake_pixels::config::Mode;
+use snake_pixels::ruleset::Ruleset;
+use std::path::PathBuf;
+
+const RULESETS_DIR: &str = "rulesets";
+const EXTENSION: &str = "toml";
+pub const MAX_NAME_LEN: usize = 8;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Preset {
+    pub mode: Mode,
+    #[serde(flatten)]
+    pub rules: Ruleset,
+}
+
+fn path(name: &str) -> PathBuf {
+    PathBuf::from(RULESETS_DIR).join(format!("{}.{}", name, EXTENSION))
+}
+
+/// Names of the saved rulesets.
+pub fn list() -> Vec<String> {
+    storage::list_files(RULESETS_DIR, EXTENSION)
+}
+
+/// Loads a saved ruleset by its name, the file name without the extension.
+pub fn load(name: &str) -> Result<Preset, String> {
+    // The name comes from the command line, and shouldn't lead out of the directory.
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        return Err(format!("Invalid name of a ruleset: {:?}", name));
+    }
+    let text = storage::read_text(path(name)).ok_or_else(|| format!("No ruleset {}", name))?;
+    let preset: Preset = toml::from_str(&text).map_err(|e| e.to_string())?;
+    preset.rules.validate()?;
+    Ok(preset)
+}
+
+/// Saves the ruleset under the name. Returns whether it succeeded, logging any errors.
+pub fn save(name: &str, preset: &Preset) -> bool {
+    let text = match toml::to_string(preset) {
+        Ok(text) => text,
+        Err(e) => {
+            error!("Failed to write ruleset {}: {}", name, e);
+            return false;
+        }
+    };
+    let saved = storage::write_text(path(name), &text);
+    if saved {
+        info!("Saved ruleset {}", name);
+    }
+    saved
+}
@@ -0,0 +1,221 @@
+use crate::achievements::Achievement;
+use crate::audio::AudioSettings;
+use crate::bindings::KeyBindings;
+#[cfg(feature = "chat")]
+use crate::chat::ChatSettings;
+use crate::i18n::{tr, tr_fmt, Language};
+use crate::speedrun::BestSplits;
+use crate::stats::LifetimeStats;
+use crate::storage;
+use log::info;
+use serde::{Deserialize, Serialize};
+use snake_pixels::campaign::PackProgress;
+use snake_pixels::config::{Difficulty, Mode};
+use snake_pixels::palette::Theme;
+use snake_pixels::ruleset::Ruleset;
+use snake_pixels::skin::Skin;
+use snake_pixels::telemetry::{self, ExportFormat, GameRecord};
+use snake_pixels::topology::Shape;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+
+pub const DEFAULT_NAME: &str = "PLAYER";
+pub const MAX_NAME_LEN: usize = 8;
+pub const MAX_UI_SCALE: u32 = 6;
+
+const PROFILES_DIR: &str = "profiles";
+const INDEX_FILE: &str = "profiles.json";
+
+/// Per-profile preferences.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub mode: Mode,
+    pub difficulty: Difficulty,
+    /// Shape of the cells of the board in the endless games.
+    pub grid: Shape,
+    /// Number of layers of the board in the endless games, see `Config::layers`.
+    pub layers: u32,
+    pub theme: Theme,
+    /// Maximum-contrast colors instead of those of the theme, with larger cells set apart by
+    /// thick borders.
+    pub high_contrast: bool,
+    /// Replaces the animations with still pictures, or leaves them out where they are only for
+    /// show, for players who are sensitive to motion.
+    pub reduced_motion: bool,
+    /// Tints the cells where the snake would crash on the next tick.
+    pub danger_zones: bool,
+    /// Leaves the board clear of text while playing, with the score shown in the title of the
+    /// window instead.
+    pub minimal_hud: bool,
+    /// Pauses the game while the window is minimized or covered by other windows.
+    pub pause_hidden: bool,
+    /// Makes the snake sprint while the key of the direction in which it moves is held.
+    pub hold_to_sprint: bool,
+    /// Thickness in cells of the frame around the board, or 0 for none.
+    pub border: u32,
+    /// Rules of the custom games by their mode, as last set on the custom game screen.
+    pub rules: BTreeMap<Mode, Ruleset>,
+    /// Shows the game time of the game, with the splits of the levels in a campaign run.
+    pub speedrun_timer: bool,
+    /// How the player's snake looks, also to the others in multiplayer.
+    pub skin: Skin,
+    /// Whether the tutorial has been played, after which PLAY starts a normal game.
+    pub tutorial_done: bool,
+    /// Size of a pixel of the game in logical pixels of the window, from 1 to `MAX_UI_SCALE`.
+    pub ui_scale: u32,
+    pub key_bindings: KeyBindings,
+    pub language: Language,
+    pub audio: AudioSettings,
+    #[cfg(feature = "chat")]
+    pub chat: ChatSettings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            mode: Mode::default(),
+            difficulty: Difficulty::default(),
+            grid: Shape::default(),
+            layers: 1,
+            theme: Theme::default(),
+            high_contrast: false,
+            reduced_motion: false,
+            danger_zones: false,
+            minimal_hud: false,
+            pause_hidden: true,
+            hold_to_sprint: false,
+            border: 1,
+            rules: BTreeMap::new(),
+            speedrun_timer: false,
+            skin: Skin::default(),
+            tutorial_done: false,
+            ui_scale: 3,
+            key_bindings: KeyBindings::default(),
+            language: Language::default(),
+            audio: AudioSettings::default(),
+            #[cfg(feature = "chat")]
+            chat: ChatSettings::default(),
+        }
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct ProfileIndex {
+    last: Option<String>,
+}
+
+/// A named player with their own settings, statistics, history of games, achievements,
+/// campaign progress and best splits, stored in `profiles/<name>/` inside the data directory.
+pub struct Profile {
+    name: String,
+    pub settings: Settings,
+    pub stats: LifetimeStats,
+    /// Every finished game, oldest first.
+    pub history: Vec<GameRecord>,
+    pub achievements: BTreeSet<Achievement>,
+    /// Progress through the campaign packs by the directory of the pack.
+    pub campaigns: BTreeMap<String, PackProgress>,
+    pub splits: BestSplits,
+}
+
+impl Profile {
+    /// Profile names double as directory names, so only uppercase letters and digits are allowed.
+    pub fn is_valid_name(name: &str) -> bool {
+        !name.is_empty()
+            && name.len() <= MAX_NAME_LEN
+            && name
+                .chars()
+                .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+    }
+
+    pub fn list() -> Vec<String> {
+        storage::list_dirs(PROFILES_DIR)
+            .into_iter()
+            .filter(|name| Profile::is_valid_name(name))
+            .collect()
+    }
+
+    /// Loads the profile, creating it if it doesn't exist, and remembers it as the last used one.
+    pub fn load(name: &str) -> Self {
+        info!("Loading profile {}", name);
+        let mut profile = Profile {
+            name: name.to_string(),
+            settings: Settings::default(),
+            stats: LifetimeStats::default(),
+            history: Vec::new(),
+            achievements: BTreeSet::new(),
+            campaigns: BTreeMap::new(),
+            splits: BestSplits::new(),
+        };
+        profile.settings = storage::load(profile.path("settings.json"));
+        profile.stats = storage::load(profile.path("stats.json"));
+        profile.history = storage::load(profile.path("history.json"));
+        profile.achievements = storage::load(profile.path("achievements.json"));
+        profile.campaigns = storage::load(profile.path("campaigns.json"));
+        profile.splits = storage::load(profile.path("splits.json"));
+        profile.save();
+        storage::save(
+            INDEX_FILE,
+            &ProfileIndex {
+                last: Some(name.to_string()),
+            },
+        );
+        profile
+    }
+
+    /// Loads the profile used most recently.
+    pub fn load_last() -> Self {
+        let index: ProfileIndex = storage::load(INDEX_FILE);
+        match index.last {
+            Some(name) if Profile::is_valid_name(&name) => Profile::load(&name),
+            _ => Profile::load(DEFAULT_NAME),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn path(&self, file: &str) -> PathBuf {
+        [PROFILES_DIR, &self.name, file].iter().collect()
+    }
+
+    pub fn save(&self) {
+        storage::save(self.path("settings.json"), &self.settings);
+        storage::save(self.path("stats.json"), &self.stats);
+        storage::save(self.path("history.json"), &self.history);
+        storage::save(self.path("achievements.json"), &self.achievements);
+        storage::save(self.path("campaigns.json"), &self.campaigns);
+        storage::save(self.path("splits.json"), &self.splits);
+    }
+
+    /// Writes the history of games as CSV to `history.csv` in the directory of the profile and
+    /// returns a line about it to show.
+    pub fn export_history(&self) -> String {
+        let text = telemetry::export(&self.history, ExportFormat::Csv);
+        if storage::write_text(self.path("history.csv"), &text) {
+            tr_fmt("EXPORTED {} GAMES", &[&self.history.len()])
+        } else {
+            tr("EXPORT FAILED")
+        }
+    }
+
+    /// Adds a finished game to the statistics and returns the newly unlocked achievements.
+    pub fn record_game(&mut self, record: &GameRecord) -> Vec<Achievement> {
+        self.stats.add(record);
+        self.history.push(record.clone());
+        let unlocked: Vec<Achievement> = Achievement::ALL
+            .iter()
+            .copied()
+            .filter(|a| !self.achievements.contains(a) && a.is_earned(&self.stats, record))
+            .collect();
+        for &achievement in unlocked.iter() {
+            info!("Achievement unlocked: {}", achievement.name());
+            self.achievements.insert(achievement);
+        }
+        self.save();
+        unlocked
+    }
+}
@@ -0,0 +1,267 @@
+//! Overlay with the time spent on every frame, toggled with F9. The time is split into updating
+//! the game, drawing into the frame buffer and presenting it, which in a window includes the
+//! upload to the GPU. The updates since the previous frame count towards the next one.
+//!
+//! Below the latest frames are histograms of all the frames and of how late the ticks of the game
+//! came after they were due, which `report` sums up for `--perf-report`.
+
+use snake_pixels::font::{draw_text, text_width, LINE_HEIGHT};
+use snake_pixels::framebuffer::{Color, FrameBuffer};
+use snake_pixels::palette::Palette;
+use std::collections::VecDeque;
+use std::time::Duration;
+use winit::keyboard::KeyCode;
+
+pub const TOGGLE_KEY: KeyCode = KeyCode::F9;
+
+/// Number of the latest frames that the averages and the graph cover.
+const FRAMES: usize = 60;
+
+/// Height of the graph in pixels, one for each millisecond of a frame.
+const GRAPH_HEIGHT: i32 = 20;
+
+/// Milliseconds of a frame at 60 FPS, marked on the graph.
+const BUDGET_MS: i32 = 16;
+
+/// Number of buckets of a histogram, a millisecond each. The last one also takes everything
+/// longer.
+const BUCKETS: usize = 32;
+
+/// Height of a histogram in pixels, reached by its largest bucket.
+const HISTOGRAM_HEIGHT: i32 = 8;
+
+/// Opacity of the background behind the overlay.
+const BACKGROUND_ALPHA: u8 = 200;
+
+#[derive(Clone, Copy, Default)]
+struct Frame {
+    update: Duration,
+    draw: Duration,
+    present: Duration,
+}
+
+impl Frame {
+    fn total(&self) -> Duration {
+        self.update + self.draw + self.present
+    }
+}
+
+/// A line of the overlay: its name, its part of a frame and its color.
+type Part = (&'static str, fn(&Frame) -> Duration, Color);
+
+fn ms(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// Counts of durations by the whole milliseconds in them.
+#[derive(Default)]
+pub struct Histogram {
+    buckets: [u64; BUCKETS],
+    count: u64,
+    total: Duration,
+    max: Duration,
+}
+
+impl Histogram {
+    pub fn add(&mut self, duration: Duration) {
+        let bucket = (duration.as_millis() as usize).min(BUCKETS - 1);
+        self.buckets[bucket] += 1;
+        self.count += 1;
+        self.total += duration;
+        self.max = self.max.max(duration);
+    }
+
+    pub fn mean(&self) -> Duration {
+        self.total / self.count.max(1) as u32
+    }
+
+    /// The end of the bucket below which the fraction `p` of the durations are, or the longest
+    /// duration if that is in the last bucket.
+    pub fn percentile(&self, p: f64) -> Duration {
+        let wanted = (self.count as f64 * p).ceil() as u64;
+        let mut seen = 0;
+        for (bucket, &count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= wanted.max(1) && bucket < BUCKETS - 1 {
+                return Duration::from_millis(bucket as u64 + 1).min(self.max);
+            }
+        }
+        self.max
+    }
+
+    /// A line with the mean, the percentiles and the longest duration, followed by a line for
+    /// every bucket that isn't empty.
+    fn report(&self, name: &str) -> String {
+        let mut report = format!(
+            "{}: {} samples, mean {:.2} ms, p50 {:.2} ms, p90 {:.2} ms, p99 {:.2} ms, max {:.2} ms\n",
+            name,
+            self.count,
+            ms(self.mean()),
+            ms(self.percentile(0.5)),
+            ms(self.percentile(0.9)),
+            ms(self.percentile(0.99)),
+            ms(self.max),
+        );
+        for (bucket, &count) in self.buckets.iter().enumerate() {
+            if count == 0 {
+                continue;
+            }
+            let range = if bucket == BUCKETS - 1 {
+                format!("{}+ ms", bucket)
+            } else {
+                format!("{}-{} ms", bucket, bucket + 1)
+            };
+            let share = 100.0 * count as f64 / self.count as f64;
+            report += &format!("  {:>8} {:>8} {:>6.2}%\n", range, count, share);
+        }
+        report
+    }
+
+    /// Draws a column for every bucket, from the shortest on the left, with the bottom left corner
+    /// at `(x, y)`. The columns are scaled logarithmically, so that the rare long durations,
+    /// which are the point, stay visible.
+    fn render(&self, buffer: &mut FrameBuffer, x: i32, y: i32, color: Color) {
+        let log = |count: u64| ((count + 1) as f64).ln();
+        let max = log(self.buckets.iter().copied().max().unwrap_or(0)).max(f64::EPSILON);
+        for (bucket, &count) in self.buckets.iter().enumerate() {
+            let height = (log(count) / max * HISTOGRAM_HEIGHT as f64).ceil() as usize;
+            buffer.fill_rectangle(x + bucket as i32, y, 1, height, color);
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Profiler {
+    /// The latest frames, oldest first.
+    frames: VecDeque<Frame>,
+    /// Time spent on the updates since the last frame.
+    update: Duration,
+    /// Total times of all the frames.
+    frame_times: Histogram,
+    /// How long after they were due the ticks came.
+    tick_latency: Histogram,
+}
+
+impl Profiler {
+    pub fn add_update(&mut self, duration: Duration) {
+        self.update += duration;
+    }
+
+    /// Records a tick of the game that came `latency` after it was due.
+    pub fn add_tick_latency(&mut self, latency: Duration) {
+        self.tick_latency.add(latency);
+    }
+
+    /// Records a frame that took `draw` to draw and `present` to show, together with the updates
+    /// since the previous one.
+    pub fn add_frame(&mut self, draw: Duration, present: Duration) {
+        if self.frames.len() == FRAMES {
+            self.frames.pop_front();
+        }
+        let frame = Frame {
+            update: std::mem::take(&mut self.update),
+            draw,
+            present,
+        };
+        self.frame_times.add(frame.total());
+        self.frames.push_back(frame);
+    }
+
+    /// Summary of the histograms of the frame times and the tick latency, to be written to a
+    /// file on exit.
+    pub fn report(&self) -> String {
+        format!(
+            "{}\n{}",
+            self.frame_times.report("Frame time"),
+            self.tick_latency.report("Tick latency"),
+        )
+    }
+
+    fn average(&self, part: fn(&Frame) -> Duration) -> Duration {
+        let total: Duration = self.frames.iter().map(part).sum();
+        total / self.frames.len().max(1) as u32
+    }
+
+    /// Draws the averages over the latest frames in the top right corner, with a graph of the
+    /// frames and the histograms below them.
+    pub fn render(&self, buffer: &mut FrameBuffer, palette: &Palette) {
+        let parts: [Part; 4] = [
+            ("FRAME", Frame::total, palette.text),
+            ("UPDATE", |f| f.update, palette.food),
+            ("DRAW", |f| f.draw, palette.head),
+            ("PRESENT", |f| f.present, palette.portal),
+        ];
+        let lines: Vec<(String, Color)> = parts
+            .iter()
+            .map(|&(name, part, color)| {
+                let ms = self.average(part).as_secs_f64() * 1000.0;
+                (format!("{} {:.2} MS", name, ms), color)
+            })
+            .collect();
+        let histograms = [
+            ("FRAME", &self.frame_times, palette.text),
+            ("TICK LATE", &self.tick_latency, palette.food),
+        ];
+        let captions: Vec<String> = histograms
+            .iter()
+            .map(|&(name, histogram, _)| {
+                format!("{} P99 {:.1} MS", name, ms(histogram.percentile(0.99)))
+            })
+            .collect();
+        let width = lines
+            .iter()
+            .map(|(line, _)| line)
+            .chain(captions.iter())
+            .map(|line| text_width(line))
+            .max()
+            .unwrap_or(0)
+            .max(FRAMES as i32);
+        let histogram_height = LINE_HEIGHT + HISTOGRAM_HEIGHT + 1;
+        let height = lines.len() as i32 * LINE_HEIGHT
+            + GRAPH_HEIGHT
+            + 2
+            + histograms.len() as i32 * histogram_height;
+        let left = buffer.width() as i32 - width - 2;
+        let top = buffer.height() as i32;
+        buffer.blend_rectangle(
+            left - 1,
+            top - height - 1,
+            width as usize + 3,
+            height as usize + 1,
+            palette.menu_bg,
+            BACKGROUND_ALPHA,
+        );
+        for (i, (line, color)) in lines.iter().enumerate() {
+            draw_text(
+                buffer,
+                left,
+                top - 1 - i as i32 * LINE_HEIGHT,
+                line,
+                1,
+                *color,
+            );
+        }
+
+        // A column for every frame, stacked in the order of the lines, newest on the right.
+        let bottom = top - height + histograms.len() as i32 * histogram_height;
+        let right = left + width;
+        for (i, frame) in self.frames.iter().rev().enumerate() {
+            let x = right - 1 - i as i32;
+            let mut y = bottom;
+            for &(_, part, color) in parts[1..].iter() {
+                let pixels = (part(frame).as_secs_f64() * 1000.0).round() as i32;
+                let pixels = pixels.min(bottom + GRAPH_HEIGHT - y);
+                buffer.fill_rectangle(x, y, 1, pixels.max(0) as usize, color);
+                y += pixels.max(0);
+            }
+        }
+        let budget = bottom + BUDGET_MS;
+        buffer.blend_rectangle(left, budget, width as usize, 1, palette.dim_text, 128);
+
+        for (i, (&(_, histogram, color), caption)) in histograms.iter().zip(&captions).enumerate() {
+            let y = bottom - 1 - i as i32 * histogram_height;
+            draw_text(buffer, left, y, caption, 1, color);
+            histogram.render(buffer, left, y - histogram_height + 1, color);
+        }
+    }
+}
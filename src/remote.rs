@@ -0,0 +1,151 @@
+//! Control of the game by other programs, such as scripts, bots and accessibility tools, over the
+//! standard input and output when the game runs with `--remote`. Every line of the input is a
+//! command, answered with a line of output:
+//!
+//! - `turn up`, `turn down`, `turn left` or `turn right` turns the player's snake,
+//! - `start` starts a game from the title screen,
+//! - `pause` and `resume` stop and continue the game,
+//! - `screenshot FILE` saves the next frame to the file as a PNG image,
+//! - `state?` describes the game in progress as JSON.
+//!
+//! The answer is `ok`, `error: ` followed by the reason, or the JSON of `state?`.
+
+use log::{info, warn};
+use serde::Serialize;
+use snake_pixels::controller::Snapshot;
+use snake_pixels::framebuffer::FrameBuffer;
+use snake_pixels::grid::Direction;
+use std::fs::File;
+use std::io::{BufRead, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RemoteCommand {
+    Turn(Direction),
+    Start,
+    Pause,
+    Resume,
+    Screenshot(PathBuf),
+    State,
+}
+
+impl RemoteCommand {
+    /// Parses a line of the input. The commands and the directions are case-insensitive.
+    pub fn parse(line: &str) -> Result<RemoteCommand, String> {
+        let mut words = line.split_whitespace();
+        let command = words.next().unwrap_or_default().to_lowercase();
+        let argument = words.next();
+        if words.next().is_some() {
+            return Err(format!("too many arguments to {}", command));
+        }
+        let command = match (command.as_str(), argument) {
+            ("turn", Some(direction)) => RemoteCommand::Turn(parse_direction(direction)?),
+            ("screenshot", Some(file)) => RemoteCommand::Screenshot(PathBuf::from(file)),
+            ("start", None) => RemoteCommand::Start,
+            ("pause", None) => RemoteCommand::Pause,
+            ("resume", None) => RemoteCommand::Resume,
+            ("state?", None) => RemoteCommand::State,
+            ("turn" | "screenshot", None) => return Err(format!("{} needs an argument", command)),
+            ("start" | "pause" | "resume" | "state?", Some(_)) => {
+                return Err(format!("{} takes no arguments", command))
+            }
+            ("", _) => return Err("empty command".to_string()),
+            _ => return Err(format!("unknown command {}", command)),
+        };
+        Ok(command)
+    }
+}
+
+fn parse_direction(word: &str) -> Result<Direction, String> {
+    match word.to_lowercase().as_str() {
+        "up" => Ok(Direction::Up),
+        "down" => Ok(Direction::Down),
+        "left" => Ok(Direction::Left),
+        "right" => Ok(Direction::Right),
+        _ => Err(format!("unknown direction {}", word)),
+    }
+}
+
+/// What `state?` answers with.
+#[derive(Clone, Debug, Serialize)]
+pub struct GameState {
+    /// `menu`, `playing` or `other` for the rest of the screens.
+    pub screen: &'static str,
+    pub paused: bool,
+    pub over: bool,
+    /// The game in progress as seen by the player's snake.
+    pub game: Option<Snapshot>,
+}
+
+/// The commands read from the standard input by a background thread.
+pub struct Remote {
+    commands: Receiver<String>,
+}
+
+impl Remote {
+    pub fn new() -> Self {
+        let (sender, commands) = mpsc::channel();
+        std::thread::spawn(move || read_commands(sender));
+        info!("Taking commands from the standard input");
+        Remote { commands }
+    }
+
+    /// The commands that arrived since the last call, each parsed or with the reason why it
+    /// couldn't be.
+    pub fn commands(&self) -> Vec<Result<RemoteCommand, String>> {
+        self.commands
+            .try_iter()
+            .map(|line| RemoteCommand::parse(&line))
+            .collect()
+    }
+
+    pub fn reply(&self, answer: Result<String, String>) {
+        let line = match answer {
+            Ok(text) => text,
+            Err(reason) => format!("error: {}", reason),
+        };
+        let mut stdout = std::io::stdout().lock();
+        if let Err(e) = writeln!(stdout, "{}", line).and_then(|_| stdout.flush()) {
+            warn!("Failed to answer a remote command: {}", e);
+        }
+    }
+
+    pub fn reply_state(&self, state: &GameState) {
+        self.reply(serde_json::to_string(state).map_err(|e| e.to_string()));
+    }
+}
+
+fn read_commands(sender: Sender<String>) {
+    for line in std::io::stdin().lock().lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => {
+                warn!("Failed to read a remote command: {}", e);
+                break;
+            }
+        };
+        if sender.send(line).is_err() {
+            break;
+        }
+    }
+    info!("The standard input is closed");
+}
+
+/// Saves the frame as a PNG image.
+pub fn save_screenshot(path: &Path, buffer: &FrameBuffer) -> Result<(), String> {
+    let mut rgba = vec![0; buffer.width() * buffer.height() * 4];
+    buffer.copy_to(&mut rgba);
+    let file = File::create(path).map_err(|e| e.to_string())?;
+    let mut encoder = png::Encoder::new(
+        BufWriter::new(file),
+        buffer.width() as u32,
+        buffer.height() as u32,
+    );
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(&rgba))
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,112 @@
+//! Destinations for the frames drawn into a `FrameBuffer`: a window, a terminal, memory or
+//! nothing at all.
+
+use crate::framebuffer::{Color, Fill, FrameBuffer};
+use std::error::Error;
+
+pub trait Renderer {
+    /// The buffer to draw the next frame into.
+    fn buffer_mut(&mut self) -> &mut FrameBuffer;
+
+    /// The buffer together with the quads that the renderer draws over it on the GPU, for the
+    /// renderers that can. The cells of the board can go there instead of into the buffer.
+    fn layers_mut(&mut self) -> (&mut FrameBuffer, Option<&mut Vec<Quad>>) {
+        (self.buffer_mut(), None)
+    }
+
+    /// Shows the frame drawn into the buffer. An error means that nothing can be shown anymore.
+    fn present(&mut self) -> Result<(), Box<dyn Error>>;
+}
+
+/// A rectangle of a solid color in the coordinates of the buffer. They are fractional, so that
+/// the GPU can draw the cells between the pixels of the buffer.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Quad {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub color: Color,
+}
+
+impl Fill for Vec<Quad> {
+    fn fill_rectangle(&mut self, x0: i32, y0: i32, w: usize, h: usize, color: Color) {
+        self.push(Quad {
+            x: x0 as f32,
+            y: y0 as f32,
+            width: w as f32,
+            height: h as f32,
+            color,
+        });
+    }
+}
+
+/// Draws the frames without showing them anywhere, for running without a display.
+pub struct NullRenderer {
+    buffer: FrameBuffer,
+    frames: u64,
+}
+
+impl NullRenderer {
+    pub fn new(width: usize, height: usize) -> Self {
+        NullRenderer {
+            buffer: FrameBuffer::new(width, height),
+            frames: 0,
+        }
+    }
+
+    /// Number of frames presented so far.
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+}
+
+impl Renderer for NullRenderer {
+    fn buffer_mut(&mut self) -> &mut FrameBuffer {
+        &mut self.buffer
+    }
+
+    fn present(&mut self) -> Result<(), Box<dyn Error>> {
+        self.frames += 1;
+        Ok(())
+    }
+}
+
+/// Keeps the last presented frame in memory, e.g. to compare it with a reference image.
+pub struct OffscreenRenderer {
+    buffer: FrameBuffer,
+    frame: Vec<u32>,
+}
+
+impl OffscreenRenderer {
+    pub fn new(width: usize, height: usize) -> Self {
+        OffscreenRenderer {
+            buffer: FrameBuffer::new(width, height),
+            frame: vec![0; width * height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.buffer.width()
+    }
+
+    pub fn height(&self) -> usize {
+        self.buffer.height()
+    }
+
+    /// The pixels of the last presented frame in the order of `FrameBuffer::pixels`.
+    pub fn frame(&self) -> &[u32] {
+        &self.frame
+    }
+}
+
+impl Renderer for OffscreenRenderer {
+    fn buffer_mut(&mut self) -> &mut FrameBuffer {
+        &mut self.buffer
+    }
+
+    fn present(&mut self) -> Result<(), Box<dyn Error>> {
+        self.frame.copy_from_slice(self.buffer.pixels());
+        Ok(())
+    }
+}
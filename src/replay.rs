@@ -0,0 +1,101 @@
+//! Finished games as they are submitted to a leaderboard: the parameters, the seed and the
+//! player's turns, which are enough to play the game again, with the result that it claims.
+//! `verify` plays it again to check that result, so that a leaderboard can turn down the
+//! submissions whose result was made up.
+
+use crate::config::Config;
+use crate::game::{State, Update};
+use crate::grid::Direction;
+use crate::telemetry::EndReason;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Replay {
+    pub config: Config,
+    pub seed: u64,
+    /// Ticks played in the game.
+    pub ticks: u64,
+    /// As returned by `State::turns`.
+    pub turns: Vec<(u64, Direction)>,
+    /// The claimed result, as in the `GameRecord` of the game.
+    pub score: u32,
+    pub length: usize,
+}
+
+/// The outcome of playing a replay again.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    /// The game ends up with the claimed result.
+    Valid,
+    /// The parameters are out of range.
+    InvalidConfig(String),
+    /// The game is over before the claimed number of ticks.
+    EndedEarly { tick: u64 },
+    /// The game ends up with another result, which is given.
+    Mismatch { score: u32, length: usize },
+}
+
+impl Verdict {
+    pub fn is_valid(&self) -> bool {
+        *self == Verdict::Valid
+    }
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Verdict::Valid => write!(f, "valid"),
+            Verdict::InvalidConfig(e) => write!(f, "invalid parameters: {}", e),
+            Verdict::EndedEarly { tick } => write!(f, "the game is over at tick {}", tick),
+            Verdict::Mismatch { score, length } => write!(
+                f,
+                "the game ends with the score {} and the length {}",
+                score, length
+            ),
+        }
+    }
+}
+
+impl Replay {
+    /// The replay of a game played with the parameters, with its result so far.
+    pub fn of(config: &Config, state: &State) -> Self {
+        let (score, length) = match state.record() {
+            Some(record) => (record.score, record.final_length),
+            None => (state.world().snakes()[0].score, state.length()),
+        };
+        Replay {
+            config: config.clone(),
+            seed: state.seed(),
+            ticks: state.world().tick(),
+            turns: state.turns().to_vec(),
+            score,
+            length,
+        }
+    }
+
+    /// Plays the game again and compares its result with the claimed one. The game may have been
+    /// left before it was over, but not played on after that.
+    pub fn verify(&self) -> Verdict {
+        if let Err(e) = self.config.validate() {
+            return Verdict::InvalidConfig(e);
+        }
+        let mut state = State::new(&self.config, self.seed);
+        let update = state.replay(self.ticks, &self.turns);
+        if update == Update::GameOver && state.world().tick() < self.ticks {
+            return Verdict::EndedEarly {
+                tick: state.world().tick(),
+            };
+        }
+        state.end(EndReason::Quit);
+        let record = state.record().expect("the game has ended");
+        if (record.score, record.final_length) == (self.score, self.length) {
+            Verdict::Valid
+        } else {
+            Verdict::Mismatch {
+                score: record.score,
+                length: record.final_length,
+            }
+        }
+    }
+}
@@ -0,0 +1,176 @@
+//! Client-side prediction for online play. The client simulates ahead of the server using its
+//! own inputs right away and guessing everyone else's. When the server confirms the inputs of a
+//! tick that were guessed wrong, the game is rewound to the state before that tick and
+//! simulated forward again.
+//!
+//! The snapshots of the past ticks are kept in a `Snapshots` ring buffer, which the practice mode
+//! also uses to rewind the game.
+
+use crate::grid::Direction;
+use crate::world::{SnakeId, World};
+use std::collections::{BTreeMap, VecDeque};
+
+/// How many ticks the prediction may run ahead of the last confirmed tick.
+pub const MAX_PREDICTION: usize = 16;
+
+/// Ring buffer of snapshots of the game before each of the last ticks, oldest first. Once it is
+/// full, a new snapshot pushes out the oldest one.
+pub struct Snapshots<T> {
+    frames: VecDeque<T>,
+    capacity: usize,
+}
+
+impl<T> Snapshots<T> {
+    pub fn new(capacity: usize) -> Self {
+        Snapshots {
+            frames: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.frames.len() >= self.capacity
+    }
+
+    pub fn push(&mut self, frame: T) {
+        if self.is_full() {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(frame);
+    }
+
+    pub fn pop_oldest(&mut self) -> Option<T> {
+        self.frames.pop_front()
+    }
+
+    /// Drops the snapshots of the last `ticks` ticks and returns the earliest of them, or the
+    /// oldest one if there are fewer.
+    pub fn rewind(&mut self, ticks: usize) -> Option<T> {
+        let keep = self.frames.len().saturating_sub(ticks.max(1));
+        self.frames.truncate(keep + 1);
+        self.frames.pop_back()
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+    }
+}
+
+/// A predicted tick that the server hasn't confirmed yet.
+struct Frame {
+    /// State right before the tick.
+    before: World,
+    /// Inputs that the tick was simulated with.
+    turns: Vec<Option<Direction>>,
+}
+
+pub struct Rollback {
+    player: Option<SnakeId>,
+    /// State after the last predicted tick.
+    world: World,
+    /// The unconfirmed ticks.
+    frames: Snapshots<Frame>,
+    /// Our own inputs by the tick at which they were requested.
+    inputs: BTreeMap<u64, Direction>,
+    confirmed_tick: u64,
+    rollbacks: u64,
+}
+
+impl Rollback {
+    /// `player` is the snake controlled locally, if any.
+    pub fn new(world: World, player: Option<SnakeId>) -> Self {
+        Rollback {
+            player,
+            confirmed_tick: world.tick(),
+            world,
+            frames: Snapshots::new(MAX_PREDICTION),
+            inputs: BTreeMap::new(),
+            rollbacks: 0,
+        }
+    }
+
+    /// The predicted state of the game.
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    pub fn confirmed_tick(&self) -> u64 {
+        self.confirmed_tick
+    }
+
+    /// Number of mispredictions so far.
+    pub fn rollbacks(&self) -> u64 {
+        self.rollbacks
+    }
+
+    /// Records a local turn for the next predicted tick and returns that tick, so that it can be
+    /// sent to the server.
+    pub fn turn(&mut self, direction: Direction) -> u64 {
+        let tick = self.world.tick() + 1;
+        self.inputs.insert(tick, direction);
+        tick
+    }
+
+    /// Predicts one more tick, unless the prediction is already too far ahead.
+    pub fn advance(&mut self) {
+        if self.frames.is_full() || self.world.is_over() {
+            return;
+        }
+        let turns = self.predict(self.world.tick() + 1);
+        self.frames.push(Frame {
+            before: self.world.clone(),
+            turns: turns.clone(),
+        });
+        self.world.step(&turns);
+    }
+
+    /// Applies the inputs of the next tick as confirmed by the server. Returns `true` if the
+    /// prediction was wrong and the game had to be re-simulated.
+    pub fn confirm(&mut self, tick: u64, turns: &[Option<Direction>]) -> bool {
+        if tick != self.confirmed_tick + 1 {
+            return false;
+        }
+        self.confirmed_tick = tick;
+        self.inputs = self.inputs.split_off(&(tick + 1));
+
+        let frame = match self.frames.pop_oldest() {
+            Some(frame) => frame,
+            None => {
+                // The prediction hasn't got this far yet.
+                self.world.step(turns);
+                return false;
+            }
+        };
+        if frame.turns == turns {
+            return false;
+        }
+
+        self.rollbacks += 1;
+        self.world = frame.before;
+        self.world.step(turns);
+        let predicted = self.frames.len();
+        self.frames.clear();
+        for _ in 0..predicted {
+            self.advance();
+        }
+        true
+    }
+
+    /// Remote players are assumed to keep going straight, which is what snakes do on almost
+    /// every tick.
+    fn predict(&self, tick: u64) -> Vec<Option<Direction>> {
+        let mut turns = vec![None; self.world.snakes().len()];
+        if let Some(player) = self.player {
+            turns[player] = self.inputs.get(&tick).copied();
+        }
+        turns
+    }
+}
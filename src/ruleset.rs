@@ -0,0 +1,108 @@
+//! Rules of a custom game, which change how a mode plays: whether the edges of the board wrap
+//! around, how much of it is walled off, how the speed changes, which kinds of food and
+//! power-ups appear and how the bots are handicapped. They come with the parameters of the game
+//! in `Config::rules`, and the standard rules are those of the other games.
+
+use crate::handicap::Handicap;
+use serde::{Deserialize, Serialize};
+
+/// Highest share of the cells in percent that can start as walls, and the step in which it
+/// changes on the custom game screen. The walls never touch each other, so no more than a quarter
+/// of the board could be walled off anyway.
+pub const MAX_OBSTACLES: u32 = 20;
+pub const OBSTACLES_STEP: u32 = 5;
+
+/// How much shorter each tick gets with every point of the player's snake, and the shortest
+/// that the ticks get as a fraction of the usual one, for the rising and the steep speed curves.
+const RISING: (f64, f64) = (0.01, 0.5);
+const STEEP: (f64, f64) = (0.03, 0.35);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpeedCurve {
+    /// The same speed all game long.
+    #[default]
+    Steady,
+    /// A little faster with every point.
+    Rising,
+    /// Much faster with every point, up to a higher top speed.
+    Steep,
+}
+
+impl SpeedCurve {
+    pub fn name(self) -> &'static str {
+        match self {
+            SpeedCurve::Steady => "STEADY",
+            SpeedCurve::Rising => "RISING",
+            SpeedCurve::Steep => "STEEP",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            SpeedCurve::Steady => SpeedCurve::Rising,
+            SpeedCurve::Rising => SpeedCurve::Steep,
+            SpeedCurve::Steep => SpeedCurve::Steady,
+        }
+    }
+
+    /// Multiplier applied to the duration of a tick once the player has `score` points.
+    pub fn tick_factor(self, score: u32) -> f64 {
+        let (step, shortest) = match self {
+            SpeedCurve::Steady => return 1.0,
+            SpeedCurve::Rising => RISING,
+            SpeedCurve::Steep => STEEP,
+        };
+        (1.0 - step * score as f64).max(shortest)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Ruleset {
+    /// Whether the snakes going over an edge of the board come back on the opposite edge. Only
+    /// boards of square cells wrap around.
+    pub wrap: bool,
+    /// Share of the cells in percent that start as walls, up to `MAX_OBSTACLES`.
+    pub obstacles: u32,
+    pub speed: SpeedCurve,
+    /// Whether food left on the board for long rots, see `Config::food_lifetime`.
+    pub rotten_food: bool,
+    /// Whether golden food appears, see `Config::golden_food_odds`.
+    pub golden_food: bool,
+    /// Whether bombs appear, see `Config::bomb_odds`.
+    pub bombs: bool,
+    /// Handicaps of the bots in the modes that have them.
+    pub handicap: Handicap,
+}
+
+impl Default for Ruleset {
+    fn default() -> Self {
+        Ruleset {
+            wrap: false,
+            obstacles: 0,
+            speed: SpeedCurve::Steady,
+            rotten_food: true,
+            golden_food: true,
+            bombs: true,
+            handicap: Handicap::default(),
+        }
+    }
+}
+
+impl Ruleset {
+    /// Whether these are the rules of the games other than the custom ones.
+    pub fn is_standard(&self) -> bool {
+        *self == Ruleset::default()
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.obstacles > MAX_OBSTACLES {
+            return Err(format!(
+                "At most {}% of the board can start as walls, not {}%",
+                MAX_OBSTACLES, self.obstacles
+            ));
+        }
+        self.handicap.validate()
+    }
+}
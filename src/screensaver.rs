@@ -0,0 +1,150 @@
+//! The background of the title screen: food raining down in single pixels behind the menu while
+//! the autopilot wanders around a board of its own, both faint enough not to get in the way of
+//! the text. With reduced motion the title screen goes without it.
+
+use rand::Rng;
+use snake_pixels::autopilot::Autopilot;
+use snake_pixels::config::Config;
+use snake_pixels::framebuffer::{Color, FrameBuffer};
+use snake_pixels::game::{State, Update};
+use snake_pixels::grid::{Tile, Vec2};
+use snake_pixels::palette::Palette;
+use std::cell::Cell;
+use std::time::Duration;
+use web_time::Instant;
+
+/// Size of the cells of the snake's board in pixels.
+const CELL: i32 = 4;
+const TICK_MS: u64 = 150;
+/// Opacity of the snake and the food over the background of the menu.
+const SNAKE_ALPHA: u8 = 70;
+const DROP_ALPHA: u8 = 110;
+/// Average number of drops that start falling per second.
+const DROPS_PER_SECOND: f32 = 12.0;
+/// Range of the speeds of the drops, in pixels per second.
+const MIN_SPEED: f32 = 15.0;
+const MAX_SPEED: f32 = 40.0;
+/// Time between two frames.
+const FRAME: Duration = Duration::from_millis(33);
+
+/// A pixel of food falling down.
+struct Drop {
+    x: i32,
+    y: f32,
+    speed: f32,
+    golden: bool,
+}
+
+pub struct Screensaver {
+    /// The snake's game, for a buffer of the size that it was started for.
+    game: Option<(State, (i32, i32))>,
+    drops: Vec<Drop>,
+    /// Size of the buffer as of the last frame, set by `render`.
+    size: Cell<(i32, i32)>,
+    last_update: Instant,
+}
+
+impl Default for Screensaver {
+    fn default() -> Self {
+        Screensaver {
+            game: None,
+            drops: Vec::new(),
+            size: Cell::new((0, 0)),
+            last_update: Instant::now(),
+        }
+    }
+}
+
+impl Screensaver {
+    /// Moves the drops and the snake on. Returns whether the screen needs to be redrawn.
+    pub fn update(&mut self) -> bool {
+        let now = Instant::now();
+        // Long gaps, while the title screen wasn't shown, don't pour all the rain at once.
+        let elapsed = (now - std::mem::replace(&mut self.last_update, now)).min(FRAME * 4);
+        let (width, height) = self.size.get();
+        if width == 0 {
+            return true;
+        }
+
+        let mut rng = rand::thread_rng();
+        let seconds = elapsed.as_secs_f32();
+        for drop in self.drops.iter_mut() {
+            drop.y -= drop.speed * seconds;
+        }
+        self.drops.retain(|drop| drop.y >= 0.0);
+        if rng.gen::<f32>() < DROPS_PER_SECOND * seconds {
+            self.drops.push(Drop {
+                x: rng.gen_range(0..width),
+                y: height as f32,
+                speed: rng.gen_range(MIN_SPEED..MAX_SPEED),
+                golden: rng.gen_ratio(1, 20),
+            });
+        }
+
+        let board = (width / CELL, height / CELL);
+        let fits = matches!(&self.game, Some((_, size)) if *size == board);
+        if !fits {
+            self.game = new_game(board);
+        }
+        if let Some((state, _)) = &mut self.game {
+            if state.update() == Update::GameOver {
+                self.game = new_game(board);
+            }
+        }
+        true
+    }
+
+    pub fn next_frame(&self) -> Instant {
+        Instant::now() + FRAME
+    }
+
+    /// Draws the snake and the drops over the background of the menu, which is already there.
+    pub fn render(&self, buffer: &mut FrameBuffer, palette: &Palette) {
+        let (width, height) = (buffer.width() as i32, buffer.height() as i32);
+        self.size.set((width, height));
+        let faint = |color: Color, alpha: u8| palette.menu_bg.blend(color, alpha);
+        if let Some((state, (board_width, board_height))) = &self.game {
+            // The board is centered, with the pixels that don't make a whole cell around it.
+            let left = (width - board_width * CELL) / 2;
+            let bottom = (height - board_height * CELL) / 2;
+            let mut fill = |pos: Vec2, color: Color| {
+                let (x, y) = (left + pos.0 * CELL, bottom + pos.1 * CELL);
+                buffer.fill_rectangle(x, y, CELL as usize, CELL as usize, color);
+            };
+            let world = state.world();
+            for (pos, tile) in world.objects() {
+                match tile {
+                    Tile::Food => fill(pos, faint(palette.food, SNAKE_ALPHA)),
+                    Tile::GoldenFood => fill(pos, faint(palette.golden_food, SNAKE_ALPHA)),
+                    _ => (),
+                }
+            }
+            let snake = &world.snakes()[0];
+            for &pos in snake.tail.iter() {
+                fill(pos, faint(palette.tail, SNAKE_ALPHA));
+            }
+            fill(snake.head, faint(palette.head, SNAKE_ALPHA));
+        }
+        for drop in self.drops.iter() {
+            let color = if drop.golden {
+                palette.golden_food
+            } else {
+                palette.food
+            };
+            buffer.set_pixel(drop.x, drop.y as i32, faint(color, DROP_ALPHA));
+        }
+    }
+}
+
+/// A game of the autopilot on a board of the given size, unless the buffer is too small for it.
+fn new_game((width, height): (i32, i32)) -> Option<(State, (i32, i32))> {
+    let config = Config {
+        width,
+        height,
+        tick_ms: TICK_MS,
+        ..Config::default()
+    };
+    config.validate().ok()?;
+    let state = State::with_controllers(&config, rand::random(), vec![Box::new(Autopilot)]);
+    Some((state, (width, height)))
+}
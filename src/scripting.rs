@@ -0,0 +1,134 @@
+//! Custom game modes written in Lua. Each mod is a directory in `mods` inside the data
+//! directory, with the script in `main.lua`.
+//!
+//! A script may define any of these functions, which are called as the game goes on:
+//!
+//! - `on_start()` before the first tick, e.g. to build the level,
+//! - `on_tick(tick)` after every tick,
+//! - `on_food_eaten(snake, x, y)` when a snake eats,
+//! - `on_death(snake, reason)` when a snake dies, with the reason such as `"wall"` or `"tail"`.
+//!
+//! From them it can change the board with `spawn_food(x, y)` and `set_tile(x, y, tile)`, where
+//! the tile is `"wall"` or `"empty"`. Both return whether the board changed: food only goes on
+//! empty cells, and only empty cells and walls can be swapped. The size of the board is in
+//! `width` and `height`. Cells are counted from 0 at the bottom left, and snakes from 0 with the
+//! player's first.
+
+use crate::storage;
+use log::{error, info};
+use mlua::{Function, IntoLuaMulti, Lua};
+use snake_pixels::grid::Vec2;
+use snake_pixels::hooks::Hooks;
+use snake_pixels::telemetry::EndReason;
+use snake_pixels::world::{SnakeId, World};
+use std::cell::RefCell;
+use std::path::Path;
+
+const MODS_DIR: &str = "mods";
+const SCRIPT: &str = "main.lua";
+
+pub struct Script {
+    name: String,
+    lua: Lua,
+    /// Set after the first error, so that a broken script doesn't flood the log on every tick.
+    failed: bool,
+}
+
+impl Script {
+    /// Names of the installed mods.
+    pub fn list() -> Vec<String> {
+        storage::list_dirs(MODS_DIR)
+    }
+
+    /// Loads and runs the script of the mod, which defines its hooks.
+    pub fn load(name: &str) -> Result<Self, String> {
+        let path = Path::new(MODS_DIR).join(name).join(SCRIPT);
+        let source =
+            storage::read_text(&path).ok_or_else(|| format!("{} not found", path.display()))?;
+        let lua = Lua::new();
+        lua.load(source.as_str())
+            .set_name(path.to_string_lossy())
+            .exec()
+            .map_err(|e| e.to_string())?;
+        info!("Loaded mod {}", name);
+        Ok(Script {
+            name: name.to_string(),
+            lua,
+            failed: false,
+        })
+    }
+
+    /// Calls a function of the script if it is defined, giving it access to the world.
+    fn call<A>(&mut self, world: &mut World, function: &str, args: A)
+    where
+        A: for<'lua> IntoLuaMulti<'lua>,
+    {
+        if self.failed {
+            return;
+        }
+        let world = RefCell::new(world);
+        let result = self.lua.scope(|scope| {
+            let globals = self.lua.globals();
+            let hook = match globals.get::<_, Option<Function>>(function)? {
+                Some(hook) => hook,
+                None => return Ok(()),
+            };
+            globals.set(
+                "spawn_food",
+                scope.create_function(|_, (x, y): (i32, i32)| {
+                    Ok(world.borrow_mut().place_food(Vec2(x, y)))
+                })?,
+            )?;
+            globals.set(
+                "set_tile",
+                scope.create_function(|_, (x, y, tile): (i32, i32, String)| {
+                    let wall = match tile.as_str() {
+                        "wall" => true,
+                        "empty" => false,
+                        _ => {
+                            return Err(mlua::Error::RuntimeError(format!(
+                                "unknown tile {:?}",
+                                tile
+                            )))
+                        }
+                    };
+                    Ok(world.borrow_mut().set_wall(Vec2(x, y), wall))
+                })?,
+            )?;
+            hook.call::<_, ()>(args)
+        });
+        if let Err(e) = result {
+            error!("Mod {} failed in {}: {}", self.name, function, e);
+            self.failed = true;
+        }
+    }
+}
+
+impl Hooks for Script {
+    fn on_start(&mut self, world: &mut World) {
+        let size = {
+            let globals = self.lua.globals();
+            globals
+                .set("width", world.width())
+                .and_then(|_| globals.set("height", world.height()))
+        };
+        if let Err(e) = size {
+            error!("Mod {} failed to start: {}", self.name, e);
+        }
+        self.call(world, "on_start", ());
+    }
+
+    fn on_tick(&mut self, world: &mut World) {
+        let tick = world.tick();
+        self.call(world, "on_tick", tick);
+    }
+
+    fn on_food_eaten(&mut self, world: &mut World, snake: SnakeId, pos: Vec2) {
+        self.call(world, "on_food_eaten", (snake, pos.0, pos.1));
+    }
+
+    fn on_death(&mut self, world: &mut World, snake: SnakeId, reason: EndReason) {
+        let reason = reason.label().to_lowercase();
+        self.call(world, "on_death", (snake, reason));
+    }
+}
@@ -0,0 +1,273 @@
+//! Short codes that others can enter to play the same game: the mode, the seed and the parameters
+//! of the board, packed into bytes and written in Crockford's base 32, e.g.
+//! `0480Y3ZYS8J21VAY00000XG`.
+//!
+//! The first byte is the version of the layout and the last one a checksum, so that a code from
+//! a newer version is told apart from a mistyped one. The layout of version 1 is:
+//!
+//! * the mode, the difficulty, the shape and the number of layers less one, two bits each;
+//! * the width and the height;
+//! * the seed, eight bytes, lowest first;
+//! * a byte with a bit for each of `EXTRAS` that differs from its default, followed by those.
+//!
+//! The sizes and the extras are LEB128 varints. The rules of custom games aren't in the layout,
+//! so only the games with the standard rules get codes.
+
+use crate::config::{Config, Difficulty, Mode, MAX_LAYERS};
+use crate::topology::Shape;
+use std::convert::{TryFrom, TryInto};
+use std::fmt;
+
+pub const VERSION: u8 = 1;
+const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+/// Reads a parameter of the game and sets it.
+type Accessors = (fn(&Config) -> u64, fn(&mut Config, u64));
+
+/// The parameters other than the size of the board that go into a code only when they differ
+/// from their defaults, in the order of their bits.
+const EXTRAS: [Accessors; 8] = [
+    (|c| c.tick_ms, |c, v| c.tick_ms = v),
+    (
+        |c| c.food_interval as u64,
+        |c, v| c.food_interval = v as u32,
+    ),
+    (|c| c.max_food as u64, |c, v| c.max_food = v as u32),
+    (|c| c.food_spread as u64, |c, v| c.food_spread = v as u32),
+    (
+        |c| c.food_lifetime as u64,
+        |c, v| c.food_lifetime = v as u32,
+    ),
+    (
+        |c| c.golden_food_odds as u64,
+        |c, v| c.golden_food_odds = v as u32,
+    ),
+    (|c| c.bomb_odds as u64, |c, v| c.bomb_odds = v as u32),
+    (|c| c.slow_start as u64, |c, v| c.slow_start = v as u32),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CodeError {
+    /// A character that isn't used in the codes.
+    Character(char),
+    /// The code was mistyped, or cut short.
+    Checksum,
+    /// The code comes from a version of the game that lays it out differently.
+    Version(u8),
+    /// The code is intact, but the game it describes can't be played.
+    Invalid,
+}
+
+impl CodeError {
+    /// Short description for the screen.
+    pub fn label(self) -> &'static str {
+        match self {
+            CodeError::Character(_) | CodeError::Checksum | CodeError::Invalid => "INVALID CODE",
+            CodeError::Version(_) => "CODE FROM ANOTHER VERSION",
+        }
+    }
+}
+
+impl fmt::Display for CodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CodeError::Character(c) => write!(f, "unexpected character {:?}", c),
+            CodeError::Checksum => write!(f, "the checksum doesn't match"),
+            CodeError::Version(version) => write!(
+                f,
+                "the code has version {}, while this game reads version {}",
+                version, VERSION
+            ),
+            CodeError::Invalid => write!(f, "the parameters of the game are out of range"),
+        }
+    }
+}
+
+impl std::error::Error for CodeError {}
+
+/// The code of the game started from the seed with the parameters.
+pub fn encode(config: &Config, seed: u64) -> String {
+    let mut bytes = vec![VERSION];
+    let mode = match config.mode {
+        Mode::Classic => 0,
+        Mode::Frenzy => 1,
+        Mode::Territory => 2,
+        Mode::CoOp => 3,
+    };
+    let difficulty = match config.difficulty {
+        Difficulty::Easy => 0,
+        Difficulty::Normal => 1,
+        Difficulty::Hard => 2,
+    };
+    let shape = match config.shape {
+        Shape::Square => 0,
+        Shape::Diagonal => 1,
+        Shape::Hex => 2,
+    };
+    let layers = (config.layers.clamp(1, MAX_LAYERS) - 1) as u8;
+    bytes.push((mode << 6) | (difficulty << 4) | (shape << 2) | layers);
+    write_varint(&mut bytes, config.width as u64);
+    write_varint(&mut bytes, config.height as u64);
+    bytes.extend_from_slice(&seed.to_le_bytes());
+    let default = Config::default();
+    let changed: Vec<usize> = (0..EXTRAS.len())
+        .filter(|&i| EXTRAS[i].0(config) != EXTRAS[i].0(&default))
+        .collect();
+    bytes.push(changed.iter().fold(0, |mask, &i| mask | (1 << i)));
+    for i in changed {
+        write_varint(&mut bytes, EXTRAS[i].0(config));
+    }
+    bytes.push(checksum(&bytes));
+    to_base32(&bytes)
+}
+
+/// The parameters and the seed of the game in a code. The case, the dashes and the spaces don't
+/// matter, and the letters I, L and O are read as the digits that they look like.
+pub fn decode(code: &str) -> Result<(Config, u64), CodeError> {
+    let bytes = from_base32(code)?;
+    let (&check, bytes) = bytes.split_last().ok_or(CodeError::Checksum)?;
+    if bytes.is_empty() || checksum(bytes) != check {
+        return Err(CodeError::Checksum);
+    }
+    if bytes[0] != VERSION {
+        return Err(CodeError::Version(bytes[0]));
+    }
+    let mut reader = Reader { bytes, at: 1 };
+    let packed = reader.byte()?;
+    let mode = match packed >> 6 {
+        0 => Mode::Classic,
+        1 => Mode::Frenzy,
+        2 => Mode::Territory,
+        _ => Mode::CoOp,
+    };
+    let difficulty = match (packed >> 4) & 3 {
+        0 => Difficulty::Easy,
+        1 => Difficulty::Normal,
+        2 => Difficulty::Hard,
+        _ => return Err(CodeError::Invalid),
+    };
+    let shape = match (packed >> 2) & 3 {
+        0 => Shape::Square,
+        1 => Shape::Diagonal,
+        2 => Shape::Hex,
+        _ => return Err(CodeError::Invalid),
+    };
+    let mut config = Config {
+        mode,
+        difficulty,
+        shape,
+        layers: (packed & 3) as u32 + 1,
+        width: i32::try_from(reader.varint()?).map_err(|_| CodeError::Invalid)?,
+        height: i32::try_from(reader.varint()?).map_err(|_| CodeError::Invalid)?,
+        ..Config::default()
+    };
+    let seed = u64::from_le_bytes(reader.take(8)?.try_into().unwrap());
+    let mask = reader.byte()?;
+    for (i, (_, set)) in EXTRAS.iter().enumerate() {
+        if mask & (1 << i) != 0 {
+            let value = reader.varint()?;
+            if i > 0 && value > u32::MAX as u64 {
+                return Err(CodeError::Invalid);
+            }
+            set(&mut config, value);
+        }
+    }
+    if reader.at != bytes.len() {
+        return Err(CodeError::Invalid);
+    }
+    config.validate().map_err(|_| CodeError::Invalid)?;
+    Ok((config, seed))
+}
+
+/// 64-bit FNV-1a folded into a byte.
+fn checksum(bytes: &[u8]) -> u8 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for &byte in bytes {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash.to_le_bytes().iter().fold(0, |sum, byte| sum ^ byte)
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+/// Reads the bytes of a code that has passed the checksum, so running out of them means that the
+/// layout is wrong rather than that the code was mistyped.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    at: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, count: usize) -> Result<&[u8], CodeError> {
+        let bytes = self
+            .bytes
+            .get(self.at..self.at + count)
+            .ok_or(CodeError::Invalid)?;
+        self.at += count;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> Result<u8, CodeError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn varint(&mut self) -> Result<u64, CodeError> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            value |= ((byte & 0x7F) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(CodeError::Invalid)
+    }
+}
+
+/// Five bits per character, the first bits first, with the last character padded with zeros.
+fn to_base32(bytes: &[u8]) -> String {
+    let mut text = String::new();
+    let (mut buffer, mut bits) = (0u32, 0);
+    for &byte in bytes {
+        buffer = (buffer << 8) | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            text.push(ALPHABET[((buffer >> bits) & 31) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        text.push(ALPHABET[((buffer << (5 - bits)) & 31) as usize] as char);
+    }
+    text
+}
+
+fn from_base32(text: &str) -> Result<Vec<u8>, CodeError> {
+    let mut bytes = Vec::new();
+    let (mut buffer, mut bits) = (0u32, 0);
+    for c in text.chars().filter(|&c| c != '-' && !c.is_whitespace()) {
+        let normalized = match c.to_ascii_uppercase() {
+            'I' | 'L' => '1',
+            'O' => '0',
+            c => c,
+        };
+        let value = ALPHABET
+            .iter()
+            .position(|&a| a as char == normalized)
+            .ok_or(CodeError::Character(c))?;
+        buffer = (buffer << 5) | value as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+    Ok(bytes)
+}
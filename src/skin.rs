@@ -0,0 +1,200 @@
+//! How a player's snake looks: the shape of its head, the pattern of its body and its color.
+//! Players pick it for themselves, and in multiplayer everyone sees it.
+
+use crate::framebuffer::{Color, Fill};
+use crate::grid::{Direction, Vec2};
+use serde::{Deserialize, Serialize};
+
+/// How much lighter the body is than the head.
+const BODY_LIGHTNESS: u8 = 48;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HeadShape {
+    #[default]
+    Square,
+    Round,
+    /// Narrowing towards the direction in which the snake goes.
+    Pointed,
+}
+
+impl HeadShape {
+    const ALL: [HeadShape; 3] = [HeadShape::Square, HeadShape::Round, HeadShape::Pointed];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            HeadShape::Square => "SQUARE",
+            HeadShape::Round => "ROUND",
+            HeadShape::Pointed => "POINTED",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        let i = HeadShape::ALL.iter().position(|&s| s == self).unwrap();
+        HeadShape::ALL[(i + 1) % HeadShape::ALL.len()]
+    }
+
+    /// Whether the pixel `(x, y)` of a cell of `size` × `size` pixels is a part of the head, with
+    /// `(0, 0)` in the bottom left corner.
+    fn covers(self, x: i32, y: i32, size: i32, direction: Direction) -> bool {
+        let last = size - 1;
+        match self {
+            HeadShape::Square => true,
+            // Too small cells stay square.
+            HeadShape::Round => size < 4 || !((x == 0 || x == last) && (y == 0 || y == last)),
+            HeadShape::Pointed => {
+                let (along, across) = direction.along_across(x, y, last);
+                2 * along < size || (2 * across - last).abs() <= 2 * (last - along)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Pattern {
+    #[default]
+    Solid,
+    /// Every other segment in the color of the head.
+    Stripes,
+    /// A dot in the color of the head in the middle of every segment.
+    Dots,
+}
+
+impl Pattern {
+    const ALL: [Pattern; 3] = [Pattern::Solid, Pattern::Stripes, Pattern::Dots];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Pattern::Solid => "SOLID",
+            Pattern::Stripes => "STRIPES",
+            Pattern::Dots => "DOTS",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        let i = Pattern::ALL.iter().position(|&p| p == self).unwrap();
+        Pattern::ALL[(i + 1) % Pattern::ALL.len()]
+    }
+}
+
+/// A color as hue, saturation and value, which is easier to pick than red, green and blue.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Hsv {
+    /// In degrees, from 0 to 359.
+    pub hue: u16,
+    /// From 0 to 100.
+    pub saturation: u8,
+    /// From 0 to 100.
+    pub value: u8,
+}
+
+impl Hsv {
+    pub const MAX_HUE: u16 = 359;
+    pub const MAX_SATURATION: u8 = 100;
+    pub const MAX_VALUE: u8 = 100;
+
+    pub fn to_color(self) -> Color {
+        let hue = self.hue.min(Hsv::MAX_HUE) as u32;
+        let saturation = self.saturation.min(Hsv::MAX_SATURATION) as u32;
+        let value = self.value.min(Hsv::MAX_VALUE) as u32;
+        // Components scaled by 60 * 100 * 100 to stay in integers.
+        let max = value * 6000;
+        let min = max - max * saturation / 100;
+        let rising = min + (max - min) * (hue % 60) / 60;
+        let falling = max - (max - min) * (hue % 60) / 60;
+        let (r, g, b) = match hue / 60 {
+            0 => (max, rising, min),
+            1 => (falling, max, min),
+            2 => (min, max, rising),
+            3 => (min, falling, max),
+            4 => (rising, min, max),
+            _ => (max, min, falling),
+        };
+        let scale = |c: u32| (c * 255 / 600_000) as u8;
+        Color::rgb(scale(r), scale(g), scale(b))
+    }
+}
+
+impl Default for Hsv {
+    /// Close to the snake of the classic theme.
+    fn default() -> Self {
+        Hsv {
+            hue: 245,
+            saturation: 76,
+            value: 91,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Skin {
+    pub head: HeadShape,
+    pub pattern: Pattern,
+    /// Whether the snake has its own color instead of the one from the theme.
+    pub custom_color: bool,
+    pub color: Hsv,
+}
+
+impl Skin {
+    /// Colors of the head and the body, or `None` for those of the theme.
+    pub fn colors(&self) -> Option<(Color, Color)> {
+        if !self.custom_color {
+            return None;
+        }
+        let head = self.color.to_color();
+        Some((
+            head,
+            head.blend(Color::rgb(0xFF, 0xFF, 0xFF), BODY_LIGHTNESS),
+        ))
+    }
+
+    /// Draws the head into the square of `size` × `size` pixels with the bottom left corner at
+    /// `corner`.
+    pub fn draw_head(
+        &self,
+        buffer: &mut impl Fill,
+        corner: Vec2,
+        size: i32,
+        direction: Direction,
+        color: Color,
+    ) {
+        if self.head == HeadShape::Square {
+            buffer.fill_rectangle(corner.0, corner.1, size as usize, size as usize, color);
+            return;
+        }
+        for y in 0..size {
+            for x in 0..size {
+                if self.head.covers(x, y, size, direction) {
+                    buffer.fill_rectangle(corner.0 + x, corner.1 + y, 1, 1, color);
+                }
+            }
+        }
+    }
+
+    /// Draws the segment of the body with the given index, counting from 0 right behind the head,
+    /// into the square of `size` × `size` pixels with the bottom left corner at `corner`.
+    pub fn draw_segment(
+        &self,
+        buffer: &mut impl Fill,
+        corner: Vec2,
+        size: i32,
+        index: usize,
+        (head, body): (Color, Color),
+    ) {
+        let mut fill = |offset: i32, size: i32, color: Color| {
+            let (x, y) = (corner.0 + offset, corner.1 + offset);
+            buffer.fill_rectangle(x, y, size as usize, size as usize, color);
+        };
+        match self.pattern {
+            Pattern::Stripes if index % 2 == 1 => fill(0, size, head),
+            Pattern::Solid | Pattern::Stripes => fill(0, size, body),
+            Pattern::Dots => {
+                fill(0, size, body);
+                let dot = size / 3;
+                fill((size - dot) / 2, dot, head);
+            }
+        }
+    }
+}
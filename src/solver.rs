@@ -0,0 +1,192 @@
+//! Bounded lookahead over the deterministic simulation. Since the food only depends on the
+//! seed and the moves, cloning the world and stepping it shows exactly what would happen,
+//! including where the next food appears. Other snakes are assumed to keep going straight.
+
+use crate::autopilot;
+use crate::controller::{Controller, GameView};
+use crate::grid::{Direction, Vec2};
+use crate::world::{Event, SnakeId, World};
+use std::collections::VecDeque;
+
+/// Lookahead of the `Solver` controller, in ticks.
+pub const DEFAULT_DEPTH: u32 = 6;
+/// Most worlds simulated for a single decision or check, so that none takes too long.
+const BUDGET: u32 = 20_000;
+/// How many ticks the snake has to survive past the moment of its death for the death to count
+/// as avoidable.
+const MARGIN: u32 = 4;
+
+/// Steps a copy of the world with the snake moving in the given direction. Returns the new
+/// world and whether the snake ate.
+fn advance(world: &World, snake: SnakeId, direction: Direction) -> (World, bool) {
+    let mut world = world.clone();
+    let mut turns = vec![None; world.snakes().len()];
+    turns[snake] = Some(direction);
+    let ate = world
+        .step(&turns)
+        .iter()
+        .any(|event| matches!(event, Event::Ate { snake: s, .. } if *s == snake));
+    (world, ate)
+}
+
+/// Moves that don't turn the snake back onto itself.
+fn moves(world: &World, snake: SnakeId) -> impl Iterator<Item = Direction> {
+    let back = world.snakes()[snake].direction.opposite();
+    world
+        .directions()
+        .iter()
+        .copied()
+        .filter(move |&d| d != back)
+}
+
+/// Cells of the board where the snake would die on the next tick, with the other snakes going
+/// straight: the walls and bodies right in front of its head and to its sides.
+pub fn lethal_cells(world: &World, snake: SnakeId) -> Vec<Vec2> {
+    if !world.snakes().get(snake).is_some_and(|s| s.alive) {
+        return Vec::new();
+    }
+    moves(world, snake)
+        .filter_map(|direction| {
+            let mut world = world.clone();
+            let mut turns = vec![None; world.snakes().len()];
+            turns[snake] = Some(direction);
+            world
+                .step(&turns)
+                .into_iter()
+                .find_map(|event| match event {
+                    Event::Died { snake: s, pos, .. } if s == snake => Some(pos),
+                    _ => None,
+                })
+        })
+        .filter(|&pos| world.tile(pos).is_some())
+        .collect()
+}
+
+/// The best line of play over the next `depth` ticks, scored by the number of ticks survived
+/// and then by the food eaten. A game that ends with the snake alive counts as surviving to
+/// the end.
+fn score(world: &World, snake: SnakeId, depth: u32, budget: &mut u32) -> (u32, u32) {
+    if !world.snakes()[snake].alive {
+        return (0, 0);
+    }
+    if depth == 0 || world.is_over() {
+        return (depth, 0);
+    }
+    let mut best = (0, 0);
+    for direction in moves(world, snake) {
+        if *budget == 0 {
+            break;
+        }
+        *budget -= 1;
+        let (next, ate) = advance(world, snake, direction);
+        if !next.snakes()[snake].alive {
+            continue;
+        }
+        let (survived, food) = score(&next, snake, depth - 1, budget);
+        best = best.max((survived + 1, food + ate as u32));
+    }
+    best
+}
+
+/// Picks the move that survives longest over the next `depth` ticks. Among the moves that
+/// survive equally long, it prefers the one the autopilot would take, since the autopilot makes
+/// sure that the snake doesn't get trapped later on, and otherwise the one that eats the most.
+pub fn best_move(world: &World, snake: SnakeId, depth: u32) -> Option<Direction> {
+    world.snakes().get(snake)?;
+    best_move_for(&GameView::new(world, snake), depth)
+}
+
+/// Like `best_move`, but the autopilot only goes by what the view shows, and the lookahead
+/// doesn't go past the sight of the view, so that the food out of sight stays out of reach.
+fn best_move_for(view: &GameView, depth: u32) -> Option<Direction> {
+    let (world, snake) = (view.world(), view.id());
+    if !view.snake().alive {
+        return None;
+    }
+    let depth = view
+        .sight()
+        .map_or(depth, |radius| depth.min(radius.max(1) as u32));
+    let preferred = autopilot::plan_for(view).direction;
+    let mut budget = BUDGET;
+    moves(world, snake)
+        .map(|direction| {
+            let (next, ate) = advance(world, snake, direction);
+            let (survived, food) = if next.snakes()[snake].alive {
+                let (survived, food) = score(&next, snake, depth - 1, &mut budget);
+                (survived + 1, food + ate as u32)
+            } else {
+                (0, 0)
+            };
+            (survived, food, Some(direction) == preferred, direction)
+        })
+        .max_by_key(|&(survived, food, preferred, _)| (survived, preferred, food))
+        .map(|(_, _, _, direction)| direction)
+}
+
+/// Whether the snake can survive the next `depth` ticks from this position.
+fn can_survive(world: &World, snake: SnakeId, depth: u32, budget: &mut u32) -> bool {
+    if !world.snakes()[snake].alive {
+        return false;
+    }
+    if depth == 0 || world.is_over() {
+        return true;
+    }
+    moves(world, snake).any(|direction| {
+        if *budget == 0 {
+            return false;
+        }
+        *budget -= 1;
+        can_survive(
+            &advance(world, snake, direction).0,
+            snake,
+            depth - 1,
+            budget,
+        )
+    })
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    /// The snake could still have escaped this many ticks before it died, but not later.
+    Avoidable { ticks_before: usize },
+    /// There was no escape from any of the positions looked at.
+    Unavoidable,
+}
+
+/// Looks back at the last moments before a death. `history` holds the world before each of the
+/// last ticks, oldest first, with the fatal tick last.
+pub fn analyze_death(history: &VecDeque<World>, snake: SnakeId) -> Verdict {
+    for (ticks_before, world) in history.iter().rev().enumerate() {
+        let ticks_before = ticks_before + 1;
+        let mut budget = BUDGET;
+        if can_survive(world, snake, ticks_before as u32 + MARGIN, &mut budget) {
+            return Verdict::Avoidable { ticks_before };
+        }
+    }
+    Verdict::Unavoidable
+}
+
+/// The hardest bot: plays the best line over a few ticks.
+pub struct Solver {
+    depth: u32,
+}
+
+impl Solver {
+    pub fn new(depth: u32) -> Self {
+        Solver {
+            depth: depth.max(1),
+        }
+    }
+}
+
+impl Default for Solver {
+    fn default() -> Self {
+        Solver::new(DEFAULT_DEPTH)
+    }
+}
+
+impl Controller for Solver {
+    fn decide(&mut self, view: &GameView) -> Direction {
+        best_move_for(view, self.depth).unwrap_or(view.snake().direction)
+    }
+}
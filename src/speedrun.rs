@@ -0,0 +1,132 @@
+//! The speedrun timer: the game time of the game being played and, in a run through a campaign
+//! pack from its first level, the splits at the end of every level next to the best ones of the
+//! profile.
+
+use snake_pixels::campaign::Manifest;
+use snake_pixels::font::{draw_text, text_width, GLYPH_HEIGHT, LINE_HEIGHT};
+use snake_pixels::framebuffer::FrameBuffer;
+use snake_pixels::palette::Palette;
+use std::collections::BTreeMap;
+
+/// Number of the latest splits shown above the timer.
+const SHOWN_SPLITS: usize = 3;
+
+/// The fewest seconds of game time from the start of the first level of a pack to the end of
+/// each of its levels, by the directory of the pack.
+pub type BestSplits = BTreeMap<String, Vec<f64>>;
+
+/// A run through a campaign pack, which goes on while its levels are cleared in order. The
+/// attempts at a level that were restarted count towards its time.
+pub struct Run {
+    pack: String,
+    /// Game time of the run before the current attempt at a level.
+    elapsed: f64,
+    /// Game time of the run at the end of every level cleared so far.
+    splits: Vec<f64>,
+    /// The best splits of the pack as of the start of the run.
+    best: Vec<f64>,
+}
+
+impl Run {
+    pub fn new(pack: &str, best: &BestSplits) -> Self {
+        Run {
+            pack: pack.to_string(),
+            elapsed: 0.0,
+            splits: Vec::new(),
+            best: best.get(pack).cloned().unwrap_or_default(),
+        }
+    }
+
+    pub fn pack(&self) -> &str {
+        &self.pack
+    }
+
+    /// Index of the level that the run goes on with.
+    pub fn next_level(&self) -> usize {
+        self.splits.len()
+    }
+
+    /// Game time of the run, given that of the current attempt.
+    pub fn time(&self, attempt: f64) -> f64 {
+        self.elapsed + attempt
+    }
+
+    /// Gives up the current attempt at the level, which took `attempt` seconds of game time.
+    pub fn restart(&mut self, attempt: f64) {
+        self.elapsed += attempt;
+    }
+
+    /// Ends the level with an attempt that cleared it and records the split in `best` if it is
+    /// the fastest yet. Returns whether it was.
+    pub fn split(&mut self, attempt: f64, best: &mut BestSplits) -> bool {
+        self.elapsed += attempt;
+        self.splits.push(self.elapsed);
+        let best = best.entry(self.pack.clone()).or_default();
+        let index = self.splits.len() - 1;
+        match best.get_mut(index) {
+            Some(time) if *time <= self.elapsed => false,
+            Some(time) => {
+                *time = self.elapsed;
+                true
+            }
+            None => {
+                // The first run that got this far, the splits before which are all set.
+                best.push(self.elapsed);
+                true
+            }
+        }
+    }
+}
+
+/// The time as minutes, seconds and hundredths, e.g. `1:05.30`.
+pub fn format_time(seconds: f64) -> String {
+    let hundredths = (seconds * 100.0).round() as u64;
+    format!(
+        "{}:{:02}.{:02}",
+        hundredths / 6000,
+        hundredths / 100 % 60,
+        hundredths % 100
+    )
+}
+
+/// The difference from a best time in seconds and hundredths, with its sign.
+fn format_delta(delta: f64) -> String {
+    let sign = if delta < 0.0 { '-' } else { '+' };
+    format!("{}{:.2}", sign, delta.abs())
+}
+
+/// Draws the timer in the bottom left corner of the screen, with the latest splits of the run
+/// above it, each with the name of its level and the difference from the best split.
+pub fn render(
+    buffer: &mut FrameBuffer,
+    palette: &Palette,
+    attempt: f64,
+    run: Option<(&Run, &Manifest)>,
+) {
+    let time = run.map_or(attempt, |(run, _)| run.time(attempt));
+    let mut y = GLYPH_HEIGHT + 1;
+    draw_text(buffer, 1, y, &format_time(time), 1, palette.text);
+    let (run, manifest) = match run {
+        Some(run) => run,
+        None => return,
+    };
+    let first = run.splits.len().saturating_sub(SHOWN_SPLITS);
+    for (i, &split) in run.splits.iter().enumerate().skip(first).rev() {
+        y += LINE_HEIGHT;
+        let name = manifest
+            .levels
+            .get(i)
+            .map_or("", |level| level.name.as_str());
+        let text = format!("{} {}", name, format_time(split));
+        draw_text(buffer, 1, y, &text, 1, palette.dim_text);
+        if let Some(&best) = run.best.get(i) {
+            let color = if split < best {
+                palette.selected
+            } else {
+                palette.dim_text
+            };
+            let x = 1 + text_width(&text) + text_width(" ");
+            draw_text(buffer, x, y, &format_delta(split - best), 1, color);
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use crate::achievements::Achievement;
+use crate::i18n::{tr, tr_fmt};
+use serde::{Deserialize, Serialize};
+use snake_pixels::font::{draw_text, draw_text_centered, text_width, LINE_HEIGHT};
+use snake_pixels::framebuffer::FrameBuffer;
+use snake_pixels::palette::Palette;
+use snake_pixels::telemetry::{EndReason, GameRecord};
+use std::collections::BTreeMap;
+
+/// Statistics accumulated over all games ever played.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LifetimeStats {
+    pub games_played: u32,
+    pub food_eaten: u64,
+    pub longest_snake: usize,
+    pub best_score: u32,
+    pub play_time_secs: f64,
+    pub end_reasons: BTreeMap<EndReason, u32>,
+}
+
+fn format_duration(secs: f64) -> String {
+    let secs = secs as u64;
+    if secs >= 3600 {
+        format!("{}H {:02}M", secs / 3600, secs / 60 % 60)
+    } else {
+        format!("{}M {:02}S", secs / 60, secs % 60)
+    }
+}
+
+impl LifetimeStats {
+    pub fn add(&mut self, record: &GameRecord) {
+        self.games_played += 1;
+        self.food_eaten += record.food_eaten as u64;
+        self.longest_snake = self.longest_snake.max(record.final_length);
+        self.best_score = self.best_score.max(record.score);
+        self.play_time_secs += record.duration_secs;
+        *self.end_reasons.entry(record.end_reason).or_insert(0) += 1;
+    }
+
+    /// Renders the stats screen. `achievements` is the number of unlocked achievements.
+    pub fn render(&self, buffer: &mut FrameBuffer, palette: &Palette, achievements: usize) {
+        buffer.clear(palette.menu_bg);
+        let top = buffer.height() as i32;
+        draw_text_centered(buffer, top - 4, &tr("STATS"), 2, palette.text);
+
+        let lines = [
+            tr_fmt("GAMES {}", &[&self.games_played]),
+            tr_fmt("FOOD {}", &[&self.food_eaten]),
+            tr_fmt("LONGEST {}", &[&self.longest_snake]),
+            tr_fmt("BEST SCORE {}", &[&self.best_score]),
+            tr_fmt("TIME {}", &[&format_duration(self.play_time_secs)]),
+            tr_fmt(
+                "ACHIEVEMENTS {}/{}",
+                &[&achievements, &Achievement::ALL.len()],
+            ),
+        ];
+        let mut y = top - 18;
+        for line in lines.iter() {
+            draw_text(buffer, 4, y, line, 1, palette.text);
+            y -= LINE_HEIGHT;
+        }
+
+        y -= 3;
+        draw_text(buffer, 4, y, &tr("GAME ENDS"), 1, palette.dim_text);
+        y -= LINE_HEIGHT;
+
+        let reasons = [
+            EndReason::Wall,
+            EndReason::Tail,
+            EndReason::BoardFull,
+            EndReason::TimeUp,
+            EndReason::Quit,
+        ];
+        let max_count = self.end_reasons.values().copied().max().unwrap_or(0).max(1);
+        let labels: Vec<String> = reasons.iter().map(|r| tr(r.label())).collect();
+        let bar_x = 4 + labels.iter().map(|l| text_width(l)).max().unwrap_or(0) + 3;
+        let max_bar = buffer.width() as i32 - bar_x - text_width("999") - 6;
+        for (&reason, label) in reasons.iter().zip(labels.iter()) {
+            let count = self.end_reasons.get(&reason).copied().unwrap_or(0);
+            let bar = max_bar * count as i32 / max_count as i32;
+            draw_text(buffer, 4, y, label, 1, palette.text);
+            buffer.fill_rectangle(bar_x, y - 5, bar as usize, 5, palette.bar);
+            draw_text(
+                buffer,
+                bar_x + bar + 2,
+                y,
+                &count.to_string(),
+                1,
+                palette.text,
+            );
+            y -= LINE_HEIGHT;
+        }
+
+        draw_text_centered(buffer, 10, &tr("E: EXPORT  ESC: BACK"), 1, palette.dim_text);
+    }
+}
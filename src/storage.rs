@@ -0,0 +1,279 @@
+//! Saved files. In the browser they are kept in the local storage, keyed by their paths.
+
+#[cfg(target_arch = "wasm32")]
+use local_storage as fs;
+use log::{error, warn};
+use serde::{de::DeserializeOwned, Serialize};
+#[cfg(not(target_arch = "wasm32"))]
+use std::ffi::OsStr;
+#[cfg(not(target_arch = "wasm32"))]
+use std::fs;
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+/// Set on Android, where the directory comes from the activity.
+#[cfg(target_os = "android")]
+static DATA_DIR: std::sync::OnceLock<PathBuf> = std::sync::OnceLock::new();
+
+#[cfg(target_os = "android")]
+pub fn set_data_dir(dir: PathBuf) {
+    let _ = DATA_DIR.set(dir);
+}
+
+/// Directory with the saved files, e.g. `~/.local/share/snake_pixels` on Linux.
+pub fn data_dir() -> Option<PathBuf> {
+    #[cfg(target_os = "android")]
+    return DATA_DIR.get().cloned();
+    #[cfg(not(any(target_arch = "wasm32", target_os = "android")))]
+    return dirs::data_dir().map(|dir| dir.join("snake_pixels"));
+    #[cfg(target_arch = "wasm32")]
+    Some(PathBuf::from("snake_pixels"))
+}
+
+/// Loads a JSON file from the data directory. Falls back to the default value if the file is
+/// missing or can't be parsed, so that a corrupted save never prevents the game from starting.
+pub fn load<T: DeserializeOwned + Default, P: AsRef<Path>>(path: P) -> T {
+    let path = match data_dir() {
+        Some(dir) => dir.join(path),
+        None => return T::default(),
+    };
+    match fs::read_to_string(&path) {
+        Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+            warn!("Failed to parse {}: {}", path.display(), e);
+            T::default()
+        }),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => T::default(),
+        Err(e) => {
+            warn!("Failed to read {}: {}", path.display(), e);
+            T::default()
+        }
+    }
+}
+
+/// Saves a value as a JSON file in the data directory, logging any errors.
+pub fn save<T: Serialize, P: AsRef<Path>>(path: P, value: &T) {
+    let path = match data_dir() {
+        Some(dir) => dir.join(path),
+        None => {
+            warn!("No data directory, not saving {}", path.as_ref().display());
+            return;
+        }
+    };
+    let result = fs::create_dir_all(path.parent().unwrap()).and_then(|_| {
+        let json = serde_json::to_string_pretty(value)?;
+        write_atomically(&path, &json)
+    });
+    if let Err(e) = result {
+        error!("Failed to save {}: {}", path.display(), e);
+    }
+}
+
+/// Reads a text file from the data directory. Returns `None` if it is missing or unreadable.
+pub fn read_text<P: AsRef<Path>>(path: P) -> Option<String> {
+    let path = data_dir()?.join(path);
+    match fs::read_to_string(&path) {
+        Ok(contents) => Some(contents),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => {
+            warn!("Failed to read {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// Saves a text file in the data directory. Returns whether it succeeded, logging any errors.
+pub fn write_text<P: AsRef<Path>>(path: P, contents: &str) -> bool {
+    let path = match data_dir() {
+        Some(dir) => dir.join(path),
+        None => {
+            warn!("No data directory, not saving {}", path.as_ref().display());
+            return false;
+        }
+    };
+    let result =
+        fs::create_dir_all(path.parent().unwrap()).and_then(|_| write_atomically(&path, contents));
+    if let Err(e) = &result {
+        error!("Failed to save {}: {}", path.display(), e);
+    }
+    result.is_ok()
+}
+
+/// Deletes a file from the data directory, if it is there, logging any errors.
+pub fn remove<P: AsRef<Path>>(path: P) {
+    let path = match data_dir() {
+        Some(dir) => dir.join(path),
+        None => return,
+    };
+    match fs::remove_file(&path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => {
+            error!("Failed to delete {}: {}", path.display(), e)
+        }
+        _ => (),
+    }
+}
+
+/// Writes the file through a temporary one next to it, so that an exit in the middle leaves
+/// either the old contents or the new ones, never a mix.
+#[cfg(not(target_arch = "wasm32"))]
+fn write_atomically(path: &Path, contents: &str) -> io::Result<()> {
+    use std::io::Write;
+
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".tmp");
+    let mut file = fs::File::create(&temp)?;
+    file.write_all(contents.as_bytes())?;
+    file.sync_all()?;
+    fs::rename(&temp, path)
+}
+
+/// Every write to the local storage is atomic.
+#[cfg(target_arch = "wasm32")]
+fn write_atomically(path: &Path, contents: &str) -> io::Result<()> {
+    fs::write(path, contents)
+}
+
+/// Reads a binary file from the data directory. Returns `None` if it is missing or unreadable.
+#[cfg(feature = "plugins")]
+pub fn read_bytes<P: AsRef<Path>>(path: P) -> Option<Vec<u8>> {
+    let path = data_dir()?.join(path);
+    match fs::read(&path) {
+        Ok(contents) => Some(contents),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => {
+            warn!("Failed to read {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// Names without the extension of the files with the given extension in a directory inside the
+/// data directory, sorted.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn list_files<P: AsRef<Path>>(path: P, extension: &str) -> Vec<String> {
+    let entries = match data_dir().map(|dir| fs::read_dir(dir.join(path))) {
+        Some(Ok(entries)) => entries,
+        _ => return Vec::new(),
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && path.extension() == Some(OsStr::new(extension)))
+        .filter_map(|path| Some(path.file_stem()?.to_str()?.to_string()))
+        .collect();
+    names.sort();
+    names
+}
+
+/// Names of the subdirectories of a directory inside the data directory, sorted.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn list_dirs<P: AsRef<Path>>(path: P) -> Vec<String> {
+    let entries = match data_dir().map(|dir| fs::read_dir(dir.join(path))) {
+        Some(Ok(entries)) => entries,
+        _ => return Vec::new(),
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .collect();
+    names.sort();
+    names
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn list_dirs<P: AsRef<Path>>(path: P) -> Vec<String> {
+    match data_dir() {
+        Some(dir) => local_storage::list_dirs(&dir.join(path)),
+        None => Vec::new(),
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn list_files<P: AsRef<Path>>(path: P, extension: &str) -> Vec<String> {
+    match data_dir() {
+        Some(dir) => local_storage::list_files(&dir.join(path), extension),
+        None => Vec::new(),
+    }
+}
+
+/// The subset of `std::fs` used above, on top of the browser's local storage.
+#[cfg(target_arch = "wasm32")]
+mod local_storage {
+    use std::io;
+    use std::path::Path;
+
+    fn storage() -> io::Result<web_sys::Storage> {
+        web_sys::window()
+            .and_then(|window| window.local_storage().ok().flatten())
+            .ok_or_else(|| io::Error::other("no local storage"))
+    }
+
+    fn key(path: &Path) -> String {
+        path.to_string_lossy().replace('\\', "/")
+    }
+
+    pub fn read_to_string(path: &Path) -> io::Result<String> {
+        storage()?
+            .get_item(&key(path))
+            .ok()
+            .flatten()
+            .ok_or_else(|| io::ErrorKind::NotFound.into())
+    }
+
+    pub fn write(path: &Path, contents: impl AsRef<str>) -> io::Result<()> {
+        storage()?
+            .set_item(&key(path), contents.as_ref())
+            .map_err(|_| io::Error::other("the local storage is full"))
+    }
+
+    pub fn remove_file(path: &Path) -> io::Result<()> {
+        storage()?
+            .remove_item(&key(path))
+            .map_err(|_| io::Error::other("failed to remove from the local storage"))
+    }
+
+    /// There are no directories, so this does nothing.
+    pub fn create_dir_all(_path: &Path) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// The directories are made up by the keys that start with `path`.
+    pub fn list_dirs(path: &Path) -> Vec<String> {
+        let storage = match storage() {
+            Ok(storage) => storage,
+            Err(_) => return Vec::new(),
+        };
+        let prefix = format!("{}/", key(path));
+        let mut names: Vec<String> = (0..storage.length().unwrap_or(0))
+            .filter_map(|i| storage.key(i).ok().flatten())
+            .filter_map(|key| {
+                let (dir, _) = key.strip_prefix(&prefix)?.split_once('/')?;
+                Some(dir.to_string())
+            })
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// The files are the keys right under `path` with the extension.
+    pub fn list_files(path: &Path, extension: &str) -> Vec<String> {
+        let storage = match storage() {
+            Ok(storage) => storage,
+            Err(_) => return Vec::new(),
+        };
+        let prefix = format!("{}/", key(path));
+        let suffix = format!(".{}", extension);
+        let mut names: Vec<String> = (0..storage.length().unwrap_or(0))
+            .filter_map(|i| storage.key(i).ok().flatten())
+            .filter_map(|key| {
+                let name = key.strip_prefix(&prefix)?.strip_suffix(&suffix)?;
+                (!name.contains('/')).then(|| name.to_string())
+            })
+            .collect();
+        names.sort();
+        names
+    }
+}
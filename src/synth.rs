@@ -0,0 +1,203 @@
+//! A tiny synthesizer for retro sound effects and music, so that the game needs no audio files.
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+pub const SAMPLE_RATE: u32 = 44_100;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Waveform {
+    Square,
+    Triangle,
+}
+
+/// Attack-decay-sustain-release envelope. Times are in seconds, `sustain` is the level held
+/// after the decay, relative to the peak.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Envelope {
+    pub attack: f32,
+    pub decay: f32,
+    pub sustain: f32,
+    pub release: f32,
+}
+
+impl Envelope {
+    /// Short and percussive, for blips.
+    pub const PLUCK: Envelope = Envelope {
+        attack: 0.002,
+        decay: 0.04,
+        sustain: 0.4,
+        release: 0.03,
+    };
+
+    /// Level `t` seconds after the start of a note that is released after `held` seconds.
+    fn level(&self, t: f32, held: f32) -> f32 {
+        let before_release = |t: f32| {
+            if t < self.attack {
+                t / self.attack
+            } else if t < self.attack + self.decay {
+                1.0 - (1.0 - self.sustain) * (t - self.attack) / self.decay
+            } else {
+                self.sustain
+            }
+        };
+        if t < held {
+            before_release(t)
+        } else if self.release > 0.0 {
+            before_release(held) * (1.0 - (t - held) / self.release).max(0.0)
+        } else {
+            0.0
+        }
+    }
+}
+
+fn default_duty() -> f32 {
+    0.5
+}
+
+/// A single note. Pitches are MIDI note numbers, so 69 is A4 at 440 Hz and fractions are
+/// allowed.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Note {
+    pub waveform: Waveform,
+    /// Seconds from the start of the sound.
+    #[serde(default)]
+    pub start: f32,
+    pub pitch: f32,
+    /// If set, the pitch slides to this one by the time of the release.
+    #[serde(default)]
+    pub end_pitch: Option<f32>,
+    /// Seconds from the start of the note to the release.
+    pub duration: f32,
+    pub volume: f32,
+    /// Fraction of the period during which a square wave is high.
+    #[serde(default = "default_duty")]
+    pub duty: f32,
+    pub envelope: Envelope,
+}
+
+impl Note {
+    pub fn new(waveform: Waveform, start: f32, pitch: f32, duration: f32, volume: f32) -> Self {
+        Note {
+            waveform,
+            start,
+            pitch,
+            end_pitch: None,
+            duration,
+            volume,
+            duty: default_duty(),
+            envelope: Envelope::PLUCK,
+        }
+    }
+
+    pub fn slide_to(self, end_pitch: f32) -> Self {
+        Note {
+            end_pitch: Some(end_pitch),
+            ..self
+        }
+    }
+
+    pub fn duty(self, duty: f32) -> Self {
+        Note { duty, ..self }
+    }
+
+    pub fn envelope(self, envelope: Envelope) -> Self {
+        Note { envelope, ..self }
+    }
+
+    fn end(&self) -> f32 {
+        self.start + self.duration + self.envelope.release
+    }
+
+    fn render_into(&self, out: &mut [f32]) {
+        let first = (self.start * SAMPLE_RATE as f32) as usize;
+        let len = ((self.duration + self.envelope.release) * SAMPLE_RATE as f32) as usize;
+        let end_pitch = self.end_pitch.unwrap_or(self.pitch);
+        let mut phase = 0.0f32;
+        for (i, sample) in out.iter_mut().skip(first).take(len).enumerate() {
+            let t = i as f32 / SAMPLE_RATE as f32;
+            let progress = (t / self.duration).min(1.0);
+            let pitch = self.pitch + (end_pitch - self.pitch) * progress;
+            phase = (phase + frequency(pitch) / SAMPLE_RATE as f32).fract();
+            let wave = match self.waveform {
+                Waveform::Square if phase < self.duty => 1.0,
+                Waveform::Square => -1.0,
+                Waveform::Triangle => 4.0 * (phase - 0.5).abs() - 1.0,
+            };
+            *sample += wave * self.volume * self.envelope.level(t, self.duration);
+        }
+    }
+}
+
+/// Frequency in Hz of a MIDI note number.
+pub fn frequency(pitch: f32) -> f32 {
+    440.0 * 2f32.powf((pitch - 69.0) / 12.0)
+}
+
+/// Mixes the notes into mono samples at `SAMPLE_RATE`.
+pub fn render(notes: &[Note]) -> Vec<f32> {
+    let end = notes.iter().map(Note::end).fold(0.0, f32::max);
+    let mut out = vec![0.0; (end * SAMPLE_RATE as f32).ceil() as usize];
+    for note in notes {
+        note.render_into(&mut out);
+    }
+    out
+}
+
+/// Semitones of the minor pentatonic scale.
+const SCALE: [i32; 5] = [0, 3, 5, 7, 10];
+/// Roots of the bars of the bass line: C, A♭, B♭, G.
+const ROOTS: [i32; 4] = [36, 32, 34, 31];
+
+/// Generates a loop of `bars` bars in 4/4: a triangle bass walking through `ROOTS` under a
+/// square lead that wanders randomly around the scale in eighth notes.
+pub fn melody(seed: u64, bars: usize, tempo: f32) -> Vec<Note> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let beat = 60.0 / tempo;
+    let mut notes = Vec::new();
+    // Position in the scale, counted in scale steps from C4.
+    let mut degree: i32 = 5;
+    for bar in 0..bars {
+        let bar_start = bar as f32 * 4.0 * beat;
+        let root = ROOTS[bar % ROOTS.len()] as f32;
+        for b in 0..4 {
+            let pitch = if b % 2 == 0 { root } else { root + 7.0 };
+            notes.push(
+                Note::new(
+                    Waveform::Triangle,
+                    bar_start + b as f32 * beat,
+                    pitch,
+                    beat * 0.8,
+                    0.3,
+                )
+                .envelope(Envelope {
+                    attack: 0.005,
+                    decay: 0.1,
+                    sustain: 0.6,
+                    release: 0.05,
+                }),
+            );
+        }
+        for eighth in 0..8 {
+            if rng.gen_bool(0.25) {
+                continue;
+            }
+            degree = (degree + rng.gen_range(-2..=2)).clamp(0, 2 * SCALE.len() as i32);
+            let octave = degree / SCALE.len() as i32;
+            let step = SCALE[(degree % SCALE.len() as i32) as usize];
+            let pitch = (60 + 12 * octave + step) as f32;
+            notes.push(
+                Note::new(
+                    Waveform::Square,
+                    bar_start + eighth as f32 * beat / 2.0,
+                    pitch,
+                    beat * 0.35,
+                    0.12,
+                )
+                .duty(0.25),
+            );
+        }
+    }
+    notes
+}
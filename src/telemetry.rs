@@ -0,0 +1,209 @@
+use crate::config::{Config, Difficulty, Mode};
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use std::path::Path;
+use web_time::{Instant, SystemTime, UNIX_EPOCH};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EndReason {
+    Wall,
+    Tail,
+    /// Crashed into another snake.
+    Snake,
+    BoardFull,
+    /// The timer of the mode ran out.
+    TimeUp,
+    Quit,
+}
+
+impl EndReason {
+    pub fn label(self) -> &'static str {
+        match self {
+            EndReason::Wall => "WALL",
+            EndReason::Tail => "TAIL",
+            EndReason::Snake => "SNAKE",
+            EndReason::BoardFull => "FULL",
+            EndReason::TimeUp => "TIME",
+            EndReason::Quit => "QUIT",
+        }
+    }
+}
+
+/// Summary of a single game, logged as one line of JSON when the game ends.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GameRecord {
+    /// Seconds since the Unix epoch.
+    pub started_at: u64,
+    pub mode: Mode,
+    pub difficulty: Difficulty,
+    pub duration_secs: f64,
+    pub ticks: u64,
+    pub food_eaten: u32,
+    pub score: u32,
+    pub end_reason: EndReason,
+    pub final_length: usize,
+    pub seed: u64,
+    pub config_hash: String,
+}
+
+impl GameRecord {
+    fn log(&self) {
+        match serde_json::to_string(self) {
+            Ok(json) => info!("{}", json),
+            Err(e) => error!("Failed to serialize game record: {}", e),
+        }
+    }
+}
+
+/// Formats of the exported game history.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+impl ExportFormat {
+    /// JSON for files ending with `.json`, CSV for anything else.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(e) if e.eq_ignore_ascii_case("json") => ExportFormat::Json,
+            _ => ExportFormat::Csv,
+        }
+    }
+}
+
+/// Formats the seconds since the Unix epoch as a UTC date and time in the ISO 8601 format, which
+/// spreadsheets recognize.
+pub fn format_date(secs: u64) -> String {
+    // Days to the civil date, from http://howardhinnant.github.io/date_algorithms.html.
+    let days = (secs / 86400) as i64 + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs / 3600 % 24,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+/// Writes the records of the games as a table with a row for every game, or as a JSON array.
+pub fn export(records: &[GameRecord], format: ExportFormat) -> String {
+    let mut out = String::new();
+    match format {
+        ExportFormat::Csv => {
+            out.push_str("date,mode,difficulty,seed,score,length,food_eaten,duration_secs,ticks,end_reason\n");
+            for r in records {
+                writeln!(
+                    out,
+                    "{},{},{},{},{},{},{},{:.2},{},{}",
+                    format_date(r.started_at),
+                    r.mode.name().to_lowercase(),
+                    r.difficulty.name().to_lowercase(),
+                    r.seed,
+                    r.score,
+                    r.final_length,
+                    r.food_eaten,
+                    r.duration_secs,
+                    r.ticks,
+                    r.end_reason.label().to_lowercase()
+                )
+                .unwrap();
+            }
+        }
+        ExportFormat::Json => {
+            out = serde_json::to_string_pretty(records).unwrap();
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// Collects the statistics of the game in progress.
+pub struct Session {
+    started: Instant,
+    started_at: SystemTime,
+    mode: Mode,
+    difficulty: Difficulty,
+    seed: u64,
+    config_hash: String,
+    ticks: u64,
+    food_eaten: u32,
+    score: u32,
+    record: Option<GameRecord>,
+}
+
+impl Session {
+    pub fn new(config: &Config, seed: u64) -> Self {
+        Session {
+            started: Instant::now(),
+            started_at: SystemTime::now(),
+            mode: config.mode,
+            difficulty: config.difficulty,
+            seed,
+            config_hash: config.hash(),
+            ticks: 0,
+            food_eaten: 0,
+            score: 0,
+            record: None,
+        }
+    }
+
+    pub fn on_tick(&mut self) {
+        self.ticks += 1;
+    }
+
+    pub fn on_food_eaten(&mut self, points: u32) {
+        self.food_eaten += 1;
+        self.score += points;
+    }
+
+    /// Replaces the score, for the modes that don't score by the food.
+    pub fn set_score(&mut self, score: u32) {
+        self.score = score;
+    }
+
+    /// Logs the record of the game. Only the first call has an effect, so that quitting right
+    /// after a game over doesn't produce a second record.
+    pub fn finish(&mut self, end_reason: EndReason, final_length: usize) {
+        if self.record.is_some() {
+            return;
+        }
+
+        let record = GameRecord {
+            started_at: self
+                .started_at
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            mode: self.mode,
+            difficulty: self.difficulty,
+            duration_secs: self.started.elapsed().as_secs_f64(),
+            ticks: self.ticks,
+            food_eaten: self.food_eaten,
+            score: self.score,
+            end_reason,
+            final_length,
+            seed: self.seed,
+            config_hash: self.config_hash.clone(),
+        };
+        record.log();
+        self.record = Some(record);
+    }
+
+    pub fn record(&self) -> Option<&GameRecord> {
+        self.record.as_ref()
+    }
+}
@@ -0,0 +1,210 @@
+//! Plays the game in a terminal, for machines without a GPU or a display server. Every character
+//! cell shows two pixels of the frame buffer, one above the other, as a half block with the
+//! foreground and background colors set to them.
+
+use crate::app::App;
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{self, Event, KeyCode as TermKey, KeyEventKind, KeyModifiers};
+use crossterm::style::{
+    Color as TermColor, Print, ResetColor, SetBackgroundColor, SetForegroundColor,
+};
+use crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+use log::{error, info};
+use snake_pixels::framebuffer::{Color, FrameBuffer};
+use snake_pixels::renderer::Renderer;
+use std::error::Error;
+use std::io::{self, Stdout, Write};
+use std::time::Duration;
+use web_time::Instant;
+use winit::keyboard::KeyCode;
+
+/// How long to wait for a key when nothing else is going on.
+const IDLE_POLL: Duration = Duration::from_millis(100);
+
+const LETTERS: [KeyCode; 26] = [
+    KeyCode::KeyA,
+    KeyCode::KeyB,
+    KeyCode::KeyC,
+    KeyCode::KeyD,
+    KeyCode::KeyE,
+    KeyCode::KeyF,
+    KeyCode::KeyG,
+    KeyCode::KeyH,
+    KeyCode::KeyI,
+    KeyCode::KeyJ,
+    KeyCode::KeyK,
+    KeyCode::KeyL,
+    KeyCode::KeyM,
+    KeyCode::KeyN,
+    KeyCode::KeyO,
+    KeyCode::KeyP,
+    KeyCode::KeyQ,
+    KeyCode::KeyR,
+    KeyCode::KeyS,
+    KeyCode::KeyT,
+    KeyCode::KeyU,
+    KeyCode::KeyV,
+    KeyCode::KeyW,
+    KeyCode::KeyX,
+    KeyCode::KeyY,
+    KeyCode::KeyZ,
+];
+
+const DIGITS: [KeyCode; 10] = [
+    KeyCode::Digit0,
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+    KeyCode::Digit8,
+    KeyCode::Digit9,
+];
+
+/// The physical key that would have produced the same input in the window.
+fn keycode(key: TermKey) -> Option<KeyCode> {
+    let keycode = match key {
+        TermKey::Up => KeyCode::ArrowUp,
+        TermKey::Down => KeyCode::ArrowDown,
+        TermKey::Left => KeyCode::ArrowLeft,
+        TermKey::Right => KeyCode::ArrowRight,
+        TermKey::Enter => KeyCode::Enter,
+        TermKey::Esc => KeyCode::Escape,
+        TermKey::Backspace => KeyCode::Backspace,
+        TermKey::Tab => KeyCode::Tab,
+        TermKey::Char(' ') => KeyCode::Space,
+        TermKey::Char('.') => KeyCode::Period,
+        TermKey::Char('`') => KeyCode::Backquote,
+        TermKey::Char('-') | TermKey::Char('_') => KeyCode::Minus,
+        TermKey::F(9) => KeyCode::F9,
+        TermKey::F(10) => KeyCode::F10,
+        TermKey::Char(c @ 'a'..='z') | TermKey::Char(c @ 'A'..='Z') => {
+            LETTERS[(c.to_ascii_lowercase() as u8 - b'a') as usize]
+        }
+        TermKey::Char(c @ '0'..='9') => DIGITS[(c as u8 - b'0') as usize],
+        _ => return None,
+    };
+    Some(keycode)
+}
+
+fn term_color(color: Color) -> TermColor {
+    let (r, g, b) = color.components();
+    TermColor::Rgb { r, g, b }
+}
+
+/// The terminal in raw mode on the alternate screen, until dropped.
+struct Terminal {
+    stdout: Stdout,
+    buffer: FrameBuffer,
+}
+
+impl Terminal {
+    fn new() -> io::Result<Self> {
+        let (columns, rows) = terminal::size()?;
+        terminal::enable_raw_mode()?;
+        let mut stdout = io::stdout();
+        execute!(stdout, EnterAlternateScreen, Hide)?;
+        Ok(Terminal {
+            stdout,
+            buffer: Terminal::buffer(columns, rows),
+        })
+    }
+
+    fn buffer(columns: u16, rows: u16) -> FrameBuffer {
+        FrameBuffer::new(columns as usize, 2 * rows as usize)
+    }
+
+    fn resize(&mut self, columns: u16, rows: u16) {
+        info!("Terminal resized to {}x{}", columns, rows);
+        self.buffer = Terminal::buffer(columns, rows);
+    }
+
+    fn draw(&mut self) -> io::Result<()> {
+        let height = self.buffer.height() as i32;
+        let mut colors = None;
+        for row in 0..height / 2 {
+            queue!(self.stdout, MoveTo(0, row as u16))?;
+            // The buffer has y pointing up, the terminal counts the rows from the top.
+            let (top, bottom) = (height - 2 * row - 1, height - 2 * row - 2);
+            for x in 0..self.buffer.width() as i32 {
+                let cell = (
+                    self.buffer.pixel(x, top).unwrap(),
+                    self.buffer.pixel(x, bottom).unwrap(),
+                );
+                if colors != Some(cell) {
+                    queue!(
+                        self.stdout,
+                        SetForegroundColor(term_color(cell.0)),
+                        SetBackgroundColor(term_color(cell.1))
+                    )?;
+                    colors = Some(cell);
+                }
+                queue!(self.stdout, Print('▀'))?;
+            }
+        }
+        self.stdout.flush()
+    }
+}
+
+impl Renderer for Terminal {
+    fn buffer_mut(&mut self) -> &mut FrameBuffer {
+        &mut self.buffer
+    }
+
+    fn present(&mut self) -> Result<(), Box<dyn Error>> {
+        Ok(self.draw()?)
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        let _ = execute!(self.stdout, ResetColor, Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Runs the application until it quits or Ctrl+C is pressed.
+pub fn run(mut app: App) -> io::Result<()> {
+    let mut terminal = Terminal::new()?;
+    let mut redraw = true;
+    loop {
+        if redraw {
+            if let Err(e) = app.render(&mut terminal) {
+                error!("Failed to draw in the terminal: {}", e);
+                break;
+            }
+        }
+        let timeout = app.next_wakeup().map_or(IDLE_POLL, |wakeup| {
+            wakeup.saturating_duration_since(Instant::now())
+        });
+        redraw = false;
+        if event::poll(timeout)? {
+            match event::read()? {
+                Event::Key(key) if key.kind != KeyEventKind::Release => {
+                    if key.modifiers.contains(KeyModifiers::CONTROL)
+                        && key.code == TermKey::Char('c')
+                    {
+                        break;
+                    }
+                    if let Some(keycode) = keycode(key.code) {
+                        if app.on_keypress(keycode) {
+                            break;
+                        }
+                        redraw = true;
+                    }
+                }
+                Event::Resize(columns, rows) => {
+                    terminal.resize(columns, rows);
+                    redraw = true;
+                }
+                _ => (),
+            }
+        }
+        redraw |= app.update();
+    }
+    app.quit();
+    Ok(())
+}
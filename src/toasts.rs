@@ -0,0 +1,146 @@
+//! Short messages about things that happened away from the player's attention, such as an
+//! unlocked achievement or a player joining the lobby. They slide in at the top of the screen one
+//! after another and fade out, over whatever screen is shown. With reduced motion they appear
+//! in place instead of sliding in.
+//!
+//! Any part of the game can post a notice through a `Notifier`, a handle on the queue of the
+//! `Toasts` that it can keep for as long as it likes.
+
+use crate::achievements::Achievement;
+use crate::i18n::{tr, tr_fmt};
+use snake_pixels::font::{draw_text_centered, LINE_HEIGHT};
+use snake_pixels::framebuffer::FrameBuffer;
+use snake_pixels::palette::Palette;
+use std::collections::VecDeque;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::Duration;
+use web_time::Instant;
+
+/// How long a toast stays, from the moment it starts to slide in.
+const TOAST_TIME: Duration = Duration::from_millis(2500);
+const SLIDE_TIME: Duration = Duration::from_millis(200);
+/// The last part of `TOAST_TIME`, during which the toast fades out.
+const FADE_TIME: Duration = Duration::from_millis(500);
+/// Notices beyond this many waiting ones are dropped, so that a burst of them doesn't keep the
+/// toasts coming for long after it.
+const MAX_WAITING: usize = 5;
+/// Opacity of the strip behind the text.
+const STRIP_ALPHA: u8 = 200;
+/// Time between two frames while a toast moves or fades.
+const FRAME: Duration = Duration::from_millis(33);
+
+pub enum Notice {
+    Achievement(Achievement),
+    /// The player's best score so far.
+    HighScore(u32),
+    PlayerJoined(String),
+    LevelSaved(String),
+    RulesetSaved(String),
+}
+
+impl Notice {
+    fn text(&self) -> String {
+        match self {
+            Notice::Achievement(achievement) => tr_fmt("UNLOCKED: {}", &[&tr(achievement.name())]),
+            Notice::HighScore(score) => tr_fmt("NEW BEST SCORE: {}", &[score]),
+            Notice::PlayerJoined(name) => tr_fmt("{} JOINED", &[name]),
+            Notice::LevelSaved(name) | Notice::RulesetSaved(name) => tr_fmt("SAVED {}", &[name]),
+        }
+    }
+}
+
+/// Posts notices to the `Toasts` that it was taken from.
+#[derive(Clone)]
+pub struct Notifier(Sender<Notice>);
+
+impl Notifier {
+    pub fn post(&self, notice: Notice) {
+        // Nobody is left to show the notice once the toasts are gone.
+        let _ = self.0.send(notice);
+    }
+}
+
+pub struct Toasts {
+    sender: Sender<Notice>,
+    notices: Receiver<Notice>,
+    waiting: VecDeque<String>,
+    /// The toast on the screen, with the time when it started to slide in.
+    shown: Option<(String, Instant)>,
+}
+
+impl Default for Toasts {
+    fn default() -> Self {
+        let (sender, notices) = mpsc::channel();
+        Toasts {
+            sender,
+            notices,
+            waiting: VecDeque::new(),
+            shown: None,
+        }
+    }
+}
+
+impl Toasts {
+    pub fn notifier(&self) -> Notifier {
+        Notifier(self.sender.clone())
+    }
+
+    /// Queues a notice from the owner of the toasts, which doesn't need a `Notifier`.
+    pub fn post(&mut self, notice: Notice) {
+        if self.waiting.len() < MAX_WAITING {
+            self.waiting.push_back(notice.text());
+        }
+    }
+
+    /// Picks up the posted notices and moves on to the next toast once the last one is gone.
+    /// Returns whether the screen needs to be redrawn.
+    pub fn update(&mut self) -> bool {
+        while let Ok(notice) = self.notices.try_recv() {
+            self.post(notice);
+        }
+        let showing = self.shown.is_some();
+        if matches!(&self.shown, Some((_, start)) if start.elapsed() >= TOAST_TIME) {
+            self.shown = None;
+        }
+        if self.shown.is_none() {
+            self.shown = self.waiting.pop_front().map(|text| (text, Instant::now()));
+        }
+        showing || self.shown.is_some()
+    }
+
+    /// When the next frame is due, while a toast is on the screen or waiting for it.
+    pub fn next_frame(&self) -> Option<Instant> {
+        (self.shown.is_some() || !self.waiting.is_empty()).then(|| Instant::now() + FRAME)
+    }
+
+    pub fn render(&self, buffer: &mut FrameBuffer, palette: &Palette, reduced_motion: bool) {
+        let Some((text, start)) = &self.shown else {
+            return;
+        };
+        let elapsed = start.elapsed();
+        if elapsed >= TOAST_TIME {
+            return;
+        }
+        let height = LINE_HEIGHT + 1;
+        // Slides down from above the top edge.
+        let slide = if reduced_motion {
+            1.0
+        } else {
+            (elapsed.as_secs_f32() / SLIDE_TIME.as_secs_f32()).min(1.0)
+        };
+        let y = buffer.height() as i32 - (slide * height as f32).round() as i32;
+        let fade = (TOAST_TIME - elapsed).as_secs_f32() / FADE_TIME.as_secs_f32();
+        let opacity = fade.min(1.0);
+        buffer.blend_rectangle(
+            0,
+            y,
+            buffer.width(),
+            height as usize,
+            palette.menu_bg,
+            (STRIP_ALPHA as f32 * opacity) as u8,
+        );
+        let under = buffer.pixel(0, y).unwrap_or(palette.menu_bg);
+        let color = under.blend(palette.selected, (255.0 * opacity) as u8);
+        draw_text_centered(buffer, y + height - 1, text, 1, color);
+    }
+}
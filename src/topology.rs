@@ -0,0 +1,270 @@
+//! How the cells of the board fit together. The classic board has square cells and the snakes go
+//! in four directions. The diagonal board has the same cells, but the snakes can also go along
+//! the diagonals, which takes them longer. The experimental hex board has hexagonal cells in rows,
+//! with every odd row shifted right by half a cell, so that each cell touches two cells in its own
+//! row and two in each of the rows above and below it. The snakes there go left, right and along
+//! the four diagonals.
+//!
+//! `World` moves the snakes and spawns the food through the topology of its board, and the camera
+//! places and shapes the cells through it.
+
+use crate::grid::{Direction, Vec2};
+use serde::{Deserialize, Serialize};
+
+pub trait Topology: Sync {
+    /// Directions in which the snakes can go.
+    fn directions(&self) -> &'static [Direction];
+
+    /// The cell next to `pos` in the direction. It may be outside of the board.
+    fn neighbor(&self, pos: Vec2, direction: Direction) -> Vec2;
+
+    /// Least number of moves from one cell to another on an empty board.
+    fn distance(&self, a: Vec2, b: Vec2) -> i32;
+
+    /// Distance between the centers of the cell and the next one in the direction, in cells. The
+    /// moves that are longer take more time.
+    fn step_length(&self, _direction: Direction) -> f64 {
+        1.0
+    }
+
+    /// Turns a direction chosen by the player into one in which the snake heading the given way
+    /// can go, or `None` if there is no such direction.
+    fn resolve(&self, wanted: Direction, heading: Direction) -> Option<Direction>;
+
+    /// Position of the bottom left corner of the cell relative to that of the board, in pixels.
+    fn corner(&self, pos: Vec2, cell: i32) -> Vec2;
+
+    /// The cell under a pixel, relative to the bottom left corner of the board.
+    fn cell_at(&self, pixel: Vec2, cell: i32) -> Vec2;
+
+    /// Size of the smallest area in whole cells that the board fits into.
+    fn extent(&self, size: (i32, i32)) -> (i32, i32);
+
+    /// Number of pixels left out at both ends of the row `y` of a cell of `size` × `size`
+    /// pixels, which gives the cell its shape.
+    fn inset(&self, y: i32, size: i32) -> i32;
+}
+
+pub struct Square;
+
+impl Topology for Square {
+    fn directions(&self) -> &'static [Direction] {
+        &Direction::ALL
+    }
+
+    fn neighbor(&self, pos: Vec2, direction: Direction) -> Vec2 {
+        pos + direction.vec()
+    }
+
+    fn distance(&self, a: Vec2, b: Vec2) -> i32 {
+        (a.0 - b.0).abs() + (a.1 - b.1).abs()
+    }
+
+    fn resolve(&self, wanted: Direction, _heading: Direction) -> Option<Direction> {
+        Direction::ALL.contains(&wanted).then_some(wanted)
+    }
+
+    fn corner(&self, pos: Vec2, cell: i32) -> Vec2 {
+        Vec2(pos.0 * cell, pos.1 * cell)
+    }
+
+    fn cell_at(&self, pixel: Vec2, cell: i32) -> Vec2 {
+        Vec2(pixel.0.div_euclid(cell), pixel.1.div_euclid(cell))
+    }
+
+    fn extent(&self, size: (i32, i32)) -> (i32, i32) {
+        size
+    }
+
+    fn inset(&self, _y: i32, _size: i32) -> i32 {
+        0
+    }
+}
+
+/// Square cells, with the snakes going to the cells that touch the corners too.
+pub struct Diagonal;
+
+impl Diagonal {
+    const DIRECTIONS: [Direction; 8] = [
+        Direction::Up,
+        Direction::Down,
+        Direction::Left,
+        Direction::Right,
+        Direction::UpLeft,
+        Direction::UpRight,
+        Direction::DownLeft,
+        Direction::DownRight,
+    ];
+}
+
+impl Topology for Diagonal {
+    fn directions(&self) -> &'static [Direction] {
+        &Diagonal::DIRECTIONS
+    }
+
+    fn neighbor(&self, pos: Vec2, direction: Direction) -> Vec2 {
+        pos + direction.vec()
+    }
+
+    fn distance(&self, a: Vec2, b: Vec2) -> i32 {
+        (a.0 - b.0).abs().max((a.1 - b.1).abs())
+    }
+
+    fn step_length(&self, direction: Direction) -> f64 {
+        let Vec2(x, y) = direction.vec();
+        if x != 0 && y != 0 {
+            std::f64::consts::SQRT_2
+        } else {
+            1.0
+        }
+    }
+
+    fn resolve(&self, wanted: Direction, _heading: Direction) -> Option<Direction> {
+        Some(wanted)
+    }
+
+    fn corner(&self, pos: Vec2, cell: i32) -> Vec2 {
+        Square.corner(pos, cell)
+    }
+
+    fn cell_at(&self, pixel: Vec2, cell: i32) -> Vec2 {
+        Square.cell_at(pixel, cell)
+    }
+
+    fn extent(&self, size: (i32, i32)) -> (i32, i32) {
+        size
+    }
+
+    fn inset(&self, _y: i32, _size: i32) -> i32 {
+        0
+    }
+}
+
+pub struct Hex;
+
+impl Hex {
+    const DIRECTIONS: [Direction; 6] = [
+        Direction::Left,
+        Direction::Right,
+        Direction::UpLeft,
+        Direction::UpRight,
+        Direction::DownLeft,
+        Direction::DownRight,
+    ];
+
+    /// Half a cell for the odd rows, which are shifted right, in the units of `scale`.
+    fn shift(y: i32, scale: i32) -> i32 {
+        y.rem_euclid(2) * scale / 2
+    }
+}
+
+impl Topology for Hex {
+    fn directions(&self) -> &'static [Direction] {
+        &Hex::DIRECTIONS
+    }
+
+    fn neighbor(&self, Vec2(x, y): Vec2, direction: Direction) -> Vec2 {
+        // The cells above and below an even row are to the left of those of an odd row.
+        let right = Hex::shift(y, 2);
+        match direction {
+            Direction::Left => Vec2(x - 1, y),
+            Direction::Right => Vec2(x + 1, y),
+            Direction::UpLeft => Vec2(x + right - 1, y + 1),
+            Direction::UpRight => Vec2(x + right, y + 1),
+            Direction::DownLeft => Vec2(x + right - 1, y - 1),
+            Direction::DownRight => Vec2(x + right, y - 1),
+            // Not a move on this board, but the cell straight above or below always touches.
+            Direction::Up => Vec2(x, y + 1),
+            Direction::Down => Vec2(x, y - 1),
+        }
+    }
+
+    fn distance(&self, a: Vec2, b: Vec2) -> i32 {
+        // In axial coordinates, where going up moves one step along the second axis and
+        // up-left also one step back along the first.
+        let axial = |Vec2(x, y): Vec2| (x - y.div_euclid(2), y);
+        let ((q1, r1), (q2, r2)) = (axial(a), axial(b));
+        let (dq, dr) = (q2 - q1, r2 - r1);
+        (dq.abs() + dr.abs() + (dq + dr).abs()) / 2
+    }
+
+    fn resolve(&self, wanted: Direction, heading: Direction) -> Option<Direction> {
+        // Up and down keep the snake on the side of the diagonal it was already going along.
+        let leftwards = matches!(
+            heading,
+            Direction::Left | Direction::UpLeft | Direction::DownLeft
+        );
+        Some(match (wanted, leftwards) {
+            (Direction::Up, true) => Direction::UpLeft,
+            (Direction::Up, false) => Direction::UpRight,
+            (Direction::Down, true) => Direction::DownLeft,
+            (Direction::Down, false) => Direction::DownRight,
+            (direction, _) => direction,
+        })
+    }
+
+    fn corner(&self, pos: Vec2, cell: i32) -> Vec2 {
+        Vec2(pos.0 * cell + Hex::shift(pos.1, cell), pos.1 * cell)
+    }
+
+    fn cell_at(&self, pixel: Vec2, cell: i32) -> Vec2 {
+        let y = pixel.1.div_euclid(cell);
+        Vec2((pixel.0 - Hex::shift(y, cell)).div_euclid(cell), y)
+    }
+
+    fn extent(&self, (width, height): (i32, i32)) -> (i32, i32) {
+        // The odd rows stick out by half a cell.
+        (width + 1, height)
+    }
+
+    fn inset(&self, y: i32, size: i32) -> i32 {
+        // The pointed top and bottom take a quarter of the height each, and too small cells
+        // stay square.
+        if size < 4 {
+            return 0;
+        }
+        let slope = size / 4;
+        let from_edge = y.min(size - 1 - y);
+        (slope - from_edge).max(0) * (size - 2) / (2 * slope)
+    }
+}
+
+/// The topologies that the board can have.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Shape {
+    #[default]
+    Square,
+    Diagonal,
+    Hex,
+}
+
+impl Shape {
+    pub fn name(self) -> &'static str {
+        match self {
+            Shape::Square => "SQUARE",
+            Shape::Diagonal => "DIAGONAL",
+            Shape::Hex => "HEX",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            Shape::Square => Shape::Diagonal,
+            Shape::Diagonal => Shape::Hex,
+            Shape::Hex => Shape::Square,
+        }
+    }
+
+    pub fn topology(self) -> &'static dyn Topology {
+        match self {
+            Shape::Square => &Square,
+            Shape::Diagonal => &Diagonal,
+            Shape::Hex => &Hex,
+        }
+    }
+
+    pub fn is_square(&self) -> bool {
+        *self == Shape::Square
+    }
+}
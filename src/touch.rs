@@ -0,0 +1,107 @@
+//! Touch screen controls. Swipes work like the arrow keys and taps like Enter. Once the screen
+//! has been touched, a d-pad is drawn in the corner, and tapping its buttons works like the
+//! arrow keys too.
+
+use snake_pixels::framebuffer::FrameBuffer;
+use snake_pixels::grid::{Direction, Vec2};
+use snake_pixels::palette::Palette;
+use winit::event::TouchPhase;
+use winit::keyboard::KeyCode;
+
+/// Size of a d-pad button in pixels.
+const BUTTON: i32 = 8;
+/// Shortest movement in pixels that counts as a swipe rather than a tap.
+const SWIPE: i32 = 6;
+
+/// Bottom left corner of the d-pad.
+const ORIGIN: Vec2 = Vec2(1, 1);
+/// Positions of the buttons in the 3×3 grid of the d-pad.
+const BUTTONS: [(Direction, Vec2); 4] = [
+    (Direction::Up, Vec2(1, 2)),
+    (Direction::Down, Vec2(1, 0)),
+    (Direction::Left, Vec2(0, 1)),
+    (Direction::Right, Vec2(2, 1)),
+];
+
+fn arrow(direction: Direction) -> KeyCode {
+    match direction {
+        Direction::Up => KeyCode::ArrowUp,
+        Direction::Down => KeyCode::ArrowDown,
+        Direction::Left => KeyCode::ArrowLeft,
+        Direction::Right => KeyCode::ArrowRight,
+        _ => unreachable!("the d-pad only has the four arrows"),
+    }
+}
+
+#[derive(Default)]
+pub struct TouchControls {
+    visible: bool,
+    /// The finger being followed and where it touched the screen.
+    start: Option<(u64, Vec2)>,
+}
+
+impl TouchControls {
+    fn button_at(pos: Vec2) -> Option<Direction> {
+        let cell = Vec2(
+            (pos.0 - ORIGIN.0).div_euclid(BUTTON),
+            (pos.1 - ORIGIN.1).div_euclid(BUTTON),
+        );
+        BUTTONS
+            .iter()
+            .find(|&&(_, button)| button == cell)
+            .map(|&(direction, _)| direction)
+    }
+
+    /// Turns the touch into a key press once the finger is lifted. `pos` is in the pixels of the
+    /// frame buffer.
+    pub fn on_touch(&mut self, id: u64, phase: TouchPhase, pos: Vec2) -> Option<KeyCode> {
+        self.visible = true;
+        match phase {
+            TouchPhase::Started => {
+                if self.start.is_none() {
+                    self.start = Some((id, pos));
+                }
+                None
+            }
+            TouchPhase::Moved => None,
+            TouchPhase::Cancelled | TouchPhase::Ended => {
+                let (_, start) = self.start.filter(|&(start_id, _)| start_id == id)?;
+                self.start = None;
+                if phase == TouchPhase::Cancelled {
+                    return None;
+                }
+                let (dx, dy) = (pos.0 - start.0, pos.1 - start.1);
+                if dx.abs().max(dy.abs()) >= SWIPE {
+                    let direction = if dx.abs() > dy.abs() {
+                        if dx > 0 {
+                            Direction::Right
+                        } else {
+                            Direction::Left
+                        }
+                    } else if dy > 0 {
+                        Direction::Up
+                    } else {
+                        Direction::Down
+                    };
+                    return Some(arrow(direction));
+                }
+                Some(TouchControls::button_at(pos).map_or(KeyCode::Enter, arrow))
+            }
+        }
+    }
+
+    pub fn render(&self, buffer: &mut FrameBuffer, palette: &Palette) {
+        if !self.visible {
+            return;
+        }
+        for &(_, button) in BUTTONS.iter() {
+            buffer.fill_rectangle(
+                ORIGIN.0 + button.0 * BUTTON,
+                ORIGIN.1 + button.1 * BUTTON,
+                (BUTTON - 1) as usize,
+                (BUTTON - 1) as usize,
+                palette.dim_text,
+            );
+        }
+    }
+}
@@ -0,0 +1,254 @@
+//! Round robin between bot policies: every pair of policies plays the same number of seeded
+//! games against each other, with the sides swapped in every other game.
+
+use crate::bots::Policy;
+use crate::config::Config;
+use crate::controller::{Controller, GameView};
+use crate::grid::Direction;
+use crate::world::{Event, SnakeId, World};
+use serde::Serialize;
+use std::fmt::Write;
+
+/// Games that take longer than this many ticks are stopped and decided by length.
+const MAX_TICKS: u64 = 10_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Csv,
+    Json,
+}
+
+impl Format {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "text" => Some(Format::Text),
+            "csv" => Some(Format::Csv),
+            "json" => Some(Format::Json),
+            _ => None,
+        }
+    }
+}
+
+/// Results of one policy over all its games.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Standing {
+    pub policy: String,
+    pub games: u32,
+    pub wins: u32,
+    pub draws: u32,
+    pub win_rate: f64,
+    pub average_length: f64,
+    /// Average number of ticks the snake stayed alive.
+    pub average_survival: f64,
+}
+
+/// Outcome of a game for one of the snakes.
+struct Outcome {
+    policy: usize,
+    won: bool,
+    draw: bool,
+    length: usize,
+    survival: u64,
+}
+
+/// A game between bots, one for every snake, played a tick at a time.
+pub struct Match {
+    world: World,
+    controllers: Vec<Box<dyn Controller>>,
+    /// The tick at which each snake died, if it did.
+    died_at: Vec<Option<u64>>,
+}
+
+impl Match {
+    pub fn new(config: &Config, controllers: Vec<Box<dyn Controller>>, seed: u64) -> Self {
+        Match {
+            world: World::new(config, controllers.len(), seed),
+            died_at: vec![None; controllers.len()],
+            controllers,
+        }
+    }
+
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    /// Whether the game has ended, or has gone on for so long that it is stopped.
+    pub fn is_over(&self) -> bool {
+        self.world.is_over() || self.world.tick() >= MAX_TICKS
+    }
+
+    pub fn step(&mut self) {
+        let world = &self.world;
+        let turns: Vec<Option<Direction>> = self
+            .controllers
+            .iter_mut()
+            .enumerate()
+            .map(|(id, controller)| {
+                let view = GameView::new(world, id);
+                view.snake().alive.then(|| controller.decide(&view))
+            })
+            .collect();
+        for event in self.world.step(&turns) {
+            if let Event::Died { snake, .. } = event {
+                self.died_at[snake] = Some(self.world.tick());
+            }
+        }
+    }
+
+    /// The snake that won the game so far, or `None` for a draw. In the territory mode the
+    /// snake with the most cells wins. Otherwise the last survivor wins, or if several are alive
+    /// or died at the same time, the longest of them.
+    pub fn winner(&self) -> Option<SnakeId> {
+        let snakes = self.world.snakes();
+        match self.world.territory() {
+            Some(cells) => leader(snakes.len(), |id| cells[id]),
+            None => leader(snakes.len(), |id| {
+                (snakes[id].alive, self.died_at[id], snakes[id].length())
+            }),
+        }
+    }
+
+    /// Number of ticks that the snake stayed alive.
+    fn survival(&self, snake: SnakeId) -> u64 {
+        self.died_at[snake].unwrap_or_else(|| self.world.tick())
+    }
+}
+
+/// The only one of the `n` snakes with the highest key, if there is only one.
+fn leader<K: Ord>(n: usize, key: impl Fn(SnakeId) -> K) -> Option<SnakeId> {
+    let best = (0..n).map(&key).max()?;
+    let mut leaders = (0..n).filter(|&id| key(id) == best);
+    match (leaders.next(), leaders.next()) {
+        (Some(id), None) => Some(id),
+        _ => None,
+    }
+}
+
+/// Plays a game between two policies and returns the outcomes for both of them.
+fn play(config: &Config, policies: &[Policy], pair: [usize; 2], seed: u64) -> [Outcome; 2] {
+    let controllers = pair
+        .iter()
+        .enumerate()
+        .map(|(i, &p)| (policies[p].create)(seed.wrapping_add(i as u64)))
+        .collect();
+    let mut game = Match::new(config, controllers, seed);
+    while !game.is_over() {
+        game.step();
+    }
+    let winner = game.winner();
+    let outcome = |id: usize| Outcome {
+        policy: pair[id],
+        won: winner == Some(id),
+        draw: winner.is_none(),
+        length: game.world().snakes()[id].length(),
+        survival: game.survival(id),
+    };
+    [outcome(0), outcome(1)]
+}
+
+/// Plays `games` games between every pair of policies, spread over all the cores.
+pub fn run(config: &Config, policies: &[Policy], games: u64, seed: u64) -> Vec<Standing> {
+    let mut jobs = Vec::new();
+    for a in 0..policies.len() {
+        for b in a + 1..policies.len() {
+            for game in 0..games {
+                let pair = if game % 2 == 0 { [a, b] } else { [b, a] };
+                jobs.push((pair, seed.wrapping_add(game)));
+            }
+        }
+    }
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk = jobs.len().div_ceil(threads).max(1);
+    let outcomes: Vec<Outcome> = std::thread::scope(|scope| {
+        let handles: Vec<_> = jobs
+            .chunks(chunk)
+            .map(|jobs| {
+                scope.spawn(move || {
+                    jobs.iter()
+                        .flat_map(|&(pair, seed)| play(config, policies, pair, seed))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect()
+    });
+
+    let mut standings: Vec<Standing> = policies
+        .iter()
+        .map(|p| Standing {
+            policy: p.name.to_string(),
+            ..Standing::default()
+        })
+        .collect();
+    let mut totals = vec![(0, 0); policies.len()];
+    for outcome in outcomes {
+        let standing = &mut standings[outcome.policy];
+        standing.games += 1;
+        standing.wins += outcome.won as u32;
+        standing.draws += outcome.draw as u32;
+        totals[outcome.policy].0 += outcome.length;
+        totals[outcome.policy].1 += outcome.survival;
+    }
+    for (standing, (length, survival)) in standings.iter_mut().zip(totals) {
+        let games = standing.games.max(1) as f64;
+        standing.win_rate = standing.wins as f64 / games;
+        standing.average_length = length as f64 / games;
+        standing.average_survival = survival as f64 / games;
+    }
+    standings.sort_by(|a, b| b.win_rate.total_cmp(&a.win_rate));
+    standings
+}
+
+pub fn format(standings: &[Standing], format: Format) -> String {
+    let mut out = String::new();
+    match format {
+        Format::Text => {
+            writeln!(
+                out,
+                "{:<12} {:>6} {:>6} {:>6} {:>9} {:>11} {:>13}",
+                "POLICY", "GAMES", "WINS", "DRAWS", "WIN RATE", "AVG LENGTH", "AVG SURVIVAL"
+            )
+            .unwrap();
+            for s in standings {
+                writeln!(
+                    out,
+                    "{:<12} {:>6} {:>6} {:>6} {:>8.1}% {:>11.1} {:>13.1}",
+                    s.policy,
+                    s.games,
+                    s.wins,
+                    s.draws,
+                    100.0 * s.win_rate,
+                    s.average_length,
+                    s.average_survival
+                )
+                .unwrap();
+            }
+        }
+        Format::Csv => {
+            out.push_str("policy,games,wins,draws,win_rate,average_length,average_survival\n");
+            for s in standings {
+                writeln!(
+                    out,
+                    "{},{},{},{},{:.4},{:.2},{:.2}",
+                    s.policy,
+                    s.games,
+                    s.wins,
+                    s.draws,
+                    s.win_rate,
+                    s.average_length,
+                    s.average_survival
+                )
+                .unwrap();
+            }
+        }
+        Format::Json => {
+            out = serde_json::to_string_pretty(standings).unwrap();
+            out.push('\n');
+        }
+    }
+    out
+}
@@ -0,0 +1,416 @@
+//! Ways of carrying the messages of `net` between the server and the clients. The server
+//! accepts players over UDP and over WebSockets on the same port number, so that browsers can
+//! join the same matches as the native game. In the browser only the WebSocket of the page is
+//! there to connect with.
+
+use crate::net::{ClientMessage, ServerMessage};
+#[cfg(not(target_arch = "wasm32"))]
+use log::{info, warn};
+#[cfg(not(target_arch = "wasm32"))]
+use std::collections::HashMap;
+use std::io::{self, ErrorKind};
+#[cfg(not(target_arch = "wasm32"))]
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs, UdpSocket};
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::{Arc, Mutex};
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use tungstenite::{Message, WebSocket};
+
+#[cfg(target_arch = "wasm32")]
+pub use browser::WebSocketTransport;
+
+#[cfg(not(target_arch = "wasm32"))]
+const MAX_DATAGRAM: usize = 65_507;
+/// How long a WebSocket connection thread waits for input before checking for outgoing
+/// messages.
+#[cfg(not(target_arch = "wasm32"))]
+const WEBSOCKET_POLL: Duration = Duration::from_millis(5);
+
+/// Client side of a connection to the server.
+pub trait Transport {
+    fn send(&mut self, message: &ClientMessage) -> io::Result<()>;
+
+    /// Returns the next message from the server if one has arrived. Never blocks.
+    fn receive(&mut self) -> io::Result<Option<ServerMessage>>;
+}
+
+/// Connects to the server. Addresses starting with `ws://` use a WebSocket, anything else is
+/// taken as `HOST:PORT` for UDP, which the browser doesn't have.
+pub fn connect(address: &str) -> io::Result<Box<dyn Transport>> {
+    if address.starts_with("ws://") {
+        return Ok(Box::new(WebSocketTransport::connect(address)?));
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    return Ok(Box::new(UdpTransport::connect(address)?));
+    #[cfg(target_arch = "wasm32")]
+    Err(io::Error::new(
+        ErrorKind::Unsupported,
+        "only ws:// addresses can be joined in the browser",
+    ))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub struct UdpTransport {
+    socket: UdpSocket,
+    server: SocketAddr,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl UdpTransport {
+    pub fn connect(address: &str) -> io::Result<Self> {
+        let server = resolve(address)?;
+        let socket = UdpSocket::bind(if server.is_ipv4() {
+            "0.0.0.0:0"
+        } else {
+            "[::]:0"
+        })?;
+        socket.set_nonblocking(true)?;
+        Ok(UdpTransport { socket, server })
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Transport for UdpTransport {
+    fn send(&mut self, message: &ClientMessage) -> io::Result<()> {
+        let data = serde_json::to_vec(message)?;
+        self.socket.send_to(&data, self.server)?;
+        Ok(())
+    }
+
+    fn receive(&mut self) -> io::Result<Option<ServerMessage>> {
+        let mut buf = vec![0; MAX_DATAGRAM];
+        loop {
+            let (len, addr) = match self.socket.recv_from(&mut buf) {
+                Ok(received) => received,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(None),
+                Err(e) => return Err(e),
+            };
+            if addr != self.server {
+                continue;
+            }
+            match serde_json::from_slice(&buf[..len]) {
+                Ok(message) => return Ok(Some(message)),
+                Err(e) => warn!("Malformed message from the server: {}", e),
+            }
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub struct WebSocketTransport {
+    socket: WebSocket<TcpStream>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl WebSocketTransport {
+    pub fn connect(url: &str) -> io::Result<Self> {
+        let host = url.trim_start_matches("ws://").split('/').next().unwrap();
+        let stream = TcpStream::connect(resolve(host)?)?;
+        let (socket, _) = tungstenite::client(url, stream)
+            .map_err(|e| io::Error::new(ErrorKind::ConnectionRefused, e.to_string()))?;
+        socket.get_ref().set_nonblocking(true)?;
+        socket.get_ref().set_nodelay(true)?;
+        Ok(WebSocketTransport { socket })
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Transport for WebSocketTransport {
+    fn send(&mut self, message: &ClientMessage) -> io::Result<()> {
+        let text = serde_json::to_string(message)?;
+        match self.socket.send(Message::Text(text)) {
+            Ok(()) => Ok(()),
+            // The message stays buffered and goes out with the next one.
+            Err(tungstenite::Error::Io(e)) if e.kind() == ErrorKind::WouldBlock => Ok(()),
+            Err(e) => Err(into_io_error(e)),
+        }
+    }
+
+    fn receive(&mut self) -> io::Result<Option<ServerMessage>> {
+        loop {
+            match self.socket.read() {
+                Ok(Message::Text(text)) => match serde_json::from_str(&text) {
+                    Ok(message) => return Ok(Some(message)),
+                    Err(e) => warn!("Malformed message from the server: {}", e),
+                },
+                Ok(_) => (),
+                Err(tungstenite::Error::Io(e)) if e.kind() == ErrorKind::WouldBlock => {
+                    return Ok(None)
+                }
+                Err(e) => return Err(into_io_error(e)),
+            }
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn resolve(address: &str) -> io::Result<SocketAddr> {
+    address
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::new(ErrorKind::NotFound, "no address for the server"))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn into_io_error(e: tungstenite::Error) -> io::Error {
+    match e {
+        tungstenite::Error::Io(e) => e,
+        e => io::Error::new(ErrorKind::ConnectionAborted, e.to_string()),
+    }
+}
+
+/// A client connected to the server.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Peer {
+    Udp(SocketAddr),
+    WebSocket(usize),
+}
+
+/// Server side of all the transports: receives the messages from every client in one place.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct Hub {
+    udp: UdpSocket,
+    inbound: Receiver<(Peer, ClientMessage)>,
+    /// Outgoing messages for the thread serving each WebSocket connection.
+    websockets: Arc<Mutex<HashMap<usize, Sender<String>>>>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Hub {
+    /// Listens for UDP datagrams and WebSocket connections on the same port.
+    pub fn bind(port: u16) -> io::Result<Self> {
+        let udp = UdpSocket::bind(("0.0.0.0", port))?;
+        let listener = TcpListener::bind(("0.0.0.0", port))?;
+        info!(
+            "Listening on udp://{} and ws://{}",
+            udp.local_addr()?,
+            listener.local_addr()?
+        );
+
+        let (sender, inbound) = mpsc::channel();
+        let websockets = Arc::new(Mutex::new(HashMap::new()));
+
+        let socket = udp.try_clone()?;
+        let udp_sender = sender.clone();
+        std::thread::spawn(move || receive_datagrams(socket, udp_sender));
+
+        let connections = Arc::clone(&websockets);
+        std::thread::spawn(move || accept_websockets(listener, sender, connections));
+
+        Ok(Hub {
+            udp,
+            inbound,
+            websockets,
+        })
+    }
+
+    /// Waits for the next message for at most `timeout`.
+    pub fn receive(&self, timeout: Duration) -> Option<(Peer, ClientMessage)> {
+        match self.inbound.recv_timeout(timeout) {
+            Ok(received) => Some(received),
+            Err(RecvTimeoutError::Timeout) => None,
+            Err(RecvTimeoutError::Disconnected) => panic!("all the listeners have stopped"),
+        }
+    }
+
+    pub fn send(&self, peer: Peer, message: &ServerMessage) {
+        let text = serde_json::to_string(message).unwrap();
+        match peer {
+            Peer::Udp(addr) => {
+                if let Err(e) = self.udp.send_to(text.as_bytes(), addr) {
+                    warn!("Failed to send a message to {}: {}", addr, e);
+                }
+            }
+            Peer::WebSocket(id) => {
+                let mut websockets = self.websockets.lock().unwrap();
+                if let Some(sender) = websockets.get(&id) {
+                    if sender.send(text).is_err() {
+                        websockets.remove(&id);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn receive_datagrams(socket: UdpSocket, inbound: Sender<(Peer, ClientMessage)>) {
+    let mut buf = vec![0; MAX_DATAGRAM];
+    loop {
+        let (len, addr) = match socket.recv_from(&mut buf) {
+            Ok(received) => received,
+            Err(e) => {
+                warn!("Failed to receive a datagram: {}", e);
+                continue;
+            }
+        };
+        match serde_json::from_slice(&buf[..len]) {
+            Ok(message) => {
+                if inbound.send((Peer::Udp(addr), message)).is_err() {
+                    return;
+                }
+            }
+            Err(e) => warn!("Malformed message from {}: {}", addr, e),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn accept_websockets(
+    listener: TcpListener,
+    inbound: Sender<(Peer, ClientMessage)>,
+    connections: Arc<Mutex<HashMap<usize, Sender<String>>>>,
+) {
+    for (id, stream) in listener.incoming().enumerate() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                warn!("Failed to accept a connection: {}", e);
+                continue;
+            }
+        };
+        let (sender, outbound) = mpsc::channel();
+        connections.lock().unwrap().insert(id, sender);
+        let inbound = inbound.clone();
+        std::thread::spawn(move || {
+            if let Err(e) = serve_websocket(id, stream, inbound, outbound) {
+                info!("WebSocket connection {} closed: {}", id, e);
+            }
+        });
+    }
+}
+
+/// Forwards the messages in both directions until the connection is closed.
+#[cfg(not(target_arch = "wasm32"))]
+fn serve_websocket(
+    id: usize,
+    stream: TcpStream,
+    inbound: Sender<(Peer, ClientMessage)>,
+    outbound: Receiver<String>,
+) -> io::Result<()> {
+    let addr = stream.peer_addr()?;
+    stream.set_nodelay(true)?;
+    let mut socket = tungstenite::accept(stream)
+        .map_err(|e| io::Error::new(ErrorKind::InvalidData, e.to_string()))?;
+    socket.get_ref().set_read_timeout(Some(WEBSOCKET_POLL))?;
+    info!("WebSocket connection {} from {}", id, addr);
+
+    loop {
+        match socket.read() {
+            Ok(Message::Text(text)) => match serde_json::from_str(&text) {
+                Ok(message) => {
+                    if inbound.send((Peer::WebSocket(id), message)).is_err() {
+                        return Ok(());
+                    }
+                }
+                Err(e) => warn!("Malformed message from {}: {}", addr, e),
+            },
+            Ok(_) => (),
+            Err(tungstenite::Error::Io(e))
+                if e.kind() == ErrorKind::WouldBlock || e.kind() == ErrorKind::TimedOut => {}
+            Err(e) => return Err(into_io_error(e)),
+        }
+
+        for text in outbound.try_iter() {
+            socket.send(Message::Text(text)).map_err(into_io_error)?;
+        }
+    }
+}
+
+/// The transport of the browser, which only has the WebSocket of the page.
+#[cfg(target_arch = "wasm32")]
+mod browser {
+    use super::Transport;
+    use crate::net::{ClientMessage, ServerMessage};
+    use log::warn;
+    use std::cell::RefCell;
+    use std::collections::VecDeque;
+    use std::io::{self, ErrorKind};
+    use std::rc::Rc;
+    use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+    use web_sys::MessageEvent;
+
+    /// The WebSocket of the page, which hands over the messages in a callback. They wait there
+    /// until they are received, and the ones sent before the connection opens wait to be sent.
+    pub struct WebSocketTransport {
+        socket: web_sys::WebSocket,
+        inbox: Rc<RefCell<VecDeque<String>>>,
+        outbox: Vec<String>,
+        /// Called by the socket for as long as it's open.
+        _on_message: Closure<dyn FnMut(MessageEvent)>,
+    }
+
+    impl WebSocketTransport {
+        pub fn connect(url: &str) -> io::Result<Self> {
+            let socket = web_sys::WebSocket::new(url).map_err(from_js_error)?;
+            let inbox = Rc::new(RefCell::new(VecDeque::new()));
+            let arrived = Rc::clone(&inbox);
+            let on_message = Closure::<dyn FnMut(MessageEvent)>::new(move |event: MessageEvent| {
+                match event.data().as_string() {
+                    Some(text) => arrived.borrow_mut().push_back(text),
+                    None => warn!("Binary message from the server"),
+                }
+            });
+            socket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+            Ok(WebSocketTransport {
+                socket,
+                inbox,
+                outbox: Vec::new(),
+                _on_message: on_message,
+            })
+        }
+
+        /// Sends the messages that waited for the connection to open, if it is open.
+        fn flush(&mut self) -> io::Result<()> {
+            match self.socket.ready_state() {
+                web_sys::WebSocket::CONNECTING => Ok(()),
+                web_sys::WebSocket::OPEN => {
+                    for text in self.outbox.drain(..) {
+                        self.socket.send_with_str(&text).map_err(from_js_error)?;
+                    }
+                    Ok(())
+                }
+                _ => Err(io::Error::new(
+                    ErrorKind::ConnectionAborted,
+                    "the connection is closed",
+                )),
+            }
+        }
+    }
+
+    impl Transport for WebSocketTransport {
+        fn send(&mut self, message: &ClientMessage) -> io::Result<()> {
+            self.outbox.push(serde_json::to_string(message)?);
+            self.flush()
+        }
+
+        fn receive(&mut self) -> io::Result<Option<ServerMessage>> {
+            loop {
+                let text = self.inbox.borrow_mut().pop_front();
+                match text {
+                    Some(text) => match serde_json::from_str(&text) {
+                        Ok(message) => return Ok(Some(message)),
+                        Err(e) => warn!("Malformed message from the server: {}", e),
+                    },
+                    None => return self.flush().map(|()| None),
+                }
+            }
+        }
+    }
+
+    impl Drop for WebSocketTransport {
+        fn drop(&mut self) {
+            self.socket.set_onmessage(None);
+            let _ = self.socket.close();
+        }
+    }
+
+    fn from_js_error(e: JsValue) -> io::Error {
+        io::Error::new(ErrorKind::ConnectionRefused, format!("{:?}", e))
+    }
+}
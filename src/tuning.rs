@@ -0,0 +1,148 @@
+//! Game parameters and colors read from the file given with `--config`, for tuning them. With the
+//! `hot-reload` feature the file and the level being played are watched, and their changes apply
+//! without restarting.
+
+use log::warn;
+use serde::Deserialize;
+use snake_pixels::config::Config;
+use snake_pixels::framebuffer::Color;
+use snake_pixels::palette::Palette;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Contents of the file, e.g.
+///
+/// ```json
+/// {
+///     "config": { "tick_ms": 250, "food_interval": 6 },
+///     "colors": { "food": "#F04040" },
+///     "reduced_motion": true
+/// }
+/// ```
+#[derive(Default, Deserialize)]
+#[serde(default)]
+pub struct Tuning {
+    /// Parameters replacing the defaults, apart from the difficulty, which comes from the profile.
+    pub config: Option<Config>,
+    /// Colors replacing those of the theme, as `#RRGGBB` by the names of the fields of `Palette`.
+    pub colors: BTreeMap<String, String>,
+    /// Replaces the setting of the profile, e.g. to see how the game looks without the
+    /// animations.
+    pub reduced_motion: Option<bool>,
+}
+
+impl Tuning {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let tuning: Tuning = serde_json::from_str(&text).map_err(|e| e.to_string())?;
+        if let Some(config) = &tuning.config {
+            config.validate()?;
+        }
+        Ok(tuning)
+    }
+
+    /// The palette with the colors replaced, logging the ones that can't be.
+    pub fn palette(&self, palette: &Palette) -> Palette {
+        let mut palette = palette.clone();
+        for (name, text) in self.colors.iter() {
+            match Color::parse(text) {
+                Some(color) if palette.set_color(name, color) => (),
+                Some(_) => warn!("Unknown color {}", name),
+                None => warn!("Color {} should be written as #RRGGBB, not {}", name, text),
+            }
+        }
+        palette
+    }
+}
+
+/// Reports the changes to a set of files.
+#[cfg(feature = "hot-reload")]
+pub struct Watcher {
+    watcher: notify::RecommendedWatcher,
+    events: std::sync::mpsc::Receiver<notify::Result<notify::Event>>,
+    /// The watched files as they were given and by their absolute paths.
+    files: Vec<(std::path::PathBuf, std::path::PathBuf)>,
+}
+
+#[cfg(feature = "hot-reload")]
+impl Watcher {
+    pub fn new() -> notify::Result<Self> {
+        let (sender, events) = std::sync::mpsc::channel();
+        Ok(Watcher {
+            watcher: notify::recommended_watcher(sender)?,
+            events,
+            files: Vec::new(),
+        })
+    }
+
+    /// Starts watching the file. Its directory is watched instead of the file itself, since many
+    /// editors save by replacing the file.
+    pub fn watch(&mut self, file: &Path) {
+        use notify::Watcher as _;
+
+        if self.files.iter().any(|(given, _)| given == file) {
+            return;
+        }
+        let dir = match file.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let absolute = match (fs::canonicalize(dir), file.file_name()) {
+            (Ok(dir), Some(name)) => dir.join(name),
+            (Err(e), _) => {
+                warn!("Failed to watch {}: {}", file.display(), e);
+                return;
+            }
+            (_, None) => return,
+        };
+        let dir = absolute.parent().unwrap();
+        if !self.files.iter().any(|(_, f)| f.parent() == Some(dir)) {
+            if let Err(e) = self.watcher.watch(dir, notify::RecursiveMode::NonRecursive) {
+                warn!("Failed to watch {}: {}", dir.display(), e);
+                return;
+            }
+        }
+        self.files.push((file.to_path_buf(), absolute));
+    }
+
+    pub fn unwatch(&mut self, file: &Path) {
+        use notify::Watcher as _;
+
+        let index = match self.files.iter().position(|(given, _)| given == file) {
+            Some(index) => index,
+            None => return,
+        };
+        let (_, absolute) = self.files.remove(index);
+        let dir = absolute.parent().unwrap();
+        if !self.files.iter().any(|(_, f)| f.parent() == Some(dir)) {
+            let _ = self.watcher.unwatch(dir);
+        }
+    }
+
+    /// The watched files that changed since the last call, as they were passed to `watch`.
+    pub fn changed(&mut self) -> Vec<std::path::PathBuf> {
+        let mut changed = Vec::new();
+        for event in self.events.try_iter() {
+            let event = match event {
+                Ok(event) => event,
+                Err(e) => {
+                    warn!("Failed to watch the files: {}", e);
+                    continue;
+                }
+            };
+            if !matches!(
+                event.kind,
+                notify::EventKind::Create(_) | notify::EventKind::Modify(_)
+            ) {
+                continue;
+            }
+            for (given, absolute) in self.files.iter() {
+                if event.paths.contains(absolute) && !changed.contains(given) {
+                    changed.push(given.clone());
+                }
+            }
+        }
+        changed
+    }
+}
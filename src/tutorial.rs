@@ -0,0 +1,203 @@
+//! A small level for new players, where the game stops the first time each of the mechanics
+//! comes up and explains it. It is played instead of the first game of a profile and can be
+//! played again from the menu.
+
+use crate::i18n::tr;
+use snake_pixels::font::{draw_text_centered, LINE_HEIGHT};
+use snake_pixels::framebuffer::FrameBuffer;
+use snake_pixels::grid::{Tile, Vec2};
+use snake_pixels::level::Level;
+use snake_pixels::palette::Palette;
+use snake_pixels::world::{Camera, World};
+
+const LEVEL: &str = "\
+###############
+#.............#
+#.............#
+#...1.........#
+#.............#
+#.............#
+#.....F.......#
+#.............#
+#.......F.....#
+#.............#
+#..........1..#
+#.............#
+#...S.........#
+#.............#
+###############
+";
+
+/// Food to eat to complete the tutorial.
+const GOAL: u32 = 5;
+
+/// Length at which the snake is long enough to run into itself.
+const TAIL_LENGTH: usize = 5;
+
+/// How close the snake has to come to a wall or a portal for it to be explained.
+const DISTANCE: i32 = 2;
+
+/// Opacity of the background behind a prompt.
+const BACKGROUND_ALPHA: u8 = 200;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Lesson {
+    Turn,
+    Eat,
+    Wall,
+    Portal,
+    Tail,
+    Done,
+}
+
+impl Lesson {
+    fn text(self) -> &'static str {
+        match self {
+            Lesson::Turn => "USE THE ARROW KEYS TO TURN",
+            Lesson::Eat => "EAT THE FOOD TO GROW",
+            Lesson::Wall => "DON'T RUN INTO WALLS",
+            Lesson::Portal => "PORTALS LEAD TO EACH OTHER",
+            Lesson::Tail => "DON'T BITE YOUR OWN TAIL",
+            Lesson::Done => "WELL DONE, HAVE FUN!",
+        }
+    }
+
+    /// The cells that the lesson is about, which are framed while it's shown.
+    fn cells(self, world: &World) -> Vec<Vec2> {
+        let snake = &world.snakes()[0];
+        match self {
+            Lesson::Turn => vec![snake.head],
+            Lesson::Eat => world.food().collect(),
+            Lesson::Wall => ahead(world).filter(|&pos| is_wall(world, pos)).collect(),
+            Lesson::Portal => world
+                .objects()
+                .filter(|&(_, tile)| tile == Tile::Portal)
+                .map(|(pos, _)| pos)
+                .collect(),
+            Lesson::Tail => snake.tail.iter().copied().collect(),
+            Lesson::Done => Vec::new(),
+        }
+    }
+
+    /// Whether the mechanic has come up in the game.
+    fn applies(self, world: &World) -> bool {
+        let snake = &world.snakes()[0];
+        match self {
+            Lesson::Turn => true,
+            Lesson::Eat => world.food().next().is_some(),
+            Lesson::Wall => ahead(world).any(|pos| is_wall(world, pos)),
+            Lesson::Portal => world.objects().any(|(pos, tile)| {
+                let distance = (pos.0 - snake.head.0).abs() + (pos.1 - snake.head.1).abs();
+                tile == Tile::Portal && distance <= DISTANCE
+            }),
+            Lesson::Tail => snake.length() >= TAIL_LENGTH,
+            Lesson::Done => snake.score >= GOAL,
+        }
+    }
+}
+
+/// The cells right in front of the player's snake.
+fn ahead(world: &World) -> impl Iterator<Item = Vec2> + '_ {
+    let snake = &world.snakes()[0];
+    let direction = snake.direction;
+    std::iter::successors(Some(snake.head), move |&pos| {
+        Some(world.neighbor(pos, direction))
+    })
+    .skip(1)
+    .take(DISTANCE as usize)
+}
+
+/// Walls and the outside of the board.
+fn is_wall(world: &World, pos: Vec2) -> bool {
+    matches!(world.tile(pos), None | Some(Tile::Wall))
+}
+
+pub struct Tutorial {
+    /// Lessons that haven't been shown yet, in the order of their priority.
+    lessons: Vec<Lesson>,
+    /// The lesson being shown, during which the game is paused.
+    shown: Option<Lesson>,
+}
+
+impl Tutorial {
+    pub fn new() -> Self {
+        Tutorial {
+            lessons: vec![
+                Lesson::Turn,
+                Lesson::Done,
+                Lesson::Wall,
+                Lesson::Portal,
+                Lesson::Eat,
+                Lesson::Tail,
+            ],
+            shown: None,
+        }
+    }
+
+    pub fn level() -> Level {
+        Level::load(LEVEL).expect("the tutorial level is valid")
+    }
+
+    /// Shows the first lesson that comes up in the game. Returns whether one is shown.
+    pub fn update(&mut self, world: &World) -> bool {
+        if self.shown.is_none() {
+            if let Some(i) = self.lessons.iter().position(|l| l.applies(world)) {
+                self.shown = Some(self.lessons.remove(i));
+            }
+        }
+        self.shown.is_some()
+    }
+
+    /// Whether a lesson is shown, during which the game doesn't go on.
+    pub fn is_shown(&self) -> bool {
+        self.shown.is_some()
+    }
+
+    /// Hides the lesson being shown. Returns whether it was the last one.
+    pub fn dismiss(&mut self) -> bool {
+        self.shown.take() == Some(Lesson::Done)
+    }
+
+    pub fn render(
+        &self,
+        buffer: &mut FrameBuffer,
+        palette: &Palette,
+        camera: &Camera,
+        world: &World,
+    ) {
+        let lesson = match self.shown {
+            Some(lesson) => lesson,
+            None => return,
+        };
+        let cell = camera.cell;
+        for pos in lesson
+            .cells(world)
+            .into_iter()
+            .filter(|&pos| camera.shows(pos))
+        {
+            let Vec2(x, y) = camera.corner(pos);
+            let size = cell as usize + 2;
+            buffer.fill_rectangle(x - 1, y - 1, size, 1, palette.selected);
+            buffer.fill_rectangle(x - 1, y + cell, size, 1, palette.selected);
+            buffer.fill_rectangle(x - 1, y - 1, 1, size, palette.selected);
+            buffer.fill_rectangle(x + cell, y - 1, 1, size, palette.selected);
+        }
+
+        // In the lower third, above the start of the snake.
+        let y = buffer.height() as i32 / 3;
+        let width = buffer.width();
+        let height = 2 * LINE_HEIGHT + 3;
+        let bottom = y - height + 2;
+        buffer.blend_rectangle(
+            0,
+            bottom,
+            width,
+            height as usize,
+            palette.menu_bg,
+            BACKGROUND_ALPHA,
+        );
+        draw_text_centered(buffer, y, &tr(lesson.text()), 1, palette.text);
+        let hint = tr("PRESS ANY KEY");
+        draw_text_centered(buffer, y - LINE_HEIGHT, &hint, 1, palette.dim_text);
+    }
+}
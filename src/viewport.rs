@@ -0,0 +1,234 @@
+//! Zooming and panning of a camera with the mouse: the wheel zooms in on the point under the
+//! cursor and dragging with the middle button moves the view. The view glides towards where it
+//! was sent rather than jumping there, and it never leaves the board.
+
+use snake_pixels::grid::Vec2;
+use snake_pixels::world::Camera;
+use std::cell::Cell;
+use std::time::Duration;
+use web_time::Instant;
+
+/// Most that the cells can be magnified over those of the camera that shows the whole board.
+pub const MAX_ZOOM: f32 = 8.0;
+/// Magnification by one notch of the mouse wheel.
+const WHEEL_STEP: f32 = 1.25;
+/// Time in which the view covers about two thirds of the way to where it was sent.
+const SMOOTHING: Duration = Duration::from_millis(80);
+/// Time between two frames while the view moves.
+const FRAME: Duration = Duration::from_millis(16);
+
+/// Magnification and the point of the board at the center of the view, in cells.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct View {
+    zoom: f32,
+    center: (f32, f32),
+}
+
+impl View {
+    /// Keeps the magnification in range and the board around the whole view.
+    fn clamped(self, (width, height): (i32, i32)) -> Self {
+        let zoom = self.zoom.clamp(1.0, MAX_ZOOM);
+        let clamp = |center: f32, cells: i32| {
+            let half = cells as f32 / (2.0 * zoom);
+            center.clamp(half, cells as f32 - half)
+        };
+        View {
+            zoom,
+            center: (clamp(self.center.0, width), clamp(self.center.1, height)),
+        }
+    }
+}
+
+/// The camera that shows the whole board and the size of the board, as of the last frame.
+#[derive(Clone, Copy, Debug)]
+struct Frame {
+    base: Camera,
+    extent: (i32, i32),
+}
+
+impl Frame {
+    /// The pixel of the buffer at the center of the board as the base camera shows it.
+    fn middle(&self) -> (f32, f32) {
+        let Vec2(x, y) = self.base.origin;
+        let half = self.base.cell as f32 / 2.0;
+        (
+            x as f32 + self.extent.0 as f32 * half,
+            y as f32 + self.extent.1 as f32 * half,
+        )
+    }
+}
+
+pub struct Viewport {
+    /// Where the view is now.
+    shown: View,
+    /// Where the view is headed.
+    target: View,
+    /// Set by `camera`, which the mouse positions are measured against.
+    frame: Cell<Option<Frame>>,
+    /// Position of the mouse in the buffer.
+    cursor: Option<Vec2>,
+    /// Whether the middle button is held.
+    dragging: bool,
+    last_update: Instant,
+}
+
+impl Viewport {
+    pub fn new() -> Self {
+        let view = View {
+            zoom: 1.0,
+            center: (0.0, 0.0),
+        };
+        Viewport {
+            shown: view,
+            target: view,
+            frame: Cell::new(None),
+            cursor: None,
+            dragging: false,
+            last_update: Instant::now(),
+        }
+    }
+
+    /// Magnifies and moves the camera that shows the whole board of the given `extent`.
+    pub fn camera(&self, base: Camera, extent: (i32, i32)) -> Camera {
+        let frame = Frame { base, extent };
+        self.frame.set(Some(frame));
+        let view = self.shown.clamped(extent);
+        let cell = (base.cell as f32 * view.zoom).round().max(1.0);
+        let (x, y) = frame.middle();
+        Camera {
+            origin: Vec2(
+                (x - view.center.0 * cell).round() as i32,
+                (y - view.center.1 * cell).round() as i32,
+            ),
+            cell: cell as i32,
+            ..base
+        }
+    }
+
+    /// The last frame, with the views brought within its board.
+    fn settle(&mut self) -> Option<Frame> {
+        let frame = self.frame.get()?;
+        if self.shown == self.target {
+            // The time while the view stood still doesn't count towards its next move.
+            self.last_update = Instant::now();
+        }
+        self.shown = self.shown.clamped(frame.extent);
+        self.target = self.target.clamped(frame.extent);
+        Some(frame)
+    }
+
+    /// Whether the view shows less than the whole board.
+    pub fn is_zoomed(&self) -> bool {
+        self.target.zoom > 1.0
+    }
+
+    /// Shows the whole board again.
+    pub fn reset(&mut self) {
+        if let Some(frame) = self.settle() {
+            self.target = View {
+                zoom: 1.0,
+                center: (frame.extent.0 as f32 / 2.0, frame.extent.1 as f32 / 2.0),
+            };
+        }
+    }
+
+    /// Magnifies the cells by `factor`, keeping the point under the mouse in its place, or the
+    /// center of the view if the mouse is elsewhere.
+    pub fn zoom(&mut self, factor: f32) {
+        let Some(frame) = self.settle() else {
+            return;
+        };
+        let middle = frame.middle();
+        let anchor = self.cursor.map_or((0.0, 0.0), |Vec2(x, y)| {
+            (x as f32 - middle.0, y as f32 - middle.1)
+        });
+        let cell = frame.base.cell as f32;
+        let target = self.target;
+        // The point of the board under the anchor stays there.
+        let point = (
+            target.center.0 + anchor.0 / (cell * target.zoom),
+            target.center.1 + anchor.1 / (cell * target.zoom),
+        );
+        let zoom = (target.zoom * factor).clamp(1.0, MAX_ZOOM);
+        self.target = View {
+            zoom,
+            center: (
+                point.0 - anchor.0 / (cell * zoom),
+                point.1 - anchor.1 / (cell * zoom),
+            ),
+        }
+        .clamped(frame.extent);
+    }
+
+    /// Moves the view by the given number of cells.
+    pub fn pan(&mut self, cells: Vec2) {
+        if let Some(frame) = self.settle() {
+            let (x, y) = self.target.center;
+            self.target.center = (x + cells.0 as f32, y + cells.1 as f32);
+            self.target = self.target.clamped(frame.extent);
+        }
+    }
+
+    /// Zooms by the notches of the mouse wheel, in or out depending on their sign.
+    pub fn on_wheel(&mut self, notches: f32) {
+        self.zoom(WHEEL_STEP.powf(notches));
+    }
+
+    /// Returns whether the screen needs to be redrawn.
+    pub fn on_cursor_moved(&mut self, pos: Vec2) -> bool {
+        let last = self.cursor.replace(pos);
+        let (Some(last), true) = (last, self.dragging) else {
+            return false;
+        };
+        let Some(frame) = self.settle() else {
+            return false;
+        };
+        // The board follows the mouse right away, without gliding.
+        let cell = frame.base.cell as f32 * self.shown.zoom;
+        let (x, y) = self.target.center;
+        self.target.center = (
+            x - (pos.0 - last.0) as f32 / cell,
+            y - (pos.1 - last.1) as f32 / cell,
+        );
+        self.target = self.target.clamped(frame.extent);
+        self.shown.center = self.target.center;
+        true
+    }
+
+    /// Starts or stops dragging the view with the middle button.
+    pub fn on_middle_button(&mut self, pressed: bool) {
+        self.dragging = pressed;
+    }
+
+    /// Brings the view closer to where it was sent. Returns whether the screen needs to be
+    /// redrawn.
+    pub fn update(&mut self) -> bool {
+        let now = Instant::now();
+        let elapsed = now - std::mem::replace(&mut self.last_update, now);
+        if self.shown == self.target {
+            return false;
+        }
+        let step = 1.0 - (-elapsed.as_secs_f32() / SMOOTHING.as_secs_f32()).exp();
+        let approach = |from: f32, to: f32, epsilon: f32| {
+            let next = from + (to - from) * step;
+            if (to - next).abs() < epsilon {
+                to
+            } else {
+                next
+            }
+        };
+        self.shown = View {
+            zoom: approach(self.shown.zoom, self.target.zoom, 0.002),
+            center: (
+                approach(self.shown.center.0, self.target.center.0, 0.01),
+                approach(self.shown.center.1, self.target.center.1, 0.01),
+            ),
+        };
+        true
+    }
+
+    /// When the next frame is due, while the view is still on its way.
+    pub fn next_frame(&self) -> Option<Instant> {
+        (self.shown != self.target).then(|| Instant::now() + FRAME)
+    }
+}
@@ -0,0 +1,2467 @@
+use crate::config::{Config, Mode, COOP_LIVES, MAX_FOOD_SPREAD};
+use crate::framebuffer::{Color, Fill, FrameBuffer};
+use crate::grid::{Direction, Grid, Tile, Vec2};
+use crate::level::{Cell, Level};
+use crate::palette::Palette;
+use crate::renderer::Quad;
+use crate::skin::Skin;
+use crate::telemetry::EndReason;
+use crate::topology::{Shape, Topology};
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::time::Duration;
+
+/// How many cells straight in front of a head are kept free of new food, so that it doesn't
+/// appear right in the mouth of a snake.
+const FOOD_CLEARANCE: usize = 3;
+/// Ticks that the nearest snake has to spare when it goes for golden food the shortest way.
+const GOLDEN_SLACK: u32 = 6;
+/// Points for eating golden food.
+pub const GOLDEN_POINTS: u32 = 5;
+/// Distance in cells from a bomb within which its explosion clears the walls.
+pub const BOMB_RADIUS: i32 = 3;
+/// Most bombs on the board at once.
+const MAX_BOMBS: usize = 3;
+/// Ticks for which the food left by the walls that a bomb cleared stays on the board.
+const BONUS_FOOD_TICKS: u64 = 12;
+/// Ticks for which the food left by a dead snake stays on the board, on top of a tick for every
+/// piece after it. The food at the tip of the tail goes first and that at the head last.
+const CORPSE_FOOD_TICKS: u64 = 20;
+/// Opacity of the colors of the snakes on the cells that they painted.
+const PAINT_ALPHA: u8 = 96;
+/// Points that a snake loses for going through its teammate in the co-op mode.
+const PASS_PENALTY: u32 = 1;
+/// How many times longer the ticks are with the player's head in mud.
+const MUD_SLOWDOWN: u32 = 2;
+/// How many times shorter the ticks are with the player's head on a boost pad.
+const BOOST_SPEEDUP: u32 = 2;
+/// Pairs of ladders between two layers of a stacked board next to each other.
+const LADDERS: usize = 2;
+/// Most tries at finding room for each ladder.
+const LADDER_TRIES: usize = 100;
+/// Most tries at finding room for each wall of a custom game, and how far the walls stay from
+/// the heads of the snakes.
+const OBSTACLE_TRIES: i32 = 20;
+const OBSTACLE_CLEARANCE: i32 = 2;
+/// How many times smaller the thumbnails of the other layers of a stacked board are at most than
+/// the layer with the player's snake.
+const THUMBNAIL_SCALE: i32 = 3;
+
+pub type SnakeId = usize;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Snake {
+    pub head: Vec2,
+    /// Segments from the one right behind the head to the tip of the tail.
+    pub tail: VecDeque<Vec2>,
+    pub direction: Direction,
+    pub alive: bool,
+    pub score: u32,
+}
+
+impl Snake {
+    fn new(topology: &dyn Topology, head: Vec2, direction: Direction) -> Self {
+        let neck = topology.neighbor(head, direction.opposite());
+        Snake {
+            head,
+            tail: VecDeque::from(vec![neck, topology.neighbor(neck, direction.opposite())]),
+            direction,
+            alive: true,
+            score: 0,
+        }
+    }
+
+    pub fn length(&self) -> usize {
+        self.tail.len() + 1
+    }
+
+    fn tip(&self) -> Vec2 {
+        *self.tail.back().unwrap_or(&self.head)
+    }
+
+    fn cells(&self) -> impl Iterator<Item = Vec2> + '_ {
+        std::iter::once(self.head).chain(self.tail.iter().copied())
+    }
+}
+
+/// Where a snake starts on an empty board: in the middle if it's alone, otherwise spread evenly
+/// across the rows and alternately heading right and left.
+fn default_snake(
+    topology: &dyn Topology,
+    (width, height): (i32, i32),
+    snakes: usize,
+    i: usize,
+) -> Snake {
+    if snakes == 1 {
+        Snake::new(topology, Vec2(width / 2 + 1, height / 2), Direction::Right)
+    } else {
+        let y = (i as i32 + 1) * height / (snakes as i32 + 1);
+        if i.is_multiple_of(2) {
+            Snake::new(topology, Vec2(2, y), Direction::Right)
+        } else {
+            Snake::new(topology, Vec2(width - 3, y), Direction::Left)
+        }
+    }
+}
+
+/// Terrain under the snakes that changes how fast the game goes while the head of the player's
+/// snake is on it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Zone {
+    Mud,
+    Boost,
+}
+
+impl Zone {
+    /// The length of a tick on the zone, for a normal tick of the given length.
+    pub fn tick(self, tick: Duration) -> Duration {
+        match self {
+            Zone::Mud => tick * MUD_SLOWDOWN,
+            Zone::Boost => tick / BOOST_SPEEDUP,
+        }
+    }
+}
+
+/// Something that happened during a tick.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Event {
+    Ate {
+        snake: SnakeId,
+        pos: Vec2,
+    },
+    /// `pos` is where the head hit the obstacle, which may be outside of the board.
+    Died {
+        snake: SnakeId,
+        reason: EndReason,
+        pos: Vec2,
+    },
+    /// The snake ate rotten food and got shorter.
+    AteRotten {
+        snake: SnakeId,
+        pos: Vec2,
+    },
+    /// The snake got to golden food in time and scored `GOLDEN_POINTS`.
+    AteGolden {
+        snake: SnakeId,
+        pos: Vec2,
+    },
+    FoodSpawned {
+        pos: Vec2,
+    },
+    /// A piece of food dropped from the body of a dead snake in a multiplayer game.
+    Dropped {
+        snake: SnakeId,
+        pos: Vec2,
+    },
+    /// Golden food appeared, to be eaten in `ticks` ticks.
+    GoldenFoodSpawned {
+        pos: Vec2,
+        ticks: u32,
+    },
+    /// The game has lasted as long as `Config::time_limit`.
+    TimeUp,
+    /// The snake went through its teammate's body in the co-op mode and lost `PASS_PENALTY`
+    /// points.
+    PassedThrough {
+        snake: SnakeId,
+        pos: Vec2,
+    },
+    /// The head of the snake touched the checkpoint at `pos`, which it will come back to after
+    /// dying.
+    CheckpointReached {
+        snake: SnakeId,
+        pos: Vec2,
+    },
+    /// The snake died and came back at `pos`, at its last checkpoint or for one of the shared
+    /// lives of the co-op mode.
+    Respawned {
+        snake: SnakeId,
+        pos: Vec2,
+    },
+    /// Nobody got to the golden food in time.
+    GoldenFoodExpired {
+        pos: Vec2,
+    },
+    /// A bomb went off, either eaten by the snake or caught in the explosion of another bomb.
+    /// The walls within `BOMB_RADIUS` of it turned into food.
+    Exploded {
+        snake: SnakeId,
+        pos: Vec2,
+    },
+    BoardFull,
+}
+
+/// Food worth `GOLDEN_POINTS` that disappears unless a snake gets to it quickly.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GoldenFood {
+    pub pos: Vec2,
+    /// Ticks until it disappears. A snake getting to it on the last of them is still in time.
+    pub ticks_left: u32,
+    /// Ticks that it had when it appeared.
+    pub lifetime: u32,
+}
+
+/// The board with all the snakes on it. The simulation only advances in whole ticks and all the
+/// randomness comes from the seed, so the same seed and the same inputs always produce the same
+/// game, independently of the timing and the platform.
+#[derive(Clone)]
+pub struct World {
+    width: i32,
+    /// Height of all the layers together.
+    height: i32,
+    shape: Shape,
+    /// Boards stacked on top of each other, kept in the grid one above the other, see `layers`.
+    layers: i32,
+    layer_height: i32,
+    /// Ladder cells, which also are portals to the cells of the layer above or below.
+    ladders: BTreeSet<Vec2>,
+    grid: Grid,
+    snakes: Vec<Snake>,
+    /// Every piece of food with the tick when it appeared.
+    food: BTreeMap<Vec2, u64>,
+    golden_food: Option<GoldenFood>,
+    /// Food left by the walls that bombs cleared and by dead snakes, with the tick when it
+    /// disappears.
+    bonus_food: BTreeMap<Vec2, u64>,
+    bombs: BTreeSet<Vec2>,
+    /// Mud and boost pads, which lie under everything else on the board.
+    zones: BTreeMap<Vec2, Zone>,
+    /// One-way gates with the direction in which the snakes can enter them. Like the zones, they
+    /// lie under everything else.
+    gates: BTreeMap<Vec2, Direction>,
+    /// Conveyors with the direction in which they carry the snakes, also under everything else.
+    conveyors: BTreeMap<Vec2, Direction>,
+    /// Checkpoints, which lie under everything else like the zones.
+    checkpoints: BTreeSet<Vec2>,
+    /// Where the snakes leaving the openings in the edges of the board or of the rooms in each
+    /// direction come in, see `Level::exit`.
+    exits: BTreeMap<(Vec2, Direction), Vec2>,
+    /// The rooms of a level made of several, see `rooms`.
+    rooms: i32,
+    room_height: i32,
+    /// Each snake as it was when its head last touched a checkpoint, to bring it back if it dies.
+    saved: BTreeMap<SnakeId, Snake>,
+    /// The snake that painted each cell last, row by row from the bottom, in the territory mode.
+    territory: Option<Vec<Option<SnakeId>>>,
+    /// Tick at which the game ends, if it has a timer.
+    time_limit: Option<u64>,
+    /// Lives left for the snakes to share in the co-op mode, where they can't hurt each other.
+    lives: Option<u32>,
+    /// Each portal cell with the other end of its portal.
+    portals: BTreeMap<Vec2, Vec2>,
+    /// Cells where the food appears if any of them is free.
+    food_spawners: Vec<Vec2>,
+    /// Snakes that stop instead of crashing.
+    invulnerable: BTreeSet<SnakeId>,
+    /// Segments that snakes still grow by without eating.
+    growth: BTreeMap<SnakeId, u32>,
+    rng: StdRng,
+    tick: u64,
+    food_interval: u32,
+    /// Most pieces of food at once, or 0 for no limit.
+    max_food: u32,
+    food_spread: u32,
+    /// Ticks until food rots, or 0 if it never does.
+    food_lifetime: u32,
+    /// Golden food appears on one tick in that many, or never if it's 0.
+    golden_food_odds: u32,
+    /// A bomb appears on one tick in that many, or never if it's 0.
+    bomb_odds: u32,
+    ticks_to_food: u32,
+    board_full: bool,
+}
+
+impl World {
+    /// Creates an empty board with `snakes` snakes, on the bottom layer if there are several.
+    pub fn new(config: &Config, snakes: usize, seed: u64) -> Self {
+        let layers = config.layers.max(1) as i32;
+        let height = config.height * layers;
+        let mut world = World::empty(config, config.width, height, seed);
+        world.layers = layers;
+        world.layer_height = config.height;
+        for i in 0..snakes {
+            let size = (config.width, config.height);
+            world.add_snake(default_snake(world.topology(), size, snakes, i));
+        }
+        world.place_ladders();
+        if config.rules.wrap {
+            world.wrap_edges();
+        }
+        world.place_obstacles(config.rules.obstacles);
+        world.spawn_food();
+        world
+    }
+
+    /// Creates the board of a level, which should have passed `Level::validate`. The snakes
+    /// start on its start cells in order, and the ones left without a start cell are placed as
+    /// on an empty board, in the first room. Portals without a pair are left out. The levels are
+    /// laid out for square cells, so their boards are always square whatever the shape in the
+    /// config.
+    pub fn from_level(config: &Config, level: &Level, snakes: usize, seed: u64) -> Self {
+        let rooms = level.rooms().len() as i32;
+        let room_height = level.room_height();
+        // The rooms go one above the other with a row of walls between them, see `rooms`.
+        let place = |pos: Vec2| Vec2(pos.0, pos.1 + pos.1 / room_height);
+        let size = (level.width(), level.height() + rooms - 1);
+        let mut world = World::empty(config, size.0, size.1, seed);
+        world.shape = Shape::Square;
+        world.rooms = rooms;
+        world.room_height = room_height;
+        for room in 1..rooms {
+            for x in 0..size.0 {
+                world
+                    .grid
+                    .set(Vec2(x, room * (room_height + 1) - 1), Tile::Wall);
+            }
+        }
+        world.exits = level_exits(level, place);
+        for (pos, cell) in level.cells() {
+            let pos = place(pos);
+            match cell {
+                Cell::Wall => world.grid.set(pos, Tile::Wall),
+                Cell::FoodSpawner => world.food_spawners.push(pos),
+                Cell::Mud => {
+                    world.zones.insert(pos, Zone::Mud);
+                }
+                Cell::Boost => {
+                    world.zones.insert(pos, Zone::Boost);
+                }
+                Cell::Gate(direction) => {
+                    world.gates.insert(pos, direction);
+                }
+                Cell::Conveyor(direction) => {
+                    world.conveyors.insert(pos, direction);
+                }
+                Cell::Checkpoint => {
+                    world.checkpoints.insert(pos);
+                }
+                Cell::Empty | Cell::Start | Cell::Portal(_) | Cell::Opening => (),
+            }
+        }
+        for (a, b) in level.portals() {
+            let (a, b) = (place(a), place(b));
+            world.grid.set(a, Tile::Portal);
+            world.grid.set(b, Tile::Portal);
+            world.portals.insert(a, b);
+            world.portals.insert(b, a);
+        }
+        let starts = level.starts();
+        for i in 0..snakes {
+            let snake = match starts.get(i) {
+                Some(&head) => {
+                    let direction = level.start_direction(head).unwrap_or(Direction::Right);
+                    Snake::new(world.topology(), place(head), direction)
+                }
+                None => default_snake(world.topology(), (size.0, room_height), snakes, i),
+            };
+            world.add_snake(snake);
+        }
+        world.spawn_food();
+        world
+    }
+
+    fn empty(config: &Config, width: i32, height: i32, seed: u64) -> Self {
+        let rules = &config.rules;
+        World {
+            width,
+            height,
+            shape: config.shape,
+            layers: 1,
+            layer_height: height,
+            ladders: BTreeSet::new(),
+            grid: Grid::new(width, height),
+            snakes: Vec::new(),
+            food: BTreeMap::new(),
+            golden_food: None,
+            bonus_food: BTreeMap::new(),
+            zones: BTreeMap::new(),
+            gates: BTreeMap::new(),
+            conveyors: BTreeMap::new(),
+            checkpoints: BTreeSet::new(),
+            exits: BTreeMap::new(),
+            rooms: 1,
+            room_height: height,
+            saved: BTreeMap::new(),
+            bombs: BTreeSet::new(),
+            territory: (config.mode == Mode::Territory)
+                .then(|| vec![None; (width * height) as usize]),
+            time_limit: config.time_limit(),
+            lives: (config.mode == Mode::CoOp).then_some(COOP_LIVES),
+            portals: BTreeMap::new(),
+            food_spawners: Vec::new(),
+            invulnerable: BTreeSet::new(),
+            growth: BTreeMap::new(),
+            rng: StdRng::seed_from_u64(seed),
+            tick: 0,
+            food_interval: config.food_pacing().0.max(1),
+            max_food: config.food_pacing().1,
+            food_spread: config.food_spread.min(MAX_FOOD_SPREAD),
+            food_lifetime: if rules.rotten_food {
+                config.food_lifetime
+            } else {
+                0
+            },
+            golden_food_odds: if rules.golden_food {
+                config.golden_food_odds
+            } else {
+                0
+            },
+            bomb_odds: if rules.bombs { config.bomb_odds } else { 0 },
+            ticks_to_food: config.food_pacing().0.max(1),
+            board_full: false,
+        }
+    }
+
+    fn add_snake(&mut self, snake: Snake) {
+        for pos in snake.cells() {
+            self.grid.set(pos, Tile::Snake);
+        }
+        self.snakes.push(snake);
+    }
+
+    /// Number of the boards stacked on top of each other. The snakes go from one layer to the
+    /// next over the ladders, which take them to the same spot on the layer above or below.
+    /// Everywhere else the layers are closed off from each other.
+    pub fn layers(&self) -> i32 {
+        self.layers
+    }
+
+    pub fn layer_height(&self) -> i32 {
+        self.layer_height
+    }
+
+    /// The layer that the cell is on, counting from the bottom.
+    pub fn layer(&self, pos: Vec2) -> i32 {
+        pos.1.div_euclid(self.layer_height)
+    }
+
+    /// The rows of the board that make up the layer.
+    fn layer_rows(&self, layer: i32) -> (i32, i32) {
+        (layer * self.layer_height, (layer + 1) * self.layer_height)
+    }
+
+    /// Whether moving onto the cell in the direction would go through the floor or the ceiling
+    /// of its layer.
+    fn crosses_layers(&self, pos: Vec2, direction: Direction) -> bool {
+        if self.layers == 1 {
+            return false;
+        }
+        let row = pos.1.rem_euclid(self.layer_height);
+        let rise = direction.vec().1;
+        (rise > 0 && row == 0) || (rise < 0 && row == self.layer_height - 1)
+    }
+
+    /// Puts `LADDERS` pairs of ladders between every two layers next to each other, at random
+    /// spots that are free on both, away from the edges so that there is room to step off them.
+    fn place_ladders(&mut self) {
+        for layer in 0..self.layers - 1 {
+            let mut placed = 0;
+            for _ in 0..LADDER_TRIES * LADDERS {
+                if placed == LADDERS {
+                    break;
+                }
+                let x = self.rng.gen_range(1..self.width - 1);
+                let y = self.rng.gen_range(1..self.layer_height - 1);
+                let bottom = Vec2(x, layer * self.layer_height + y);
+                let top = Vec2(x, bottom.1 + self.layer_height);
+                let free = |pos: Vec2| {
+                    self.grid.get(pos) == Some(Tile::Empty)
+                        && self
+                            .directions()
+                            .iter()
+                            .all(|&d| self.grid.get(self.neighbor(pos, d)) == Some(Tile::Empty))
+                };
+                if free(bottom) && free(top) {
+                    for (a, b) in [(bottom, top), (top, bottom)] {
+                        self.grid.set(a, Tile::Portal);
+                        self.portals.insert(a, b);
+                        self.ladders.insert(a);
+                    }
+                    placed += 1;
+                }
+            }
+        }
+    }
+
+    /// Makes every cell on an edge of each layer an opening to the cell on the opposite edge of
+    /// the same layer. Boards of cells other than square ones don't line up with themselves, so
+    /// they don't wrap.
+    fn wrap_edges(&mut self) {
+        if self.shape != Shape::Square {
+            return;
+        }
+        let right = self.width - 1;
+        for layer in 0..self.layers {
+            let bottom = layer * self.layer_height;
+            let top = bottom + self.layer_height - 1;
+            for x in 0..self.width {
+                let (low, high) = (Vec2(x, bottom), Vec2(x, top));
+                self.exits.insert((low, Direction::Down), high);
+                self.exits.insert((high, Direction::Up), low);
+            }
+            for y in bottom..=top {
+                let (left, right) = (Vec2(0, y), Vec2(right, y));
+                self.exits.insert((left, Direction::Left), right);
+                self.exits.insert((right, Direction::Right), left);
+            }
+        }
+    }
+
+    /// Turns about `percent` percent of the cells into walls. Every wall stands alone, with free
+    /// cells all around it, so the walls never cut the board into parts, and none of them is near
+    /// the head of a snake.
+    fn place_obstacles(&mut self, percent: u32) {
+        let target = self.width * self.height * percent as i32 / 100;
+        let mut placed = 0;
+        for _ in 0..OBSTACLE_TRIES * target {
+            if placed == target {
+                break;
+            }
+            let pos = Vec2(
+                self.rng.gen_range(0..self.width),
+                self.rng.gen_range(0..self.height),
+            );
+            let alone = (-1..=1).all(|dx| {
+                (-1..=1).all(|dy| self.grid.get(Vec2(pos.0 + dx, pos.1 + dy)) == Some(Tile::Empty))
+            });
+            let near_head = self.snakes.iter().any(|snake| {
+                let Vec2(dx, dy) = Vec2(pos.0 - snake.head.0, pos.1 - snake.head.1);
+                dx.abs().max(dy.abs()) <= OBSTACLE_CLEARANCE
+            });
+            if alone && !near_head {
+                self.grid.set(pos, Tile::Wall);
+                placed += 1;
+            }
+        }
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    pub fn shape(&self) -> Shape {
+        self.shape
+    }
+
+    /// How the cells of the board fit together.
+    pub fn topology(&self) -> &'static dyn Topology {
+        self.shape.topology()
+    }
+
+    /// Directions in which the snakes can go on this board.
+    pub fn directions(&self) -> &'static [Direction] {
+        self.topology().directions()
+    }
+
+    /// The cell next to `pos` in the direction. It may be outside of the board, unless `pos` is
+    /// an opening that leads back onto the board or into another room.
+    pub fn neighbor(&self, pos: Vec2, direction: Direction) -> Vec2 {
+        match self.exits.get(&(pos, direction)) {
+            Some(&exit) => exit,
+            None => self.topology().neighbor(pos, direction),
+        }
+    }
+
+    /// Number of the rooms of a level made of several. They are kept one above the other with a
+    /// row of walls between every two of them, so the snakes only get from one room to another
+    /// through the openings.
+    pub fn rooms(&self) -> i32 {
+        self.rooms
+    }
+
+    /// The room that the cell is in, counting from the bottom.
+    pub fn room(&self, pos: Vec2) -> i32 {
+        pos.1.div_euclid(self.room_height + 1)
+    }
+
+    /// The rows of the board that make up the room.
+    fn room_rows(&self, room: i32) -> (i32, i32) {
+        let bottom = room * (self.room_height + 1);
+        (bottom, bottom + self.room_height)
+    }
+
+    /// Whether the cell is in one of the rows of walls between the rooms.
+    fn is_seam(&self, pos: Vec2) -> bool {
+        self.rooms > 1 && pos.1.rem_euclid(self.room_height + 1) == self.room_height
+    }
+
+    /// Size in whole cells of what is shown of the board, for fitting it on the screen, see
+    /// `Topology::extent`. A stacked board shows one layer, with a column of the thumbnails of the
+    /// others on its right, and a level with several rooms shows one room.
+    pub fn extent(&self) -> (i32, i32) {
+        let rows = if self.rooms > 1 {
+            self.room_height
+        } else {
+            self.layer_height
+        };
+        let (width, height) = self.topology().extent((self.width, rows));
+        if self.layers == 1 {
+            return (width, height);
+        }
+        (
+            width + 1 + (width + THUMBNAIL_SCALE - 1) / THUMBNAIL_SCALE,
+            height,
+        )
+    }
+
+    /// Points the camera showing the `extent` of the board at the layer or the room with the head
+    /// of the player's snake.
+    pub fn focus(&self, camera: Camera) -> Camera {
+        let head = self.snakes.first().map_or(Vec2(0, 0), |snake| snake.head);
+        if self.rooms > 1 {
+            let room = self.room(head).clamp(0, self.rooms - 1);
+            return camera.with_rows(self.room_rows(room));
+        }
+        if self.layers == 1 {
+            return camera;
+        }
+        let layer = self.layer(head).clamp(0, self.layers - 1);
+        camera.with_rows(self.layer_rows(layer))
+    }
+
+    /// The cells in a straight line from `pos` in the direction, not including `pos` itself.
+    fn ray(&self, pos: Vec2, direction: Direction) -> impl Iterator<Item = Vec2> + '_ {
+        std::iter::successors(Some(pos), move |&pos| Some(self.neighbor(pos, direction))).skip(1)
+    }
+
+    /// Returns `None` for positions outside of the board.
+    pub fn tile(&self, pos: Vec2) -> Option<Tile> {
+        self.grid.get(pos)
+    }
+
+    pub fn snakes(&self) -> &[Snake] {
+        &self.snakes
+    }
+
+    /// Positions of the fresh food.
+    pub fn food(&self) -> impl Iterator<Item = Vec2> + '_ {
+        self.food
+            .keys()
+            .copied()
+            .filter(move |&pos| self.grid.get(pos) == Some(Tile::Food))
+    }
+
+    pub fn golden_food(&self) -> Option<GoldenFood> {
+        self.golden_food
+    }
+
+    /// Whether this is a game in the territory mode.
+    pub fn has_territory(&self) -> bool {
+        self.territory.is_some()
+    }
+
+    /// The snake that painted the cell last, in the territory mode.
+    pub fn owner(&self, pos: Vec2) -> Option<SnakeId> {
+        let territory = self.territory.as_ref()?;
+        if !(0..self.width).contains(&pos.0) || !(0..self.height).contains(&pos.1) {
+            return None;
+        }
+        territory[(pos.1 * self.width + pos.0) as usize]
+    }
+
+    /// Number of cells that each snake owns, in the territory mode.
+    pub fn territory(&self) -> Option<Vec<usize>> {
+        let mut counts = vec![0; self.snakes.len()];
+        for &owner in self.territory.as_ref()?.iter().flatten() {
+            counts[owner] += 1;
+        }
+        Some(counts)
+    }
+
+    /// The mud or the boost pad on the cell, if any.
+    pub fn zone(&self, pos: Vec2) -> Option<Zone> {
+        self.zones.get(&pos).copied()
+    }
+
+    /// The direction of the one-way gate on the cell, if there is one.
+    pub fn gate(&self, pos: Vec2) -> Option<Direction> {
+        self.gates.get(&pos).copied()
+    }
+
+    /// The direction in which the conveyor on the cell carries the snakes, if there is one.
+    pub fn conveyor(&self, pos: Vec2) -> Option<Direction> {
+        self.conveyors.get(&pos).copied()
+    }
+
+    /// The checkpoint that the snake touched last, where it comes back if it dies.
+    pub fn checkpoint(&self, snake: SnakeId) -> Option<Vec2> {
+        self.saved.get(&snake).map(|saved| saved.head)
+    }
+
+    /// Whether a snake heading in the direction can move onto the cell as far as the gates are
+    /// concerned: the cell has no gate or one pointing the same way.
+    pub fn can_enter(&self, pos: Vec2, direction: Direction) -> bool {
+        self.gate(pos).is_none_or(|gate| gate == direction) && !self.crosses_layers(pos, direction)
+    }
+
+    /// Whether the snakes play together in the co-op mode.
+    pub fn is_coop(&self) -> bool {
+        self.lives.is_some()
+    }
+
+    /// Lives left in the co-op mode.
+    pub fn lives(&self) -> Option<u32> {
+        self.lives
+    }
+
+    /// Ticks until the game ends, if it has a timer.
+    pub fn ticks_left(&self) -> Option<u64> {
+        Some(self.time_limit?.saturating_sub(self.tick))
+    }
+
+    pub fn bombs(&self) -> impl Iterator<Item = Vec2> + '_ {
+        self.bombs.iter().copied()
+    }
+
+    pub fn rotten_food(&self) -> impl Iterator<Item = Vec2> + '_ {
+        self.food
+            .keys()
+            .copied()
+            .filter(move |&pos| self.grid.get(pos) == Some(Tile::RottenFood))
+    }
+
+    /// A single-player or a co-op game ends when all the snakes die, any other multiplayer one
+    /// when at most one snake is left. Either ends when there is no room left for food or when
+    /// the time is up.
+    pub fn is_over(&self) -> bool {
+        let alive = self.snakes.iter().filter(|s| s.alive).count();
+        self.board_full
+            || alive == 0
+            || (self.snakes.len() > 1 && alive == 1 && !self.is_coop())
+            || self.ticks_left() == Some(0)
+    }
+
+    /// Advances the game by one tick. `turns` holds the new direction for each snake, if any.
+    /// Directions that the board doesn't have are turned into the closest ones it has, see
+    /// `Topology::resolve`, or ignored if there are none.
+    pub fn step(&mut self, turns: &[Option<Direction>]) -> Vec<Event> {
+        let mut events = Vec::new();
+        self.tick += 1;
+
+        let topology = self.topology();
+        for (snake, turn) in self.snakes.iter_mut().zip(turns) {
+            if let Some(direction) = turn.and_then(|t| topology.resolve(t, snake.direction)) {
+                snake.direction = direction;
+            }
+        }
+
+        let moves: Vec<Option<Direction>> = self.snakes.iter().map(|s| Some(s.direction)).collect();
+        self.advance(&moves, &mut events);
+        // Conveyors carry the heads that are on them one more cell after the move, in the same
+        // tick, whichever way the snakes are heading.
+        let pushes: Vec<Option<Direction>> = self
+            .snakes
+            .iter()
+            .map(|s| self.conveyors.get(&s.head).copied().filter(|_| s.alive))
+            .collect();
+        if pushes.iter().any(Option::is_some) {
+            self.advance(&pushes, &mut events);
+        }
+
+        self.age_food();
+        self.update_golden_food(&mut events);
+        self.spawn_bomb();
+        if self.ticks_left() == Some(0) {
+            events.push(Event::TimeUp);
+        }
+
+        // The countdown waits while the board has as much food as it can take.
+        if !self.has_max_food() {
+            self.ticks_to_food -= 1;
+        }
+        if self.food.is_empty() || (self.ticks_to_food == 0 && !self.has_max_food()) {
+            match self.spawn_food() {
+                Some(pos) => events.push(Event::FoodSpawned { pos }),
+                None => events.push(Event::BoardFull),
+            }
+        }
+
+        events
+    }
+
+    /// Moves the snakes one cell in the given directions, or leaves them where they are for
+    /// `None`, and deals with whatever they run into or eat.
+    fn advance(&mut self, moves: &[Option<Direction>], events: &mut Vec<Event>) {
+        // A snake entering a portal comes out of the cell next to its other end.
+        let moving: Vec<bool> = self
+            .snakes
+            .iter()
+            .zip(moves)
+            .map(|(s, m)| s.alive && m.is_some())
+            .collect();
+        let new_heads: Vec<Vec2> = self
+            .snakes
+            .iter()
+            .zip(moves)
+            .map(|(s, m)| {
+                let direction = match m {
+                    Some(direction) => *direction,
+                    None => return s.head,
+                };
+                let ahead = self.neighbor(s.head, direction);
+                match self.portals.get(&ahead) {
+                    Some(&exit) => self.neighbor(exit, direction),
+                    None => ahead,
+                }
+            })
+            .collect();
+        let eating: Vec<bool> = (0..self.snakes.len())
+            .map(|id| {
+                let tile = self.grid.get(new_heads[id]);
+                moving[id] && matches!(tile, Some(Tile::Food) | Some(Tile::GoldenFood))
+            })
+            .collect();
+        let spoiling: Vec<bool> = (0..self.snakes.len())
+            .map(|id| moving[id] && self.grid.get(new_heads[id]) == Some(Tile::RottenFood))
+            .collect();
+        // Tips of the tails that move out of the way during this move, by the snake. Those of the
+        // snakes that still grow stay where they are.
+        let mut vacated: Vec<(SnakeId, Vec2)> = self
+            .snakes
+            .iter()
+            .enumerate()
+            .filter(|&(id, _)| moving[id] && !eating[id])
+            .filter(|&(id, _)| self.growth.get(&id).is_none_or(|&growth| growth == 0))
+            .map(|(id, s)| (id, s.tip()))
+            .collect();
+
+        let coop = self.is_coop();
+        // A blocked snake doesn't move its tail either, so whoever heads for the tip of its tail
+        // runs into it, and may be blocked in turn. The collisions are worked out again until no
+        // more snakes are blocked.
+        let (deaths, blocked, passing) = loop {
+            let mut deaths: Vec<(SnakeId, EndReason)> = Vec::new();
+            // Invulnerable snakes that would have crashed and stay where they are instead.
+            let mut blocked: Vec<SnakeId> = Vec::new();
+            // Snakes going through their teammates in the co-op mode.
+            let mut passing: Vec<SnakeId> = Vec::new();
+            for (id, snake) in self.snakes.iter().enumerate() {
+                let direction = match moves[id] {
+                    Some(direction) if moving[id] => direction,
+                    _ => continue,
+                };
+                let head = new_heads[id];
+                let head_on = (0..self.snakes.len())
+                    .any(|other| other != id && moving[other] && new_heads[other] == head);
+                let reason = match self.grid.get(head) {
+                    None | Some(Tile::Wall) | Some(Tile::Portal) => Some(EndReason::Wall),
+                    // Going through a gate the wrong way is like running into a wall.
+                    _ if !self.can_enter(head, direction) => Some(EndReason::Wall),
+                    Some(Tile::Snake) if !vacated.iter().any(|&(_, tip)| tip == head) => {
+                        if snake.cells().any(|pos| pos == head) {
+                            Some(EndReason::Tail)
+                        } else if coop {
+                            passing.push(id);
+                            None
+                        } else {
+                            Some(EndReason::Snake)
+                        }
+                    }
+                    _ if head_on && !coop => Some(EndReason::Snake),
+                    _ => None,
+                };
+                match reason {
+                    Some(_) if self.invulnerable.contains(&id) => blocked.push(id),
+                    Some(reason) => deaths.push((id, reason)),
+                    None => (),
+                }
+            }
+            let before = vacated.len();
+            vacated.retain(|(id, _)| !blocked.contains(id));
+            if vacated.len() == before {
+                break (deaths, blocked, passing);
+            }
+        };
+
+        // Cells that snakes leave during this move. They only become empty if no other snake is
+        // there, which can happen when teammates go through each other.
+        let mut left: Vec<Vec2> = Vec::new();
+        // Cells of the bodies of the dead snakes that turn into food. A snake that ran into
+        // another one leaves half of its length for it, the others all of it.
+        let mut corpses: Vec<(SnakeId, Vec<Vec2>)> = Vec::new();
+        for &(id, reason) in deaths.iter() {
+            self.snakes[id].alive = false;
+            // In a single-player game the board stays as it was at the moment of the crash.
+            if self.snakes.len() > 1 {
+                let body: Vec<Vec2> = self.snakes[id].cells().collect();
+                let pieces = if reason == EndReason::Snake {
+                    body.iter()
+                        .copied()
+                        .step_by(2)
+                        .take(body.len() / 2)
+                        .collect()
+                } else {
+                    body.clone()
+                };
+                corpses.push((id, pieces));
+                left.extend(body);
+            }
+            events.push(Event::Died {
+                snake: id,
+                reason,
+                pos: new_heads[id],
+            });
+        }
+        for &id in passing.iter() {
+            let snake = &mut self.snakes[id];
+            snake.score = snake.score.saturating_sub(PASS_PENALTY);
+            events.push(Event::PassedThrough {
+                snake: id,
+                pos: new_heads[id],
+            });
+        }
+
+        for (id, snake) in self.snakes.iter_mut().enumerate() {
+            if !snake.alive || !moving[id] || eating[id] || blocked.contains(&id) {
+                continue;
+            }
+            match self.growth.get_mut(&id) {
+                Some(growth) if *growth > 0 => *growth -= 1,
+                _ => left.push(snake.tail.pop_back().unwrap()),
+            }
+            // Rotten food takes off one more segment, but leaves at least one behind the head.
+            if spoiling[id] && !snake.tail.is_empty() {
+                left.push(snake.tail.pop_back().unwrap());
+            }
+        }
+
+        let mut detonated = Vec::new();
+        for (id, snake) in self.snakes.iter_mut().enumerate() {
+            if !snake.alive || !moving[id] || blocked.contains(&id) {
+                continue;
+            }
+            let head = new_heads[id];
+            if self.bombs.remove(&head) {
+                detonated.push((id, head));
+            }
+            if eating[id] && self.golden_food.is_some_and(|g| g.pos == head) {
+                self.golden_food = None;
+                snake.score += GOLDEN_POINTS;
+                events.push(Event::AteGolden {
+                    snake: id,
+                    pos: head,
+                });
+            } else if eating[id] {
+                self.food.remove(&head);
+                self.bonus_food.remove(&head);
+                snake.score += 1;
+                events.push(Event::Ate {
+                    snake: id,
+                    pos: head,
+                });
+            } else if spoiling[id] {
+                self.food.remove(&head);
+                self.bonus_food.remove(&head);
+                events.push(Event::AteRotten {
+                    snake: id,
+                    pos: head,
+                });
+            }
+            snake.tail.push_front(snake.head);
+            snake.head = head;
+            self.grid.set(head, Tile::Snake);
+            if self.checkpoints.contains(&head) {
+                let previous = self.saved.insert(id, snake.clone());
+                if previous.is_none_or(|saved| saved.head != head) {
+                    events.push(Event::CheckpointReached {
+                        snake: id,
+                        pos: head,
+                    });
+                }
+            }
+        }
+        for pos in left {
+            let occupied = self
+                .snakes
+                .iter()
+                .any(|s| s.alive && s.cells().any(|cell| cell == pos));
+            if !occupied {
+                self.grid.set(pos, Tile::Empty);
+            }
+        }
+        for (id, corpse) in corpses {
+            self.drop_food(id, &corpse, events);
+        }
+        for &(id, _) in deaths.iter() {
+            self.respawn(id, events);
+        }
+        for (snake, pos) in detonated {
+            self.explode(snake, pos, events);
+        }
+        if let Some(territory) = self.territory.as_mut() {
+            for (id, snake) in self.snakes.iter().enumerate().filter(|(_, s)| s.alive) {
+                let Vec2(x, y) = snake.head;
+                if (0..self.width).contains(&x) && (0..self.height).contains(&y) {
+                    territory[(y * self.width + x) as usize] = Some(id);
+                }
+            }
+        }
+    }
+
+    /// Puts food on the cell if it is empty. Returns whether it did.
+    pub fn place_food(&mut self, pos: Vec2) -> bool {
+        if self.grid.get(pos) != Some(Tile::Empty) {
+            return false;
+        }
+        self.food.insert(pos, self.tick);
+        self.grid.set(pos, Tile::Food);
+        true
+    }
+
+    /// Takes the food off the cell. Returns whether there was any.
+    pub fn remove_food(&mut self, pos: Vec2) -> bool {
+        self.bonus_food.remove(&pos);
+        if self.food.remove(&pos).is_none() {
+            return false;
+        }
+        self.grid.set(pos, Tile::Empty);
+        true
+    }
+
+    /// Paints the cell for the snake, or leaves it unpainted, in the territory mode. Returns
+    /// whether the cell is on a board with territory.
+    pub fn set_owner(&mut self, pos: Vec2, owner: Option<SnakeId>) -> bool {
+        let territory = match self.territory.as_mut() {
+            Some(territory) => territory,
+            None => return false,
+        };
+        if !(0..self.width).contains(&pos.0) || !(0..self.height).contains(&pos.1) {
+            return false;
+        }
+        territory[(pos.1 * self.width + pos.0) as usize] = owner;
+        true
+    }
+
+    /// Builds or removes a wall on the cell. Cells taken by snakes or food are left alone.
+    /// Returns whether the cell changed.
+    pub fn set_wall(&mut self, pos: Vec2, wall: bool) -> bool {
+        let (from, to) = if wall {
+            (Tile::Empty, Tile::Wall)
+        } else {
+            (Tile::Wall, Tile::Empty)
+        };
+        if self.grid.get(pos) != Some(from) {
+            return false;
+        }
+        self.grid.set(pos, to);
+        true
+    }
+
+    /// Makes the snake stop in front of obstacles instead of crashing into them, or makes it
+    /// crash again.
+    pub fn set_invulnerable(&mut self, snake: SnakeId, invulnerable: bool) {
+        if invulnerable {
+            self.invulnerable.insert(snake);
+        } else {
+            self.invulnerable.remove(&snake);
+        }
+    }
+
+    pub fn is_invulnerable(&self, snake: SnakeId) -> bool {
+        self.invulnerable.contains(&snake)
+    }
+
+    /// Makes the snake longer by `segments` over the next ticks, one segment per tick, as if it
+    /// ate without scoring.
+    pub fn grow(&mut self, snake: SnakeId, segments: u32) {
+        *self.growth.entry(snake).or_insert(0) += segments;
+    }
+
+    /// Takes all the food off the board. New food appears on the next tick.
+    pub fn clear_food(&mut self) {
+        for pos in std::mem::take(&mut self.food).into_keys() {
+            self.grid.set(pos, Tile::Empty);
+        }
+        self.bonus_food.clear();
+        if let Some(golden) = self.golden_food.take() {
+            self.grid.set(golden.pos, Tile::Empty);
+        }
+    }
+
+    /// Moves the snake together with its body, so that its head gets to `head`. Returns `false`
+    /// without moving it if the body wouldn't fit on empty cells there.
+    pub fn teleport(&mut self, snake: SnakeId, head: Vec2) -> bool {
+        let old = &self.snakes[snake];
+        let shift = Vec2(head.0 - old.head.0, head.1 - old.head.1);
+        let fits = old.cells().all(|pos| {
+            let pos = pos + shift;
+            self.grid.get(pos) == Some(Tile::Empty)
+                || (self.grid.get(pos) == Some(Tile::Snake) && old.cells().any(|c| c == pos))
+        });
+        if !old.alive || !fits {
+            return false;
+        }
+        for pos in old.cells().collect::<Vec<_>>() {
+            self.grid.set(pos, Tile::Empty);
+        }
+        let snake = &mut self.snakes[snake];
+        snake.head = head;
+        for pos in snake.tail.iter_mut() {
+            *pos += shift;
+        }
+        for pos in snake.cells().collect::<Vec<_>>() {
+            self.grid.set(pos, Tile::Snake);
+        }
+        true
+    }
+
+    /// Changes the number of ticks between two pieces of food and their limit, starting with the
+    /// next piece.
+    pub fn set_food_pacing(&mut self, interval: u32, max_food: u32) {
+        self.food_interval = interval.max(1);
+        self.max_food = max_food;
+        self.ticks_to_food = self.ticks_to_food.min(self.food_interval);
+    }
+
+    /// Changes the number of ticks that food stays fresh, see `Config::food_lifetime`. Food that
+    /// is already on the board rots or disappears by its age under the new lifetime.
+    pub fn set_food_lifetime(&mut self, lifetime: u32) {
+        self.food_lifetime = lifetime;
+    }
+
+    /// Changes how often golden food appears, see `Config::golden_food_odds`.
+    pub fn set_golden_food_odds(&mut self, odds: u32) {
+        self.golden_food_odds = odds;
+    }
+
+    /// Changes how often bombs appear, see `Config::bomb_odds`.
+    pub fn set_bomb_odds(&mut self, odds: u32) {
+        self.bomb_odds = odds;
+    }
+
+    /// Changes how strongly the food prefers the cells far from the snakes, see
+    /// `Config::food_spread`.
+    pub fn set_food_spread(&mut self, spread: u32) {
+        self.food_spread = spread.min(MAX_FOOD_SPREAD);
+    }
+
+    /// Replaces the walls, portals and food spawners with those of the level, e.g. after its file
+    /// has changed. Cells taken by snakes or food keep them, and portals with an end on such a
+    /// cell are left out. Returns `false` without changing anything if the level has a different
+    /// size or a different number of rooms.
+    pub fn set_layout(&mut self, level: &Level) -> bool {
+        let rooms = level.rooms().len() as i32;
+        if (level.width(), level.height(), rooms) != (self.width, self.height, self.rooms) {
+            return false;
+        }
+        let old: Vec<Vec2> = self
+            .objects()
+            .filter(|(_, tile)| matches!(tile, Tile::Wall | Tile::Portal))
+            .map(|(pos, _)| pos)
+            .collect();
+        for pos in old {
+            self.grid.set(pos, Tile::Empty);
+        }
+        self.portals.clear();
+        self.food_spawners.clear();
+        self.zones.clear();
+        self.gates.clear();
+        self.conveyors.clear();
+        self.checkpoints.clear();
+        self.exits = level_exits(level, |pos| pos);
+        for (pos, cell) in level.cells() {
+            match cell {
+                Cell::Wall => {
+                    self.set_wall(pos, true);
+                }
+                Cell::FoodSpawner => self.food_spawners.push(pos),
+                Cell::Mud => {
+                    self.zones.insert(pos, Zone::Mud);
+                }
+                Cell::Boost => {
+                    self.zones.insert(pos, Zone::Boost);
+                }
+                Cell::Gate(direction) => {
+                    self.gates.insert(pos, direction);
+                }
+                Cell::Conveyor(direction) => {
+                    self.conveyors.insert(pos, direction);
+                }
+                Cell::Checkpoint => {
+                    self.checkpoints.insert(pos);
+                }
+                Cell::Empty | Cell::Start | Cell::Portal(_) | Cell::Opening => (),
+            }
+        }
+        for (a, b) in level.portals() {
+            if self.grid.get(a) == Some(Tile::Empty) && self.grid.get(b) == Some(Tile::Empty) {
+                self.grid.set(a, Tile::Portal);
+                self.grid.set(b, Tile::Portal);
+                self.portals.insert(a, b);
+                self.portals.insert(b, a);
+            }
+        }
+        true
+    }
+
+    /// The food, walls and portals on the board. The ladders are drawn on their own and left
+    /// out.
+    pub fn objects(&self) -> impl Iterator<Item = (Vec2, Tile)> + '_ {
+        self.objects_in((0, self.height))
+    }
+
+    /// The objects in the rows of the board from `.0` to `.1`.
+    pub fn objects_in(&self, (from, to): (i32, i32)) -> impl Iterator<Item = (Vec2, Tile)> + '_ {
+        (from.max(0)..to.min(self.height))
+            .flat_map(move |y| (0..self.width).map(move |x| Vec2(x, y)))
+            .filter_map(move |pos| match self.grid.get(pos)? {
+                Tile::Empty | Tile::Snake => None,
+                Tile::Portal if self.ladders.contains(&pos) => None,
+                tile => Some((pos, tile)),
+            })
+    }
+
+    /// Turns the cells of the body of the dead snake, listed from the head, into food that
+    /// disappears bit by bit from the tail.
+    fn drop_food(&mut self, snake: SnakeId, cells: &[Vec2], events: &mut Vec<Event>) {
+        for (i, &pos) in cells.iter().enumerate() {
+            if self.grid.get(pos) == Some(Tile::Empty) {
+                let lasts = CORPSE_FOOD_TICKS + (cells.len() - 1 - i) as u64;
+                self.food.insert(pos, self.tick);
+                self.bonus_food.insert(pos, self.tick + lasts);
+                self.grid.set(pos, Tile::Food);
+                events.push(Event::Dropped { snake, pos });
+            }
+        }
+    }
+
+    /// Brings back a dead snake as it was at its last checkpoint, with the score that it has
+    /// now. The cells of the snake at the checkpoint have to be free or have food on them, which
+    /// it eats without growing. Returns whether the snake came back.
+    fn return_to_checkpoint(&mut self, id: SnakeId, events: &mut Vec<Event>) -> bool {
+        let saved = match self.saved.get(&id) {
+            Some(saved) => saved.clone(),
+            None => return false,
+        };
+        // In a single-player game the body stays on the board after a crash.
+        let body: Vec<Vec2> = self.snakes[id].cells().collect();
+        for pos in body {
+            let occupied = self
+                .snakes
+                .iter()
+                .any(|s| s.alive && s.cells().any(|cell| cell == pos));
+            if !occupied && self.grid.get(pos) == Some(Tile::Snake) {
+                self.grid.set(pos, Tile::Empty);
+            }
+        }
+        let free = saved.cells().all(|pos| {
+            matches!(
+                self.grid.get(pos),
+                Some(Tile::Empty) | Some(Tile::Food) | Some(Tile::RottenFood)
+            )
+        });
+        if !free {
+            return false;
+        }
+        for pos in saved.cells() {
+            self.remove_food(pos);
+            self.grid.set(pos, Tile::Snake);
+        }
+        let head = saved.head;
+        self.snakes[id] = Snake {
+            score: self.snakes[id].score,
+            ..saved
+        };
+        self.growth.remove(&id);
+        events.push(Event::Respawned {
+            snake: id,
+            pos: head,
+        });
+        true
+    }
+
+    /// Brings back a snake that died at its last checkpoint, or in the co-op mode if the team has
+    /// a life left, on a random spot with room for the snake and for a couple of moves ahead of it.
+    fn respawn(&mut self, id: SnakeId, events: &mut Vec<Event>) {
+        if self.return_to_checkpoint(id, events) || self.lives.unwrap_or(0) == 0 {
+            return;
+        }
+        let mut spots = Vec::new();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                for &direction in self.directions() {
+                    // Two cells for the body and two for the moves ahead.
+                    let head = Vec2(x, y);
+                    let room = std::iter::once(head)
+                        .chain(self.ray(head, direction).take(2))
+                        .chain(self.ray(head, direction.opposite()).take(2))
+                        .all(|pos| {
+                            self.grid.get(pos) == Some(Tile::Empty)
+                                && self.layer(pos) == self.layer(head)
+                        });
+                    if room {
+                        spots.push((Vec2(x, y), direction));
+                    }
+                }
+            }
+        }
+        let Some(&(head, direction)) = spots.choose(&mut self.rng) else {
+            return;
+        };
+        self.lives = self.lives.map(|lives| lives - 1);
+        let score = self.snakes[id].score;
+        let snake = Snake {
+            score,
+            ..Snake::new(self.topology(), head, direction)
+        };
+        for pos in snake.cells() {
+            self.grid.set(pos, Tile::Snake);
+        }
+        self.snakes[id] = snake;
+        self.growth.remove(&id);
+        events.push(Event::Respawned {
+            snake: id,
+            pos: head,
+        });
+    }
+
+    fn spawn_food(&mut self) -> Option<Vec2> {
+        let spawners: Vec<Vec2> = self
+            .food_spawners
+            .iter()
+            .copied()
+            .filter(|&pos| self.grid.get(pos) == Some(Tile::Empty))
+            .collect();
+        let pos = match spawners.choose(&mut self.rng) {
+            Some(&pos) => Some(pos),
+            None => self
+                .pick_food_cell()
+                .or_else(|| self.grid.random_free(&mut self.rng)),
+        };
+        match pos {
+            Some(pos) => {
+                self.food.insert(pos, self.tick);
+                self.grid.set(pos, Tile::Food);
+                self.ticks_to_food = self.food_wait();
+                Some(pos)
+            }
+            None => {
+                self.board_full = true;
+                None
+            }
+        }
+    }
+
+    /// Sets off the bomb at `pos` and the bombs caught in its explosion, turning the walls around
+    /// them into food for a short while.
+    fn explode(&mut self, snake: SnakeId, pos: Vec2, events: &mut Vec<Event>) {
+        let mut queue = vec![pos];
+        while let Some(center) = queue.pop() {
+            events.push(Event::Exploded { snake, pos: center });
+            for cell in disc(center, BOMB_RADIUS) {
+                match self.grid.get(cell) {
+                    // The walls between the rooms stay, so that they can't be crossed.
+                    Some(Tile::Wall) if self.is_seam(cell) => (),
+                    Some(Tile::Wall) => {
+                        self.grid.set(cell, Tile::Food);
+                        self.food.insert(cell, self.tick);
+                        self.bonus_food.insert(cell, self.tick + BONUS_FOOD_TICKS);
+                    }
+                    Some(Tile::Bomb) => {
+                        self.bombs.remove(&cell);
+                        self.grid.set(cell, Tile::Empty);
+                        queue.push(cell);
+                    }
+                    _ => (),
+                }
+            }
+        }
+    }
+
+    /// Now and then puts a bomb on a free cell close enough to a wall for the explosion to reach
+    /// it. Bombs only appear on boards with walls.
+    fn spawn_bomb(&mut self) {
+        if self.bomb_odds == 0
+            || self.bombs.len() >= MAX_BOMBS
+            || self.rng.gen_range(0..self.bomb_odds) != 0
+        {
+            return;
+        }
+        let cells: BTreeSet<Vec2> = self
+            .objects()
+            .filter(|&(_, tile)| tile == Tile::Wall)
+            .flat_map(|(pos, _)| disc(pos, BOMB_RADIUS))
+            .filter(|&pos| self.grid.get(pos) == Some(Tile::Empty))
+            .collect();
+        let cells: Vec<Vec2> = cells.into_iter().collect();
+        if let Some(&pos) = cells.choose(&mut self.rng) {
+            self.bombs.insert(pos);
+            self.grid.set(pos, Tile::Bomb);
+        }
+    }
+
+    /// Turns the food that has been fresh for its whole lifetime into rotten food, and takes the
+    /// food that has been rotten for half of the lifetime off the board.
+    fn age_food(&mut self) {
+        let expired: Vec<Vec2> = self
+            .bonus_food
+            .iter()
+            .filter(|&(_, &until)| until <= self.tick)
+            .map(|(&pos, _)| pos)
+            .collect();
+        for pos in expired {
+            self.remove_food(pos);
+        }
+        if self.food_lifetime == 0 {
+            return;
+        }
+        let lifetime = self.food_lifetime as u64;
+        let shelf_life = lifetime + (lifetime / 2).max(1);
+        let mut gone = Vec::new();
+        for (&pos, &appeared) in self.food.iter() {
+            let age = self.tick - appeared;
+            if age >= shelf_life {
+                gone.push(pos);
+            } else if age >= lifetime {
+                self.grid.set(pos, Tile::RottenFood);
+            }
+        }
+        for pos in gone {
+            self.remove_food(pos);
+        }
+    }
+
+    /// Counts down the time left for the golden food and takes it away when the time is up. Without
+    /// golden food, now and then puts one on a cell as far from the snakes as there is, with just
+    /// enough time for the nearest snake to get there.
+    fn update_golden_food(&mut self, events: &mut Vec<Event>) {
+        if let Some(golden) = self.golden_food.as_mut() {
+            golden.ticks_left -= 1;
+            if golden.ticks_left == 0 {
+                let pos = golden.pos;
+                self.golden_food = None;
+                self.grid.set(pos, Tile::Empty);
+                events.push(Event::GoldenFoodExpired { pos });
+            }
+            return;
+        }
+        if self.golden_food_odds == 0 || self.rng.gen_range(0..self.golden_food_odds) != 0 {
+            return;
+        }
+        let distances = self.distances_from_heads();
+        let reachable: Vec<(Vec2, u32)> = self
+            .grid
+            .free_cells()
+            .iter()
+            .filter_map(|pos| Some((*pos, *distances.get(pos)?)))
+            .collect();
+        let farthest = match reachable.iter().map(|&(_, distance)| distance).max() {
+            Some(distance) => distance,
+            None => return,
+        };
+        let cells: Vec<Vec2> = reachable
+            .into_iter()
+            .filter(|&(_, distance)| distance == farthest)
+            .map(|(pos, _)| pos)
+            .collect();
+        let pos = *cells.choose(&mut self.rng).unwrap();
+        let lifetime = farthest + GOLDEN_SLACK;
+        self.golden_food = Some(GoldenFood {
+            pos,
+            ticks_left: lifetime,
+            lifetime,
+        });
+        self.grid.set(pos, Tile::GoldenFood);
+        events.push(Event::GoldenFoodSpawned {
+            pos,
+            ticks: lifetime,
+        });
+    }
+
+    fn has_max_food(&self) -> bool {
+        self.max_food > 0 && self.food.len() >= self.max_food as usize
+    }
+
+    /// Ticks until the next piece of food. Without a limit that's always the interval, otherwise
+    /// it is as short as the interval on an empty board and grows as the food gets closer to the
+    /// limit.
+    fn food_wait(&self) -> u32 {
+        let interval = self.food_interval as u64;
+        let max_food = self.max_food as u64;
+        let room = max_food.saturating_sub(self.food.len() as u64);
+        if max_food == 0 || room == 0 {
+            return self.food_interval;
+        }
+        (interval * max_food / (room + 1)).clamp(interval, u32::MAX as u64) as u32
+    }
+
+    /// Picks a free cell for food, weighted by `food_spread`. Cells that no snake can get to and
+    /// those straight in front of a head are left out. Returns `None` if no cell is left after that.
+    fn pick_food_cell(&mut self) -> Option<Vec2> {
+        let distances = self.distances_from_heads();
+        let mut ahead = BTreeSet::new();
+        for snake in self.snakes.iter().filter(|s| s.alive) {
+            ahead.extend(self.ray(snake.head, snake.direction).take(FOOD_CLEARANCE));
+        }
+        let weights: Vec<(Vec2, u64)> = self
+            .grid
+            .free_cells()
+            .iter()
+            .filter(|pos| !ahead.contains(pos))
+            .filter_map(|pos| {
+                let distance = *distances.get(pos)? as u64;
+                Some((*pos, distance.saturating_pow(self.food_spread)))
+            })
+            .collect();
+        let total: u64 = weights.iter().map(|&(_, weight)| weight).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut target = self.rng.gen_range(0..total);
+        for (pos, weight) in weights {
+            if target < weight {
+                return Some(pos);
+            }
+            target -= weight;
+        }
+        None
+    }
+
+    /// Length of the shortest way from the nearest head of a live snake to every cell that the
+    /// snakes can get to, going around walls and bodies and through portals.
+    fn distances_from_heads(&self) -> HashMap<Vec2, u32> {
+        let mut distances = HashMap::new();
+        let mut queue: VecDeque<(Vec2, u32)> = self
+            .snakes
+            .iter()
+            .filter(|s| s.alive)
+            .map(|s| (s.head, 0))
+            .collect();
+        while let Some((pos, distance)) = queue.pop_front() {
+            for &direction in self.directions() {
+                let next = self.neighbor(pos, direction);
+                let next = match self.portals.get(&next) {
+                    Some(&exit) => self.neighbor(exit, direction),
+                    None => next,
+                };
+                if self.crosses_layers(next, direction) {
+                    continue;
+                }
+                if matches!(
+                    self.grid.get(next),
+                    Some(Tile::Empty)
+                        | Some(Tile::Food)
+                        | Some(Tile::RottenFood)
+                        | Some(Tile::GoldenFood)
+                        | Some(Tile::Bomb)
+                ) && !distances.contains_key(&next)
+                {
+                    distances.insert(next, distance + 1);
+                    queue.push_back((next, distance + 1));
+                }
+            }
+        }
+        distances
+    }
+
+    pub fn render(&self, buffer: &mut FrameBuffer, palette: &Palette) {
+        let camera = self.focus(Camera::fit(buffer, self.extent()).with_shape(self.shape));
+        self.render_with_camera(buffer, palette, &camera, &[]);
+    }
+
+    pub fn render_with_camera(
+        &self,
+        buffer: &mut FrameBuffer,
+        palette: &Palette,
+        camera: &Camera,
+        skins: &[Skin],
+    ) {
+        let size = (self.width, self.height);
+        // Only the rows that the buffer holds are visited, which splits the work between the
+        // bands of `FrameBuffer::draw_in_bands`.
+        let band = camera.within(buffer);
+        let rows = band.rows.unwrap_or((0, self.height));
+        render_board(
+            buffer,
+            palette,
+            &band,
+            size,
+            &self.snakes,
+            skins,
+            self.objects_in(rows),
+        );
+        self.render_overlays(buffer, palette, &band, skins);
+        self.render_thumbnails(buffer, palette, camera, skins);
+    }
+
+    /// Draws a frame `thickness` cells wide around the rows that the camera shows, in the space
+    /// that the camera leaves around them. The frame has gaps next to the openings, where the
+    /// floor goes on.
+    pub fn render_border(
+        &self,
+        buffer: &mut FrameBuffer,
+        palette: &Palette,
+        camera: &Camera,
+        thickness: i32,
+    ) {
+        if thickness <= 0 {
+            return;
+        }
+        let cell = camera.cell;
+        let band = thickness * cell;
+        let (from, to) = camera.rows.unwrap_or((0, self.height));
+        let (width, height) = self.topology().extent((self.width, to - from));
+        let (width, height) = (width * cell, height * cell);
+        let Vec2(left, bottom) = camera.origin + Vec2(0, from * cell);
+        let (right, top) = (left + width, bottom + height);
+        let sides = [
+            (left - band, bottom - band, band, height + 2 * band),
+            (right, bottom - band, band, height + 2 * band),
+            (left, bottom - band, width, band),
+            (left, top, width, band),
+        ];
+        for (x, y, w, h) in sides {
+            buffer.fill_rectangle(x, y, w as usize, h as usize, palette.border);
+        }
+        let size = cell;
+        for &(pos, direction) in self.exits.keys().filter(|(pos, _)| camera.shows(*pos)) {
+            let Vec2(x, y) = camera.corner(pos);
+            let (x, y, w, h) = match direction {
+                Direction::Left => (left - band, y, band, size),
+                Direction::Right => (right, y, band, size),
+                Direction::Down => (x, bottom - band, size, band),
+                Direction::Up => (x, top, size, band),
+                _ => continue,
+            };
+            buffer.fill_rectangle(x, y, w as usize, h as usize, palette.bg);
+        }
+    }
+
+    /// Like `render_with_camera`, but leaves the snakes and the objects to the GPU as quads.
+    pub fn render_with_quads(
+        &self,
+        buffer: &mut FrameBuffer,
+        quads: &mut Vec<Quad>,
+        palette: &Palette,
+        camera: &Camera,
+        skins: &[Skin],
+    ) {
+        render_background(buffer, palette, camera, (self.width, self.height));
+        render_cells(quads, palette, camera, &self.snakes, skins, self.objects());
+        self.render_overlays(buffer, palette, camera, skins);
+        self.render_thumbnails(buffer, palette, camera, skins);
+    }
+
+    /// Draws the layers of a stacked board other than the one that the camera shows, small and
+    /// one above the other, in the column on the right of the `extent` of the board. The higher
+    /// layers are higher up.
+    fn render_thumbnails(
+        &self,
+        buffer: &mut FrameBuffer,
+        palette: &Palette,
+        camera: &Camera,
+        skins: &[Skin],
+    ) {
+        let Some((shown, _)) = camera.rows.filter(|_| self.layers > 1) else {
+            return;
+        };
+        let shown = self.layer(Vec2(0, shown));
+        let others = self.layers - 1;
+        let (width, height) = self.topology().extent((self.width, self.layer_height));
+        // All the thumbnails fit into the height of the layer, with a pixel between them.
+        let cell = (camera.cell / THUMBNAIL_SCALE)
+            .min((height * camera.cell - others + 1) / (others * height))
+            .max(1);
+        let Vec2(left, bottom) = camera.corner(Vec2(0, self.layer_rows(shown).0));
+        let left = left + (width + 1) * camera.cell;
+        let mut top = bottom + height * camera.cell;
+        for layer in (0..self.layers).rev().filter(|&layer| layer != shown) {
+            top -= height * cell;
+            let (from, to) = self.layer_rows(layer);
+            let thumbnail = Camera {
+                origin: Vec2(left, top),
+                cell,
+                border: 0,
+                shape: self.shape,
+                rows: None,
+                still: camera.still,
+            }
+            .with_rows((from, to))
+            .within(buffer);
+            let rows = thumbnail.rows.unwrap_or((from, to));
+            top -= 1;
+            if rows.0 >= rows.1 {
+                continue;
+            }
+            render_floor(buffer, palette, &thumbnail, (self.width, self.height));
+            render_cells(
+                buffer,
+                palette,
+                &thumbnail,
+                &self.snakes,
+                skins,
+                self.objects_in(rows),
+            );
+            self.render_ladders(buffer, palette, &thumbnail);
+        }
+    }
+
+    /// Draws the ladders as two rails with rungs between them.
+    fn render_ladders(&self, buffer: &mut FrameBuffer, palette: &Palette, camera: &Camera) {
+        let cell = camera.cell;
+        let rail = (cell / 6).max(1);
+        for &pos in self.ladders.iter().filter(|&&pos| camera.shows(pos)) {
+            let Vec2(x, y) = camera.corner(pos);
+            if cell < 4 {
+                buffer.fill_rectangle(x, y, cell as usize, cell as usize, palette.portal);
+                continue;
+            }
+            let (inset, size) = (cell / 5, cell as usize);
+            buffer.fill_rectangle(x + inset, y, rail as usize, size, palette.portal);
+            buffer.fill_rectangle(
+                x + cell - inset - rail,
+                y,
+                rail as usize,
+                size,
+                palette.portal,
+            );
+            for rung in (1..cell).step_by(3) {
+                let width = (cell - 2 * inset) as usize;
+                buffer.fill_rectangle(x + inset, y + rung, width, 1, palette.portal);
+            }
+        }
+    }
+
+    /// Everything on the board apart from the snakes and the objects: the zones, the gates, the
+    /// conveyors, the checkpoints, the territory and the timer of the golden food.
+    fn render_overlays(
+        &self,
+        buffer: &mut FrameBuffer,
+        palette: &Palette,
+        camera: &Camera,
+        skins: &[Skin],
+    ) {
+        self.render_zones(buffer, palette, camera);
+        self.render_ladders(buffer, palette, camera);
+        for (&pos, &direction) in self.gates.iter() {
+            if self.grid.get(pos) == Some(Tile::Empty) && camera.shows(pos) {
+                let corner = camera.corner(pos);
+                render_arrow(buffer, corner, camera.cell, direction, palette.gate);
+            }
+        }
+        for (&pos, &direction) in self.conveyors.iter() {
+            if self.grid.get(pos) == Some(Tile::Empty) && camera.shows(pos) {
+                self.render_conveyor(buffer, palette.conveyor, camera, pos, direction);
+            }
+        }
+        for &pos in self.checkpoints.iter() {
+            if self.grid.get(pos) == Some(Tile::Empty) && camera.shows(pos) {
+                let corner = camera.corner(pos);
+                // The checkpoint that the player will come back to stands out.
+                let color = if self.checkpoint(0) == Some(pos) {
+                    palette.head
+                } else {
+                    palette.checkpoint
+                };
+                render_flag(buffer, corner, camera.cell, color);
+            }
+        }
+        if self.territory.is_some() {
+            self.render_territory(buffer, palette, camera, skins);
+        }
+        if let Some(golden) = self.golden_food.filter(|golden| camera.shows(golden.pos)) {
+            render_countdown(buffer, palette.golden_food, camera, golden);
+        }
+    }
+
+    /// Draws the texture of the mud and the boost pads on the empty cells: specks of dirt for the
+    /// mud and arrows pointing right for the boost pads.
+    fn render_zones(&self, buffer: &mut FrameBuffer, palette: &Palette, camera: &Camera) {
+        let cell = camera.cell;
+        for (&pos, &zone) in self.zones.iter() {
+            if self.grid.get(pos) != Some(Tile::Empty) || !camera.shows(pos) {
+                continue;
+            }
+            let corner = camera.corner(pos);
+            let middle = (cell - 1) / 2;
+            for y in 0..cell {
+                for x in 0..cell {
+                    let (covered, color) = match zone {
+                        Zone::Mud => ((x + 2 * y) % 3 == 0 && y % 2 == 0, palette.mud),
+                        Zone::Boost => ((x + (y - middle).abs()) % 4 == 0, palette.boost),
+                    };
+                    if covered {
+                        buffer.fill_rectangle(corner.0 + x, corner.1 + y, 1, 1, color);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Draws the belt of the conveyor as stripes across it, which move a pixel its way every
+    /// tick.
+    fn render_conveyor(
+        &self,
+        buffer: &mut FrameBuffer,
+        color: Color,
+        camera: &Camera,
+        pos: Vec2,
+        direction: Direction,
+    ) {
+        let (corner, cell) = (camera.corner(pos), camera.cell);
+        let last = cell - 1;
+        let shift = (self.tick % 4) as i32;
+        for y in 0..cell {
+            for x in 0..cell {
+                let (along, _) = direction.along_across(x, y, last);
+                if (along - shift).rem_euclid(4) == 0 {
+                    buffer.fill_rectangle(corner.0 + x, corner.1 + y, 1, 1, color);
+                }
+            }
+        }
+    }
+
+    /// Tints the empty cells that the snakes painted with their colors.
+    fn render_territory(
+        &self,
+        buffer: &mut FrameBuffer,
+        palette: &Palette,
+        camera: &Camera,
+        skins: &[Skin],
+    ) {
+        let cell = camera.cell;
+        let (from, to) = camera.rows.unwrap_or((0, self.height));
+        for y in from.max(0)..to.min(self.height) {
+            for x in 0..self.width {
+                let pos = Vec2(x, y);
+                let owner = match self.owner(pos) {
+                    Some(owner) if self.grid.get(pos) == Some(Tile::Empty) && camera.shows(pos) => {
+                        owner
+                    }
+                    _ => continue,
+                };
+                let skin = skins.get(owner).copied().unwrap_or_default();
+                let (color, _) = skin.colors().unwrap_or_else(|| palette.snake_colors(owner));
+                let corner = camera.corner(pos);
+                buffer.blend_rectangle(
+                    corner.0,
+                    corner.1,
+                    cell as usize,
+                    cell as usize,
+                    color,
+                    PAINT_ALPHA,
+                );
+            }
+        }
+    }
+}
+
+/// Draws an arrow pointing in the direction into the square of `size` × `size` pixels with the
+/// bottom left corner at `corner`, as the mark of a one-way gate.
+pub fn render_arrow(
+    buffer: &mut FrameBuffer,
+    corner: Vec2,
+    size: i32,
+    direction: Direction,
+    color: Color,
+) {
+    let last = size - 1;
+    for y in 0..size {
+        for x in 0..size {
+            let (along, across) = direction.along_across(x, y, last);
+            // A triangle taking the front two thirds of the square.
+            if 3 * along >= size && (2 * across - last).abs() <= 2 * (last - along) {
+                buffer.fill_rectangle(corner.0 + x, corner.1 + y, 1, 1, color);
+            }
+        }
+    }
+}
+
+/// Draws a checkpoint flag in the square of the given size: a pole on the left with a cloth
+/// flying from its top.
+pub fn render_flag(buffer: &mut FrameBuffer, corner: Vec2, size: i32, color: Color) {
+    let pole = size / 4;
+    let thickness = 1.max(size / 8);
+    buffer.fill_rectangle(
+        corner.0 + pole,
+        corner.1,
+        thickness as usize,
+        size as usize,
+        color,
+    );
+    // The rows go up from the bottom, so the cloth takes the upper half of the square.
+    let cloth = (size + 1) / 2;
+    let width = size - pole - thickness;
+    buffer.fill_rectangle(
+        corner.0 + pole,
+        corner.1 + size - cloth,
+        width as usize,
+        cloth as usize,
+        color,
+    );
+}
+
+/// Where the snakes going through the openings of the level come in, with the cells of the level
+/// placed on the board by `place`.
+fn level_exits(level: &Level, place: impl Fn(Vec2) -> Vec2) -> BTreeMap<(Vec2, Direction), Vec2> {
+    let mut exits = BTreeMap::new();
+    for pos in level.openings() {
+        for direction in Direction::ALL {
+            if let Some(exit) = level.exit(pos, direction) {
+                exits.insert((place(pos), direction), place(exit));
+            }
+        }
+    }
+    exits
+}
+
+/// Cells within `radius` of `center`, which may be outside of the board.
+fn disc(center: Vec2, radius: i32) -> impl Iterator<Item = Vec2> {
+    (-radius..=radius).flat_map(move |dy| {
+        (-radius..=radius)
+            .filter(move |dx| dx * dx + dy * dy <= radius * radius)
+            .map(move |dx| center + Vec2(dx, dy))
+    })
+}
+
+/// Draws a ring around the golden food, which gets shorter as its time runs out. A still camera
+/// takes the ring away a side at a time.
+fn render_countdown(buffer: &mut FrameBuffer, color: Color, camera: &Camera, golden: GoldenFood) {
+    // The ring starts from its top left corner and leaves a pixel of a gap around the cell, so
+    // that it doesn't merge with the food.
+    let corner = camera.corner(golden.pos);
+    let (left, bottom) = (corner.0 - 2, corner.1 - 2);
+    let side = camera.cell + 3;
+    let (right, top) = (left + side, bottom + side);
+    let ring = (0..side)
+        .map(|i| Vec2(left + i, top))
+        .chain((0..side).map(|i| Vec2(right, top - i)))
+        .chain((0..side).map(|i| Vec2(right - i, bottom)))
+        .chain((0..side).map(|i| Vec2(left, bottom + i)));
+    let total = 4 * side as u32;
+    let lifetime = golden.lifetime.max(1);
+    let lit = if camera.still {
+        side as u32 * (4 * golden.ticks_left).div_ceil(lifetime)
+    } else {
+        (total * golden.ticks_left).div_ceil(lifetime)
+    };
+    for Vec2(x, y) in ring.take(lit as usize) {
+        buffer.fill_rectangle(x, y, 1, 1, color);
+    }
+}
+
+/// Which part of the board is visible and how large its cells are.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Camera {
+    /// Position of the bottom left corner of the board in the buffer, in pixels.
+    pub origin: Vec2,
+    /// Size of a cell in pixels.
+    pub cell: i32,
+    /// Width in pixels of the frame of the background left around the contents of every cell,
+    /// which makes the cells easier to tell apart.
+    pub border: i32,
+    /// Shape of the cells of the board.
+    pub shape: Shape,
+    /// The rows of the board from `.0` to `.1` that are shown, or `None` for all of them, see
+    /// `with_rows`.
+    pub rows: Option<(i32, i32)>,
+    /// Whether what changes on the board is shown in as few steps as it can be, for players who
+    /// are sensitive to motion.
+    pub still: bool,
+}
+
+impl Camera {
+    /// Shows the whole board centered, with the largest cells that fit into the buffer.
+    pub fn fit(buffer: &FrameBuffer, (width, height): (i32, i32)) -> Self {
+        let cell = std::cmp::max(
+            1,
+            std::cmp::min(
+                buffer.width() as i32 / width.max(1),
+                buffer.height() as i32 / height.max(1),
+            ),
+        );
+        Camera::centered(buffer, (width, height), cell)
+    }
+
+    /// Centers the board in the buffer with the given cell size.
+    pub fn centered(buffer: &FrameBuffer, (width, height): (i32, i32), cell: i32) -> Self {
+        Camera {
+            origin: Vec2(
+                (buffer.width() as i32 - width * cell) / 2,
+                (buffer.height() as i32 - height * cell) / 2,
+            ),
+            cell,
+            border: 0,
+            shape: Shape::Square,
+            rows: None,
+            still: false,
+        }
+    }
+
+    /// Shows the board with cells of at least `cell` pixels. If the board doesn't fit, the view
+    /// is centered on `target` as far as it can be without showing anything beyond the board.
+    pub fn follow(
+        buffer: &FrameBuffer,
+        (width, height): (i32, i32),
+        cell: i32,
+        target: Vec2,
+    ) -> Self {
+        let fit = Camera::fit(buffer, (width, height));
+        if fit.cell >= cell {
+            return fit;
+        }
+        let origin = |pixels: usize, cells: i32, target: i32| {
+            let (pixels, board) = (pixels as i32, cells * cell);
+            if board <= pixels {
+                (pixels - board) / 2
+            } else {
+                (pixels / 2 - target * cell - cell / 2).clamp(pixels - board, 0)
+            }
+        };
+        Camera {
+            origin: Vec2(
+                origin(buffer.width(), width, target.0),
+                origin(buffer.height(), height, target.1),
+            ),
+            cell,
+            border: 0,
+            shape: Shape::Square,
+            rows: None,
+            still: false,
+        }
+    }
+
+    pub fn with_border(self, border: i32) -> Self {
+        Camera { border, ..self }
+    }
+
+    /// Lays out the cells for the board of the given shape. The size of the board passed to the
+    /// constructors should be its `Topology::extent`.
+    pub fn with_shape(self, shape: Shape) -> Self {
+        Camera { shape, ..self }
+    }
+
+    pub fn with_still(self, still: bool) -> Self {
+        Camera { still, ..self }
+    }
+
+    /// Shows only the rows from `from` to `to` of the board, with the first of them where the
+    /// bottom row is otherwise, e.g. to show one layer of a stacked board.
+    pub fn with_rows(self, (from, to): (i32, i32)) -> Self {
+        Camera {
+            origin: Vec2(self.origin.0, self.origin.1 - from * self.cell),
+            rows: Some((from, to)),
+            ..self
+        }
+    }
+
+    /// Shows only the rows of the board that reach into the rows of the buffer, e.g. into a band
+    /// of `FrameBuffer::draw_in_bands`, and the rows next to them, which can draw over the edge.
+    pub fn within(&self, buffer: &FrameBuffer) -> Self {
+        let (bottom, top) = buffer.rows();
+        // The countdown ring of the golden food sticks out of its cell by two pixels, and the
+        // patch joining two cells of a snake along a diagonal needs both of them shown.
+        let margin = self.cell + 2;
+        let from = self.cell_at(Vec2(0, bottom - margin)).1;
+        let to = self.cell_at(Vec2(0, top - 1 + margin)).1 + 1;
+        let rows = match self.rows {
+            Some((shown_from, shown_to)) => (from.max(shown_from), to.min(shown_to).max(from)),
+            None => (from, to),
+        };
+        Camera {
+            rows: Some(rows),
+            ..*self
+        }
+    }
+
+    /// Whether the cell is in the rows that are shown.
+    pub fn shows(&self, pos: Vec2) -> bool {
+        self.rows
+            .is_none_or(|(from, to)| (from..to).contains(&pos.1))
+    }
+
+    /// Position in the buffer of the bottom left corner of the cell.
+    pub fn corner(&self, pos: Vec2) -> Vec2 {
+        self.origin + self.shape.topology().corner(pos, self.cell)
+    }
+
+    /// The cell under a pixel of the buffer. It may be outside of the board.
+    pub fn cell_at(&self, pixel: Vec2) -> Vec2 {
+        let pixel = Vec2(pixel.0 - self.origin.0, pixel.1 - self.origin.1);
+        self.shape.topology().cell_at(pixel, self.cell)
+    }
+}
+
+/// Draws the board as seen through the camera. Snakes without a skin of their own in `skins`
+/// look as the palette has them.
+pub fn render_board(
+    buffer: &mut FrameBuffer,
+    palette: &Palette,
+    camera: &Camera,
+    size: (i32, i32),
+    snakes: &[Snake],
+    skins: &[Skin],
+    objects: impl Iterator<Item = (Vec2, Tile)>,
+) {
+    render_background(buffer, palette, camera, size);
+    render_cells(buffer, palette, camera, snakes, skins, objects);
+}
+
+/// Clears the buffer and draws the empty board.
+pub fn render_background(
+    buffer: &mut FrameBuffer,
+    palette: &Palette,
+    camera: &Camera,
+    size: (i32, i32),
+) {
+    buffer.clear(palette.menu_bg);
+    render_floor(buffer, palette, camera, size);
+}
+
+/// Draws the empty rows of the board that the camera shows.
+fn render_floor(
+    buffer: &mut FrameBuffer,
+    palette: &Palette,
+    camera: &Camera,
+    (width, height): (i32, i32),
+) {
+    // Row by row, since on the hex board every other row is shifted.
+    for y in (0..height).filter(|&y| camera.shows(Vec2(0, y))) {
+        let Vec2(left, bottom) = camera.corner(Vec2(0, y));
+        let cell = camera.cell as usize;
+        buffer.fill_rectangle(left, bottom, width as usize * cell, cell, palette.bg);
+    }
+}
+
+/// Draws the snakes and the objects on the board, see `render_board`.
+pub fn render_cells(
+    target: &mut impl Fill,
+    palette: &Palette,
+    camera: &Camera,
+    snakes: &[Snake],
+    skins: &[Skin],
+    objects: impl Iterator<Item = (Vec2, Tile)>,
+) {
+    let Camera {
+        cell,
+        border,
+        shape,
+        ..
+    } = *camera;
+    let inner = (cell - 2 * border).max(1);
+    let corner = |pos: Vec2| camera.corner(pos) + Vec2(border, border);
+    for (id, snake) in snakes.iter().enumerate() {
+        // Dead snakes are cleared from a multiplayer board.
+        if !snake.alive && snakes.len() > 1 {
+            continue;
+        }
+        let skin = skins.get(id).copied().unwrap_or_default();
+        let colors = skin.colors().unwrap_or_else(|| palette.snake_colors(id));
+        for (i, &pos) in snake.tail.iter().enumerate() {
+            if !camera.shows(pos) {
+                continue;
+            }
+            let at = corner(pos);
+            skin.draw_segment(
+                &mut Outline::new(target, shape, at, inner),
+                at,
+                inner,
+                i,
+                colors,
+            );
+        }
+        if camera.shows(snake.head) {
+            let at = corner(snake.head);
+            let mut outline = Outline::new(target, shape, at, inner);
+            skin.draw_head(&mut outline, at, inner, snake.direction, colors.0);
+        }
+        // Cells next to each other along a diagonal only touch at a corner, so a patch over the
+        // corner joins them.
+        let cells = std::iter::once(snake.head)
+            .chain(snake.tail.iter().copied())
+            .filter(|&pos| camera.shows(pos));
+        for (a, b) in cells.clone().map(corner).zip(cells.skip(1).map(corner)) {
+            if (a.0 - b.0).abs() == cell && (a.1 - b.1).abs() == cell {
+                let size = (inner / 2).max(1);
+                let (x, y) = (
+                    (a.0 + b.0 + inner - size) / 2,
+                    (a.1 + b.1 + inner - size) / 2,
+                );
+                target.fill_rectangle(x, y, size as usize, size as usize, colors.1);
+            }
+        }
+    }
+    for (pos, tile) in objects {
+        let color = match tile {
+            Tile::Food => palette.food,
+            Tile::RottenFood => palette.rotten_food,
+            Tile::GoldenFood => palette.golden_food,
+            Tile::Bomb => palette.bomb,
+            Tile::Wall => palette.wall,
+            Tile::Portal => palette.portal,
+            Tile::Empty | Tile::Snake => continue,
+        };
+        if !camera.shows(pos) {
+            continue;
+        }
+        let at = corner(pos);
+        let size = inner as usize;
+        Outline::new(target, shape, at, inner).fill_rectangle(at.0, at.1, size, size, color);
+    }
+}
+
+/// Clips whatever is drawn into a cell to its outline, see `Topology::inset`. The square cells
+/// are drawn as they are.
+struct Outline<'a, F: Fill> {
+    target: &'a mut F,
+    shape: Shape,
+    corner: Vec2,
+    size: i32,
+}
+
+impl<'a, F: Fill> Outline<'a, F> {
+    fn new(target: &'a mut F, shape: Shape, corner: Vec2, size: i32) -> Self {
+        Outline {
+            target,
+            shape,
+            corner,
+            size,
+        }
+    }
+}
+
+impl<F: Fill> Fill for Outline<'_, F> {
+    fn fill_rectangle(&mut self, x0: i32, y0: i32, w: usize, h: usize, color: Color) {
+        if self.shape.is_square() {
+            self.target.fill_rectangle(x0, y0, w, h, color);
+            return;
+        }
+        let topology = self.shape.topology();
+        for y in y0..y0 + h as i32 {
+            let inset = topology.inset(y - self.corner.1, self.size);
+            let left = x0.max(self.corner.0 + inset);
+            let right = (x0 + w as i32).min(self.corner.0 + self.size - inset);
+            if left < right {
+                self.target
+                    .fill_rectangle(left, y, (right - left) as usize, 1, color);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A board with one snake made of `cells`, the head first, heading away from its neck. No
+    /// food, golden food or bombs appear on their own until a tick passes without any food.
+    fn world_with_snake(cells: &[Vec2]) -> World {
+        world_with_snakes(&[cells])
+    }
+
+    /// Like `world_with_snake`, with a snake for each list of cells.
+    fn world_with_snakes(snakes: &[&[Vec2]]) -> World {
+        let config = Config {
+            width: 8,
+            height: 8,
+            food_lifetime: 0,
+            golden_food_odds: 0,
+            bomb_odds: 0,
+            ..Config::default()
+        };
+        let mut world = World::new(&config, snakes.len(), 0);
+        world.clear_food();
+        for id in 0..snakes.len() {
+            for pos in world.snakes[id].cells().collect::<Vec<_>>() {
+                world.grid.set(pos, Tile::Empty);
+            }
+        }
+        for (id, cells) in snakes.iter().enumerate() {
+            for &pos in cells.iter() {
+                world.grid.set(pos, Tile::Snake);
+            }
+            let direction = towards(&world, cells[1], cells[0]);
+            let snake = &mut world.snakes[id];
+            snake.direction = direction;
+            snake.head = cells[0];
+            snake.tail = cells[1..].iter().copied().collect();
+        }
+        world
+    }
+
+    /// The direction from a cell to its neighbor.
+    fn towards(world: &World, from: Vec2, to: Vec2) -> Direction {
+        *world
+            .directions()
+            .iter()
+            .find(|&&direction| world.neighbor(from, direction) == to)
+            .unwrap()
+    }
+
+    fn cells(world: &World) -> Vec<Vec2> {
+        world.snakes[0].cells().collect()
+    }
+
+    fn died(events: &[Event]) -> Option<EndReason> {
+        events.iter().find_map(|event| match event {
+            Event::Died { reason, .. } => Some(*reason),
+            _ => None,
+        })
+    }
+
+    #[test]
+    fn eating_grows_the_snake_by_a_segment() {
+        let mut world = world_with_snake(&[Vec2(3, 3), Vec2(2, 3), Vec2(1, 3)]);
+        world.place_food(Vec2(4, 3));
+        let events = world.step(&[None]);
+        assert!(events.contains(&Event::Ate {
+            snake: 0,
+            pos: Vec2(4, 3)
+        }));
+        assert_eq!(
+            cells(&world),
+            [Vec2(4, 3), Vec2(3, 3), Vec2(2, 3), Vec2(1, 3)]
+        );
+        world.step(&[None]);
+        assert_eq!(
+            cells(&world),
+            [Vec2(5, 3), Vec2(4, 3), Vec2(3, 3), Vec2(2, 3)]
+        );
+        assert_eq!(world.tile(Vec2(1, 3)), Some(Tile::Empty));
+    }
+
+    #[test]
+    fn growing_without_eating_keeps_the_tail_in_place() {
+        let mut world = world_with_snake(&[Vec2(3, 3), Vec2(2, 3), Vec2(1, 3)]);
+        world.grow(0, 2);
+        world.step(&[None]);
+        world.step(&[None]);
+        assert_eq!(world.snakes[0].length(), 5);
+        world.step(&[None]);
+        assert_eq!(world.snakes[0].length(), 5);
+        assert_eq!(world.snakes[0].tail.back(), Some(&Vec2(2, 3)));
+    }
+
+    #[test]
+    fn rotten_food_shrinks_the_snake() {
+        let mut world = world_with_snake(&[Vec2(3, 3), Vec2(2, 3), Vec2(1, 3), Vec2(0, 3)]);
+        world.place_food(Vec2(4, 3));
+        world.grid.set(Vec2(4, 3), Tile::RottenFood);
+        let events = world.step(&[None]);
+        assert!(events.contains(&Event::AteRotten {
+            snake: 0,
+            pos: Vec2(4, 3)
+        }));
+        assert_eq!(cells(&world), [Vec2(4, 3), Vec2(3, 3), Vec2(2, 3)]);
+        assert_eq!(world.tile(Vec2(1, 3)), Some(Tile::Empty));
+        assert_eq!(world.tile(Vec2(0, 3)), Some(Tile::Empty));
+    }
+
+    #[test]
+    fn rotten_food_leaves_a_segment_behind_the_head() {
+        let mut world = world_with_snake(&[Vec2(3, 3), Vec2(2, 3)]);
+        world.place_food(Vec2(4, 3));
+        world.grid.set(Vec2(4, 3), Tile::RottenFood);
+        world.step(&[None]);
+        assert_eq!(cells(&world), [Vec2(4, 3), Vec2(3, 3)]);
+    }
+
+    /// A snake coiled in a square of 2 × 2 cells, with the tip of the tail next to the head.
+    const COIL: [Vec2; 4] = [Vec2(1, 1), Vec2(2, 1), Vec2(2, 2), Vec2(1, 2)];
+
+    #[test]
+    fn moving_into_the_tip_of_the_tail_as_it_moves_away() {
+        let mut world = world_with_snake(&COIL);
+        let turn = towards(&world, Vec2(1, 1), Vec2(1, 2));
+        let events = world.step(&[Some(turn)]);
+        assert_eq!(died(&events), None);
+        assert!(world.snakes[0].alive);
+        assert_eq!(
+            cells(&world),
+            [Vec2(1, 2), Vec2(1, 1), Vec2(2, 1), Vec2(2, 2)]
+        );
+        assert_eq!(world.tile(Vec2(1, 2)), Some(Tile::Snake));
+    }
+
+    #[test]
+    fn moving_into_the_tip_of_the_tail_while_growing() {
+        let mut world = world_with_snake(&COIL);
+        world.grow(0, 1);
+        let turn = towards(&world, Vec2(1, 1), Vec2(1, 2));
+        let events = world.step(&[Some(turn)]);
+        assert_eq!(died(&events), Some(EndReason::Tail));
+        assert!(!world.snakes[0].alive);
+    }
+
+    #[test]
+    fn moving_into_the_body() {
+        let mut body = COIL.to_vec();
+        body.push(Vec2(0, 2));
+        let mut world = world_with_snake(&body);
+        let turn = towards(&world, Vec2(1, 1), Vec2(1, 2));
+        let events = world.step(&[Some(turn)]);
+        assert_eq!(died(&events), Some(EndReason::Tail));
+        assert!(!world.snakes[0].alive);
+        assert_eq!(cells(&world), body);
+    }
+
+    #[test]
+    fn tip_of_the_tail_of_a_blocked_snake_stays_solid() {
+        // The first snake can't crash, so it stays where it is instead of leaving the board,
+        // and the second one runs into the tip of its tail.
+        let first = [Vec2(0, 1), Vec2(1, 1), Vec2(2, 1)];
+        let second = [Vec2(2, 2), Vec2(2, 3), Vec2(2, 4)];
+        let mut world = world_with_snakes(&[&first, &second]);
+        world.set_invulnerable(0, true);
+        let down = towards(&world, Vec2(2, 2), Vec2(2, 1));
+        let events = world.step(&[None, Some(down)]);
+        assert!(events.contains(&Event::Died {
+            snake: 1,
+            reason: EndReason::Snake,
+            pos: Vec2(2, 1)
+        }));
+        assert!(world.snakes[0].alive);
+        assert_eq!(cells(&world), first);
+        assert_eq!(world.tile(Vec2(2, 1)), Some(Tile::Snake));
+    }
+
+    #[test]
+    fn bands_draw_the_same_frame_as_one_thread() {
+        let boards = [
+            Config {
+                layers: 3,
+                ..Config::default()
+            },
+            Config {
+                shape: Shape::Hex,
+                golden_food_odds: 1,
+                ..Config::default()
+            },
+            Config {
+                shape: Shape::Diagonal,
+                golden_food_odds: 1,
+                ..Config::default()
+            },
+            Config {
+                mode: Mode::Territory,
+                ..Config::default()
+            },
+        ];
+        let palette = crate::palette::Theme::Classic.palette();
+        for config in boards.iter() {
+            let mut world = World::new(config, 2, 0);
+            for _ in 0..10 {
+                world.step(&[None, None]);
+            }
+            // The edges of most of the bands cut through the cells.
+            let mut whole = FrameBuffer::new(203, 157);
+            world.render(&mut whole, palette);
+            for threads in 2..=40 {
+                let mut buffer = FrameBuffer::new(203, 157);
+                buffer.draw_in_bands(threads, |band| world.render(band, palette));
+                assert_eq!(buffer.pixels(), whole.pixels(), "{} bands", threads);
+            }
+        }
+    }
+}
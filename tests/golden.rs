@@ -0,0 +1,217 @@
+//! Compares frames of scripted games with the reference images in `tests/goldens`. After an
+//! intended change of the visuals, run the tests with `UPDATE_GOLDENS=1` to rewrite the images
+//! and check the new ones in. The frames that don't match are saved next to the test binary for
+//! inspection.
+
+use snake_pixels::config::Config;
+use snake_pixels::font::draw_text;
+use snake_pixels::game::{State, Update, CELL_SIZE};
+use snake_pixels::grid::Direction;
+use snake_pixels::level::Level;
+use snake_pixels::palette::{Palette, Theme};
+use snake_pixels::renderer::{OffscreenRenderer, Renderer};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+/// Largest difference of a color channel that still counts as a match.
+const TOLERANCE: u8 = 8;
+
+/// Walls around the board, a portal and a single food spawner, so that the food doesn't depend
+/// on the random numbers.
+const LEVEL: &str = "\
+###############
+#.............#
+#.............#
+#.............#
+#....1........#
+#......F......#
+#.............#
+#...S....1....#
+#.............#
+#.............#
+#.............#
+#.............#
+#.............#
+#.............#
+###############
+";
+
+fn config() -> Config {
+    Config {
+        // Only the spawner's piece of food is on the board.
+        food_interval: 1000,
+        ..Config::default()
+    }
+}
+
+fn new_game() -> State {
+    let level = Level::load(LEVEL).unwrap();
+    State::with_level(&config(), &level, 0)
+}
+
+/// Makes a tick for every entry of `turns`, turning the snake first where there is a direction.
+fn play(state: &mut State, turns: &[Option<Direction>]) -> Update {
+    let mut update = Update::Idle;
+    for turn in turns {
+        if let Some(direction) = *turn {
+            state.turn(direction);
+        }
+        update = state.step();
+    }
+    update
+}
+
+fn render(state: &State, palette: &Palette) -> OffscreenRenderer {
+    let config = config();
+    let (width, height) = (config.width * CELL_SIZE, config.height * CELL_SIZE);
+    let mut renderer = OffscreenRenderer::new(width as usize, height as usize);
+    state.render(&mut renderer, palette);
+    renderer.present().unwrap();
+    renderer
+}
+
+fn golden_path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("goldens")
+        .join(format!("{}.png", name))
+}
+
+fn to_rgba(frame: &[u32]) -> Vec<u8> {
+    frame.iter().flat_map(|pixel| pixel.to_le_bytes()).collect()
+}
+
+fn write_png(path: &Path, width: usize, height: usize, rgba: &[u8]) {
+    let file = File::create(path).unwrap();
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().unwrap();
+    writer.write_image_data(rgba).unwrap();
+}
+
+fn read_png(path: &Path) -> Option<(usize, usize, Vec<u8>)> {
+    let decoder = png::Decoder::new(File::open(path).ok()?);
+    let mut reader = decoder.read_info().unwrap();
+    let mut rgba = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut rgba).unwrap();
+    assert_eq!(info.color_type, png::ColorType::Rgba, "{}", path.display());
+    rgba.truncate(info.buffer_size());
+    Some((info.width as usize, info.height as usize, rgba))
+}
+
+fn assert_golden(name: &str, renderer: &OffscreenRenderer) {
+    let (width, height) = (renderer.width(), renderer.height());
+    let actual = to_rgba(renderer.frame());
+    let path = golden_path(name);
+    if std::env::var_os("UPDATE_GOLDENS").is_some() {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        write_png(&path, width, height, &actual);
+        return;
+    }
+    let (golden_width, golden_height, expected) = read_png(&path).unwrap_or_else(|| {
+        panic!(
+            "Missing {}, run with UPDATE_GOLDENS=1 to create it",
+            path.display()
+        )
+    });
+    assert_eq!(
+        (golden_width, golden_height),
+        (width, height),
+        "size of {}",
+        name
+    );
+    let different = actual
+        .chunks_exact(4)
+        .zip(expected.chunks_exact(4))
+        .filter(|(a, e)| {
+            a.iter()
+                .zip(e.iter())
+                .any(|(a, e)| a.abs_diff(*e) > TOLERANCE)
+        })
+        .count();
+    if different > 0 {
+        let actual_path =
+            Path::new(env!("CARGO_TARGET_TMPDIR")).join(format!("{}.actual.png", name));
+        write_png(&actual_path, width, height, &actual);
+        panic!(
+            "{} pixels of {} differ from the golden, the frame is in {}",
+            different,
+            name,
+            actual_path.display()
+        );
+    }
+}
+
+#[test]
+fn level_start() {
+    let state = new_game();
+    assert_golden("level_start", &render(&state, Theme::Classic.palette()));
+}
+
+#[test]
+fn turn() {
+    let mut state = new_game();
+    let update = play(&mut state, &[None, None, Some(Direction::Up), None, None]);
+    assert_eq!(update, Update::Changed);
+    assert_golden("turn", &render(&state, Theme::Classic.palette()));
+}
+
+#[test]
+fn bands() {
+    let mut state = new_game();
+    play(&mut state, &[None, None, Some(Direction::Up), None, None]);
+    let (world, palette) = (state.world(), Theme::Classic.palette());
+    let mut renderer = render(&new_game(), palette);
+    // Three bands don't divide the rows evenly, so they have different heights.
+    renderer
+        .buffer_mut()
+        .draw_in_bands(3, |buffer| world.render(buffer, palette));
+    renderer.present().unwrap();
+    assert_golden("turn", &renderer);
+}
+
+#[test]
+fn through_portal() {
+    let mut state = new_game();
+    let update = play(&mut state, &[None; 6]);
+    assert_eq!(update, Update::Changed);
+    assert_golden("through_portal", &render(&state, Theme::Classic.palette()));
+}
+
+#[test]
+fn crash() {
+    let mut state = new_game();
+    let mut turns = vec![None; 7];
+    turns[0] = Some(Direction::Down);
+    assert_eq!(play(&mut state, &turns), Update::GameOver);
+    assert_golden("crash", &render(&state, Theme::Classic.palette()));
+}
+
+#[test]
+fn themes() {
+    let state = new_game();
+    assert_golden("night", &render(&state, Theme::Night.palette()));
+    assert_golden("gameboy", &render(&state, Theme::Gameboy.palette()));
+}
+
+#[test]
+fn text() {
+    let palette = Theme::Classic.palette();
+    let mut renderer = OffscreenRenderer::new(120, 40);
+    let buffer = renderer.buffer_mut();
+    buffer.clear(palette.menu_bg);
+    draw_text(buffer, 2, 38, "ABCDEFGHIJKLMNOPQRSTUVWXYZ", 1, palette.text);
+    draw_text(
+        buffer,
+        2,
+        31,
+        "0123456789 .,:-+/%!?<>=()#_'*",
+        1,
+        palette.dim_text,
+    );
+    draw_text(buffer, 2, 22, "SNAKE", 2, palette.selected);
+    renderer.present().unwrap();
+    assert_golden("text", &renderer);
+}
@@ -0,0 +1,889 @@
+//! Invariants of the game rules, checked after every tick of random games: on boards of random
+//! sizes, with one to four snakes turning at random.
+
+use proptest::prelude::*;
+use snake_pixels::bots::Random;
+use snake_pixels::config::{Config, Mode};
+use snake_pixels::controller::GameView;
+use snake_pixels::game::{State, Update, REWIND_TICKS};
+use snake_pixels::grid::{Direction, Tile, Vec2};
+use snake_pixels::handicap::{Handicap, MAX_REACTION_DELAY};
+use snake_pixels::level::{Cell, Level};
+use snake_pixels::replay::{Replay, Verdict};
+use snake_pixels::ruleset::{Ruleset, MAX_OBSTACLES};
+use snake_pixels::telemetry::EndReason;
+use snake_pixels::topology::Shape;
+use snake_pixels::world::{Event, Snake, World};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Longest game to play, in ticks.
+const MAX_TICKS: usize = 400;
+
+/// Turns of a snake during a tick: one of the directions for the codes below 4, nothing otherwise,
+/// so that the snakes mostly go straight.
+fn turn(code: u8) -> Option<Direction> {
+    Direction::ALL.get(code as usize).copied()
+}
+
+fn new_world(seed: u64, size: (i32, i32), snakes: usize, food_interval: u32) -> World {
+    let config = Config {
+        width: size.0,
+        height: size.1,
+        food_interval,
+        ..Config::default()
+    };
+    World::new(&config, snakes, seed)
+}
+
+/// Plays the game with the snakes turning by `codes`, calling `check` with the state before and
+/// after every tick and the events of the tick.
+fn play(
+    world: &mut World,
+    codes: &[u8],
+    mut check: impl FnMut(&[Snake], &World, &[Event]) -> Result<(), TestCaseError>,
+) -> Result<(), TestCaseError> {
+    let snakes = world.snakes().len();
+    let mut ticks = codes.chunks(snakes);
+    for _ in 0..MAX_TICKS {
+        if world.is_over() {
+            break;
+        }
+        // Go straight after the turns run out, to get to the walls eventually.
+        let mut turns: Vec<Option<Direction>> = match ticks.next() {
+            Some(tick) => tick.iter().map(|&code| turn(code)).collect(),
+            None => Vec::new(),
+        };
+        turns.resize(snakes, None);
+        let before = world.snakes().to_vec();
+        let events = world.step(&turns);
+        check(&before, world, &events)?;
+    }
+    Ok(())
+}
+
+fn cells(snake: &Snake) -> impl Iterator<Item = Vec2> + '_ {
+    std::iter::once(snake.head).chain(snake.tail.iter().copied())
+}
+
+fn ate(events: &[Event], id: usize) -> usize {
+    events
+        .iter()
+        .filter(|e| {
+            matches!(e, Event::Ate { snake, .. } | Event::AteGolden { snake, .. } if *snake == id)
+        })
+        .count()
+}
+
+fn ate_rotten(events: &[Event], id: usize) -> bool {
+    events
+        .iter()
+        .any(|e| matches!(e, Event::AteRotten { snake, .. } if *snake == id))
+}
+
+proptest! {
+    #[test]
+    fn length_changes_only_by_eating(
+        seed in any::<u64>(),
+        width in 8i32..24,
+        height in 8i32..24,
+        snakes in 1usize..5,
+        food_interval in 1u32..8,
+        codes in prop::collection::vec(0u8..12, 0..600),
+    ) {
+        let mut world = new_world(seed, (width, height), snakes, food_interval);
+        play(&mut world, &codes, |before, world, events| {
+            for (id, (old, new)) in before.iter().zip(world.snakes()).enumerate() {
+                let eaten = ate(events, id);
+                prop_assert!(eaten <= 1, "snake {} ate {} times in a tick", id, eaten);
+                // Rotten food shortens the snake, down to the head and one segment.
+                let expected = if ate_rotten(events, id) {
+                    (old.length() - 1).max(2)
+                } else {
+                    old.length() + eaten
+                };
+                prop_assert_eq!(
+                    new.length(),
+                    expected,
+                    "length of snake {} at tick {}",
+                    id,
+                    world.tick()
+                );
+                prop_assert!(old.alive || !new.alive, "snake {} came back to life", id);
+            }
+            Ok(())
+        })?;
+    }
+
+    #[test]
+    fn snakes_never_overlap(
+        seed in any::<u64>(),
+        width in 8i32..24,
+        height in 8i32..24,
+        snakes in 1usize..5,
+        codes in prop::collection::vec(0u8..12, 0..600),
+    ) {
+        let mut world = new_world(seed, (width, height), snakes, 3);
+        play(&mut world, &codes, |_, world, _| {
+            let mut occupied = BTreeSet::new();
+            for (id, snake) in world.snakes().iter().enumerate().filter(|(_, s)| s.alive) {
+                for pos in cells(snake) {
+                    prop_assert!(
+                        occupied.insert((pos.0, pos.1)),
+                        "snake {} overlaps at {:?} at tick {}",
+                        id,
+                        pos,
+                        world.tick()
+                    );
+                    prop_assert_eq!(world.tile(pos), Some(Tile::Snake));
+                }
+            }
+            Ok(())
+        })?;
+    }
+
+    #[test]
+    fn food_spawns_on_free_cells(
+        seed in any::<u64>(),
+        width in 8i32..24,
+        height in 8i32..24,
+        snakes in 1usize..5,
+        food_interval in 1u32..8,
+        codes in prop::collection::vec(0u8..12, 0..600),
+    ) {
+        let mut world = new_world(seed, (width, height), snakes, food_interval);
+        play(&mut world, &codes, |_, world, events| {
+            for event in events {
+                if let Event::FoodSpawned { pos } = *event {
+                    for snake in world.snakes().iter().filter(|s| s.alive) {
+                        prop_assert!(
+                            cells(snake).all(|cell| cell != pos),
+                            "food spawned on a snake at {:?} at tick {}",
+                            pos,
+                            world.tick()
+                        );
+                    }
+                }
+            }
+            for pos in world.food() {
+                prop_assert_eq!(world.tile(pos), Some(Tile::Food));
+            }
+            Ok(())
+        })?;
+    }
+
+    #[test]
+    fn food_stays_within_the_limit(
+        seed in any::<u64>(),
+        width in 8i32..24,
+        height in 8i32..24,
+        snakes in 1usize..5,
+        max_food in 1u32..12,
+        codes in prop::collection::vec(0u8..12, 0..600),
+    ) {
+        let config = Config {
+            width,
+            height,
+            food_interval: 1,
+            max_food,
+            ..Config::default()
+        };
+        let mut world = World::new(&config, snakes, seed);
+        // Food dropped by dead snakes doesn't count towards the limit.
+        let mut dropped = BTreeSet::new();
+        play(&mut world, &codes, |_, world, events| {
+            for event in events {
+                match *event {
+                    Event::Dropped { pos, .. } => {
+                        dropped.insert((pos.0, pos.1));
+                    }
+                    Event::Ate { pos, .. } | Event::AteRotten { pos, .. } => {
+                        dropped.remove(&(pos.0, pos.1));
+                    }
+                    _ => (),
+                }
+            }
+            let food = world
+                .food()
+                .filter(|pos| !dropped.contains(&(pos.0, pos.1)))
+                .count();
+            prop_assert!(
+                food <= max_food as usize,
+                "{} pieces of food at tick {}",
+                food,
+                world.tick()
+            );
+            Ok(())
+        })?;
+    }
+
+    #[test]
+    fn score_is_monotonic(
+        seed in any::<u64>(),
+        width in 8i32..24,
+        height in 8i32..24,
+        snakes in 1usize..5,
+        food_interval in 1u32..8,
+        codes in prop::collection::vec(0u8..12, 0..600),
+    ) {
+        let mut world = new_world(seed, (width, height), snakes, food_interval);
+        play(&mut world, &codes, |before, world, events| {
+            for (id, (old, new)) in before.iter().zip(world.snakes()).enumerate() {
+                prop_assert_eq!(
+                    new.score,
+                    old.score + ate(events, id) as u32,
+                    "score of snake {} at tick {}",
+                    id,
+                    world.tick()
+                );
+            }
+            Ok(())
+        })?;
+    }
+
+    #[test]
+    fn dead_snakes_turn_into_food(
+        seed in any::<u64>(),
+        width in 8i32..24,
+        height in 8i32..24,
+        snakes in 1usize..5,
+        codes in prop::collection::vec(0u8..12, 0..600),
+    ) {
+        let mut world = new_world(seed, (width, height), snakes, 3);
+        play(&mut world, &codes, |before, world, events| {
+            for (id, snake) in before.iter().enumerate() {
+                let dropped: Vec<Vec2> = events
+                    .iter()
+                    .filter_map(|e| match *e {
+                        Event::Dropped { snake, pos } if snake == id => Some(pos),
+                        _ => None,
+                    })
+                    .collect();
+                let reason = events.iter().find_map(|e| match *e {
+                    Event::Died { snake, reason, .. } if snake == id => Some(reason),
+                    _ => None,
+                });
+                // Running into another snake leaves only half of the length to it, and in a
+                // single-player game the body stays as it is.
+                let most = match reason {
+                    _ if before.len() == 1 => 0,
+                    Some(EndReason::Snake) => snake.length() / 2,
+                    Some(_) => snake.length(),
+                    None => 0,
+                };
+                prop_assert!(dropped.len() <= most, "snake {} dropped {}", id, dropped.len());
+                for pos in dropped {
+                    prop_assert!(cells(snake).any(|cell| cell == pos));
+                    prop_assert_eq!(world.tile(pos), Some(Tile::Food));
+                }
+            }
+            Ok(())
+        })?;
+    }
+
+    #[test]
+    fn teammates_never_kill_each_other(
+        seed in any::<u64>(),
+        width in 8i32..24,
+        height in 8i32..24,
+        codes in prop::collection::vec(0u8..12, 0..600),
+    ) {
+        let config = Config {
+            mode: Mode::CoOp,
+            width,
+            height,
+            ..Config::default()
+        };
+        let mut world = World::new(&config, 2, seed);
+        let mut lives = world.lives().unwrap();
+        play(&mut world, &codes, |_, world, events| {
+            for event in events {
+                match *event {
+                    Event::Died { reason, .. } => {
+                        prop_assert_ne!(reason, EndReason::Snake, "at tick {}", world.tick())
+                    }
+                    Event::Respawned { .. } => lives -= 1,
+                    _ => (),
+                }
+            }
+            prop_assert_eq!(world.lives(), Some(lives));
+            for snake in world.snakes().iter().filter(|s| s.alive) {
+                for pos in cells(snake) {
+                    prop_assert_eq!(world.tile(pos), Some(Tile::Snake), "at {:?}", pos);
+                }
+            }
+            // The game goes on while one of the teammates is alive.
+            let alive = world.snakes().iter().any(|s| s.alive);
+            let board_full = events.contains(&Event::BoardFull);
+            prop_assert!(!alive || board_full || !world.is_over(), "at tick {}", world.tick());
+            Ok(())
+        })?;
+    }
+
+    #[test]
+    fn snakes_come_back_at_checkpoints(
+        seed in any::<u64>(),
+        codes in prop::collection::vec(0u8..12, 0..400),
+    ) {
+        let level = Level::load(concat!(
+            "............\n",
+            "..C......C..\n",
+            "............\n",
+            "..S...C.....\n",
+            "............\n",
+            "..C......C..\n",
+            "............\n",
+        ))
+        .unwrap();
+        let mut world = World::from_level(&Config::default(), &level, 1, seed);
+        // The position and the length of the snake when its head was last on a checkpoint.
+        let mut saved: BTreeMap<usize, (Vec2, usize)> = BTreeMap::new();
+        play(&mut world, &codes, |_, world, events| {
+            for event in events {
+                if let Event::Respawned { snake, pos } = *event {
+                    let (checkpoint, length) = saved[&snake];
+                    prop_assert_eq!(pos, checkpoint, "at tick {}", world.tick());
+                    prop_assert_eq!(cells(&world.snakes()[snake]).count(), length);
+                    for pos in cells(&world.snakes()[snake]) {
+                        prop_assert_eq!(world.tile(pos), Some(Tile::Snake), "at {:?}", pos);
+                    }
+                }
+            }
+            for (id, snake) in world.snakes().iter().enumerate() {
+                if snake.alive && world.checkpoint(id) == Some(snake.head) {
+                    saved.insert(id, (snake.head, cells(snake).count()));
+                }
+                prop_assert_eq!(world.checkpoint(id), saved.get(&id).map(|&(pos, _)| pos));
+            }
+            Ok(())
+        })?;
+    }
+
+    #[test]
+    fn snakes_wrap_only_through_openings(
+        seed in any::<u64>(),
+        codes in prop::collection::vec(0u8..12, 0..400),
+    ) {
+        let level = Level::load(concat!(
+            "####O#######\n",
+            "#..........#\n",
+            "O..S.......O\n",
+            "#..........#\n",
+            "####O#######\n",
+        ))
+        .unwrap();
+        let openings = level.openings();
+        let mut world = World::from_level(&Config::default(), &level, 1, seed);
+        play(&mut world, &codes, |before, world, _| {
+            let (old, snake) = (&before[0], &world.snakes()[0]);
+            if !snake.alive || old.head == snake.head {
+                return Ok(());
+            }
+            prop_assert!(world.tile(snake.head).is_some(), "off the board at {:?}", snake.head);
+            let Vec2(dx, dy) = Vec2(snake.head.0 - old.head.0, snake.head.1 - old.head.1);
+            if dx.abs() + dy.abs() > 1 {
+                prop_assert!(
+                    openings.contains(&old.head),
+                    "jumped from {:?} to {:?} at tick {}",
+                    old.head,
+                    snake.head,
+                    world.tick()
+                );
+            }
+            Ok(())
+        })?;
+    }
+
+    #[test]
+    fn snakes_change_rooms_only_through_openings(
+        seed in any::<u64>(),
+        codes in prop::collection::vec(0u8..12, 0..400),
+    ) {
+        let level = Level::load(concat!(
+            "= HALL right:CAVE up:TOWER\n",
+            "######O#####\n",
+            "#..........#\n",
+            "#..S.......O\n",
+            "#..........#\n",
+            "############\n",
+            "= CAVE left:HALL\n",
+            "############\n",
+            "#..........#\n",
+            "O..........#\n",
+            "#..........#\n",
+            "############\n",
+            "= TOWER down:HALL\n",
+            "############\n",
+            "#..........#\n",
+            "#..........#\n",
+            "#..........#\n",
+            "######O#####\n",
+        ))
+        .unwrap();
+        let mut world = World::from_level(&Config::default(), &level, 1, seed);
+        prop_assert_eq!(world.rooms(), 3);
+        play(&mut world, &codes, |before, world, _| {
+            let (old, snake) = (&before[0], &world.snakes()[0]);
+            if !snake.alive || world.room(old.head) == world.room(snake.head) {
+                return Ok(());
+            }
+            // The rooms are a row of walls apart on the board and right next to each other in
+            // the level.
+            let Vec2(x, y) = old.head;
+            let cell = level.get(Vec2(x, y - world.room(old.head)));
+            prop_assert_eq!(
+                cell,
+                Some(Cell::Opening),
+                "left room {} from {:?} at tick {}",
+                world.room(old.head),
+                old.head,
+                world.tick()
+            );
+            Ok(())
+        })?;
+    }
+
+    #[test]
+    fn custom_boards_stay_in_one_piece(
+        seed in any::<u64>(),
+        width in 8i32..40,
+        height in 8i32..40,
+        snakes in 1usize..5,
+        obstacles in 0..=MAX_OBSTACLES,
+        wrap in any::<bool>(),
+        codes in prop::collection::vec(0u8..12, 0..400),
+    ) {
+        let config = Config {
+            width,
+            height,
+            rules: Ruleset { wrap, obstacles, ..Ruleset::default() },
+            ..Config::default()
+        };
+        let mut world = World::new(&config, snakes, seed);
+        // Every cell that isn't a wall can be reached from the head of the first snake.
+        let head = world.snakes()[0].head;
+        let mut reached = BTreeSet::from([head]);
+        let mut queue = vec![head];
+        while let Some(pos) = queue.pop() {
+            for &direction in world.directions() {
+                let next = world.neighbor(pos, direction);
+                let open = matches!(world.tile(next), Some(tile) if tile != Tile::Wall);
+                if open && reached.insert(next) {
+                    queue.push(next);
+                }
+            }
+        }
+        let open = (0..width)
+            .flat_map(|x| (0..height).map(move |y| Vec2(x, y)))
+            .filter(|&pos| world.tile(pos) != Some(Tile::Wall))
+            .count();
+        prop_assert_eq!(reached.len(), open);
+        play(&mut world, &codes, |_, world, events| {
+            for event in events {
+                if let Event::Died { reason, pos, .. } = event {
+                    prop_assert!(
+                        !wrap || *reason != EndReason::Wall || world.tile(*pos).is_some(),
+                        "died off the edge of a wrapping board at {:?}",
+                        pos
+                    );
+                }
+            }
+            Ok(())
+        })?;
+    }
+
+    #[test]
+    fn late_bots_never_turn_back(
+        seed in any::<u64>(),
+        width in 8i32..24,
+        height in 8i32..24,
+        reaction_delay in 1..=MAX_REACTION_DELAY,
+        blind_radius in 0u32..12,
+    ) {
+        let mut world = new_world(seed, (width, height), 1, 4);
+        let handicap = Handicap { reaction_delay, blind_radius, head_start: 0 };
+        let mut bot = handicap.hinder(Box::new(Random::new(seed)));
+        while !world.is_over() && world.tick() < MAX_TICKS as u64 {
+            let heading = world.snakes()[0].direction;
+            let direction = bot.decide(&GameView::new(&world, 0));
+            prop_assert_ne!(direction, heading.opposite(), "at tick {}", world.tick());
+            world.step(&[Some(direction)]);
+        }
+    }
+
+    #[test]
+    fn same_seed_same_game(
+        seed in any::<u64>(),
+        snakes in 1usize..5,
+        codes in prop::collection::vec(0u8..12, 0..300),
+    ) {
+        let mut first = new_world(seed, (15, 15), snakes, 4);
+        let mut second = new_world(seed, (15, 15), snakes, 4);
+        let mut history = Vec::new();
+        play(&mut first, &codes, |_, world, events| {
+            let food: Vec<Vec2> = world.food().collect();
+            history.push((world.snakes().to_vec(), food, events.to_vec()));
+            Ok(())
+        })?;
+        let mut tick = 0;
+        play(&mut second, &codes, |_, world, events| {
+            let (snakes, food, expected) = &history[tick];
+            tick += 1;
+            prop_assert_eq!(format!("{:?}", world.snakes()), format!("{:?}", snakes));
+            prop_assert_eq!(&world.food().collect::<Vec<_>>(), food);
+            prop_assert_eq!(events, &expected[..]);
+            Ok(())
+        })?;
+        prop_assert_eq!(tick, history.len());
+    }
+
+    #[test]
+    fn replay_restores_the_game(
+        seed in any::<u64>(),
+        codes in prop::collection::vec(0u8..12, 0..300),
+    ) {
+        let config = Config::default();
+        let mut state = State::new(&config, seed);
+        for &code in codes.iter() {
+            if let Some(direction) = turn(code) {
+                state.turn(direction);
+            }
+            if state.step() == Update::GameOver {
+                break;
+            }
+        }
+        let ticks = state.world().tick();
+        if state.world().is_over() {
+            // A finished game ends in the same way when it is played again.
+            prop_assert_eq!(
+                State::new(&config, seed).replay(ticks, state.turns()),
+                Update::GameOver
+            );
+            return Ok(());
+        }
+        let mut restored = State::new(&config, seed);
+        restored.replay(ticks, state.turns());
+        prop_assert_eq!(restored.world().tick(), ticks);
+        prop_assert_eq!(
+            format!("{:?}", restored.world().snakes()),
+            format!("{:?}", state.world().snakes())
+        );
+        prop_assert_eq!(
+            restored.world().food().collect::<Vec<_>>(),
+            state.world().food().collect::<Vec<_>>()
+        );
+        prop_assert_eq!(restored.turns(), state.turns());
+    }
+
+    #[test]
+    fn replays_verify_their_result(
+        seed in any::<u64>(),
+        mode in prop_oneof![Just(Mode::Classic), Just(Mode::Frenzy), Just(Mode::Territory)],
+        codes in prop::collection::vec(0u8..12, 0..300),
+    ) {
+        let config = Config { mode, ..Config::default() };
+        let mut state = State::new(&config, seed);
+        for &code in codes.iter() {
+            if let Some(direction) = turn(code) {
+                state.turn(direction);
+            }
+            if state.step() == Update::GameOver {
+                break;
+            }
+        }
+        state.end(EndReason::Quit);
+        let replay = Replay::of(&config, &state);
+        prop_assert_eq!(replay.verify(), Verdict::Valid);
+        let inflated = Replay { score: replay.score + 1, ..replay.clone() };
+        let mismatch = matches!(inflated.verify(), Verdict::Mismatch { .. });
+        prop_assert!(mismatch);
+        if state.world().is_over() {
+            // Nothing can be played after the end.
+            let longer = Replay { ticks: replay.ticks + 10, ..replay.clone() };
+            prop_assert_eq!(longer.verify(), Verdict::EndedEarly { tick: replay.ticks });
+        }
+    }
+
+    #[test]
+    fn rewind_restores_an_earlier_tick(
+        seed in any::<u64>(),
+        codes in prop::collection::vec(0u8..12, 1..300),
+    ) {
+        let config = Config::default();
+        let mut state = State::new(&config, seed);
+        state.set_practice();
+        for &code in codes.iter() {
+            if let Some(direction) = turn(code) {
+                state.turn(direction);
+            }
+            if state.step() == Update::GameOver {
+                break;
+            }
+        }
+        let ticks = state.world().tick();
+        prop_assert!(state.rewind());
+        let tick = state.world().tick();
+        prop_assert_eq!(tick, ticks.saturating_sub(REWIND_TICKS as u64));
+        prop_assert!(!state.world().is_over());
+        // The rewound game is the same as the one played up to that tick.
+        let mut restored = State::new(&config, seed);
+        restored.replay(tick, state.turns());
+        prop_assert_eq!(
+            format!("{:?}", restored.world().snakes()),
+            format!("{:?}", state.world().snakes())
+        );
+        prop_assert_eq!(
+            restored.world().food().collect::<Vec<_>>(),
+            state.world().food().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn safe_moves_never_kill(
+        seed in any::<u64>(),
+        width in 8i32..24,
+        height in 8i32..24,
+        food_interval in 1u32..8,
+        codes in prop::collection::vec(any::<u8>(), 0..600),
+    ) {
+        let mut world = new_world(seed, (width, height), 1, food_interval);
+        for &code in codes.iter() {
+            if world.is_over() {
+                break;
+            }
+            let view = GameView::new(&world, 0);
+            let snapshot = view.snapshot();
+            prop_assert_eq!(snapshot.occupied.len(), (width * height) as usize);
+            for pos in snapshot.food.iter() {
+                prop_assert!(!snapshot.occupied[(pos.1 * width + pos.0) as usize]);
+            }
+            let safe: Vec<Direction> =
+                Direction::ALL.iter().copied().filter(|&d| view.is_safe(d)).collect();
+            if safe.is_empty() {
+                break;
+            }
+            let direction = safe[code as usize % safe.len()];
+            world.step(&[Some(direction)]);
+            prop_assert!(
+                world.snakes()[0].alive,
+                "died going {:?} from {:?} at tick {}",
+                direction,
+                snapshot.head,
+                world.tick()
+            );
+        }
+    }
+
+    #[test]
+    fn snakes_move_between_neighbors(
+        seed in any::<u64>(),
+        width in 8i32..24,
+        height in 8i32..24,
+        snakes in 1usize..5,
+        shape in prop_oneof![Just(Shape::Diagonal), Just(Shape::Hex)],
+        codes in prop::collection::vec(0u8..16, 0..600),
+    ) {
+        let config = Config {
+            width,
+            height,
+            shape,
+            ..Config::default()
+        };
+        let mut world = World::new(&config, snakes, seed);
+        // Any of the eight directions for the codes below 8, so that the boards that don't have
+        // some of them get those too.
+        let directions = [
+            Direction::UpLeft,
+            Direction::UpRight,
+            Direction::DownLeft,
+            Direction::DownRight,
+        ];
+        for tick in codes.chunks(snakes).take(MAX_TICKS) {
+            if world.is_over() {
+                break;
+            }
+            let turns: Vec<Option<Direction>> = tick
+                .iter()
+                .map(|&code| turn(code).or_else(|| directions.get(code as usize - 4).copied()))
+                .collect();
+            world.step(&turns);
+            for (id, snake) in world.snakes().iter().enumerate().filter(|(_, s)| s.alive) {
+                prop_assert!(world.directions().contains(&snake.direction));
+                let body: Vec<Vec2> = cells(snake).collect();
+                for pair in body.windows(2) {
+                    let touching = world
+                        .directions()
+                        .iter()
+                        .any(|&d| world.neighbor(pair[0], d) == pair[1]);
+                    prop_assert!(
+                        touching,
+                        "snake {} is torn between {:?} and {:?} at tick {}",
+                        id,
+                        pair[0],
+                        pair[1],
+                        world.tick()
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn snakes_change_layers_only_on_ladders(
+        seed in any::<u64>(),
+        width in 8i32..24,
+        height in 8i32..24,
+        layers in 2u32..=4,
+        snakes in 1usize..5,
+        codes in prop::collection::vec(0u8..8, 0..600),
+    ) {
+        let config = Config {
+            width,
+            height,
+            layers,
+            ..Config::default()
+        };
+        let mut world = World::new(&config, snakes, seed);
+        for tick in codes.chunks(snakes).take(MAX_TICKS) {
+            if world.is_over() {
+                break;
+            }
+            let before: Vec<Snake> = world.snakes().to_vec();
+            let turns: Vec<Option<Direction>> = tick.iter().map(|&code| turn(code)).collect();
+            world.step(&turns);
+            for (id, (old, snake)) in before.iter().zip(world.snakes()).enumerate() {
+                if !snake.alive || world.layer(old.head) == world.layer(snake.head) {
+                    continue;
+                }
+                let entered = world.neighbor(old.head, snake.direction);
+                prop_assert_eq!(
+                    world.tile(entered),
+                    Some(Tile::Portal),
+                    "snake {} went from layer {} to {} at tick {} without a ladder",
+                    id,
+                    world.layer(old.head),
+                    world.layer(snake.head),
+                    world.tick()
+                );
+            }
+        }
+    }
+}
+
+/// A board with the food coming only once in a long while and no golden food or bombs.
+fn quiet_world(snakes: usize) -> World {
+    let config = Config {
+        width: 10,
+        height: 10,
+        food_interval: 1000,
+        golden_food_odds: 0,
+        bomb_odds: 0,
+        ..Config::default()
+    };
+    let mut world = World::new(&config, snakes, 1);
+    world.clear_food();
+    world
+}
+
+/// Lets the food left by a bomb or a dead snake at `pos` rot away, puts new food on the same
+/// cell, and checks that it stays there past the time when the old food would have run out.
+fn check_food_after_bonus_food(world: &mut World, pos: Vec2) {
+    assert_eq!(world.tile(pos), Some(Tile::Food));
+    world.set_food_lifetime(2);
+    for _ in 0..3 {
+        world.step(&[]);
+    }
+    assert_ne!(
+        world.tile(pos),
+        Some(Tile::Food),
+        "the food at {:?} didn't rot",
+        pos
+    );
+    world.set_food_lifetime(0);
+    world.place_food(pos);
+    for _ in 0..30 {
+        world.step(&[]);
+        assert_eq!(
+            world.tile(pos),
+            Some(Tile::Food),
+            "the food at {:?} is gone at tick {}",
+            pos,
+            world.tick()
+        );
+    }
+}
+
+/// Turns that keep a snake of three segments that starts heading right going round in a square
+/// of two by two cells.
+const CIRCLE: [Direction; 4] = [
+    Direction::Up,
+    Direction::Left,
+    Direction::Down,
+    Direction::Right,
+];
+
+/// Food left by the second of two snakes, which runs into the first one going round.
+fn corpse_food() -> (World, Vec2) {
+    let mut world = quiet_world(2);
+    let mut dropped = None;
+    for tick in 0..10 {
+        let turn = if tick == 5 {
+            Some(Direction::Down)
+        } else {
+            None
+        };
+        for event in world.step(&[Some(CIRCLE[tick % 4]), turn]) {
+            if let Event::Dropped { pos, .. } = event {
+                dropped = Some(pos);
+            }
+        }
+        if dropped.is_some() {
+            break;
+        }
+    }
+    (world, dropped.expect("the second snake didn't die"))
+}
+
+/// Food from a wall cleared by a bomb, which a snake going round on a board full of walls runs
+/// into.
+fn bomb_food() -> (World, Vec2) {
+    let mut world = quiet_world(1);
+    // Food in the corner, so that no more of it appears where the bombs should.
+    let corner = Vec2(0, 0);
+    world.place_food(corner);
+    world.step(&[Some(CIRCLE[0])]);
+    // Only the square where the snake goes round is left free, so that the bombs appear there.
+    let head = world.snakes()[0].head;
+    let square = [Vec2(-1, -1), Vec2(0, -1), Vec2(-1, 0), Vec2(0, 0)].map(|d| head + d);
+    for y in 0..world.height() {
+        for x in 0..world.width() {
+            if !square.contains(&Vec2(x, y)) {
+                world.set_wall(Vec2(x, y), true);
+            }
+        }
+    }
+    world.set_bomb_odds(1);
+    let mut exploded = false;
+    for tick in 1..20 {
+        let events = world.step(&[Some(CIRCLE[tick % 4])]);
+        if events.iter().any(|e| matches!(e, Event::Exploded { .. })) {
+            exploded = true;
+            break;
+        }
+    }
+    assert!(exploded, "the snake didn't run into a bomb");
+    world.set_bomb_odds(0);
+    // Food out of the way of the snake, which goes straight from now on.
+    let head = world.snakes()[0].head;
+    let pos = world
+        .food()
+        .find(|&pos| pos != corner && pos.0 != head.0 && pos.1 != head.1)
+        .unwrap();
+    (world, pos)
+}
+
+#[test]
+fn food_on_the_cell_of_rotten_bonus_food_stays() {
+    for (mut world, pos) in [corpse_food(), bomb_food()] {
+        check_food_after_bonus_food(&mut world, pos);
+    }
+}
@@ -0,0 +1,101 @@
+//! The codes of `share` give back the game that they were made from, and tell mistyped codes and
+//! codes of other versions apart.
+
+use proptest::prelude::*;
+use snake_pixels::config::{Config, Difficulty, Mode, MAX_FOOD_SPREAD, MAX_LAYERS};
+use snake_pixels::ruleset::Ruleset;
+use snake_pixels::share::{decode, encode, CodeError};
+use snake_pixels::topology::Shape;
+
+fn config() -> impl Strategy<Value = Config> {
+    (
+        (
+            prop_oneof![
+                Just(Mode::Classic),
+                Just(Mode::Frenzy),
+                Just(Mode::Territory),
+                Just(Mode::CoOp)
+            ],
+            prop_oneof![
+                Just(Difficulty::Easy),
+                Just(Difficulty::Normal),
+                Just(Difficulty::Hard)
+            ],
+            prop_oneof![Just(Shape::Square), Just(Shape::Diagonal), Just(Shape::Hex)],
+            1..=MAX_LAYERS,
+            8i32..=512,
+            8i32..=512,
+        ),
+        (
+            1u64..2000,
+            0u32..20,
+            0u32..20,
+            0..=MAX_FOOD_SPREAD,
+            0u32..200,
+        ),
+        (0u32..1000, 0u32..1000, 0u32..20),
+    )
+        .prop_map(|(board, food, rest)| Config {
+            mode: board.0,
+            difficulty: board.1,
+            shape: board.2,
+            layers: board.3,
+            width: board.4,
+            height: board.5,
+            tick_ms: food.0,
+            food_interval: food.1,
+            max_food: food.2,
+            food_spread: food.3,
+            food_lifetime: food.4,
+            golden_food_odds: rest.0,
+            bomb_odds: rest.1,
+            slow_start: rest.2,
+            // The codes are only given to the games with the standard rules.
+            rules: Ruleset::default(),
+        })
+}
+
+proptest! {
+    #[test]
+    fn codes_give_back_the_game(config in config(), seed in any::<u64>()) {
+        let code = encode(&config, seed);
+        let (decoded, decoded_seed) = decode(&code).unwrap();
+        prop_assert_eq!(decoded_seed, seed);
+        prop_assert_eq!(decoded.hash(), config.hash());
+        // Written down in groups and typed back in lowercase.
+        let groups: Vec<String> = code
+            .as_bytes()
+            .chunks(5)
+            .map(|g| String::from_utf8_lossy(g).to_lowercase())
+            .collect();
+        let typed = groups.join("-");
+        prop_assert_eq!(decode(&typed).unwrap().1, seed);
+    }
+}
+
+#[test]
+fn default_game() {
+    let code = encode(&Config::default(), 42);
+    assert_eq!(decode(&code).map(|(_, seed)| seed), Ok(42));
+    // The parameters at their defaults are left out.
+    assert_eq!(code.len(), 23);
+    let typo = format!("X{}", &code[1..]);
+    assert_eq!(
+        decode(&typo).map(|(_, seed)| seed),
+        Err(CodeError::Checksum)
+    );
+}
+
+#[test]
+fn codes_of_other_versions() {
+    assert_eq!(decode("").map(|(_, seed)| seed), Err(CodeError::Checksum));
+    assert_eq!(
+        decode("AB-CU").map(|(_, seed)| seed),
+        Err(CodeError::Character('U'))
+    );
+    // Version 2 with the checksum of its byte, 0x46.
+    assert_eq!(
+        decode("0930").map(|(_, seed)| seed),
+        Err(CodeError::Version(2))
+    );
+}